@@ -0,0 +1,397 @@
+## Event card
+
+event-start = Start: { $start }.
+starts-at = Start: { $start }
+event-limits = Event { $id } / { $adults }({ $children })
+free-seats = Free seats: { $seats }
+free-adult-seats = Free adult seats: { $seats }
+free-child-seats = Free child seats: { $seats }
+registration-closes = Registration until { $closes }.
+registration-opens = Registration opens { $opens }.
+registration-closed = Registration is closed.
+participants = Signed up { $adults }({ $children }):
+event-messages = Event messages
+messages-for-reserved = for participants
+messages-for-waiting-list = for the waiting list
+attendance-line = { $title }: { $present } of { $reserved } came, { $no_show } did not.
+attendance-participants = Participants
+attendance-seats = Seats
+attendance-adults = Adults
+attendance-children = Children
+reminder =
+    Hello!
+    Please don't forget that you have signed up for
+    <a href="{ $link }">{ $name }</a>
+    Start: { $start }
+    Please cancel your seats in time if your plans have changed.
+
+## Notifications and booking errors
+
+to-event = To the event
+payment-received = Payment received, your tickets:
+payment-received-tickets = Payment received, your tickets are below. They are also available with /my_tickets
+waiting-list-prompt =
+    Someone has cancelled a booking for the event: "{ $name }".
+    You can try to sign up.
+seat-offered =
+    A seat is available for the event { $title } (Start: { $start }).
+    The seat is held for you until { $hold_until }, please { $paid ->
+        [yes] pay for the ticket
+       *[no] confirm your participation
+    } on the event page. Otherwise it passes to the next in the waiting list.
+offer-expired = The seat hold for the event "{ $name }" has expired, the seat passed to the next in the waiting list.
+approval-expired = The payment deadline for your approved application for the event "{ $name }" has passed, the seats were released.
+please-pay = Please pay for the ticket.
+hold-expired = The hold has expired.
+no-seats-left = Unfortunately, there are no free seats left.
+time-conflict = You have already signed up for another event at this time.
+application-exists = The application has already been submitted.
+application-not-approved = The application has not been approved.
+application-approved = { $paid ->
+        [yes]
+            Your application for the event { $title } (Start: { $start }) has been approved.
+            Please pay for the tickets by { $hold_until }, otherwise the seats are released.
+       *[no] Your application for the event { $title } (Start: { $start }) has been approved.
+    }
+application-rejected = Unfortunately, your application for the event { $title } (Start: { $start }) has been rejected.
+application-rejected-reason =
+    Unfortunately, your application for the event { $title } (Start: { $start }) has been rejected.
+    Reason: { $reason }
+no-paid-tickets = There are no paid tickets.
+refund-impossible = Tickets for this event can no longer be refunded.
+refund-approved = The refund of tickets for the event { $title } (Start: { $start }) has been approved: { $amount }.
+refund-rejected = Unfortunately, the refund of tickets for the event { $title } (Start: { $start }) has been rejected.
+promo-code-invalid = The promo code is not valid.
+event-message =
+    <a href="tg://user?id={ $sender_id }">{ $sender }</a>:
+    Message about the event { $title } (Start: { $start })
+    { $text }
+broadcast-message =
+    <a href="tg://user?id={ $sender_id }">{ $sender }</a>:
+    { $text }
+broadcast-usage =
+    /broadcast <audience> [in <delay, e.g. 2h>] text
+    Audience: all, attended <event>, no_show <event>, tag <tag>, organization <organization>
+broadcast-no-recipients = Nobody to send to: { $audience }.
+broadcast-preview =
+    Message for { $audience }, recipients: { $recipients }, sending at { $start }:
+
+    { $text }
+broadcast-media = Attachment: { $kind }
+broadcast-send-button = Send
+broadcast-cancel-button = Cancel
+broadcast-scheduled = The message has been scheduled for sending to { $recipients } recipients at { $start }.
+broadcast-cancelled = The message has been cancelled.
+broadcast-not-found = The message has already been sent or cancelled.
+delivery-report = Message { $message_id }: { $targeted } recipients, { $delivered } delivered, { $failed } failed, { $pending } pending.
+delivery-report-failure = { $reason }: { $count }
+delivery-report-updated = As of { $time }
+delivery-report-button = Delivery report
+delivery-report-not-found = The message has not been found.
+subscription-new-event = New event { $title } (Start: { $start }).
+feedback-request = How was { $title } (Start: { $start })? Please rate the event from 1 to 5.
+feedback-thanks = Thank you for the rating! You can send a comment as a message.
+feedback-skip-button = No comment
+feedback-done = Thank you for the feedback!
+feedback-comment-too-long = The comment is too long, at most { $max } characters.
+feedback-unavailable = The survey is not available.
+feedback-summary = { $name } ({ $start }): { $average }⭐, { $responses } of { $surveyed } responded, { $comments } comments
+feedback-rating = { $rating }⭐: { $count }
+feedback-no-responses = No responses yet.
+no-feedback = No surveys yet.
+
+## Commands
+
+help =
+    Here you can book seats for events.
+
+    /start - show the list of events
+    /my - my bookings
+    /calendar - calendar subscription
+    /subscriptions - new events of organizers and tags
+    /my_tickets - my tickets
+    /language - bot language
+    /help - this help
+    <a href="{ $help }">Detailed instructions</a>
+    /donate - support the channel.
+language-usage =
+    Bot language: English.
+    /language ru - русский
+    /language en - English
+language-set = Bot language: English.
+event-list =
+    Programme
+    time / adult (child) seats / event
+    <a href="{ $help }">instructions</a> /donate
+no-events = No events.
+subscription-button = 🔔 Subscribe
+subscription-choose = Get a message about new events of the organizer or with the tag:
+subscription-organization = Organizer: { $name }
+subscription-tag = #{ $tag }
+subscription-on = ✅ { $topic }
+subscription-off = 🔔 { $topic }
+subscription-remove = ❌ { $topic }
+subscriptions = You get messages about new events of:
+no-subscriptions = You have no subscriptions. Open an event and press "🔔 Subscribe" to learn about new events of its organizer or with its tags.
+
+## Event screen
+
+booking-blocked = Sorry, booking is not possible because you previously neither used nor cancelled a reservation. If this is a mistake, please contact <a href="tg://user?id={ $support }">support</a> and give them the code { $code }. <a href="{ $help }">Instructions</a>.
+late-cancel-banned =
+    ATTENTION!
+    Unfortunately, you cancelled your tickets too late and can no longer book free tickets.
+late-wont-go = Unfortunately, you are cancelling your tickets too late and can no longer book free tickets.
+wont-go = We are sorry you can't make it. See you another time. Thank you!
+attachment-has-digits =
+    ATTENTION!
+    Your note contains digits. They do not change the number of booked seats. The number of seats can only be changed with the "Sign up/Cancel" buttons.
+attachment = Note: { $attachment }.
+seats-hint = The number of seats can be changed with the "Sign up/Cancel" buttons. To add a note to the booking, send a message to the bot.
+my-reserved = <b>You have booked: { $count }</b>
+my-waiting = <b>You are on the waiting list: { $count }</b>
+my-pending = <b>Your application is under review: { $count }</b>
+my-approved = <b>Your application is approved, awaiting payment: { $count }</b>
+sign-up = Sign up +1
+sign-up-adult = Sign up an adult +1
+sign-up-child = Sign up a child +1
+wait = Join the waiting list +1
+wait-adult = Waiting list, adult +1
+wait-child = Waiting list, child +1
+cancel-seat = Cancel -1
+cancel-adult = Cancel an adult -1
+cancel-child = Cancel a child -1
+event-list-button = Events
+waiting-list-button = Waiting list
+applications-button = Applications
+presence-button = Attendance
+close-registration = Close registration
+open-registration = Open registration
+back = Back
+waiting-list = Waiting list:
+waiting-list-empty = The waiting list is empty.
+send-rejection-reason = Send the reason for the rejection in a message, it will be passed on to the applicant.
+reject-without-reason = Reject without a reason
+no-applications = No applications.
+applications = Applications:
+all-present = All participants are marked.
+select-present = Please select the participants who came:
+present-button = Marked
+nobody-present = Nobody is marked yet.
+present-list = Marked participants, tap one to remove the mark:
+
+## Paid events
+
+bought-before = <b>You have already bought: { $count }</b>
+booking-fee = <b>Booking fee: { $order }, total { $total }</b>
+order-adults = { $count } { $count ->
+        [one] adult
+       *[other] adults
+    }
+order-children = { $count } { $count ->
+        [one] child
+       *[other] children
+    }
+pay-approved-hint = Press "Pay" to pay for the approved tickets. The name you entered will be on the ticket.
+apply-hint = Choose the number of tickets and press "Apply". Once the organizer approves the application, the tickets can be paid for.
+pay-hint = Choose the number of tickets and press "Pay". The name you entered will be on the ticket.
+book = Book +1
+book-adult = Book adult +1
+book-child = Book child +1
+unbook = Remove -1
+unbook-adult = Remove adult -1
+unbook-child = Remove child -1
+pay-button = Pay
+apply-button = Apply
+invoice-title = Tickets: { $order }
+donation-title = Donation
+donation-description = Support the "Vienna Stories" channel
+amount-due = Amount due: { $amount }
+crypto-invoice =
+    Amount due: { $amount } ({ $crypto_amount })
+    Transfer address: <code>{ $address }</code>
+    The invoice is valid until { $valid_until }.
+pay-link = Pay
+promo-code-applied = Promo code { $code }: { $discount } off.
+promo-code-button = Promo code
+send-promo-code = Send the promo code as a message.
+refund-pending = <b>Refund request for { $amount } is under review.</b>
+refund-button = Refund tickets ({ $percent }%)
+refunds-button = Refunds
+no-refund-requests = No refund requests.
+refund-requests = Refund requests, after approval return the money via the payment system:
+refund-links = Return the money: { $links }
+refund-link = <a href="{ $url }">link</a>
+
+## Waiting list, tickets and questionnaire
+
+seats-held = <b>Seats available for you: { $seats }. { $paid ->
+        [yes] Pay for the tickets
+       *[no] Confirm your participation
+    } by { $hold_until }.</b>
+pay-seat = Pay for the seat
+accept-seat = Confirm participation
+leave-waiting-list = Leave the waiting list
+sign-up-first = Please sign up for the event first.
+name-attendees = <b>Please enter the names of the attendees.</b>
+attendees-button = Attendee names
+tickets-button = Tickets
+seat = Seat { $seat }
+no-tickets = You have no tickets.
+my-bookings = Your bookings:
+no-bookings = You have no bookings for upcoming events. /start - list of events
+booking-confirmed = ✅ Booked: { $seats }{ $paid ->
+        [yes] , paid
+       *[no] {""}
+    }
+booking-unpaid = 💳 Awaiting payment: { $seats }
+booking-pending = 📝 Application under review: { $seats }
+booking-waiting = ⏳ Waiting list: { $seats }
+booking-attended = You attended the event.
+cancel-booking-button = Cancel booking
+calendar-button = 📅 Add to calendar
+past-bookings-button = Past events
+upcoming-bookings-button = Upcoming events
+calendar-file-button = 📅 Calendar file
+calendar-file = Open the file to add the event to your calendar.
+calendar-hint = /calendar - subscribe to all your bookings.
+calendar-subscription =
+    Add this link to your calendar app as a subscription, it will show all your confirmed bookings:
+    { $url }
+    The link is personal. /calendar reset - issue a new link, the old one stops working.
+calendar-unavailable = Calendar subscription is not available.
+my-tickets = Tickets: { $count }. Show the QR code at the entrance.
+ask-attendee =
+    Attendee name, seat { $seat } of { $seats }:
+    Send the first and last name as a message. An email can be added after a comma, for example: John Smith, john@example.com
+invalid-name = Invalid name, please try again.
+attendees = Attendees:
+next-attendee = Send the name of the next attendee as a message.
+edit-button = Edit
+fill-questionnaire = <b>Please fill in the participant questionnaire.</b>
+questionnaire-button = Questionnaire
+answers-button = Questionnaires
+questionnaire-complete = Thank you, the questionnaire is complete.
+question = Questionnaire, question { $number } of { $count }:
+send-answer = Send the answer as a message.
+send-phone = Send the phone number as a message.
+send-email = Send the email address as a message.
+choose-option = Choose an answer.
+choose-options = Select the matching options and press "Done".
+done-button = Done
+skip-button = Skip
+question-not-found = Question not found.
+option-not-found = Answer option not found.
+question-required = This question is required.
+use-option-buttons = Please choose an answer with the buttons.
+invalid-phone = Invalid phone number, please try again.
+invalid-email = Invalid email address, please try again.
+empty-answer = Empty answer, please try again.
+no-answers = No questionnaires.
+
+## Check-in
+
+check-in-button = Ticket check
+check-in =
+    Ticket check: { $checked_in } of { $total } checked in.
+    Scan the ticket QR code with the camera, send a photo of it or the code as a message.
+stop-check-in = Finish check
+checked-ticket = seat { $seat }, <a href="tg://user?id={ $user_id }">participant</a>
+ticket-valid = <b>Come in</b>: { $ticket }.
+ticket-already-checked-in = <b>Ticket already checked in</b> { $ts }: { $ticket }.
+ticket-cancelled = <b>Booking cancelled or not confirmed</b>: { $ticket }.
+ticket-wrong-event = <b>Ticket for another event</b>: { $name }.
+ticket-unknown = <b>Unknown ticket</b>.
+ticket-code-invalid = <b>Could not recognize the ticket code</b>.
+qr-code-not-found = <b>No QR code found in the photo</b>.
+
+## Administration
+
+donation-totals = Donations: { $count }, total { $amount }
+payment-totals =
+    Payments: { $tickets }, total { $ticket_amount }
+    Refunds: { $refunds }, total { $refund_amount }
+    Net: { $total }
+unmatched-payment = Payment without a booking: user { $user_id }, { $amount }, { $charge_id }
+unrecorded-charge = Booking without a ledger entry: { $charge_id }
+promo-codes = Promo codes:
+promo-code-line = { $code } - { $discount }, event: { $event }, used: { $uses }
+all-events = all
+promo-code-max-uses = of { $max }
+promo-code-expires = until { $expires }
+yes = yes
+no = no
+confirm-unban =
+    Ban reason: { $reason }
+    Remove user <a href="tg://user?id={ $user_id }">{ $user_id }</a> from the black list?
+black-list = Black list. Tap a button to remove a user from the list.
+black-list-empty = The black list is empty.
+template-empty = The template is empty.
+template-too-long = The template is longer than { $max } characters.
+template-unbalanced = The template has an unclosed curly brace.
+template-unknown-placeholder = Unknown placeholder { $name }, available: { $placeholders }
+template-no-text = The template must contain the {"{"}text{"}"} placeholder.
+template-wrong-target = Specify the template kind: reminder, waiting_list or send and the event, 0 for all events.
+template-default = The default text is used.
+template-placeholders = Placeholders: { $placeholders }
+template-saved = The template has been saved, the message will look like this:
+template-deleted = The template has been deleted.
+template-not-found = Template not found.
+admin-help =
+    Add an event:
+    {"{"} "name":"test", "link":"https://t.me/storiesvienna/21", "start":"2022-05-29 15:00 +02:00", "max_adults":15, "max_children":15, "max_adults_per_reservation":15, "max_children_per_reservation":15, "currency":"EUR" {"}"}
+
+    Edit: add "id":<event> to the command above
+
+    Reminders (default from the configuration): add "reminders":[{"{"}"before":"1d"{"}"}, {"{"}"before":"2h", "template":"{"{"}name{"}"} starts at {"{"}start{"}"}"{"}"}] to the command above
+
+    Registration period: add "registration_opens_at":"2022-05-20 10:00 +02:00", "registration_closes_at":"2022-05-29 12:00 +02:00" to the command above
+
+    Registration by application: add "requires_approval":true to the command above
+
+    Participant questionnaire: add "questions":[{"{"}"text":"Phone", "kind":"phone", "required":true{"}"}, {"{"}"text":"T-shirt size", "kind":"single_choice", "options":["S", "M", "L"]{"}"}] to the command above, question kinds: text, single_choice, multiple_choice, phone, email
+
+    Named tickets: add "named_tickets":true to the command above
+
+    Waiting list seats offered in turn with a 2 hour hold: add "waiting_list_hold":"2h" to the command above
+
+    Ticket refunds, in full until 2 days before the start, then 50%: add "refund_deadline":"2d", "late_refund_percent":50 to the command above
+
+    Venue for message templates: add "venue":"Cafe, 1 Main Street" to the command above
+
+    Organizer and tags for the public event feed: add "organization":"Board game club", "tags":["games", "kids"] to the command above
+
+    Ticket prices: add "adult_ticket_price":200, "child_ticket_price":100 in the chosen currency to the command above
+
+    Send a message:
+    /send confirmed <event> text
+    /send waiting <event> text
+    /broadcast <audience> [in <delay, e.g. 2h>] text - with a preview, audience: all, attended <event>, no_show <event>, tag <tag>, organization <organization>
+    Reply with /send or /broadcast to a photo, document, video, animation, audio or poll to send it along, the text becomes its caption
+    /report <message> - recipients, delivered, failed and pending
+    /feedback [event] - ratings from the survey after events
+
+    Black list:
+    /ban <user>
+    /show_black_list
+
+    /delete_event <event>
+    /delete_link <url>
+    /delete_reservation <event> <user>
+    /set_group_leader <event> <user>
+    /approve <event> <user>
+    /reject <event> <user> [reason]
+    /export_answers <event>
+    /export_tickets <event>
+    /payments <event> - payments and refunds, 0 - donations
+    /add_promo <code> <10% or 5.50> [<event> [<max uses> [<valid for, e.g. 30d>]]] - 0 for all events and no limit
+    /delete_promo <code>
+    /promo_codes
+    /attendance <event>
+    /set_event_limits <event> <max_adults> <max_children>
+
+    Templates of reminders, waiting list prompts and /send messages, <event> 0 for all events:
+    /template <reminder|waiting_list|send> <event>
+    /preview_template <reminder|waiting_list|send> <event> <template>
+    /set_template <reminder|waiting_list|send> <event> <template>
+    /delete_template <reminder|waiting_list|send> <event>
+    Placeholders: {"{"}name{"}"} {"{"}start{"}"} {"{"}link{"}"} {"{"}venue{"}"} {"{"}seats{"}"} {"{"}user{"}"}, in /send messages also {"{"}sender{"}"} and the required {"{"}text{"}"}
@@ -0,0 +1,391 @@
+## Event card
+
+event-start = Начало: { $start }.
+starts-at = Начало: { $start }
+event-limits = Мероприятие { $id } / { $adults }({ $children })
+free-seats = Свободные места: { $seats }
+free-adult-seats = Взрослые свободные места: { $seats }
+free-child-seats = Детские свободные места: { $seats }
+registration-closes = Запись до { $closes }.
+registration-opens = Запись откроется { $opens }.
+registration-closed = Запись остановлена.
+participants = Записались { $adults }({ $children }):
+event-messages = Cообщения по мероприятию
+messages-for-reserved = для забронировавших
+messages-for-waiting-list = для списка ожидания
+attendance-line = { $title }: пришли { $present } из { $reserved }, не пришли { $no_show }.
+attendance-participants = Участники
+attendance-seats = Места
+attendance-adults = Взрослые
+attendance-children = Дети
+reminder =
+    Здравствуйте!
+    Не забудьте, пожалуйста, что вы записались на
+    <a href="{ $link }">{ $name }</a>
+    Начало: { $start }
+    Пожалуйста, вовремя откажитесь от мест, если ваши планы изменились.
+
+## Notifications and booking errors
+
+to-event = К мероприятию
+payment-received = Оплата получена, ваши билеты:
+payment-received-tickets = Оплата получена, ваши билеты ниже. Они также доступны по команде /my_tickets
+waiting-list-prompt =
+    Кто-то отменил бронирование на мероприятие: "{ $name }".
+    Вы можете попробовать записаться.
+seat-offered =
+    Освободилось место на мероприятие { $title } (Начало: { $start }).
+    Место закреплено за вами до { $hold_until }, пожалуйста, { $paid ->
+        [yes] оплатите билет
+       *[no] подтвердите участие
+    } на странице мероприятия. Иначе оно перейдёт следующему в листе ожидания.
+offer-expired = Время брони места на мероприятие "{ $name }" истекло, место передано следующему в листе ожидания.
+approval-expired = Срок оплаты по одобренной заявке на мероприятие "{ $name }" истёк, места освобождены.
+please-pay = Пожалуйста, оплатите билет.
+hold-expired = Время брони истекло.
+no-seats-left = К сожалению, свободные места закончились.
+time-conflict = Вы уже записаны на другое мероприятие в это время.
+application-exists = Заявка уже подана.
+application-not-approved = Заявка не одобрена.
+application-approved = { $paid ->
+        [yes]
+            Ваша заявка на мероприятие { $title } (Начало: { $start }) одобрена.
+            Пожалуйста, оплатите билеты до { $hold_until }, иначе места будут освобождены.
+       *[no] Ваша заявка на мероприятие { $title } (Начало: { $start }) одобрена.
+    }
+application-rejected = К сожалению, ваша заявка на мероприятие { $title } (Начало: { $start }) отклонена.
+application-rejected-reason =
+    К сожалению, ваша заявка на мероприятие { $title } (Начало: { $start }) отклонена.
+    Причина: { $reason }
+no-paid-tickets = Нет оплаченных билетов.
+refund-impossible = Возврат билетов на это мероприятие уже невозможен.
+refund-approved = Возврат билетов на мероприятие { $title } (Начало: { $start }) одобрен: { $amount }.
+refund-rejected = К сожалению, возврат билетов на мероприятие { $title } (Начало: { $start }) отклонён.
+promo-code-invalid = Промокод недействителен.
+event-message =
+    <a href="tg://user?id={ $sender_id }">{ $sender }</a>:
+    Сообщение по мероприятию { $title } (Начало: { $start })
+    { $text }
+broadcast-message =
+    <a href="tg://user?id={ $sender_id }">{ $sender }</a>:
+    { $text }
+broadcast-usage =
+    /broadcast <получатели> [in <задержка, например 2h>] текст
+    Получатели: all, attended <event>, no_show <event>, tag <тег>, organization <организатор>
+broadcast-no-recipients = Некому отправить: { $audience }.
+broadcast-preview =
+    Сообщение для { $audience }, получателей: { $recipients }, отправка в { $start }:
+
+    { $text }
+broadcast-media = Вложение: { $kind }
+broadcast-send-button = Отправить
+broadcast-cancel-button = Отменить
+broadcast-scheduled = Сообщение поставлено в очередь для { $recipients } получателей, отправка в { $start }.
+broadcast-cancelled = Сообщение отменено.
+broadcast-not-found = Сообщение уже отправлено или отменено.
+delivery-report = Сообщение { $message_id }: получателей { $targeted }, доставлено { $delivered }, не доставлено { $failed }, в очереди { $pending }.
+delivery-report-failure = { $reason }: { $count }
+delivery-report-updated = По состоянию на { $time }
+delivery-report-button = Отчёт о доставке
+delivery-report-not-found = Сообщение не найдено.
+subscription-new-event = Новое мероприятие { $title } (Начало: { $start }).
+feedback-request = Как прошло мероприятие { $title } (Начало: { $start })? Пожалуйста, оцените его от 1 до 5.
+feedback-thanks = Спасибо за оценку! Можете отправить комментарий сообщением.
+feedback-skip-button = Без комментария
+feedback-done = Спасибо за отзыв!
+feedback-comment-too-long = Комментарий слишком длинный, не больше { $max } символов.
+feedback-unavailable = Опрос недоступен.
+feedback-summary = { $name } ({ $start }): { $average }⭐, ответили { $responses } из { $surveyed }, комментариев { $comments }
+feedback-rating = { $rating }⭐: { $count }
+feedback-no-responses = Ответов пока нет.
+no-feedback = Опросов пока нет.
+
+## Commands
+
+help =
+    Здесь вы можете бронировать места на мероприятия.
+
+    /start - показать список мероприятий
+    /my - мои брони
+    /calendar - подписка на календарь
+    /subscriptions - новые мероприятия организаторов и тегов
+    /my_tickets - мои билеты
+    /language - язык бота
+    /help - эта подсказка
+    <a href="{ $help }">Подробная инструкция</a>
+    /donate - поддержать канал.
+language-usage =
+    Язык бота: русский.
+    /language ru - русский
+    /language en - English
+language-set = Язык бота: русский.
+event-list =
+    Программа
+    время / взросл.(детск.) места  / мероприятие
+    <a href="{ $help }">инструкция</a> /donate
+no-events = Нет мероприятий.
+subscription-button = 🔔 Подписаться
+subscription-choose = Получать сообщения о новых мероприятиях организатора или с тегом:
+subscription-organization = Организатор: { $name }
+subscription-tag = #{ $tag }
+subscription-on = ✅ { $topic }
+subscription-off = 🔔 { $topic }
+subscription-remove = ❌ { $topic }
+subscriptions = Вы получаете сообщения о новых мероприятиях:
+no-subscriptions = У вас нет подписок. Откройте мероприятие и нажмите "🔔 Подписаться", чтобы узнавать о новых мероприятиях его организатора или с его тегами.
+
+## Event screen
+
+booking-blocked = Извините, но бронирование невозможно, поскольку ранее Вы не использовали и не отменили бронь. Если это ошибка, пожалуйста, свяжитесь с <a href="tg://user?id={ $support }">поддержкой</a> и сообщите код { $code }. <a href="{ $help }">Инструкция</a>.
+late-cancel-banned =
+    ВНИМАНИЕ!
+    К сожалению, вы отказались от билетов слишком поздно и не сможете больше бронировать бесплатные билеты.
+late-wont-go = К сожалению, вы отказываетесь от билетов слишком поздно и не сможете больше бронировать бесплатные билеты.
+wont-go = Мы сожалеем, что вы не сможете пойти. Увидимся в другой раз. Спасибо!
+attachment-has-digits =
+    ВНИМАНИЕ!
+    Ваше примечание содержит цифры. Они никак не влияют на количество забронированных мест. Количество мест можно менять только кнопками "Записать/Отписать".
+attachment = Примечание: { $attachment }.
+seats-hint = Количество мест можно менять кнопками "Записаться/Отписаться". Примечание к брони можно добавить, послав сообщение боту.
+my-reserved = <b>У вас забронировано: { $count }</b>
+my-waiting = <b>У вас в списке ожидания: { $count }</b>
+my-pending = <b>Ваша заявка на рассмотрении: { $count }</b>
+my-approved = <b>Ваша заявка одобрена, ожидает оплаты: { $count }</b>
+sign-up = Записаться +1
+sign-up-adult = Записать взрослого +1
+sign-up-child = Записать ребёнка +1
+wait = В лист ожидания +1
+wait-adult = В лист ожидания взрослого +1
+wait-child = В лист ожидания ребёнка +1
+cancel-seat = Отписаться -1
+cancel-adult = Отписать взрослого -1
+cancel-child = Отписать ребёнка -1
+event-list-button = Список мероприятий
+waiting-list-button = Список ожидания
+applications-button = Заявки
+presence-button = Присутствие
+close-registration = Остановить запись
+open-registration = Разрешить запись
+back = Назад
+waiting-list = Список ожидания:
+waiting-list-empty = Пустой список ожидания.
+send-rejection-reason = Отправьте причину отказа сообщением, она будет передана автору заявки.
+reject-without-reason = Отклонить без причины
+no-applications = Нет заявок.
+applications = Заявки на участие:
+all-present = Все участники отмечены.
+select-present = Пожалуйста, выберите присутствующих:
+present-button = Отмеченные
+nobody-present = Пока никто не отмечен.
+present-list = Отмеченные участники, нажмите, чтобы снять отметку:
+
+## Paid events
+
+bought-before = <b>Вы ранее купили: { $count }</b>
+booking-fee = <b>Сбор за бронирование: { $order }, всего { $total }</b>
+order-adults = { $count } взросл.
+order-children = { $count } детск.
+pay-approved-hint = Нажмите "К оплате", чтобы оплатить одобренные билеты. Введённое имя будет на билете.
+apply-hint = Выберите необходимое количество билетов и нажмите "Подать заявку". После одобрения заявки организатором билеты можно будет оплатить.
+pay-hint = Выберите необходимое количество билетов и нажмите "К оплате". Введённое имя будет на билете.
+book = Забронировать +1
+book-adult = Забронировать взрослый +1
+book-child = Забронировать детский +1
+unbook = Отменить -1
+unbook-adult = Отменить взрослый -1
+unbook-child = Отменить детский -1
+pay-button = К оплате
+apply-button = Подать заявку
+invoice-title = Билеты: { $order }
+donation-title = Донат
+donation-description = Поддержать работу канала "Венские Истории"
+amount-due = К оплате: { $amount }
+crypto-invoice =
+    К оплате: { $amount } ({ $crypto_amount })
+    Адрес для перевода: <code>{ $address }</code>
+    Счёт действителен до { $valid_until }.
+pay-link = Оплатить
+promo-code-applied = Промокод { $code }: скидка { $discount }.
+promo-code-button = Промокод
+send-promo-code = Отправьте промокод сообщением.
+refund-pending = <b>Запрос на возврат { $amount } на рассмотрении.</b>
+refund-button = Вернуть билеты ({ $percent }%)
+refunds-button = Возвраты
+no-refund-requests = Нет запросов на возврат.
+refund-requests = Запросы на возврат, после одобрения верните деньги через платёжную систему:
+refund-links = Верните деньги: { $links }
+refund-link = <a href="{ $url }">ссылка</a>
+
+## Waiting list, tickets and questionnaire
+
+seats-held = <b>Для вас освободилось мест: { $seats }. { $paid ->
+        [yes] Оплатите билеты
+       *[no] Подтвердите участие
+    } до { $hold_until }.</b>
+pay-seat = Оплатить место
+accept-seat = Подтвердить участие
+leave-waiting-list = Покинуть лист ожидания
+sign-up-first = Сначала запишитесь на мероприятие.
+name-attendees = <b>Пожалуйста, укажите имена участников.</b>
+attendees-button = Имена участников
+tickets-button = Билеты
+seat = Место { $seat }
+no-tickets = У вас нет билетов.
+my-bookings = Ваши брони:
+no-bookings = У вас нет броней на предстоящие мероприятия. /start - список мероприятий
+booking-confirmed = ✅ Забронировано: { $seats }{ $paid ->
+        [yes] , оплачено
+       *[no] {""}
+    }
+booking-unpaid = 💳 Ожидает оплаты: { $seats }
+booking-pending = 📝 Заявка на рассмотрении: { $seats }
+booking-waiting = ⏳ Лист ожидания: { $seats }
+booking-attended = Вы были на мероприятии.
+cancel-booking-button = Отменить бронь
+calendar-button = 📅 Добавить в календарь
+past-bookings-button = Прошедшие мероприятия
+upcoming-bookings-button = Предстоящие мероприятия
+calendar-file-button = 📅 Файл календаря
+calendar-file = Откройте файл, чтобы добавить мероприятие в календарь.
+calendar-hint = /calendar - подписка на все ваши брони.
+calendar-subscription =
+    Добавьте эту ссылку в приложение календаря как подписку, в нём появятся все ваши подтверждённые брони:
+    { $url }
+    Ссылка личная. /calendar reset - выпустить новую ссылку, старая перестанет работать.
+calendar-unavailable = Подписка на календарь недоступна.
+my-tickets = Билетов: { $count }. Покажите QR-код на входе.
+ask-attendee =
+    Имя участника, место { $seat } из { $seats }:
+    Отправьте имя и фамилию сообщением. Email можно указать через запятую, например: Иван Петров, ivan@example.com
+invalid-name = Неверное имя, попробуйте ещё раз.
+attendees = Участники:
+next-attendee = Отправьте имя следующего участника сообщением.
+edit-button = Изменить
+fill-questionnaire = <b>Пожалуйста, заполните анкету участника.</b>
+questionnaire-button = Анкета
+answers-button = Анкеты
+questionnaire-complete = Спасибо, анкета заполнена.
+question = Анкета, вопрос { $number } из { $count }:
+send-answer = Отправьте ответ сообщением.
+send-phone = Отправьте номер телефона сообщением.
+send-email = Отправьте адрес электронной почты сообщением.
+choose-option = Выберите вариант ответа.
+choose-options = Отметьте подходящие варианты и нажмите "Готово".
+done-button = Готово
+skip-button = Пропустить
+question-not-found = Вопрос не найден.
+option-not-found = Вариант ответа не найден.
+question-required = Это обязательный вопрос.
+use-option-buttons = Пожалуйста, выберите вариант ответа кнопками.
+invalid-phone = Неверный номер телефона, попробуйте ещё раз.
+invalid-email = Неверный адрес электронной почты, попробуйте ещё раз.
+empty-answer = Пустой ответ, попробуйте ещё раз.
+no-answers = Нет анкет.
+
+## Check-in
+
+check-in-button = Проверка билетов
+check-in =
+    Проверка билетов: отмечено { $checked_in } из { $total }.
+    Отсканируйте QR-код билета камерой, отправьте его фото или код сообщением.
+stop-check-in = Завершить проверку
+checked-ticket = место { $seat }, <a href="tg://user?id={ $user_id }">участник</a>
+ticket-valid = <b>Проходите</b>: { $ticket }.
+ticket-already-checked-in = <b>Билет уже отмечен</b> { $ts }: { $ticket }.
+ticket-cancelled = <b>Бронь отменена или не подтверждена</b>: { $ticket }.
+ticket-wrong-event = <b>Билет на другое мероприятие</b>: { $name }.
+ticket-unknown = <b>Неизвестный билет</b>.
+ticket-code-invalid = <b>Не удалось распознать код билета</b>.
+qr-code-not-found = <b>QR-код на фото не найден</b>.
+
+## Administration
+
+donation-totals = Донаты: { $count }, всего { $amount }
+payment-totals =
+    Оплаты: { $tickets }, всего { $ticket_amount }
+    Возвраты: { $refunds }, всего { $refund_amount }
+    Итого: { $total }
+unmatched-payment = Оплата без брони: пользователь { $user_id }, { $amount }, { $charge_id }
+unrecorded-charge = Бронь без записи в журнале: { $charge_id }
+promo-codes = Промокоды:
+promo-code-line = { $code } - { $discount }, мероприятие: { $event }, использован: { $uses }
+all-events = все
+promo-code-max-uses = из { $max }
+promo-code-expires = до { $expires }
+yes = да
+no = нет
+confirm-unban =
+    Причина бана: { $reason }
+    Удалить пользавателя <a href="tg://user?id={ $user_id }">{ $user_id }</a> из чёрного списка?
+black-list = Чёрный список. Нажмите кнопку чтобы удалить из списка.
+black-list-empty = Чёрный список пуст.
+template-empty = Шаблон пуст.
+template-too-long = Шаблон длиннее { $max } символов.
+template-unbalanced = В шаблоне не закрыта фигурная скобка.
+template-unknown-placeholder = Неизвестная подстановка { $name }, можно использовать: { $placeholders }
+template-no-text = В шаблоне должна быть подстановка {"{"}text{"}"}.
+template-wrong-target = Укажите вид шаблона: reminder, waiting_list или send и мероприятие, 0 - для всех мероприятий.
+template-default = Используется текст по умолчанию.
+template-placeholders = Подстановки: { $placeholders }
+template-saved = Шаблон сохранён, так будет выглядеть сообщение:
+template-deleted = Шаблон удалён.
+template-not-found = Шаблон не найден.
+admin-help =
+    Добавить мероприятие:
+    {"{"} "name":"тест", "link":"https://t.me/storiesvienna/21", "start":"2022-05-29 15:00 +02:00", "max_adults":15, "max_children":15, "max_adults_per_reservation":15, "max_children_per_reservation":15, "currency":"EUR" {"}"}
+
+    Отредактировать: добавьте "id":<event> в команду выше
+
+    Напоминания (по умолчанию из конфигурации): добавьте "reminders":[{"{"}"before":"1d"{"}"}, {"{"}"before":"2h", "template":"{"{"}name{"}"} начнётся в {"{"}start{"}"}"{"}"}] в команду выше
+
+    Период записи: добавьте "registration_opens_at":"2022-05-20 10:00 +02:00", "registration_closes_at":"2022-05-29 12:00 +02:00" в команду выше
+
+    Запись по заявкам: добавьте "requires_approval":true в команду выше
+
+    Анкета участника: добавьте "questions":[{"{"}"text":"Телефон", "kind":"phone", "required":true{"}"}, {"{"}"text":"Размер футболки", "kind":"single_choice", "options":["S", "M", "L"]{"}"}] в команду выше, типы вопросов: text, single_choice, multiple_choice, phone, email
+
+    Именные билеты: добавьте "named_tickets":true в команду выше
+
+    Места из листа ожидания по очереди с бронью на 2 часа: добавьте "waiting_list_hold":"2h" в команду выше
+
+    Возврат билетов: полный за 2 дня до начала, затем 50%: добавьте "refund_deadline":"2d", "late_refund_percent":50 в команду выше
+
+    Место проведения для шаблонов сообщений: добавьте "venue":"Кафе, Главная улица 1" в команду выше
+
+    Организатор и теги для публичной ленты мероприятий: добавьте "organization":"Клуб настольных игр", "tags":["игры", "дети"] в команду выше
+
+    Цены билетов: добавьте "adult_ticket_price":200, "child_ticket_price":100 в выбранной валюте в команду выше
+
+    Послать сообщение:
+    /send confirmed <event> текст
+    /send waiting <event> текст
+    /broadcast <получатели> [in <задержка, например 2h>] текст - с предпросмотром, получатели: all (все пользователи бота), attended <event> (пришедшие), no_show <event> (не пришедшие), tag <тег>, organization <организатор> (подписчики)
+    Ответьте командой /send или /broadcast на фото, документ, видео, анимацию, аудио или опрос, чтобы отправить их вместе с сообщением, текст станет подписью
+    /report <message> - получатели, доставленные, недоставленные и ожидающие отправки
+    /feedback [event] - оценки из опроса после мероприятий
+
+    Чёрный список:
+    /ban <user>
+    /show_black_list
+
+    /delete_event <event>
+    /delete_link <url>
+    /delete_reservation <event> <user>
+    /set_group_leader <event> <user>
+    /approve <event> <user>
+    /reject <event> <user> [причина]
+    /export_answers <event>
+    /export_tickets <event>
+    /payments <event> - оплаты и возвраты, 0 - донаты
+    /add_promo <code> <10% или 5.50> [<event> [<max uses> [<срок, например 30d>]]] - 0 для всех мероприятий и без ограничения
+    /delete_promo <code>
+    /promo_codes
+    /attendance <event>
+    /set_event_limits <event> <max_adults> <max_children>
+
+    Шаблоны напоминаний, приглашений из списка ожидания и сообщений /send, <event> 0 - для всех мероприятий:
+    /template <reminder|waiting_list|send> <event>
+    /preview_template <reminder|waiting_list|send> <event> <шаблон>
+    /set_template <reminder|waiting_list|send> <event> <шаблон>
+    /delete_template <reminder|waiting_list|send> <event>
+    Подстановки: {"{"}name{"}"} {"{"}start{"}"} {"{"}link{"}"} {"{"}venue{"}"} {"{"}seats{"}"} {"{"}user{"}"}, в сообщениях /send ещё {"{"}sender{"}"} и обязательно {"{"}text{"}"}
@@ -1,17 +1,28 @@
+use crate::broadcasts;
 use crate::configuration::config::Config;
 use crate::db;
+use crate::feedback;
 use crate::format;
+use crate::i18n::Lang;
 use crate::message_handler;
 use crate::message_handler::CallbackQuery;
+use crate::payments;
+use crate::promo_codes;
+use crate::questionnaire;
 use crate::reply::*;
-use crate::types::{Connection, Context, Event, MessageType, User};
+use crate::templates::{self, TemplateKind};
+use crate::tickets;
+use crate::types::{
+    is_valid_tags, Connection, Context, Event, Media, PromoCode, Question, Reminder, User,
+};
+use crate::util::parse_duration;
 use anyhow::anyhow;
 use chrono::DateTime;
 
 use std::env;
 use teloxide::{
     types::{InlineKeyboardButton, ParseMode},
-    utils::markdown,
+    utils::{html, markdown},
 };
 
 #[derive(Deserialize, Serialize, Clone, Default, Debug)]
@@ -20,7 +31,7 @@ struct NewEvent {
     name: String,
     link: String,
     start: String,
-    remind: String,
+    reminders: Option<Vec<NewReminder>>,
     max_adults: u64,
     max_children: u64,
     max_adults_per_reservation: u64,
@@ -28,13 +39,31 @@ struct NewEvent {
     adult_ticket_price: Option<f64>,
     child_ticket_price: Option<f64>,
     currency: String,
+    registration_opens_at: Option<String>,
+    registration_closes_at: Option<String>,
+    requires_approval: Option<bool>,
+    questions: Option<Vec<Question>>,
+    named_tickets: Option<bool>,
+    waiting_list_hold: Option<String>,
+    refund_deadline: Option<String>,
+    late_refund_percent: Option<u64>,
+    venue: Option<String>,
+    organization: Option<String>,
+    tags: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+struct NewReminder {
+    before: String,
+    template: Option<String>,
 }
 
-/// Command line processor.
+/// Command line processor, `media` is the message the command replies to.
 pub fn handle_message(
     conn: &Connection,
     user: &User,
     data: &str,
+    media: Option<Media>,
     ctx: &Context,
 ) -> anyhow::Result<Reply> {
     let pars: Vec<&str> = data.splitn(4, ' ').collect();
@@ -55,31 +84,47 @@ pub fn handle_message(
                 if let Ok(event_id) = pars[2].parse::<u64>() {
                     match db::get_event(conn, event_id, user.id.0) {
                         Ok(s) => {
-                            let text = format!(
-                                    "<a href=\"tg://user?id={}\">{}</a>:\nСообщение по мероприятию {} (Начало: {})\n{}",
-                                    user.id.0,
-                                    user.user_name1,
-                                    format::event_title(&s.event),
-                                    format::ts(s.event.ts),
-                                    pars[3].to_string()
-                                );
+                            let text = templates::text(
+                                conn,
+                                TemplateKind::Message,
+                                event_id,
+                                &s.event,
+                                text!(
+                                    "event-message",
+                                    sender_id = user.id.0.to_string(),
+                                    sender = user.user_name1.as_str(),
+                                    title = format::event_title(&s.event),
+                                    start = format::ts(s.event.ts),
+                                    text = pars[3],
+                                ),
+                            )?
+                            .arg("sender", user.user_name1.as_str())
+                            .arg("text", pars[3]);
 
-                            if db::enqueue_message(
+                            if let Ok(message_id) = db::enqueue_media_message(
                                 conn,
                                 event_id,
                                 &user.user_name1,
                                 waiting_list,
-                                MessageType::Direct,
                                 &text,
+                                media.as_ref(),
                                 crate::util::get_unix_time(),
-                            )
-                            .is_ok()
-                            {
-                                return Ok(ReplyMessage::new(format!(
+                            ) {
+                                let mut reply = ReplyMessage::new(format!(
                                     "The following message has been scheduled for sending:\n{}",
-                                    text
-                                ))
-                                .into());
+                                    text.render(user.lang)
+                                ));
+                                if let Some(media) = &media {
+                                    reply = reply.text(Some(format!(
+                                        "\n{}",
+                                        t!(user.lang, "broadcast-media", kind = media.kind())
+                                    )));
+                                }
+                                return Ok(reply
+                                    .keyboard(vec![vec![broadcasts::report_button(
+                                        user.lang, message_id,
+                                    )?]])
+                                    .into());
                             } else {
                                 return Ok(ReplyMessage::new("Failed to send message.").into());
                             }
@@ -91,6 +136,29 @@ pub fn handle_message(
                 }
             }
         }
+        "/feedback" => {
+            // /feedback [event]
+            return match pars.get(1).map(|event_id| event_id.parse::<u64>()) {
+                Some(Ok(event_id)) => feedback::show_responses(conn, user, event_id, 0, ctx),
+                Some(Err(_)) => Err(anyhow!("Wrong event id")),
+                None => feedback::show_summaries(conn, user, 0, ctx),
+            };
+        }
+        "/report" if pars.len() == 2 => {
+            if let Ok(message_id) = pars[1].parse::<u64>() {
+                return broadcasts::report(conn, user, message_id, ctx);
+            }
+        }
+        "/broadcast" => {
+            // /broadcast <all|attended <event>|no_show <event>|tag <tag>|organization <name>> [in <delay>] text
+            return broadcasts::prepare(
+                conn,
+                user,
+                data.trim_start_matches("/broadcast"),
+                media,
+                ctx,
+            );
+        }
         "/ban" if pars.len() == 2 => {
             if let Ok(user_id) = pars[1].parse::<u64>() {
                 if db::add_to_black_list(
@@ -103,7 +171,7 @@ pub fn handle_message(
                 {
                     error!("Failed to add user {} to black list", user_id);
                 }
-                return show_black_list(conn, &ctx.config, 0);
+                return show_black_list(conn, &ctx.config, 0, user.lang);
             }
         }
         "/remove_from_black_list" if pars.len() == 2 => {
@@ -111,7 +179,7 @@ pub fn handle_message(
                 if db::remove_from_black_list(conn, user_id).is_ok() == false {
                     error!("Failed to remove user {} from black list", user_id);
                 }
-                return show_black_list(conn, &ctx.config, 0);
+                return show_black_list(conn, &ctx.config, 0, user.lang);
             }
         }
         "/delete_event" if pars.len() == 2 => {
@@ -164,8 +232,189 @@ pub fn handle_message(
                 };
             }
         }
+        "/approve" if pars.len() == 3 => {
+            if let (Ok(event_id), Ok(user_id)) = (pars[1].parse::<u64>(), pars[2].parse::<u64>()) {
+                match db::approve_reservation(conn, event_id, user_id) {
+                    Ok(_) => {
+                        return Ok(ReplyMessage::new("Application approved.").into());
+                    }
+                    Err(e) => {
+                        return Err(anyhow!("Failed to approve application: {}.", e));
+                    }
+                };
+            }
+        }
+        "/reject" if pars.len() >= 3 => {
+            if let (Ok(event_id), Ok(user_id)) = (pars[1].parse::<u64>(), pars[2].parse::<u64>()) {
+                match db::reject_reservation(conn, event_id, user_id, pars.get(3).copied()) {
+                    Ok(_) => {
+                        return Ok(ReplyMessage::new("Application rejected.").into());
+                    }
+                    Err(e) => {
+                        return Err(anyhow!("Failed to reject application: {}.", e));
+                    }
+                };
+            }
+        }
+        "/export_answers" if pars.len() == 2 => {
+            if let Ok(event_id) = pars[1].parse::<u64>() {
+                match questionnaire::export_answers(conn, event_id) {
+                    Ok(csv) => {
+                        return Ok(
+                            ReplyMessage::new(format!("<pre>{}</pre>", html::escape(&csv))).into(),
+                        );
+                    }
+                    Err(e) => {
+                        return Err(anyhow!("Failed to export answers: {}.", e));
+                    }
+                };
+            }
+        }
+        "/export_tickets" if pars.len() == 2 => {
+            if let Ok(event_id) = pars[1].parse::<u64>() {
+                match tickets::export_tickets(conn, event_id) {
+                    Ok(csv) => {
+                        return Ok(
+                            ReplyMessage::new(format!("<pre>{}</pre>", html::escape(&csv))).into(),
+                        );
+                    }
+                    Err(e) => {
+                        return Err(anyhow!("Failed to export tickets: {}.", e));
+                    }
+                };
+            }
+        }
+        "/attendance" if pars.len() == 2 => {
+            if let Ok(event_id) = pars[1].parse::<u64>() {
+                match (
+                    db::get_event(conn, event_id, user.id.0),
+                    db::get_attendance_stats(conn, event_id),
+                ) {
+                    (Ok(s), Ok(stats)) => {
+                        return Ok(ReplyMessage::new(format!(
+                            "{}\n{}\n{}",
+                            format::event_title(&s.event),
+                            t!(user.lang, "starts-at", start = format::ts(s.event.ts)),
+                            format::attendance(
+                                &stats,
+                                s.event.max_adults == 0 || s.event.max_children == 0,
+                                user.lang,
+                            )
+                        ))
+                        .into());
+                    }
+                    (Err(e), _) | (_, Err(e)) => {
+                        return Err(anyhow!("Failed to get attendance: {}.", e));
+                    }
+                }
+            }
+        }
+        "/payments" if pars.len() == 2 => {
+            if let Ok(event_id) = pars[1].parse::<u64>() {
+                match (
+                    db::get_payment_totals(conn, event_id),
+                    db::reconcile_payments(conn, event_id),
+                ) {
+                    (Ok(totals), Ok(reconciliation)) => {
+                        return Ok(ReplyMessage::new(payments::describe_totals(
+                            &totals,
+                            &reconciliation,
+                            user.lang,
+                        ))
+                        .into());
+                    }
+                    (Err(e), _) | (_, Err(e)) => {
+                        return Err(anyhow!("Failed to get payments: {}.", e));
+                    }
+                }
+            }
+        }
+        "/add_promo" if pars.len() >= 3 => {
+            // /add_promo <code> <10%|5.50> [<event> [<max uses> [<valid for>]]]
+            let args: Vec<&str> = data.split_whitespace().collect();
+            let number = |i: usize| args.get(i).map_or(Some(0), |v| v.parse::<u64>().ok());
+            let expires_at = match args.get(5) {
+                Some(v) => parse_duration(v).map(|d| crate::util::get_unix_time() + d),
+                None => Some(0),
+            };
+            if let (Some((percent, amount)), Some(event_id), Some(max_uses), Some(expires_at)) = (
+                promo_codes::parse_discount(args[2]),
+                number(3),
+                number(4),
+                expires_at,
+            ) {
+                let p = PromoCode {
+                    code: args[1].to_uppercase(),
+                    event_id,
+                    percent,
+                    amount,
+                    max_uses,
+                    uses: 0,
+                    expires_at,
+                };
+                match db::add_promo_code(conn, &p) {
+                    Ok(_) => {
+                        return Ok(
+                            ReplyMessage::new(format!("Promo code {} saved.", p.code)).into()
+                        );
+                    }
+                    Err(e) => {
+                        return Err(anyhow!("Failed to add promo code: {}.", e));
+                    }
+                }
+            }
+        }
+        "/delete_promo" if pars.len() == 2 => match db::delete_promo_code(conn, pars[1]) {
+            Ok(0) => return Err(anyhow!("Promo code not found.")),
+            Ok(_) => return Ok(ReplyMessage::new("Promo code deleted.").into()),
+            Err(e) => return Err(anyhow!("Failed to delete promo code: {}.", e)),
+        },
+        "/promo_codes" => match db::get_promo_codes(conn) {
+            Ok(codes) => {
+                let mut text = t!(user.lang, "promo-codes");
+                for p in &codes {
+                    text.push_str(&format!(
+                        "\n{}{}{}",
+                        t!(
+                            user.lang,
+                            "promo-code-line",
+                            code = p.code.as_str(),
+                            discount = promo_codes::describe(p, "").trim_end(),
+                            event = if p.event_id == 0 {
+                                t!(user.lang, "all-events")
+                            } else {
+                                p.event_id.to_string()
+                            },
+                            uses = p.uses
+                        ),
+                        if p.max_uses == 0 {
+                            "".to_string()
+                        } else {
+                            format!(
+                                " {}",
+                                t!(user.lang, "promo-code-max-uses", max = p.max_uses)
+                            )
+                        },
+                        if p.expires_at == 0 {
+                            "".to_string()
+                        } else {
+                            format!(
+                                ", {}",
+                                t!(
+                                    user.lang,
+                                    "promo-code-expires",
+                                    expires = format::ts(p.expires_at)
+                                )
+                            )
+                        }
+                    ));
+                }
+                return Ok(ReplyMessage::new(text).into());
+            }
+            Err(e) => return Err(anyhow!("Failed to get promo codes: {}.", e)),
+        },
         "/show_black_list" => {
-            return show_black_list(conn, &ctx.config, 0);
+            return show_black_list(conn, &ctx.config, 0, user.lang);
         }
         "/set_event_limits" if pars.len() == 4 => {
             if let (Ok(event_id), Ok(max_adults), Ok(max_children)) = (
@@ -183,30 +432,61 @@ pub fn handle_message(
                 };
             }
         }
+        "/template" if pars.len() == 3 => {
+            // /template <kind> <event|0>
+            let (kind, event_id) = template_target(pars[1], pars[2])?;
+            let template = match templates::find(conn, event_id, kind)? {
+                Some(template) => format!("<code>{}</code>", html::escape(&template)),
+                None => t!(user.lang, "template-default"),
+            };
+            return Ok(ReplyMessage::new(format!(
+                "{}\n{}",
+                template,
+                t!(
+                    user.lang,
+                    "template-placeholders",
+                    placeholders = kind.describe_placeholders()
+                )
+            ))
+            .into());
+        }
+        "/preview_template" if pars.len() == 4 => {
+            // /preview_template <kind> <event|0> <template>
+            let (kind, event_id) = template_target(pars[1], pars[2])?;
+            let preview = templates::preview(conn, event_id, kind, pars[3], &user.user_name1)?;
+            return Ok(ReplyMessage::new(preview).into());
+        }
+        "/set_template" if pars.len() == 4 => {
+            // /set_template <kind> <event|0> <template>
+            let (kind, event_id) = template_target(pars[1], pars[2])?;
+            let preview = templates::preview(conn, event_id, kind, pars[3], &user.user_name1)?;
+            db::set_template(conn, event_id, kind.name(), pars[3])?;
+            return Ok(ReplyMessage::new(format!(
+                "{}\n{}",
+                t!(user.lang, "template-saved"),
+                preview
+            ))
+            .into());
+        }
+        "/delete_template" if pars.len() == 3 => {
+            // /delete_template <kind> <event|0>
+            let (kind, event_id) = template_target(pars[1], pars[2])?;
+            return match db::delete_template(conn, event_id, kind.name())? {
+                0 => Err(text!("template-not-found").into()),
+                _ => Ok(ReplyMessage::new(t!(user.lang, "template-deleted")).into()),
+            };
+        }
         "/help" => {
-            return Ok(ReplyMessage::new(markdown::escape(
-                        "Добавить мероприятие: \
-                        \n { \"name\":\"тест\", \"link\":\"https://t.me/storiesvienna/21\", \"start\":\"2022-05-29 15:00 +02:00\", \"remind\":\"2022-05-28 15:00 +02:00\", \"max_adults\":15, \"max_children\":15, \"max_adults_per_reservation\":15, \"max_children_per_reservation\":15, \"currency\":\"EUR\" }\
-                        \n\n Отредактировать: добавьте \"id\":<event> в команду выше \
-                        \n\n Цены билетов: добавьте \"adult_ticket_price\":200, \"child_ticket_price\":100 в выбранной валюте в команду выше \
-                        \n \nПослать сообщение: \
-                        \n /send confirmed <event> текст \
-                        \n /send waiting <event> текст \
-                        \n \nЧёрный список: \
-                        \n /ban <user> \
-                        \n /show_black_list \
-                        \n \
-                        \n /delete_event <event> \
-                        \n /delete_link <url> \
-                        \n /delete_reservation <event> <user> \
-                        \n /set_group_leader <event> <user> \
-                        \n /set_event_limits <event> <max_adults> <max_children> \
-                        ")).parse_mode(ParseMode::MarkdownV2).into());
+            return Ok(
+                ReplyMessage::new(markdown::escape(&t!(user.lang, "admin-help")))
+                    .parse_mode(ParseMode::MarkdownV2)
+                    .into(),
+            );
         }
         _ => {
             if let Some(ch) = data.chars().next() {
                 if ch == '{' {
-                    return add_event(conn, data);
+                    return add_event(conn, data, &ctx.config);
                 }
             }
             return crate::message_handler::handle_message(conn, user, data, ctx);
@@ -232,26 +512,45 @@ pub fn handle_callback(
                         Err(e) => Err(anyhow!("Failed to close event: {}.", e)),
                     }
                 }
-                ShowBlackList { offset } => show_black_list(conn, &ctx.config, offset),
+                ShowBlackList { offset } => show_black_list(conn, &ctx.config, offset, user.lang),
+                SendBroadcast {
+                    message_id,
+                    send_at,
+                } => broadcasts::send(conn, user, message_id, send_at, ctx),
+                CancelBroadcast { message_id } => broadcasts::cancel(conn, user, message_id, ctx),
+                DeliveryReport { message_id } => broadcasts::report(conn, user, message_id, ctx),
+                ShowFeedbackSummaries { offset } => {
+                    feedback::show_summaries(conn, user, offset, ctx)
+                }
+                ShowFeedback { event_id, offset } => {
+                    feedback::show_responses(conn, user, event_id, offset, ctx)
+                }
                 RemoveFromBlackList { user_id } => {
                     if db::remove_from_black_list(conn, user_id).is_ok() == false {
                         error!("Failed to remove user {} from black list", user_id);
                     }
-                    show_black_list(conn, &ctx.config, 0)
+                    show_black_list(conn, &ctx.config, 0, user.lang)
                 }
                 ConfirmRemoveFromBlackList { user_id } => {
                     if let Ok(reason) = db::get_ban_reason(conn, user_id) {
                         let keyboard: Vec<Vec<InlineKeyboardButton>> = vec![vec![
                             InlineKeyboardButton::callback(
-                                "да",
+                                t!(user.lang, "yes"),
                                 serde_json::to_string(&RemoveFromBlackList { user_id })?,
                             ),
                             InlineKeyboardButton::callback(
-                                "нет",
+                                t!(user.lang, "no"),
                                 serde_json::to_string(&ShowBlackList { offset: 0 })?,
                             ),
                         ]];
-                        Ok(ReplyMessage::new(format!("Причина бана: {reason}\nУдалить пользавателя <a href=\"tg://user?id={0}\">{0}</a> из чёрного списка?", user_id)).keyboard(keyboard).into())
+                        Ok(ReplyMessage::new(t!(
+                            user.lang,
+                            "confirm-unban",
+                            reason = reason,
+                            user_id = user_id.to_string()
+                        ))
+                        .keyboard(keyboard)
+                        .into())
                     } else {
                         Err(anyhow!("Failed to find ban reason"))
                     }
@@ -266,71 +565,136 @@ pub fn handle_callback(
     }
 }
 
-fn add_event(conn: &Connection, data: &str) -> anyhow::Result<Reply> {
+/// Kind of the template and the event, 0 for all events.
+fn template_target(kind: &str, event: &str) -> anyhow::Result<(TemplateKind, u64)> {
+    match (TemplateKind::parse(kind), event.parse::<u64>()) {
+        (Some(kind), Ok(event_id)) => Ok((kind, event_id)),
+        _ => Err(text!("template-wrong-target").into()),
+    }
+}
+
+fn add_event(conn: &Connection, data: &str, config: &Config) -> anyhow::Result<Reply> {
     match serde_json::from_str::<NewEvent>(&data) {
-        Ok(v) => {
-            match (
-                DateTime::parse_from_str(&v.start, "%Y-%m-%d %H:%M  %z"),
-                DateTime::parse_from_str(&v.remind, "%Y-%m-%d %H:%M  %z"),
-            ) {
-                (Ok(ts), Ok(remind)) => {
-                    let event = Event {
-                        id: v.id.unwrap_or(0),
-                        name: v.name,
-                        link: v.link,
-                        max_adults: v.max_adults,
-                        max_children: v.max_children,
-                        max_adults_per_reservation: v.max_adults_per_reservation,
-                        max_children_per_reservation: v.max_children_per_reservation,
-                        ts: ts.timestamp() as u64,
-                        remind: remind.timestamp() as u64,
-                        adult_ticket_price: (v.adult_ticket_price.unwrap_or(0.00f64) * 100.0)
-                            as u64,
-                        child_ticket_price: (v.child_ticket_price.unwrap_or(0.00f64) * 100.0)
-                            as u64,
-                        currency: v.currency,
-                    };
+        Ok(v) => match DateTime::parse_from_str(&v.start, "%Y-%m-%d %H:%M  %z") {
+            Ok(ts) => {
+                let reminders = match v.reminders {
+                    Some(reminders) => reminders
+                        .into_iter()
+                        .map(|r| match parse_duration(&r.before) {
+                            Some(before) => Ok(Reminder {
+                                before,
+                                template: r.template,
+                            }),
+                            None => Err(anyhow!("Failed to parse reminder: {}", r.before)),
+                        })
+                        .collect::<anyhow::Result<Vec<Reminder>>>()?,
+                    None => config.default_reminders.clone(),
+                };
+                let event = Event {
+                    id: v.id.unwrap_or(0),
+                    name: v.name,
+                    link: v.link,
+                    max_adults: v.max_adults,
+                    max_children: v.max_children,
+                    max_adults_per_reservation: v.max_adults_per_reservation,
+                    max_children_per_reservation: v.max_children_per_reservation,
+                    ts: ts.timestamp() as u64,
+                    reminders,
+                    adult_ticket_price: (v.adult_ticket_price.unwrap_or(0.00f64) * 100.0) as u64,
+                    child_ticket_price: (v.child_ticket_price.unwrap_or(0.00f64) * 100.0) as u64,
+                    currency: v.currency,
+                    registration_opens_at: parse_optional_date(&v.registration_opens_at)?,
+                    registration_closes_at: parse_optional_date(&v.registration_closes_at)?,
+                    requires_approval: v.requires_approval.unwrap_or(false),
+                    questions: v.questions.unwrap_or_default(),
+                    named_tickets: v.named_tickets.unwrap_or(false),
+                    waiting_list_hold: match &v.waiting_list_hold {
+                        Some(hold) => parse_duration(hold).ok_or_else(|| {
+                            anyhow!("Failed to parse waiting list hold: {}", hold)
+                        })?,
+                        None => 0,
+                    },
+                    refund_deadline: match &v.refund_deadline {
+                        Some(deadline) => parse_duration(deadline).ok_or_else(|| {
+                            anyhow!("Failed to parse refund deadline: {}", deadline)
+                        })?,
+                        None => 0,
+                    },
+                    late_refund_percent: v.late_refund_percent.unwrap_or(0),
+                    venue: v.venue.unwrap_or_default(),
+                    organization: v.organization.unwrap_or_default(),
+                    tags: v.tags.unwrap_or_default(),
+                };
 
-                    if event.adult_ticket_price != 0 && event.max_adults == 0
-                        || event.child_ticket_price != 0 && event.max_children == 0
-                    {
-                        return Err(anyhow!("Wrong event format"));
-                    }
-                    match crate::db::mutate_event(conn, &event) {
-                        Ok(id) => {
-                            return Ok(ReplyMessage::new(if id > 0 {
-                                let bot_name = env::var("BOT_NAME").unwrap();
-                                format!("Direct event link: https://t.me/{}?start={}", bot_name, id)
-                            } else {
-                                format!("Failed to add event.")
-                            })
-                            .into());
-                        }
-                        Err(e) => {
-                            return Err(anyhow!("Failed to add event: {}.", e));
-                        }
-                    }
+                if !event.questions.iter().all(Question::is_valid) {
+                    return Err(anyhow!("Wrong question format"));
                 }
-                _ => {
-                    return Err(anyhow!("Failed to parse date"));
+
+                if !is_valid_tags(&event.tags) {
+                    return Err(anyhow!("Wrong tags format"));
+                }
+
+                for template in event.reminders.iter().filter_map(|r| r.template.as_ref()) {
+                    templates::validate(TemplateKind::Reminder, template)?;
+                }
+
+                if event.late_refund_percent > 100 {
+                    return Err(anyhow!("Wrong refund percent"));
+                }
+
+                if event.registration_opens_at != 0
+                    && event.registration_closes_at != 0
+                    && event.registration_closes_at <= event.registration_opens_at
+                {
+                    return Err(anyhow!("Wrong registration period"));
+                }
+
+                if event.adult_ticket_price != 0 && event.max_adults == 0
+                    || event.child_ticket_price != 0 && event.max_children == 0
+                {
+                    return Err(anyhow!("Wrong event format"));
+                }
+                match crate::db::mutate_event(conn, &event) {
+                    Ok(id) => Ok(ReplyMessage::new(if id > 0 {
+                        let bot_name = env::var("BOT_NAME").unwrap();
+                        format!("Direct event link: https://t.me/{}?start={}", bot_name, id)
+                    } else {
+                        "Failed to add event.".to_string()
+                    })
+                    .into()),
+                    Err(e) => Err(anyhow!("Failed to add event: {}.", e)),
                 }
             }
-        }
-        Err(e) => {
-            return Err(anyhow!("Failed to parse json: {}", e));
-        }
+            _ => Err(anyhow!("Failed to parse date")),
+        },
+        Err(e) => Err(anyhow!("Failed to parse json: {}", e)),
     }
 }
 
-fn show_black_list(conn: &Connection, config: &Config, offset: u64) -> anyhow::Result<Reply> {
+fn parse_optional_date(value: &Option<String>) -> anyhow::Result<u64> {
+    match value {
+        Some(v) => match DateTime::parse_from_str(v, "%Y-%m-%d %H:%M  %z") {
+            Ok(ts) => Ok(ts.timestamp() as u64),
+            Err(_) => Err(anyhow!("Failed to parse date: {}", v)),
+        },
+        None => Ok(0),
+    }
+}
+
+fn show_black_list(
+    conn: &Connection,
+    config: &Config,
+    offset: u64,
+    lang: Lang,
+) -> anyhow::Result<Reply> {
     match db::get_black_list(conn, offset, config.presence_page_size) {
         Ok(participants) => {
             Ok(
                 // header
                 ReplyMessage::new(if participants.len() != 0 || offset > 0 {
-                    "Чёрный список. Нажмите кнопку чтобы удалить из списка."
+                    t!(lang, "black-list")
                 } else {
-                    "Чёрный список пуст."
+                    t!(lang, "black-list-empty")
                 })
                 // list
                 .keyboard(
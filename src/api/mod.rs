@@ -3,20 +3,46 @@ mod services;
 mod shared;
 mod utils;
 
+use crate::api::services::calendar::calendar_scope;
 use crate::api::services::event::event_scope;
+use crate::api::services::feedback::feedback_scope;
+use crate::api::services::message::message_scope;
+use crate::api::services::payment::payment_scope;
+use crate::api::services::public::public_scope;
+use crate::api::services::template::template_scope;
+use crate::api::services::user::user_scope;
+use crate::configuration::config::Config;
+use crate::payment_providers::PaymentProvider;
 use crate::types::DbPool;
 use actix_web::dev::Server;
 use actix_web::{web, App, HttpServer};
 use middlewares::cors_middleware;
 use std::net::ToSocketAddrs;
+use std::sync::Arc;
 
-pub fn setup_api_server<Addr: ToSocketAddrs>(addr: &Addr, con_pool: &DbPool) -> Server {
+pub fn setup_api_server<Addr: ToSocketAddrs>(
+    addr: &Addr,
+    con_pool: &DbPool,
+    config: &Config,
+    payment_provider: &Arc<dyn PaymentProvider>,
+) -> Server {
     let pool = con_pool.clone();
+    let config = config.clone();
+    let payment_provider = payment_provider.clone();
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(pool.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .app_data(web::Data::from(payment_provider.clone()))
             .wrap(cors_middleware())
+            .service(calendar_scope())
             .service(event_scope())
+            .service(feedback_scope())
+            .service(message_scope())
+            .service(payment_scope())
+            .service(public_scope())
+            .service(template_scope())
+            .service(user_scope())
     })
     .bind(&addr)
     .expect("to bind on socket")
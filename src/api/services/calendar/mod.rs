@@ -0,0 +1,7 @@
+mod user_calendar;
+
+use actix_web::{web, Scope};
+
+pub fn calendar_scope() -> Scope {
+    web::scope("/calendar").service(user_calendar::user_calendar)
+}
@@ -0,0 +1,40 @@
+use crate::api::shared::{into_internal_server_error_response, QueryError};
+use crate::calendar;
+use crate::db::{get_calendar_user, get_confirmed_events, get_events_modified};
+use crate::types::DbPool;
+use crate::util::get_unix_time;
+use actix_web::web::{Data, Path};
+use actix_web::{get, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+/// Subscription feed with the confirmed bookings of the user the secret token was issued to.
+#[get("/{token}.ics")]
+pub async fn user_calendar(
+    token: Path<String>,
+    pool: Data<DbPool>,
+) -> actix_web::Result<impl Responder> {
+    let ics = spawn_blocking(move || perform_select_calendar(&pool.into_inner(), &token))
+        .await
+        .map_err(into_internal_server_error_response)?
+        .map_err(into_internal_server_error_response)?;
+
+    Ok(match ics {
+        Some(ics) => HttpResponse::Ok()
+            .content_type("text/calendar; charset=utf-8")
+            .body(ics),
+        None => HttpResponse::NotFound().finish(),
+    })
+}
+
+fn perform_select_calendar(pool: &DbPool, token: &str) -> Result<Option<String>, QueryError> {
+    let conn = pool.get()?;
+
+    Ok(match get_calendar_user(&conn, token)? {
+        Some(user) => Some(calendar::ics(
+            &get_confirmed_events(&conn, user)?,
+            "event-manager-telegram-bot",
+            get_events_modified(&conn, get_unix_time())?,
+        )),
+        None => None,
+    })
+}
@@ -1,6 +1,8 @@
-use crate::api::services::event::types::RawEvent;
+use crate::api::services::event::db;
+use crate::api::services::event::types::{legacy_reminders, RawEvent};
 use crate::api::shared::{into_internal_server_error_response, QueryError};
 use crate::api::utils::json_response;
+use crate::configuration::config::Config;
 use crate::db::mutate_event;
 use crate::types::{DbPool, Event};
 use actix_web::http::StatusCode;
@@ -11,23 +13,33 @@ use tokio::task::spawn_blocking;
 #[post("")]
 pub async fn create_event(
     pool: Data<DbPool>,
+    config: Data<Config>,
     event_to_create: Json<RawEvent>,
 ) -> actix_web::Result<impl Responder> {
     event_to_create.validation()?;
 
-    let mut event: Event = event_to_create.into_inner().into();
-    let cloned = event.clone();
-    let event_id = spawn_blocking(move || insert_event(&pool, &cloned))
+    let mut event_to_create = event_to_create.into_inner();
+    if event_to_create.reminders.is_none() {
+        event_to_create.reminders = Some(
+            legacy_reminders(
+                event_to_create.remind,
+                event_to_create.event_start_time.timestamp() as u64,
+            )
+            .unwrap_or_else(|| config.default_reminders.clone()),
+        );
+    }
+    let event: Event = event_to_create.into();
+    let event = spawn_blocking(move || insert_event(&pool, &event))
         .await
         .map_err(into_internal_server_error_response)?
         .map_err(into_internal_server_error_response)?;
 
-    event.id = event_id;
-
     Ok(json_response(&event, StatusCode::CREATED))
 }
 
-fn insert_event(pool: &DbPool, event: &Event) -> Result<u64, QueryError> {
+/// Stores the event and reads it back, questions get their ids when stored.
+fn insert_event(pool: &DbPool, event: &Event) -> Result<Event, QueryError> {
     let con = pool.get()?;
-    Ok(mutate_event(&con, &event)?)
+    let event_id = mutate_event(&con, event)?;
+    db::select_event(&con, event_id as i64)
 }
@@ -1,5 +1,6 @@
-use crate::api::services::event::types::{EventWithId, RawEvent};
+use crate::api::services::event::types::{legacy_remind, optional_datetime, EventWithId, RawEvent};
 use crate::api::shared::{Pagination, QueryError};
+use crate::db::{questions_from_row, reminders_from_row, tags_from_row};
 use crate::format::from_timestamp;
 use crate::types::{Connection, DbPool, Event};
 use rusqlite::{params, Error, Row};
@@ -20,10 +21,21 @@ pub fn select_event(conn: &Connection, id: i64) -> Result<Event, QueryError> {
         max_adults_per_reservation: row.get("max_adults_per_reservation")?,
         max_children_per_reservation: row.get("max_children_per_reservation")?,
         ts: row.get("ts")?,
-        remind: row.get("remind")?,
+        reminders: reminders_from_row(row)?,
         adult_ticket_price: row.get("adult_ticket_price")?,
         child_ticket_price: row.get("child_ticket_price")?,
         currency: row.get("currency")?,
+        registration_opens_at: row.get("registration_opens_at")?,
+        registration_closes_at: row.get("registration_closes_at")?,
+        requires_approval: row.get("requires_approval")?,
+        questions: questions_from_row(row)?,
+        named_tickets: row.get("named_tickets")?,
+        waiting_list_hold: row.get("waiting_list_hold")?,
+        refund_deadline: row.get("refund_deadline")?,
+        late_refund_percent: row.get("late_refund_percent")?,
+        venue: row.get("venue")?,
+        organization: row.get("organization")?,
+        tags: tags_from_row(row)?,
     })
 }
 
@@ -40,6 +52,8 @@ pub fn get_event_list(pool: &DbPool, pag: &Pagination) -> Result<Vec<EventWithId
 }
 
 fn map_row(row: &Row) -> Result<EventWithId, Error> {
+    let ts = row.get("ts")?;
+    let reminders = reminders_from_row(row)?;
     Ok(EventWithId {
         id: row.get("id")?,
         entity: RawEvent {
@@ -49,11 +63,23 @@ fn map_row(row: &Row) -> Result<EventWithId, Error> {
             max_children: row.get("max_children")?,
             max_adults_per_reservation: row.get("max_adults_per_reservation")?,
             max_children_per_reservation: row.get("max_children_per_reservation")?,
-            event_start_time: from_timestamp(row.get("ts")?),
-            remind: from_timestamp(row.get("remind")?),
+            event_start_time: from_timestamp(ts),
+            remind: legacy_remind(ts, &reminders),
+            reminders: Some(reminders),
             adult_ticket_price: row.get("adult_ticket_price")?,
             child_ticket_price: row.get("child_ticket_price")?,
             currency: row.get("currency")?,
+            registration_opens_at: optional_datetime(row.get("registration_opens_at")?),
+            registration_closes_at: optional_datetime(row.get("registration_closes_at")?),
+            requires_approval: row.get("requires_approval")?,
+            questions: questions_from_row(row)?,
+            named_tickets: row.get("named_tickets")?,
+            waiting_list_hold: row.get("waiting_list_hold")?,
+            refund_deadline: row.get("refund_deadline")?,
+            late_refund_percent: row.get("late_refund_percent")?,
+            venue: row.get("venue")?,
+            organization: row.get("organization")?,
+            tags: tags_from_row(row)?,
         },
     })
 }
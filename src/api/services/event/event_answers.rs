@@ -0,0 +1,59 @@
+use crate::api::shared::{
+    into_internal_server_error_response, Pagination, QueryError, RawPagination, TelegramUser,
+};
+use crate::api::utils::json_response;
+use crate::configuration::config::Config;
+use crate::db::get_event_answers;
+use crate::types::{DbPool, ParticipantAnswers};
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Path, Query};
+use actix_web::{get, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+/// Questionnaire answers of the participants, for admins and managers of the event.
+#[get("/{id}/answers")]
+pub async fn event_answers(
+    id: Path<u64>,
+    user: TelegramUser,
+    pool: Data<DbPool>,
+    config: Data<Config>,
+    params: Query<RawPagination>,
+) -> actix_web::Result<impl Responder> {
+    let answers = spawn_blocking(move || {
+        perform_select_answers(
+            &pool.into_inner(),
+            &config,
+            &user,
+            id.into_inner(),
+            params.into_inner(),
+        )
+    })
+    .await
+    .map_err(into_internal_server_error_response)?
+    .map_err(into_internal_server_error_response)?;
+
+    Ok(match answers {
+        Some(answers) => json_response(&answers, StatusCode::OK),
+        None => HttpResponse::Forbidden().finish(),
+    })
+}
+
+fn perform_select_answers(
+    pool: &DbPool,
+    config: &Config,
+    user: &TelegramUser,
+    id: u64,
+    params: RawPagination,
+) -> Result<Option<Vec<ParticipantAnswers>>, QueryError> {
+    let conn = pool.get()?;
+    if !user.is_manager(&conn, config, id)? {
+        return Ok(None);
+    }
+    let pag: Pagination = params.into();
+    Ok(Some(get_event_answers(
+        &conn,
+        id,
+        (pag.offset() / pag.limit()) as u64,
+        pag.limit() as u64,
+    )?))
+}
@@ -0,0 +1,44 @@
+use crate::api::shared::{into_internal_server_error_response, QueryError, TelegramUser};
+use crate::api::utils::json_response;
+use crate::configuration::config::Config;
+use crate::db::get_attendance_stats;
+use crate::types::{AttendanceStats, DbPool};
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Path};
+use actix_web::{get, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+/// Attendance and check-ins of the event, for admins and managers of the event.
+#[get("/{id}/attendance")]
+pub async fn event_attendance(
+    id: Path<u64>,
+    user: TelegramUser,
+    pool: Data<DbPool>,
+    config: Data<Config>,
+) -> actix_web::Result<impl Responder> {
+    let stats = spawn_blocking(move || {
+        perform_select_attendance(&pool.into_inner(), &config, &user, id.into_inner())
+    })
+    .await
+    .map_err(into_internal_server_error_response)?
+    .map_err(into_internal_server_error_response)?;
+
+    Ok(match stats {
+        Some(stats) => json_response(&stats, StatusCode::OK),
+        None => HttpResponse::Forbidden().finish(),
+    })
+}
+
+fn perform_select_attendance(
+    pool: &DbPool,
+    config: &Config,
+    user: &TelegramUser,
+    id: u64,
+) -> Result<Option<AttendanceStats>, QueryError> {
+    let conn = pool.get()?;
+    if !user.is_manager(&conn, config, id)? {
+        return Ok(None);
+    }
+
+    Ok(Some(get_attendance_stats(&conn, id)?))
+}
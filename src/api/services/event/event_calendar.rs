@@ -0,0 +1,34 @@
+use crate::api::shared::{into_internal_server_error_response, QueryError};
+use crate::calendar;
+use crate::db::{find_event, get_events_modified};
+use crate::types::DbPool;
+use crate::util::get_unix_time;
+use actix_web::web::{Data, Path};
+use actix_web::{get, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+/// The event as an iCalendar file.
+#[get("/{id:\\d+}.ics")]
+pub async fn event_calendar(
+    id: Path<u64>,
+    pool: Data<DbPool>,
+) -> actix_web::Result<impl Responder> {
+    let ics = spawn_blocking(move || perform_select_calendar(&pool.into_inner(), id.into_inner()))
+        .await
+        .map_err(into_internal_server_error_response)?
+        .map_err(into_internal_server_error_response)?;
+
+    Ok(match ics {
+        Some(ics) => HttpResponse::Ok()
+            .content_type("text/calendar; charset=utf-8")
+            .body(ics),
+        None => HttpResponse::NotFound().finish(),
+    })
+}
+
+fn perform_select_calendar(pool: &DbPool, id: u64) -> Result<Option<String>, QueryError> {
+    let conn = pool.get()?;
+
+    let modified = get_events_modified(&conn, get_unix_time())?;
+    Ok(find_event(&conn, id)?.map(|e| calendar::ics(std::slice::from_ref(&e), &e.name, modified)))
+}
@@ -0,0 +1,57 @@
+use crate::api::shared::{into_internal_server_error_response, QueryError, TelegramUser};
+use crate::api::utils::json_response;
+use crate::configuration::config::Config;
+use crate::db::check_in;
+use crate::types::{CheckIn, DbPool};
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Json, Path};
+use actix_web::{post, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+#[derive(Deserialize)]
+pub struct ScannedTicket {
+    code: String,
+}
+
+/// Checks a ticket in, for admins and managers of the event.
+#[post("/{id}/check_in")]
+pub async fn event_check_in(
+    id: Path<u64>,
+    user: TelegramUser,
+    pool: Data<DbPool>,
+    config: Data<Config>,
+    ticket: Json<ScannedTicket>,
+) -> actix_web::Result<impl Responder> {
+    let result = spawn_blocking(move || {
+        perform_check_in(
+            &pool.into_inner(),
+            &config,
+            &user,
+            id.into_inner(),
+            &ticket.code,
+        )
+    })
+    .await
+    .map_err(into_internal_server_error_response)?
+    .map_err(into_internal_server_error_response)?;
+
+    Ok(match result {
+        Some(result) => json_response(&result, StatusCode::OK),
+        None => HttpResponse::Forbidden().finish(),
+    })
+}
+
+fn perform_check_in(
+    pool: &DbPool,
+    config: &Config,
+    user: &TelegramUser,
+    id: u64,
+    code: &str,
+) -> Result<Option<CheckIn>, QueryError> {
+    let conn = pool.get()?;
+    if !user.is_manager(&conn, config, id)? {
+        return Ok(None);
+    }
+
+    Ok(Some(check_in(&conn, id, code)?))
+}
@@ -0,0 +1,96 @@
+use crate::api::shared::{into_internal_server_error_response, QueryError, TelegramUser};
+use crate::api::utils::json_response;
+use crate::configuration::config::Config;
+use crate::db::{get_payment_totals, get_payments, reconcile_payments};
+use crate::payments::export_payments;
+use crate::types::{DbPool, Payment, PaymentTotals, Reconciliation};
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Path, Query};
+use actix_web::{get, HttpResponse, Responder};
+use serde::Serialize;
+use tokio::task::spawn_blocking;
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    format: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PaymentSummary {
+    #[serde(flatten)]
+    totals: PaymentTotals,
+    reconciliation: Reconciliation,
+}
+
+/// Payments ledger of the event as JSON, or as CSV with `?format=csv`. Event 0 holds donations.
+/// For admins and managers of the event.
+#[get("/{id}/payments")]
+pub async fn event_payments(
+    id: Path<u64>,
+    user: TelegramUser,
+    pool: Data<DbPool>,
+    config: Data<Config>,
+    params: Query<ExportParams>,
+) -> actix_web::Result<impl Responder> {
+    let payments = spawn_blocking(move || {
+        perform_select_payments(&pool.into_inner(), &config, &user, id.into_inner())
+    })
+    .await
+    .map_err(into_internal_server_error_response)?
+    .map_err(into_internal_server_error_response)?;
+
+    let payments = match payments {
+        Some(payments) => payments,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    if params.format.as_deref() == Some("csv") {
+        Ok(HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .body(export_payments(&payments)))
+    } else {
+        Ok(json_response(&payments, StatusCode::OK))
+    }
+}
+
+/// Revenue totals, for admins only.
+#[get("/{id}/payments/totals")]
+pub async fn event_payment_totals(
+    id: Path<u64>,
+    user: TelegramUser,
+    pool: Data<DbPool>,
+    config: Data<Config>,
+) -> actix_web::Result<impl Responder> {
+    if !user.is_admin(&config) {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let summary =
+        spawn_blocking(move || perform_select_totals(&pool.into_inner(), id.into_inner()))
+            .await
+            .map_err(into_internal_server_error_response)?
+            .map_err(into_internal_server_error_response)?;
+
+    Ok(json_response(&summary, StatusCode::OK))
+}
+
+fn perform_select_payments(
+    pool: &DbPool,
+    config: &Config,
+    user: &TelegramUser,
+    id: u64,
+) -> Result<Option<Vec<Payment>>, QueryError> {
+    let conn = pool.get()?;
+    if !user.is_manager(&conn, config, id)? {
+        return Ok(None);
+    }
+
+    Ok(Some(get_payments(&conn, id)?))
+}
+
+fn perform_select_totals(pool: &DbPool, id: u64) -> Result<PaymentSummary, QueryError> {
+    let conn = pool.get()?;
+
+    Ok(PaymentSummary {
+        totals: get_payment_totals(&conn, id)?,
+        reconciliation: reconcile_payments(&conn, id)?,
+    })
+}
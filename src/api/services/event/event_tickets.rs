@@ -0,0 +1,44 @@
+use crate::api::shared::{into_internal_server_error_response, QueryError, TelegramUser};
+use crate::api::utils::json_response;
+use crate::configuration::config::Config;
+use crate::db::get_event_tickets;
+use crate::types::{DbPool, Ticket};
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Path};
+use actix_web::{get, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+/// Attendee names and emails, for admins and managers of the event.
+#[get("/{id}/tickets")]
+pub async fn event_tickets(
+    id: Path<u64>,
+    user: TelegramUser,
+    pool: Data<DbPool>,
+    config: Data<Config>,
+) -> actix_web::Result<impl Responder> {
+    let tickets = spawn_blocking(move || {
+        perform_select_tickets(&pool.into_inner(), &config, &user, id.into_inner())
+    })
+    .await
+    .map_err(into_internal_server_error_response)?
+    .map_err(into_internal_server_error_response)?;
+
+    Ok(match tickets {
+        Some(tickets) => json_response(&tickets, StatusCode::OK),
+        None => HttpResponse::Forbidden().finish(),
+    })
+}
+
+fn perform_select_tickets(
+    pool: &DbPool,
+    config: &Config,
+    user: &TelegramUser,
+    id: u64,
+) -> Result<Option<Vec<Ticket>>, QueryError> {
+    let conn = pool.get()?;
+    if !user.is_manager(&conn, config, id)? {
+        return Ok(None);
+    }
+
+    Ok(Some(get_event_tickets(&conn, id)?))
+}
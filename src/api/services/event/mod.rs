@@ -1,6 +1,12 @@
 mod create_event;
 mod db;
+mod event_answers;
+mod event_attendance;
+mod event_calendar;
+mod event_check_in;
 mod event_list;
+mod event_payments;
+mod event_tickets;
 mod get_event;
 mod remove_event;
 mod types;
@@ -11,7 +17,14 @@ use actix_web::{web, Scope};
 pub fn event_scope() -> Scope {
     web::scope("/event")
         .service(event_list::event_list)
+        .service(event_calendar::event_calendar)
         .service(create_event::create_event)
         .service(remove_event::remove_event)
         .service(update_event::update_event)
+        .service(event_answers::event_answers)
+        .service(event_tickets::event_tickets)
+        .service(event_check_in::event_check_in)
+        .service(event_attendance::event_attendance)
+        .service(event_payments::event_payment_totals)
+        .service(event_payments::event_payments)
 }
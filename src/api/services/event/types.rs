@@ -1,7 +1,8 @@
 use crate::api::shared::WithId;
 use crate::api::utils::{validation_error_to_http, ValidationError};
 use crate::format::from_timestamp;
-use crate::types::Event;
+use crate::templates::{self, TemplateKind};
+use crate::types::{is_valid_tags, Event, Question, Reminder};
 use chrono::{DateTime, Utc};
 use std::borrow::Cow;
 use std::collections::HashMap;
@@ -18,10 +19,36 @@ pub struct RawEvent {
     pub max_adults_per_reservation: u64,
     pub max_children_per_reservation: u64,
     pub event_start_time: DateTime<Utc>,
-    pub remind: DateTime<Utc>,
+    /// Deprecated single reminder time, superseded by `reminders`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remind: Option<DateTime<Utc>>,
+    pub reminders: Option<Vec<Reminder>>,
     pub adult_ticket_price: u64,
     pub child_ticket_price: u64,
     pub currency: String,
+    pub registration_opens_at: Option<DateTime<Utc>>,
+    pub registration_closes_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub requires_approval: bool,
+    #[serde(default)]
+    pub questions: Vec<Question>,
+    #[serde(default)]
+    pub named_tickets: bool,
+    #[serde(default)]
+    pub waiting_list_hold: u64,
+    #[serde(default)]
+    pub refund_deadline: u64,
+    #[serde(default)]
+    #[validate(range(max = 100))]
+    pub late_refund_percent: u64,
+    #[serde(default)]
+    #[validate(length(max = 255))]
+    pub venue: String,
+    #[serde(default)]
+    #[validate(length(max = 255))]
+    pub organization: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Deserialize, Validate)]
@@ -35,10 +62,119 @@ pub struct OptionalRawEvent {
     pub max_adults_per_reservation: Option<u64>,
     pub max_children_per_reservation: Option<u64>,
     pub event_start_time: Option<DateTime<Utc>>,
+    /// Deprecated single reminder time, superseded by `reminders`.
     pub remind: Option<DateTime<Utc>>,
+    pub reminders: Option<Vec<Reminder>>,
     pub adult_ticket_price: Option<u64>,
     pub child_ticket_price: Option<u64>,
     pub currency: Option<String>,
+    /// Missing keeps the current value, null clears it.
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub registration_opens_at: Option<Option<DateTime<Utc>>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub registration_closes_at: Option<Option<DateTime<Utc>>>,
+    pub requires_approval: Option<bool>,
+    pub questions: Option<Vec<Question>>,
+    pub named_tickets: Option<bool>,
+    pub waiting_list_hold: Option<u64>,
+    pub refund_deadline: Option<u64>,
+    #[validate(range(max = 100))]
+    pub late_refund_percent: Option<u64>,
+    #[validate(length(max = 255))]
+    pub venue: Option<String>,
+    #[validate(length(max = 255))]
+    pub organization: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// Tells an explicit null from a missing field.
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: serde::Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Converts an optional date into a timestamp, 0 meaning "not set".
+pub fn optional_timestamp(value: Option<DateTime<Utc>>) -> u64 {
+    value.map(|v| v.timestamp() as u64).unwrap_or(0)
+}
+
+/// Converts a timestamp into an optional date, 0 meaning "not set".
+pub fn optional_datetime(ts: u64) -> Option<DateTime<Utc>> {
+    if ts != 0 {
+        Some(from_timestamp(ts))
+    } else {
+        None
+    }
+}
+
+/// Turns the deprecated `remind` time into a reminder for an event starting at `ts`.
+pub fn legacy_reminders(remind: Option<DateTime<Utc>>, ts: u64) -> Option<Vec<Reminder>> {
+    remind.map(|remind| {
+        vec![Reminder {
+            before: ts.saturating_sub(remind.timestamp() as u64),
+            template: None,
+        }]
+    })
+}
+
+/// The earliest reminder time, reported as `remind` for older clients.
+pub fn legacy_remind(ts: u64, reminders: &[Reminder]) -> Option<DateTime<Utc>> {
+    reminders
+        .iter()
+        .map(|r| r.before)
+        .max()
+        .map(|before| from_timestamp(ts.saturating_sub(before)))
+}
+
+fn registration_window_error() -> validator::ValidationError {
+    validator::ValidationError {
+        code: Cow::from("registration_closes_at"),
+        message: Some(Cow::from(
+            "registration_closes_at should be after registration_opens_at",
+        )),
+        params: HashMap::new(),
+    }
+}
+
+fn questions_error() -> validator::ValidationError {
+    validator::ValidationError {
+        code: Cow::from("questions"),
+        message: Some(Cow::from(
+            "questions should have a text, choice questions should have options",
+        )),
+        params: HashMap::new(),
+    }
+}
+
+fn reminders_error() -> validator::ValidationError {
+    validator::ValidationError {
+        code: Cow::from("reminders"),
+        message: Some(Cow::from(
+            "reminder templates should be valid, see the reminder template placeholders",
+        )),
+        params: HashMap::new(),
+    }
+}
+
+fn tags_error() -> validator::ValidationError {
+    validator::ValidationError {
+        code: Cow::from("tags"),
+        message: Some(Cow::from(
+            "tags should be non-empty words of at most 64 characters, at most 20 tags",
+        )),
+        params: HashMap::new(),
+    }
+}
+
+/// Reminders with a template which would fail to render.
+fn has_invalid_reminders(reminders: &[Reminder]) -> bool {
+    reminders
+        .iter()
+        .filter_map(|r| r.template.as_ref())
+        .any(|t| templates::validate(TemplateKind::Reminder, t).is_err())
 }
 
 pub type EventWithId = WithId<u64, RawEvent>;
@@ -55,10 +191,22 @@ impl From<Event> for EventWithId {
                 max_adults_per_reservation: event.max_adults_per_reservation,
                 max_children_per_reservation: event.max_children_per_reservation,
                 event_start_time: from_timestamp(event.ts),
-                remind: from_timestamp(event.remind),
+                remind: legacy_remind(event.ts, &event.reminders),
+                reminders: Some(event.reminders),
                 adult_ticket_price: event.adult_ticket_price,
                 child_ticket_price: event.child_ticket_price,
                 currency: event.currency,
+                registration_opens_at: optional_datetime(event.registration_opens_at),
+                registration_closes_at: optional_datetime(event.registration_closes_at),
+                requires_approval: event.requires_approval,
+                questions: event.questions,
+                named_tickets: event.named_tickets,
+                waiting_list_hold: event.waiting_list_hold,
+                refund_deadline: event.refund_deadline,
+                late_refund_percent: event.late_refund_percent,
+                venue: event.venue,
+                organization: event.organization,
+                tags: event.tags,
             },
         }
     }
@@ -123,6 +271,26 @@ impl RawEvent {
             )
         }
 
+        if let (Some(opens_at), Some(closes_at)) =
+            (self.registration_opens_at, self.registration_closes_at)
+        {
+            if closes_at <= opens_at {
+                errors.add("registration_closes_at", registration_window_error())
+            }
+        }
+
+        if !self.questions.iter().all(Question::is_valid) {
+            errors.add("questions", questions_error())
+        }
+
+        if self.reminders.as_deref().is_some_and(has_invalid_reminders) {
+            errors.add("reminders", reminders_error())
+        }
+
+        if !is_valid_tags(&self.tags) {
+            errors.add("tags", tags_error())
+        }
+
         match errors.is_empty() {
             true => Ok(()),
             false => Err(validation_error_to_http(errors)),
@@ -196,6 +364,30 @@ impl OptionalRawEvent {
             )
         }
 
+        let opens_at = self.get_registration_opens_at(current_event);
+        let closes_at = self.get_registration_closes_at(current_event);
+        if opens_at != 0 && closes_at != 0 && closes_at <= opens_at {
+            errors.add("registration_closes_at", registration_window_error())
+        }
+
+        if let Some(questions) = &self.questions {
+            if !questions.iter().all(Question::is_valid) {
+                errors.add("questions", questions_error())
+            }
+        }
+
+        if self.reminders.as_deref().is_some_and(has_invalid_reminders) {
+            errors.add("reminders", reminders_error())
+        }
+
+        if self
+            .tags
+            .as_deref()
+            .is_some_and(|tags| !is_valid_tags(tags))
+        {
+            errors.add("tags", tags_error())
+        }
+
         match errors.is_empty() {
             true => Ok(()),
             false => Err(validation_error_to_http(errors)),
@@ -229,10 +421,23 @@ impl OptionalRawEvent {
         self.max_children_per_reservation
             .unwrap_or(current_event.max_children_per_reservation)
     }
+
+    pub fn get_registration_opens_at(&self, current_event: &Event) -> u64 {
+        self.registration_opens_at
+            .map(optional_timestamp)
+            .unwrap_or(current_event.registration_opens_at)
+    }
+
+    pub fn get_registration_closes_at(&self, current_event: &Event) -> u64 {
+        self.registration_closes_at
+            .map(optional_timestamp)
+            .unwrap_or(current_event.registration_closes_at)
+    }
 }
 
 impl Into<Event> for RawEvent {
     fn into(self) -> Event {
+        let ts = self.event_start_time.timestamp() as u64;
         Event {
             id: 0,
             name: self.name,
@@ -241,18 +446,33 @@ impl Into<Event> for RawEvent {
             max_children: self.max_children as u64,
             max_adults_per_reservation: self.max_adults_per_reservation as u64,
             max_children_per_reservation: self.max_children_per_reservation as u64,
-            ts: self.event_start_time.timestamp() as u64,
-            remind: self.remind.timestamp() as u64,
+            ts,
+            reminders: self
+                .reminders
+                .or_else(|| legacy_reminders(self.remind, ts))
+                .unwrap_or_default(),
             adult_ticket_price: self.adult_ticket_price as u64,
             child_ticket_price: self.child_ticket_price as u64,
             currency: self.currency,
+            registration_opens_at: optional_timestamp(self.registration_opens_at),
+            registration_closes_at: optional_timestamp(self.registration_closes_at),
+            requires_approval: self.requires_approval,
+            questions: self.questions,
+            named_tickets: self.named_tickets,
+            waiting_list_hold: self.waiting_list_hold,
+            refund_deadline: self.refund_deadline,
+            late_refund_percent: self.late_refund_percent,
+            venue: self.venue,
+            organization: self.organization,
+            tags: self.tags,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::api::services::event::types::RawEvent;
+    use crate::api::services::event::types::{OptionalRawEvent, RawEvent};
+    use crate::types::{Event, Reminder};
     use serde_json;
     use serde_json::Result;
 
@@ -267,7 +487,7 @@ mod tests {
               "max_adults_per_reservation": 4,
               "max_children_per_reservation": 5,
               "event_start_time": "2023-05-26T17:22:00+03:00",
-              "remind": "2023-05-26T16:22:00+03:00",
+              "reminders": [{ "before": 3600 }, { "before": 86400, "template": "{name} starts at {start}" }],
               "adult_ticket_price": 50,
               "child_ticket_price": 25,
               "currency": "USD"
@@ -286,4 +506,40 @@ mod tests {
             validation_result.err().unwrap()
         )
     }
+
+    #[test]
+    fn optional_raw_event_clears_registration_window() {
+        let event: OptionalRawEvent =
+            serde_json::from_str(r#"{ "registration_closes_at": null }"#).unwrap();
+        assert_eq!(event.registration_closes_at, Some(None));
+        assert_eq!(event.registration_opens_at, None);
+    }
+
+    #[test]
+    fn raw_event_reminders_compatibility() {
+        let str = r#"
+            {
+              "name": "Test name",
+              "link": "https://google.com",
+              "max_adults": 2000,
+              "max_children": 1000,
+              "max_adults_per_reservation": 4,
+              "max_children_per_reservation": 5,
+              "event_start_time": "2023-05-26T17:00:00+00:00",
+              "remind": "2023-05-25T17:00:00+00:00",
+              "adult_ticket_price": 50,
+              "child_ticket_price": 25,
+              "currency": "USD"
+            }
+        "#;
+        let event: Event = serde_json::from_str::<RawEvent>(str).unwrap().into();
+        assert_eq!(event.reminders.len(), 1);
+        assert_eq!(event.reminders[0].before, 86400);
+
+        let reminders: Vec<Reminder> =
+            serde_json::from_str(r#"[{ "before": "1d" }, { "before": 7200 }]"#).unwrap();
+        assert_eq!(reminders[0].before, 86400);
+        assert_eq!(reminders[1].before, 7200);
+        assert!(serde_json::from_str::<Vec<Reminder>>(r#"[{ "before": "1x" }]"#).is_err());
+    }
 }
@@ -1,5 +1,5 @@
 use crate::api::services::event::db;
-use crate::api::services::event::types::OptionalRawEvent;
+use crate::api::services::event::types::{legacy_reminders, OptionalRawEvent};
 use crate::api::shared::{into_internal_server_error_response, QueryError};
 use crate::api::utils::json_response;
 use crate::db::mutate_event;
@@ -52,6 +52,12 @@ pub fn perform_update_event(
 ) -> Result<Event, QueryError> {
     let conn = pool.get()?;
 
+    let registration_opens_at = event_to_update.get_registration_opens_at(current_event);
+    let registration_closes_at = event_to_update.get_registration_closes_at(current_event);
+    let ts = event_to_update
+        .event_start_time
+        .map(|val| val.timestamp() as u64)
+        .unwrap_or(current_event.ts);
     let new_event = Event {
         id: id as u64,
         name: event_to_update.name.unwrap_or(current_event.name.clone()),
@@ -70,20 +76,43 @@ pub fn perform_update_event(
             .max_children_per_reservation
             .unwrap_or(current_event.max_children_per_reservation)
             as u64,
-        ts: event_to_update
-            .event_start_time
-            .map(|val| val.timestamp() as u64)
-            .unwrap_or(current_event.ts),
-        remind: event_to_update
-            .remind
-            .map(|val| val.timestamp() as u64)
-            .unwrap_or(current_event.remind),
+        ts,
+        reminders: event_to_update
+            .reminders
+            .or_else(|| legacy_reminders(event_to_update.remind, ts))
+            .unwrap_or(current_event.reminders.clone()),
         adult_ticket_price: current_event.adult_ticket_price,
         child_ticket_price: current_event.child_ticket_price,
         currency: current_event.currency.clone(),
+        registration_opens_at,
+        registration_closes_at,
+        requires_approval: event_to_update
+            .requires_approval
+            .unwrap_or(current_event.requires_approval),
+        questions: event_to_update
+            .questions
+            .unwrap_or(current_event.questions.clone()),
+        named_tickets: event_to_update
+            .named_tickets
+            .unwrap_or(current_event.named_tickets),
+        waiting_list_hold: event_to_update
+            .waiting_list_hold
+            .unwrap_or(current_event.waiting_list_hold),
+        refund_deadline: event_to_update
+            .refund_deadline
+            .unwrap_or(current_event.refund_deadline),
+        late_refund_percent: event_to_update
+            .late_refund_percent
+            .unwrap_or(current_event.late_refund_percent),
+        venue: event_to_update.venue.unwrap_or(current_event.venue.clone()),
+        organization: event_to_update
+            .organization
+            .unwrap_or(current_event.organization.clone()),
+        tags: event_to_update.tags.unwrap_or(current_event.tags.clone()),
     };
 
     mutate_event(&conn, &new_event)?;
 
-    Ok(new_event)
+    // Questions get their ids when stored.
+    db::select_event(&conn, id)
 }
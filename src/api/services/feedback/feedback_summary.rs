@@ -0,0 +1,70 @@
+use crate::api::shared::{into_internal_server_error_response, QueryError, TelegramUser};
+use crate::api::utils::json_response;
+use crate::configuration::config::Config;
+use crate::db::{get_feedback_summaries, get_feedback_summary};
+use crate::types::{DbPool, FeedbackSummary};
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Path};
+use actix_web::{get, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+/// Ratings of all surveyed events, the latest first, for admins.
+#[get("")]
+pub async fn feedback_summaries(
+    user: TelegramUser,
+    pool: Data<DbPool>,
+    config: Data<Config>,
+) -> actix_web::Result<impl Responder> {
+    if !user.is_admin(&config) {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let summaries = spawn_blocking(move || perform_select_summaries(&pool.into_inner()))
+        .await
+        .map_err(into_internal_server_error_response)?
+        .map_err(into_internal_server_error_response)?;
+
+    Ok(json_response(&summaries, StatusCode::OK))
+}
+
+/// Ratings of the event, for admins and managers of the event.
+#[get("/{id}")]
+pub async fn feedback_summary(
+    id: Path<u64>,
+    user: TelegramUser,
+    pool: Data<DbPool>,
+    config: Data<Config>,
+) -> actix_web::Result<impl Responder> {
+    let summary = spawn_blocking(move || {
+        perform_select_summary(&pool.into_inner(), &config, &user, id.into_inner())
+    })
+    .await
+    .map_err(into_internal_server_error_response)?
+    .map_err(into_internal_server_error_response)?;
+
+    Ok(match summary {
+        Some(Some(summary)) => json_response(&summary, StatusCode::OK),
+        Some(None) => HttpResponse::NotFound().finish(),
+        None => HttpResponse::Forbidden().finish(),
+    })
+}
+
+fn perform_select_summaries(pool: &DbPool) -> Result<Vec<FeedbackSummary>, QueryError> {
+    let conn = pool.get()?;
+
+    Ok(get_feedback_summaries(&conn, 0, 0)?)
+}
+
+/// None if the user doesn't manage the event.
+fn perform_select_summary(
+    pool: &DbPool,
+    config: &Config,
+    user: &TelegramUser,
+    id: u64,
+) -> Result<Option<Option<FeedbackSummary>>, QueryError> {
+    let conn = pool.get()?;
+    if !user.is_manager(&conn, config, id)? {
+        return Ok(None);
+    }
+
+    Ok(Some(get_feedback_summary(&conn, id)?))
+}
@@ -0,0 +1,11 @@
+mod feedback_responses;
+mod feedback_summary;
+
+use actix_web::{web, Scope};
+
+pub fn feedback_scope() -> Scope {
+    web::scope("/feedback")
+        .service(feedback_summary::feedback_summaries)
+        .service(feedback_summary::feedback_summary)
+        .service(feedback_responses::feedback_responses)
+}
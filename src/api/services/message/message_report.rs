@@ -0,0 +1,51 @@
+use crate::api::shared::{into_internal_server_error_response, QueryError, TelegramUser};
+use crate::api::utils::json_response;
+use crate::configuration::config::Config;
+use crate::db::get_delivery_report;
+use crate::types::{DbPool, DeliveryReport};
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Path};
+use actix_web::{get, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+/// Delivery of a message sent with /send or /broadcast, the id is shown in the bot's report.
+/// For admins and managers of the event the message was sent to.
+#[get("/{id}/report")]
+pub async fn message_report(
+    id: Path<u64>,
+    user: TelegramUser,
+    pool: Data<DbPool>,
+    config: Data<Config>,
+) -> actix_web::Result<impl Responder> {
+    let report = spawn_blocking(move || {
+        perform_select_report(&pool.into_inner(), &config, &user, id.into_inner())
+    })
+    .await
+    .map_err(into_internal_server_error_response)?
+    .map_err(into_internal_server_error_response)?;
+
+    Ok(match report {
+        Some(Some(report)) => json_response(&report, StatusCode::OK),
+        Some(None) => HttpResponse::Forbidden().finish(),
+        None => HttpResponse::NotFound().finish(),
+    })
+}
+
+/// Some(None) if the user doesn't manage the event of the message.
+fn perform_select_report(
+    pool: &DbPool,
+    config: &Config,
+    user: &TelegramUser,
+    id: u64,
+) -> Result<Option<Option<DeliveryReport>>, QueryError> {
+    let conn = pool.get()?;
+    let report = match get_delivery_report(&conn, id)? {
+        Some(report) => report,
+        None => return Ok(None),
+    };
+    if !user.is_manager(&conn, config, report.event_id)? {
+        return Ok(Some(None));
+    }
+
+    Ok(Some(Some(report)))
+}
@@ -0,0 +1,7 @@
+mod message_report;
+
+use actix_web::{web, Scope};
+
+pub fn message_scope() -> Scope {
+    web::scope("/messages").service(message_report::message_report)
+}
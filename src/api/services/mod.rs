@@ -1 +1,8 @@
+pub mod calendar;
 pub mod event;
+pub mod feedback;
+pub mod message;
+pub mod payment;
+pub mod public;
+pub mod template;
+pub mod user;
@@ -0,0 +1,7 @@
+mod webhook;
+
+use actix_web::{web, Scope};
+
+pub fn payment_scope() -> Scope {
+    web::scope("/payments").service(webhook::payment_webhook)
+}
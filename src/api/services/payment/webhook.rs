@@ -0,0 +1,60 @@
+use crate::api::shared::into_internal_server_error_response;
+use crate::db;
+use crate::payment_providers::PaymentProvider;
+use crate::types::{DbPool, MessageType};
+use crate::util::get_unix_time;
+use actix_web::web::{Bytes, Data};
+use actix_web::{post, HttpRequest, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// Charge confirmed by the payment service, the body is signed with HMAC-SHA256 in the `X-Signature` header.
+#[post("/webhook")]
+pub async fn payment_webhook(
+    request: HttpRequest,
+    body: Bytes,
+    pool: Data<DbPool>,
+    provider: Data<dyn PaymentProvider>,
+) -> actix_web::Result<impl Responder> {
+    let signature = request
+        .headers()
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let charge = match provider.verify_webhook(&body, &signature) {
+        Ok(Some(charge)) => charge,
+        // Status update which does not confirm a charge.
+        Ok(None) => return Ok(HttpResponse::Ok().finish()),
+        Err(e) => {
+            warn!("Rejected payment webhook: {}", e);
+            return Ok(HttpResponse::Unauthorized().body(e.to_string()));
+        }
+    };
+    let result = spawn_blocking(move || -> anyhow::Result<()> {
+        let conn = pool.get()?;
+        let booking = provider.complete(&conn, &charge)?;
+        if booking.event_id != 0 {
+            db::enqueue_personal_message(
+                &conn,
+                booking.event_id,
+                booking.user_id,
+                MessageType::Tickets,
+                &text!("payment-received-tickets"),
+                get_unix_time(),
+            )?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(into_internal_server_error_response)?;
+
+    match result {
+        Ok(_) => Ok(HttpResponse::Ok().finish()),
+        Err(e) => {
+            error!("Failed to complete payment: {}", e);
+            Ok(HttpResponse::UnprocessableEntity().body(e.to_string()))
+        }
+    }
+}
@@ -0,0 +1,11 @@
+mod public_events;
+mod types;
+
+use actix_web::{web, Scope};
+
+pub fn public_scope() -> Scope {
+    web::scope("/public")
+        .service(public_events::events_calendar)
+        .service(public_events::events_json_ld)
+        .service(public_events::events_html)
+}
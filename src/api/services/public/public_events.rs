@@ -0,0 +1,139 @@
+use crate::api::services::public::types::{html_page, json_ld, FeedQuery};
+use crate::api::shared::{into_internal_server_error_response, QueryError};
+use crate::calendar;
+use crate::db::{get_events, get_events_modified};
+use crate::types::{DbPool, Event};
+use crate::util::get_unix_time;
+use actix_web::http::header::{self, HttpDate};
+use actix_web::web::{Data, Query};
+use actix_web::{get, HttpRequest, HttpResponse, Responder};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::spawn_blocking;
+
+/// Events the feed is built from, past ones are filtered out.
+const MAX_EVENTS: u64 = 1000;
+
+/// Seconds clients may reuse the feed without revalidating.
+const MAX_AGE: u64 = 300;
+
+struct Feed {
+    events: Vec<Event>,
+    modified: u64,
+}
+
+/// Upcoming events as an iCalendar feed.
+#[get("/events.ics")]
+pub async fn events_calendar(
+    req: HttpRequest,
+    query: Query<FeedQuery>,
+    pool: Data<DbPool>,
+) -> actix_web::Result<impl Responder> {
+    let feed = select_feed(query, pool).await?;
+    Ok(cached(
+        &req,
+        "text/calendar; charset=utf-8",
+        calendar::ics(&feed.events, "Events", feed.modified),
+        feed.modified,
+    ))
+}
+
+/// Upcoming events as schema.org/Event JSON-LD.
+#[get("/events.jsonld")]
+pub async fn events_json_ld(
+    req: HttpRequest,
+    query: Query<FeedQuery>,
+    pool: Data<DbPool>,
+) -> actix_web::Result<impl Responder> {
+    let feed = select_feed(query, pool).await?;
+    Ok(cached(
+        &req,
+        "application/ld+json",
+        json_ld(&feed.events).to_string(),
+        feed.modified,
+    ))
+}
+
+/// Upcoming events as an HTML list with embedded JSON-LD.
+#[get("/events.html")]
+pub async fn events_html(
+    req: HttpRequest,
+    query: Query<FeedQuery>,
+    pool: Data<DbPool>,
+) -> actix_web::Result<impl Responder> {
+    let feed = select_feed(query, pool).await?;
+    Ok(cached(
+        &req,
+        "text/html; charset=utf-8",
+        html_page(&feed.events),
+        feed.modified,
+    ))
+}
+
+async fn select_feed(query: Query<FeedQuery>, pool: Data<DbPool>) -> actix_web::Result<Feed> {
+    let feed = spawn_blocking(move || perform_select_feed(&pool.into_inner(), &query))
+        .await
+        .map_err(into_internal_server_error_response)?
+        .map_err(into_internal_server_error_response)?;
+
+    Ok(feed)
+}
+
+fn perform_select_feed(pool: &DbPool, query: &FeedQuery) -> Result<Feed, QueryError> {
+    let conn = pool.get()?;
+    let now = get_unix_time();
+
+    Ok(Feed {
+        events: get_events(&conn, 0, 0, MAX_EVENTS)?
+            .into_iter()
+            .map(|s| s.event)
+            .filter(|e| query.matches(e, now))
+            .collect(),
+        modified: get_events_modified(&conn, now)?,
+    })
+}
+
+/// Response with validators, or 304 if the client has the same content.
+/// If-None-Match takes precedence over If-Modified-Since as the content also changes when events are removed.
+fn cached(req: &HttpRequest, content_type: &str, body: String, modified: u64) -> HttpResponse {
+    let etag = format!(
+        "\"{}\"",
+        Sha256::digest(body.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    );
+    let last_modified = HttpDate::from(UNIX_EPOCH + Duration::from_secs(modified));
+    let headers = req.headers();
+    let not_modified = match headers.get(header::IF_NONE_MATCH) {
+        Some(v) => v.to_str().is_ok_and(|v| {
+            v.split(',').any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag.trim_start_matches("W/") == etag
+            })
+        }),
+        None => headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<HttpDate>().ok())
+            .is_some_and(|since| {
+                SystemTime::from(since) >= UNIX_EPOCH + Duration::from_secs(modified)
+            }),
+    };
+    let mut res = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    res.insert_header((header::ETAG, etag))
+        .insert_header((header::LAST_MODIFIED, last_modified))
+        .insert_header((
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", MAX_AGE),
+        ));
+    if not_modified {
+        res.finish()
+    } else {
+        res.content_type(content_type).body(body)
+    }
+}
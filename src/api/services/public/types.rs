@@ -0,0 +1,153 @@
+use crate::calendar::DEFAULT_DURATION;
+use crate::format::{self, from_timestamp};
+use crate::types::{Event, EventType};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use teloxide::utils::html;
+
+#[derive(Deserialize)]
+pub struct FeedQuery {
+    pub organization: Option<String>,
+    pub tag: Option<String>,
+    /// Events starting at or after, defaults to now.
+    pub from: Option<DateTime<Utc>>,
+    /// Events starting before.
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl FeedQuery {
+    /// Upcoming events open to the public which pass the filters.
+    pub fn matches(&self, e: &Event, now: u64) -> bool {
+        let from = self
+            .from
+            .map_or(now, |from| (from.timestamp() as u64).max(now));
+        e.get_type() != EventType::Announcement
+            && e.ts >= from
+            && self.to.is_none_or(|to| e.ts < to.timestamp() as u64)
+            && self
+                .organization
+                .as_ref()
+                .is_none_or(|o| e.organization.to_lowercase() == o.to_lowercase())
+            && self.tag.as_ref().is_none_or(|t| e.has_tag(t))
+    }
+}
+
+/// schema.org/Event description of the events.
+pub fn json_ld(events: &[Event]) -> Value {
+    json!({
+        "@context": "https://schema.org",
+        "@graph": events.iter().map(json_ld_event).collect::<Vec<Value>>(),
+    })
+}
+
+fn json_ld_event(e: &Event) -> Value {
+    let mut event = json!({
+        "@type": "Event",
+        "name": e.name,
+        "startDate": from_timestamp(e.ts).to_rfc3339(),
+        "endDate": from_timestamp(e.ts + DEFAULT_DURATION).to_rfc3339(),
+        "eventStatus": "https://schema.org/EventScheduled",
+        "url": e.link,
+        "offers": {
+            "@type": "Offer",
+            "url": e.link,
+            "price": format!("{:.2}", e.adult_ticket_price as f64 / 100.0),
+            "priceCurrency": e.currency,
+        },
+    });
+    if !e.venue.is_empty() {
+        event["location"] = json!({ "@type": "Place", "name": e.venue });
+    }
+    if !e.organization.is_empty() {
+        event["organizer"] = json!({ "@type": "Organization", "name": e.organization });
+    }
+    if !e.tags.is_empty() {
+        event["keywords"] = json!(e.tags.join(", "));
+    }
+    event
+}
+
+/// Page listing the events with their JSON-LD for search engines and the website.
+pub fn html_page(events: &[Event]) -> String {
+    let escape = |text: &str| html::escape(text).replace('"', "&quot;");
+    let mut items = String::new();
+    for e in events {
+        items.push_str(&format!(
+            "<li><time datetime=\"{}\">{}</time> <a href=\"{}\">{}</a>",
+            from_timestamp(e.ts).to_rfc3339(),
+            format::ts(e.ts),
+            escape(&e.link),
+            escape(&e.name)
+        ));
+        if !e.venue.is_empty() {
+            items.push_str(&format!(", {}", escape(&e.venue)));
+        }
+        items.push_str("</li>\n");
+    }
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Events</title>\n\
+        <script type=\"application/ld+json\">{}</script>\n</head>\n<body>\n<ul>\n{}</ul>\n</body>\n</html>\n",
+        json_ld(events).to_string().replace("</", "<\\/"),
+        items
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::services::public::types::{json_ld, FeedQuery};
+    use crate::types::Event;
+    use actix_web::web::Query;
+
+    fn event(ts: u64) -> Event {
+        Event {
+            id: 1,
+            name: "Concert".to_string(),
+            link: "https://example.com/1".to_string(),
+            max_adults: 10,
+            max_children: 0,
+            max_adults_per_reservation: 1,
+            max_children_per_reservation: 0,
+            ts,
+            reminders: vec![],
+            adult_ticket_price: 1050,
+            child_ticket_price: 0,
+            currency: "EUR".to_string(),
+            registration_opens_at: 0,
+            registration_closes_at: 0,
+            requires_approval: false,
+            questions: vec![],
+            named_tickets: false,
+            waiting_list_hold: 0,
+            refund_deadline: 0,
+            late_refund_percent: 0,
+            venue: "Cafe".to_string(),
+            organization: "Club".to_string(),
+            tags: vec!["Music".to_string()],
+        }
+    }
+
+    #[test]
+    fn feed_filters() {
+        let now = 1650445814;
+        let query = |q: &str| Query::<FeedQuery>::from_query(q).unwrap().into_inner();
+        let e = event(now + 60);
+        assert!(query("").matches(&e, now));
+        assert!(!query("").matches(&event(now - 60), now));
+        assert!(query("organization=club&tag=music").matches(&e, now));
+        assert!(!query("tag=talks").matches(&e, now));
+        assert!(!query("to=2022-04-20T09:10:00Z").matches(&e, now));
+        assert!(!query("from=2022-04-21T00:00:00Z").matches(&e, now));
+    }
+
+    #[test]
+    fn feed_json_ld() {
+        let ld = json_ld(&[event(1650445814)]);
+        let e = &ld["@graph"][0];
+        assert_eq!(e["@type"], "Event");
+        assert_eq!(e["startDate"], "2022-04-20T09:10:14+00:00");
+        assert_eq!(e["offers"]["price"], "10.50");
+        assert_eq!(e["location"]["name"], "Cafe");
+        assert_eq!(e["organizer"]["name"], "Club");
+        assert_eq!(e["keywords"], "Music");
+    }
+}
@@ -0,0 +1,41 @@
+use crate::api::services::template::types::TemplateView;
+use crate::api::shared::{into_internal_server_error_response, QueryError};
+use crate::api::utils::json_response;
+use crate::templates::{self, TemplateKind};
+use crate::types::DbPool;
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Path};
+use actix_web::{get, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+#[get("/{event}/{kind}")]
+pub async fn get_template(
+    path: Path<(u64, String)>,
+    pool: Data<DbPool>,
+) -> actix_web::Result<impl Responder> {
+    let (event_id, kind) = path.into_inner();
+    let kind = match TemplateKind::parse(&kind) {
+        Some(kind) => kind,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let template =
+        spawn_blocking(move || perform_select_template(&pool.into_inner(), event_id, kind))
+            .await
+            .map_err(into_internal_server_error_response)?
+            .map_err(into_internal_server_error_response)?;
+
+    Ok(json_response(
+        &TemplateView::new(event_id, kind, template),
+        StatusCode::OK,
+    ))
+}
+
+fn perform_select_template(
+    pool: &DbPool,
+    event_id: u64,
+    kind: TemplateKind,
+) -> Result<Option<String>, QueryError> {
+    let conn = pool.get()?;
+
+    Ok(templates::find(&conn, event_id, kind)?)
+}
@@ -0,0 +1,15 @@
+mod get_template;
+mod preview_template;
+mod remove_template;
+mod set_template;
+mod types;
+
+use actix_web::{web, Scope};
+
+pub fn template_scope() -> Scope {
+    web::scope("/templates")
+        .service(get_template::get_template)
+        .service(set_template::set_template)
+        .service(preview_template::preview_template)
+        .service(remove_template::remove_template)
+}
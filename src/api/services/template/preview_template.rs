@@ -0,0 +1,42 @@
+use crate::api::services::template::types::{RawTemplate, TemplatePreview};
+use crate::api::shared::into_internal_server_error_response;
+use crate::api::utils::json_response;
+use crate::templates::{self, TemplateKind};
+use crate::types::DbPool;
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Json, Path};
+use actix_web::{post, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+/// Renders the template with the event data without saving it.
+#[post("/{event}/{kind}/preview")]
+pub async fn preview_template(
+    path: Path<(u64, String)>,
+    template: Json<RawTemplate>,
+    pool: Data<DbPool>,
+) -> actix_web::Result<impl Responder> {
+    let (event_id, kind) = path.into_inner();
+    let kind = match TemplateKind::parse(&kind) {
+        Some(kind) => kind,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let template = template.into_inner();
+    if let Err(e) = templates::validate(kind, &template.text) {
+        return Ok(HttpResponse::BadRequest().body(e.to_string()));
+    }
+    let result = spawn_blocking(move || -> anyhow::Result<TemplatePreview> {
+        let conn = pool.get()?;
+        let preview = templates::preview(&conn, event_id, kind, &template.text, &template.user)?;
+        Ok(TemplatePreview {
+            text: template.text,
+            preview,
+        })
+    })
+    .await
+    .map_err(into_internal_server_error_response)?;
+
+    match result {
+        Ok(preview) => Ok(json_response(&preview, StatusCode::OK)),
+        Err(e) => Ok(HttpResponse::UnprocessableEntity().body(e.to_string())),
+    }
+}
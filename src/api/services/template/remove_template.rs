@@ -0,0 +1,47 @@
+use crate::api::shared::{into_internal_server_error_response, QueryError, TelegramUser};
+use crate::configuration::config::Config;
+use crate::db;
+use crate::templates::TemplateKind;
+use crate::types::DbPool;
+use actix_web::web::{Data, Path};
+use actix_web::{delete, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+/// Deletes the template, the one for all events or the default text is used instead.
+/// Only admins can delete templates.
+#[delete("/{event}/{kind}")]
+pub async fn remove_template(
+    path: Path<(u64, String)>,
+    user: TelegramUser,
+    pool: Data<DbPool>,
+    config: Data<Config>,
+) -> actix_web::Result<impl Responder> {
+    if !user.is_admin(&config) {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let (event_id, kind) = path.into_inner();
+    let kind = match TemplateKind::parse(&kind) {
+        Some(kind) => kind,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let deleted =
+        spawn_blocking(move || perform_delete_template(&pool.into_inner(), event_id, kind))
+            .await
+            .map_err(into_internal_server_error_response)?
+            .map_err(into_internal_server_error_response)?;
+
+    Ok(match deleted {
+        0 => HttpResponse::NotFound().finish(),
+        _ => HttpResponse::NoContent().finish(),
+    })
+}
+
+fn perform_delete_template(
+    pool: &DbPool,
+    event_id: u64,
+    kind: TemplateKind,
+) -> Result<usize, QueryError> {
+    let conn = pool.get()?;
+
+    Ok(db::delete_template(&conn, event_id, kind.name())?)
+}
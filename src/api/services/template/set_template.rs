@@ -0,0 +1,51 @@
+use crate::api::services::template::types::{RawTemplate, TemplatePreview};
+use crate::api::shared::{into_internal_server_error_response, TelegramUser};
+use crate::api::utils::json_response;
+use crate::configuration::config::Config;
+use crate::db;
+use crate::templates::{self, TemplateKind};
+use crate::types::DbPool;
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Json, Path};
+use actix_web::{put, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+/// Validates and saves the template, event 0 for all events, and returns its preview.
+/// Templates go out to every user, only admins can change them.
+#[put("/{event}/{kind}")]
+pub async fn set_template(
+    path: Path<(u64, String)>,
+    user: TelegramUser,
+    template: Json<RawTemplate>,
+    pool: Data<DbPool>,
+    config: Data<Config>,
+) -> actix_web::Result<impl Responder> {
+    if !user.is_admin(&config) {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let (event_id, kind) = path.into_inner();
+    let kind = match TemplateKind::parse(&kind) {
+        Some(kind) => kind,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let template = template.into_inner();
+    if let Err(e) = templates::validate(kind, &template.text) {
+        return Ok(HttpResponse::BadRequest().body(e.to_string()));
+    }
+    let result = spawn_blocking(move || -> anyhow::Result<TemplatePreview> {
+        let conn = pool.get()?;
+        let preview = templates::preview(&conn, event_id, kind, &template.text, &template.user)?;
+        db::set_template(&conn, event_id, kind.name(), &template.text)?;
+        Ok(TemplatePreview {
+            text: template.text,
+            preview,
+        })
+    })
+    .await
+    .map_err(into_internal_server_error_response)?;
+
+    match result {
+        Ok(preview) => Ok(json_response(&preview, StatusCode::OK)),
+        Err(e) => Ok(HttpResponse::UnprocessableEntity().body(e.to_string())),
+    }
+}
@@ -0,0 +1,35 @@
+use crate::templates::TemplateKind;
+
+#[derive(Deserialize)]
+pub struct RawTemplate {
+    pub text: String,
+    /// Name shown for {user} in the preview.
+    #[serde(default)]
+    pub user: String,
+}
+
+#[derive(Serialize)]
+pub struct TemplateView {
+    pub event: u64,
+    pub kind: &'static str,
+    /// Template of the event or the one for all events, none if the default text is used.
+    pub text: Option<String>,
+    pub placeholders: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+pub struct TemplatePreview {
+    pub text: String,
+    pub preview: String,
+}
+
+impl TemplateView {
+    pub fn new(event: u64, kind: TemplateKind, text: Option<String>) -> Self {
+        TemplateView {
+            event,
+            kind: kind.name(),
+            text,
+            placeholders: kind.placeholders(),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+mod user_bookings;
+
+use actix_web::{web, Scope};
+
+pub fn user_scope() -> Scope {
+    web::scope("/user").service(user_bookings::user_bookings)
+}
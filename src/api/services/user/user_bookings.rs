@@ -0,0 +1,48 @@
+use crate::api::shared::{into_internal_server_error_response, QueryError, TelegramUser};
+use crate::api::utils::json_response;
+use crate::db::get_user_bookings;
+use crate::types::{DbPool, UserBooking};
+use crate::util::get_unix_time;
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Path, Query};
+use actix_web::{get, HttpResponse, Responder};
+use tokio::task::spawn_blocking;
+
+#[derive(Deserialize)]
+pub struct BookingsQuery {
+    /// Adds past events the user attended.
+    #[serde(default)]
+    past: bool,
+}
+
+/// Upcoming events the user has confirmed, waiting, pending or unpaid seats for.
+/// Only the user themselves can see their bookings.
+#[get("/{id}/bookings")]
+pub async fn user_bookings(
+    id: Path<u64>,
+    user: TelegramUser,
+    query: Query<BookingsQuery>,
+    pool: Data<DbPool>,
+) -> actix_web::Result<impl Responder> {
+    if *id != user.id {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let past = query.into_inner().past;
+    let bookings =
+        spawn_blocking(move || perform_select_bookings(&pool.into_inner(), id.into_inner(), past))
+            .await
+            .map_err(into_internal_server_error_response)?
+            .map_err(into_internal_server_error_response)?;
+
+    Ok(json_response(&bookings, StatusCode::OK))
+}
+
+fn perform_select_bookings(
+    pool: &DbPool,
+    id: u64,
+    past: bool,
+) -> Result<Vec<UserBooking>, QueryError> {
+    let conn = pool.get()?;
+
+    Ok(get_user_bookings(&conn, id, get_unix_time(), past)?)
+}
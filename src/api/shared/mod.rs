@@ -1,10 +1,12 @@
 mod internal_server_error;
+mod telegram_auth;
 
 use crate::util;
 pub use internal_server_error::into_internal_server_error_response;
 pub use internal_server_error::InternalServerError;
 pub use internal_server_error::QueryError;
 use serde::Serialize;
+pub use telegram_auth::TelegramUser;
 
 #[derive(Deserialize)]
 pub struct RawPagination {
@@ -0,0 +1,149 @@
+use crate::configuration::config::Config;
+use crate::db;
+use crate::types::Connection;
+use crate::util::get_unix_time;
+use actix_web::dev::Payload;
+use actix_web::error::ErrorUnauthorized;
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpRequest};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::future::{ready, Ready};
+
+/// Launch data older than this is rejected.
+const MAX_AGE: u64 = 24 * 60 * 60;
+
+/// User of the Telegram Mini App, authenticated by its launch data (`initData`) signed with the bot token.
+/// The launch data is passed in the `Authorization: tma <initData>` header.
+pub struct TelegramUser {
+    pub id: u64,
+}
+
+impl TelegramUser {
+    pub fn is_admin(&self, config: &Config) -> bool {
+        config.admins.contains(&self.id)
+    }
+
+    /// Admins and event managers, like in the bot.
+    pub fn is_manager(
+        &self,
+        conn: &Connection,
+        config: &Config,
+        event_id: u64,
+    ) -> Result<bool, rusqlite::Error> {
+        Ok(self.is_admin(config) || db::is_group_leader(conn, event_id, self.id)?)
+    }
+}
+
+impl FromRequest for TelegramUser {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let init_data = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("tma "));
+        let config = request.app_data::<Data<Config>>();
+        ready(match (init_data, config) {
+            (Some(init_data), Some(config)) => {
+                verify_init_data(init_data, &config.telegram_bot_token, get_unix_time())
+                    .map(|id| TelegramUser { id })
+                    .ok_or_else(|| ErrorUnauthorized("invalid Telegram init data"))
+            }
+            _ => Err(ErrorUnauthorized("Telegram init data required")),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct WebAppUser {
+    id: u64,
+}
+
+/// Id of the user from launch data with a valid signature, see
+/// https://core.telegram.org/bots/webapps#validating-data-received-via-the-mini-app
+fn verify_init_data(init_data: &str, bot_token: &str, now: u64) -> Option<u64> {
+    if bot_token.is_empty() {
+        return None;
+    }
+    let mut hash = None;
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for (key, value) in url::form_urlencoded::parse(init_data.as_bytes()) {
+        if key == "hash" {
+            hash = Some(value.into_owned());
+        } else {
+            fields.push((key.into_owned(), value.into_owned()));
+        }
+    }
+    fields.sort();
+    let data_check_string = fields
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(&secret_key(bot_token)).expect("HMAC takes any key size");
+    mac.update(data_check_string.as_bytes());
+    mac.verify_slice(&hex::decode(hash?).ok()?).ok()?;
+
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+    let auth_date: u64 = field("auth_date")?.parse().ok()?;
+    if auth_date + MAX_AGE < now {
+        return None;
+    }
+    serde_json::from_str::<WebAppUser>(field("user")?)
+        .ok()
+        .map(|user| user.id)
+}
+
+fn secret_key(bot_token: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"WebAppData").expect("HMAC takes any key size");
+    mac.update(bot_token.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(data_check_string: &str, bot_token: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&secret_key(bot_token)).unwrap();
+        mac.update(data_check_string.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    #[test]
+    fn init_data_validation() {
+        let token = "123:abc";
+        let user = r#"{"id":42,"first_name":"Ann"}"#;
+        let hash = sign(&format!("auth_date=1000\nquery_id=q\nuser={}", user), token);
+        let init_data = format!(
+            "query_id=q&user={}&auth_date=1000&hash={}",
+            url::form_urlencoded::byte_serialize(user.as_bytes()).collect::<String>(),
+            hash
+        );
+        assert_eq!(verify_init_data(&init_data, token, 2000), Some(42));
+        assert_eq!(verify_init_data(&init_data, "123:other", 2000), None);
+        assert_eq!(
+            verify_init_data(&init_data, token, 1000 + MAX_AGE + 1),
+            None
+        );
+        assert_eq!(
+            verify_init_data(
+                &init_data.replace("auth_date=1000", "auth_date=1001"),
+                token,
+                2000
+            ),
+            None
+        );
+        assert_eq!(verify_init_data(&init_data, "", 2000), None);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::calendar::DEFAULT_DURATION;
+use crate::db;
+use crate::format;
+use crate::i18n::Lang;
+use crate::message_handler::CallbackQuery;
+use crate::payments::describe_order;
+use crate::reply::*;
+use crate::types::{Connection, Context, User, UserBooking};
+use crate::util::get_unix_time;
+use teloxide::types::InlineKeyboardButton;
+use url::Url;
+
+/// Upcoming events the user has seats for, `past` adds the attended ones.
+pub fn show_my_bookings(
+    conn: &Connection,
+    user: &User,
+    past: bool,
+    _ctx: &Context,
+) -> anyhow::Result<Reply> {
+    let now = get_unix_time();
+    let bookings = db::get_user_bookings(conn, user.id.0, now, past)?;
+    let mut message = ReplyMessage::new(if bookings.is_empty() {
+        t!(user.lang, "no-bookings")
+    } else {
+        t!(user.lang, "my-bookings")
+    });
+    let mut keyboard = Vec::new();
+    for b in &bookings {
+        message = message.text(Some(describe(b, user.lang)));
+        keyboard.extend(controls(b, now, user.lang)?);
+    }
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        if past {
+            t!(user.lang, "upcoming-bookings-button")
+        } else {
+            t!(user.lang, "past-bookings-button")
+        },
+        serde_json::to_string(&CallbackQuery::MyBookings { past: !past })?,
+    )]);
+    Ok(message.keyboard(keyboard).into())
+}
+
+/// Seats of the booking by category with the payment status and the note.
+fn describe(b: &UserBooking, lang: Lang) -> String {
+    let seats = |adults, children| describe_order(adults, children, b.no_age_distinction, lang);
+    let mut text = format!("\n\n<b>{} {}</b>", format::ts(b.ts), b.name);
+    if !b.venue.is_empty() {
+        text.push_str(&format!("\n{}", b.venue));
+    }
+    if b.adults + b.children > 0 {
+        text.push_str(&format!(
+            "\n{}",
+            t!(
+                lang,
+                "booking-confirmed",
+                seats = seats(b.adults, b.children),
+                paid = if b.is_paid { "yes" } else { "no" }
+            )
+        ));
+    }
+    if b.unpaid_adults + b.unpaid_children > 0 {
+        text.push_str(&format!(
+            "\n{}",
+            t!(
+                lang,
+                "booking-unpaid",
+                seats = seats(b.unpaid_adults, b.unpaid_children)
+            )
+        ));
+    }
+    if b.pending_adults + b.pending_children > 0 {
+        text.push_str(&format!(
+            "\n{}",
+            t!(
+                lang,
+                "booking-pending",
+                seats = seats(b.pending_adults, b.pending_children)
+            )
+        ));
+    }
+    if b.waiting_adults + b.waiting_children > 0 {
+        text.push_str(&format!(
+            "\n{}",
+            t!(
+                lang,
+                "booking-waiting",
+                seats = seats(b.waiting_adults, b.waiting_children)
+            )
+        ));
+    }
+    if b.attended {
+        text.push_str(&format!("\n{}", t!(lang, "booking-attended")));
+    }
+    if let Some(attachment) = &b.attachment {
+        text.push_str(&format!(
+            "\n{}",
+            t!(lang, "attachment", attachment = attachment.as_str())
+        ));
+    }
+    text
+}
+
+/// Buttons to open the event, cancel the booking and add it to a calendar.
+fn controls(
+    b: &UserBooking,
+    now: u64,
+    lang: Lang,
+) -> anyhow::Result<Vec<Vec<InlineKeyboardButton>>> {
+    let event_id = b.event_id;
+    let mut keyboard = vec![vec![InlineKeyboardButton::callback(
+        format!("{} {}", format::ts(b.ts), b.name),
+        serde_json::to_string(&if b.is_paid {
+            CallbackQuery::PaidEvent {
+                event_id,
+                adults: 0,
+                children: 0,
+                offset: 0,
+            }
+        } else {
+            CallbackQuery::Event {
+                event_id,
+                offset: 0,
+            }
+        })?,
+    )]];
+    if b.ts < now {
+        return Ok(keyboard);
+    }
+    let mut row = Vec::new();
+    // Paid seats are returned with a refund request on the event screen.
+    if !b.is_paid || b.adults + b.children == 0 {
+        row.push(InlineKeyboardButton::callback(
+            t!(lang, "cancel-booking-button"),
+            serde_json::to_string(&CallbackQuery::WontGo { event_id })?,
+        ));
+    }
+    if let Some(url) = calendar_link(b) {
+        row.push(InlineKeyboardButton::url(t!(lang, "calendar-button"), url));
+    }
+    keyboard.push(row);
+    Ok(keyboard)
+}
+
+/// Google Calendar link which opens a new entry for the event.
+pub fn calendar_link(b: &UserBooking) -> Option<Url> {
+    let date = |ts| {
+        format::from_timestamp(ts)
+            .format("%Y%m%dT%H%M%SZ")
+            .to_string()
+    };
+    Url::parse_with_params(
+        "https://calendar.google.com/calendar/render",
+        &[
+            ("action", "TEMPLATE".to_string()),
+            ("text", b.name.clone()),
+            (
+                "dates",
+                format!("{}/{}", date(b.ts), date(b.ts + DEFAULT_DURATION)),
+            ),
+            ("details", b.link.clone()),
+            ("location", b.venue.clone()),
+        ],
+    )
+    .ok()
+}
@@ -0,0 +1,348 @@
+use crate::db;
+use crate::format;
+use crate::i18n::Lang;
+use crate::message_handler::CallbackQuery;
+use crate::reply::*;
+use crate::types::{Audience, Connection, Context, Media, User};
+use crate::util::{get_unix_time, parse_duration};
+use teloxide::{
+    adaptors::AutoSend,
+    prelude::*,
+    types::{
+        ChatId, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, Message, ParseMode, PollType,
+    },
+    ApiError, RequestError,
+};
+
+/// Telegram limit of media captions.
+const MAX_CAPTION_LENGTH: usize = 1024;
+
+/// Media of the message an admin replied to with /send or /broadcast, text messages have none.
+pub fn media(msg: &Message) -> Option<Media> {
+    if msg.text().is_some() {
+        return None;
+    }
+    Some(if let Some(photo) = msg.photo() {
+        // The largest size comes last.
+        Media::Photo {
+            file_id: photo.last()?.file_id.clone(),
+        }
+    } else if let Some(animation) = msg.animation() {
+        Media::Animation {
+            file_id: animation.file_id.clone(),
+        }
+    } else if let Some(document) = msg.document() {
+        Media::Document {
+            file_id: document.file_id.clone(),
+        }
+    } else if let Some(video) = msg.video() {
+        Media::Video {
+            file_id: video.file_id.clone(),
+        }
+    } else if let Some(audio) = msg.audio() {
+        Media::Audio {
+            file_id: audio.file_id.clone(),
+        }
+    } else if let Some(poll) = msg.poll() {
+        Media::Poll {
+            question: poll.question.clone(),
+            options: poll.options.iter().map(|o| o.text.clone()).collect(),
+            is_anonymous: poll.is_anonymous,
+            allows_multiple_answers: poll.allows_multiple_answers,
+            correct_option_id: match poll.poll_type {
+                PollType::Quiz => poll.correct_option_id,
+                PollType::Regular => None,
+            },
+        }
+    } else {
+        Media::Copy {
+            chat_id: msg.chat.id.0,
+            message_id: msg.id,
+        }
+    })
+}
+
+/// Sends the text with the media, as its caption if it fits, the keyboard goes with the last message.
+pub async fn deliver(
+    bot: &AutoSend<Bot>,
+    user: UserId,
+    text: String,
+    media: Option<&Media>,
+    keyboard: Vec<Vec<InlineKeyboardButton>>,
+) -> Result<(), RequestError> {
+    let markup = InlineKeyboardMarkup::new(keyboard.clone());
+    let media = match media {
+        Some(media) => media,
+        None => {
+            let mut req = bot
+                .send_message(user, text)
+                .parse_mode(ParseMode::Html)
+                .disable_web_page_preview(true);
+            if !keyboard.is_empty() {
+                req = req.reply_markup(markup);
+            }
+            req.await?;
+            return Ok(());
+        }
+    };
+    let caption = if media.has_caption() && text.chars().count() <= MAX_CAPTION_LENGTH {
+        Some(text)
+    } else {
+        bot.send_message(user, text)
+            .parse_mode(ParseMode::Html)
+            .disable_web_page_preview(true)
+            .await?;
+        None
+    };
+    let markup = (!keyboard.is_empty()).then_some(markup);
+    macro_rules! send_file {
+        ($method:ident, $file_id:expr) => {{
+            let mut req = bot.$method(user, InputFile::file_id($file_id.as_str()));
+            if let Some(caption) = caption {
+                req = req.caption(caption).parse_mode(ParseMode::Html);
+            }
+            if let Some(markup) = markup {
+                req = req.reply_markup(markup);
+            }
+            req.await?;
+        }};
+    }
+    match media {
+        Media::Photo { file_id } => send_file!(send_photo, file_id),
+        Media::Document { file_id } => send_file!(send_document, file_id),
+        Media::Video { file_id } => send_file!(send_video, file_id),
+        Media::Animation { file_id } => send_file!(send_animation, file_id),
+        Media::Audio { file_id } => send_file!(send_audio, file_id),
+        Media::Poll {
+            question,
+            options,
+            is_anonymous,
+            allows_multiple_answers,
+            correct_option_id,
+        } => {
+            let mut req = bot
+                .send_poll(user, question.as_str(), options.clone())
+                .is_anonymous(*is_anonymous)
+                .allows_multiple_answers(*allows_multiple_answers);
+            if let Some(correct_option_id) = correct_option_id {
+                req = req
+                    .type_(PollType::Quiz)
+                    .correct_option_id(*correct_option_id);
+            }
+            if let Some(markup) = markup {
+                req = req.reply_markup(markup);
+            }
+            req.await?;
+        }
+        Media::Copy {
+            chat_id,
+            message_id,
+        } => {
+            let mut req = bot.copy_message(user, ChatId(*chat_id), *message_id);
+            if let Some(markup) = markup {
+                req = req.reply_markup(markup);
+            }
+            req.await?;
+        }
+    }
+    Ok(())
+}
+
+/// Why the message can't be delivered to the user, None for errors worth retrying later.
+pub fn failure_reason(e: &RequestError) -> Option<String> {
+    match e {
+        RequestError::Api(ApiError::BotBlocked) => Some("bot_blocked".to_string()),
+        RequestError::Api(ApiError::BotKicked) => Some("bot_kicked".to_string()),
+        RequestError::Api(ApiError::UserDeactivated) => Some("user_deactivated".to_string()),
+        RequestError::Api(ApiError::ChatNotFound) => Some("chat_not_found".to_string()),
+        RequestError::Api(e) => Some(e.to_string()),
+        RequestError::MigrateToChatId(_) => Some("chat_migrated".to_string()),
+        _ => None,
+    }
+}
+
+/// Button showing the delivery report, pressing it again refreshes the report.
+pub fn report_button(lang: Lang, message_id: u64) -> anyhow::Result<InlineKeyboardButton> {
+    Ok(InlineKeyboardButton::callback(
+        t!(lang, "delivery-report-button"),
+        serde_json::to_string(&CallbackQuery::DeliveryReport { message_id })?,
+    ))
+}
+
+/// Recipients targeted, delivered, failed with the reasons and still pending.
+pub fn report(
+    conn: &Connection,
+    user: &User,
+    message_id: u64,
+    _ctx: &Context,
+) -> anyhow::Result<Reply> {
+    let report = match db::get_delivery_report(conn, message_id)? {
+        Some(report) => report,
+        None => return Err(text!("delivery-report-not-found").into()),
+    };
+    let mut reply = ReplyMessage::new(t!(
+        user.lang,
+        "delivery-report",
+        message_id = message_id.to_string(),
+        targeted = report.targeted,
+        delivered = report.delivered,
+        failed = report.failed,
+        pending = report.pending
+    ));
+    for (reason, count) in &report.failures {
+        reply = reply.text(Some(format!(
+            "\n{}",
+            t!(
+                user.lang,
+                "delivery-report-failure",
+                reason = reason.as_str(),
+                count = *count
+            )
+        )));
+    }
+    Ok(reply
+        .text(Some(format!(
+            "\n{}",
+            t!(
+                user.lang,
+                "delivery-report-updated",
+                time = format::ts(get_unix_time())
+            )
+        )))
+        .keyboard(vec![vec![report_button(user.lang, message_id)?]])
+        .into())
+}
+
+/// Splits "<audience> [in <delay>] <text>" into its parts.
+fn parse(data: &str) -> Option<(Audience, u64, &str)> {
+    let (kind, rest) = data.trim_start().split_once(' ')?;
+    let (audience, rest) = match kind {
+        "all" => (Audience::All, rest),
+        _ => {
+            let (value, rest) = rest.trim_start().split_once(' ')?;
+            (format!("{} {}", kind, value).parse().ok()?, rest)
+        }
+    };
+    let rest = rest.trim_start();
+    let (delay, text) = match rest.strip_prefix("in ") {
+        Some(scheduled) => {
+            let (delay, text) = scheduled.trim_start().split_once(' ')?;
+            (parse_duration(delay)?, text)
+        }
+        None => (0, rest),
+    };
+    match text.trim() {
+        "" => None,
+        text => Some((audience, delay, text)),
+    }
+}
+
+/// Stores the broadcast and shows it with the number of recipients before sending.
+pub fn prepare(
+    conn: &Connection,
+    user: &User,
+    data: &str,
+    media: Option<Media>,
+    _ctx: &Context,
+) -> anyhow::Result<Reply> {
+    let (audience, delay, text) = match parse(data) {
+        Some(broadcast) => broadcast,
+        None => return Err(text!("broadcast-usage").into()),
+    };
+    let text = text!(
+        "broadcast-message",
+        sender_id = user.id.0.to_string(),
+        sender = user.user_name1.as_str(),
+        text = text,
+    );
+    let recipients = db::get_audience(conn, &audience)?.len();
+    if recipients == 0 {
+        return Err(text!("broadcast-no-recipients", audience = audience.to_string()).into());
+    }
+    let message_id =
+        db::create_broadcast(conn, &user.user_name1, &audience, &text, media.as_ref())?;
+    let send_at = get_unix_time() + delay;
+    let mut reply = ReplyMessage::new(t!(
+        user.lang,
+        "broadcast-preview",
+        audience = audience.to_string(),
+        recipients = recipients,
+        start = format::ts(send_at),
+        text = text.render(user.lang)
+    ));
+    if let Some(media) = &media {
+        reply = reply.text(Some(format!(
+            "\n{}",
+            t!(user.lang, "broadcast-media", kind = media.kind())
+        )));
+    }
+    Ok(reply
+        .keyboard(vec![vec![
+            InlineKeyboardButton::callback(
+                t!(user.lang, "broadcast-send-button"),
+                serde_json::to_string(&CallbackQuery::SendBroadcast {
+                    message_id,
+                    send_at,
+                })?,
+            ),
+            InlineKeyboardButton::callback(
+                t!(user.lang, "broadcast-cancel-button"),
+                serde_json::to_string(&CallbackQuery::CancelBroadcast { message_id })?,
+            ),
+        ]])
+        .into())
+}
+
+/// Puts the confirmed broadcast into the outbox, sent within the mailing hours.
+pub fn send(
+    conn: &Connection,
+    user: &User,
+    message_id: u64,
+    send_at: u64,
+    _ctx: &Context,
+) -> anyhow::Result<Reply> {
+    Ok(match db::schedule_broadcast(conn, message_id, send_at)? {
+        0 => ReplyMessage::new(t!(user.lang, "broadcast-not-found")),
+        recipients => ReplyMessage::new(t!(
+            user.lang,
+            "broadcast-scheduled",
+            recipients = recipients,
+            start = format::ts(send_at)
+        ))
+        .keyboard(vec![vec![report_button(user.lang, message_id)?]]),
+    }
+    .into())
+}
+
+pub fn cancel(
+    conn: &Connection,
+    user: &User,
+    message_id: u64,
+    _ctx: &Context,
+) -> anyhow::Result<Reply> {
+    Ok(match db::delete_broadcast(conn, message_id)? {
+        0 => ReplyMessage::new(t!(user.lang, "broadcast-not-found")),
+        _ => ReplyMessage::new(t!(user.lang, "broadcast-cancelled")),
+    }
+    .into())
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(
+        parse("all hello there"),
+        Some((Audience::All, 0, "hello there"))
+    );
+    assert_eq!(
+        parse("attended 12 in 2h see you"),
+        Some((Audience::Attended(12), 2 * 60 * 60, "see you"))
+    );
+    assert_eq!(
+        parse("tag music new concert"),
+        Some((Audience::Tag("music".to_string()), 0, "new concert"))
+    );
+    assert_eq!(parse("no_show x text"), None);
+    assert_eq!(parse("all in 2x text"), None);
+    assert_eq!(parse("all "), None);
+    assert_eq!(parse("everyone text"), None);
+}
@@ -0,0 +1,192 @@
+use crate::db::{self, EventStats};
+use crate::format;
+use crate::i18n::Lang;
+use crate::message_handler::{back_to_event, CallbackQuery};
+use crate::reply::*;
+use crate::types::{Connection, Context, Event, EventType, User};
+use crate::util::get_unix_time;
+use std::env;
+use teloxide::types::InlineKeyboardButton;
+
+/// Events have no end time, calendar entries last this long.
+pub const DEFAULT_DURATION: u64 = 2 * 60 * 60;
+
+/// Content lines are folded after this many octets.
+const MAX_LINE_LENGTH: usize = 75;
+
+pub fn controls(s: &EventStats, lang: Lang) -> anyhow::Result<Vec<Vec<InlineKeyboardButton>>> {
+    if s.event.get_type() == EventType::Announcement {
+        return Ok(vec![]);
+    }
+    Ok(vec![vec![InlineKeyboardButton::callback(
+        t!(lang, "calendar-file-button"),
+        serde_json::to_string(&CallbackQuery::Calendar {
+            event_id: s.event.id,
+        })?,
+    )]])
+}
+
+/// Sends the event as an .ics file.
+pub fn send_event(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    let modified = db::get_events_modified(conn, get_unix_time())?;
+    let mut message = ReplyMessage::new(t!(user.lang, "calendar-file"));
+    if !ctx.config.public_url.is_empty() {
+        message = message.text(Some(format!("\n{}", t!(user.lang, "calendar-hint"))));
+    }
+    Ok(message
+        .keyboard(vec![vec![back_to_event(&s, user.lang)?]])
+        .documents(vec![ReplyDocument {
+            file_name: format!("event-{}.ics", event_id),
+            content: ics(std::slice::from_ref(&s.event), &s.event.name, modified).into_bytes(),
+        }])
+        .into())
+}
+
+/// Link to the user's calendar feed, `reset` issues a new one and the old link stops working.
+pub fn show_subscription(
+    conn: &Connection,
+    user: &User,
+    reset: bool,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    if ctx.config.public_url.is_empty() {
+        return Err(text!("calendar-unavailable").into());
+    }
+    let token = db::get_calendar_token(conn, user.id.0, reset)?;
+    Ok(ReplyMessage::new(t!(
+        user.lang,
+        "calendar-subscription",
+        url = format!("{}/calendar/{}.ics", ctx.config.public_url, token)
+    ))
+    .into())
+}
+
+/// RFC 5545 calendar with the events, reminders become alarms.
+/// `modified` is the DTSTAMP, so the calendar stays the same until the events change.
+pub fn ics(events: &[Event], name: &str, modified: u64) -> String {
+    let stamp = date(modified);
+    let domain = match env::var("BOT_NAME") {
+        Ok(bot_name) if !bot_name.is_empty() => bot_name,
+        _ => "event-manager-telegram-bot".to_string(),
+    };
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//event-manager-telegram-bot//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ];
+    for e in events {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:event-{}@{}", e.id, domain));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART:{}", date(e.ts)));
+        lines.push(format!("DTEND:{}", date(e.ts + DEFAULT_DURATION)));
+        lines.push(format!("SUMMARY:{}", escape(&e.name)));
+        if !e.venue.is_empty() {
+            lines.push(format!("LOCATION:{}", escape(&e.venue)));
+        }
+        if !e.link.is_empty() {
+            lines.push(format!("URL:{}", e.link));
+            lines.push(format!("DESCRIPTION:{}", escape(&e.link)));
+        }
+        for r in &e.reminders {
+            lines.push("BEGIN:VALARM".to_string());
+            lines.push("ACTION:DISPLAY".to_string());
+            lines.push(format!("DESCRIPTION:{}", escape(&e.name)));
+            lines.push(format!("TRIGGER:-PT{}S", r.before));
+            lines.push("END:VALARM".to_string());
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|l| fold(l)).collect()
+}
+
+fn date(ts: u64) -> String {
+    format::from_timestamp(ts)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// Escapes a TEXT value.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Splits a content line into lines of at most 75 octets, continuations start with a space.
+fn fold(line: &str) -> String {
+    let mut res = String::with_capacity(line.len() + 2);
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            res.push_str("\r\n ");
+            length = 1;
+        }
+        res.push(c);
+        length += c.len_utf8();
+    }
+    res.push_str("\r\n");
+    res
+}
+
+#[test]
+fn test_ics() {
+    use crate::types::Reminder;
+    let e = Event {
+        id: 7,
+        name: "Concert; songs, dances".to_string(),
+        link: "https://example.com/7".to_string(),
+        max_adults: 10,
+        max_children: 0,
+        max_adults_per_reservation: 1,
+        max_children_per_reservation: 0,
+        ts: 1650445814,
+        reminders: vec![Reminder {
+            before: 2 * 60 * 60,
+            template: None,
+        }],
+        adult_ticket_price: 0,
+        child_ticket_price: 0,
+        currency: "".to_string(),
+        registration_opens_at: 0,
+        registration_closes_at: 0,
+        requires_approval: false,
+        questions: vec![],
+        named_tickets: false,
+        waiting_list_hold: 0,
+        refund_deadline: 0,
+        late_refund_percent: 0,
+        venue: "Cafe".to_string(),
+        organization: "".to_string(),
+        tags: vec![],
+    };
+    let ics = ics(&[e], "Events", 1650400000);
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    assert!(ics.contains("\r\nDTSTAMP:20220419T202640Z\r\n"));
+    assert!(ics.contains("\r\nDTSTART:20220420T091014Z\r\n"));
+    assert!(ics.contains("\r\nDTEND:20220420T111014Z\r\n"));
+    assert!(ics.contains("\r\nSUMMARY:Concert\\; songs\\, dances\r\n"));
+    assert!(ics.contains("\r\nLOCATION:Cafe\r\n"));
+    assert!(ics.contains("\r\nTRIGGER:-PT7200S\r\n"));
+    assert!(ics.contains("\r\nUID:event-7@"));
+
+    let folded = fold(&"я".repeat(50));
+    assert!(folded.split("\r\n").all(|l| l.len() <= MAX_LINE_LENGTH));
+    assert_eq!(
+        folded.replace("\r\n ", ""),
+        format!("{}\r\n", "я".repeat(50))
+    );
+}
@@ -0,0 +1,229 @@
+use crate::db::{self, EventStats};
+use crate::format;
+use crate::i18n::Lang;
+use crate::message_handler::{is_manager, show_event, CallbackQuery};
+use crate::reply::*;
+use crate::tickets;
+use crate::types::{CheckIn, Connection, Context, Ticket, User};
+use crate::util::read_qr;
+use anyhow::anyhow;
+use teloxide::types::InlineKeyboardButton;
+use teloxide::utils::html;
+
+const CODE_LENGTH: usize = 32;
+
+/// Extracts a ticket code from a scanned deep link, a /start command or the bare code.
+fn code_from_text(data: &str) -> Option<&str> {
+    let data = data.trim();
+    let code = if let Some((_, code)) = data.split_once("?start=t") {
+        code
+    } else if let Some(code) = data.strip_prefix("/start t") {
+        code
+    } else {
+        data
+    };
+    if code.len() == CODE_LENGTH && code.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(code)
+    } else {
+        None
+    }
+}
+
+pub fn controls(
+    s: &EventStats,
+    is_manager: bool,
+    lang: Lang,
+) -> anyhow::Result<Vec<Vec<InlineKeyboardButton>>> {
+    if is_manager && (s.adults.reserved > 0 || s.children.reserved > 0) {
+        Ok(vec![vec![InlineKeyboardButton::callback(
+            t!(lang, "check-in-button"),
+            serde_json::to_string(&CallbackQuery::CheckIn {
+                event_id: s.event.id,
+            })?,
+        )]])
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Starts a check-in session, codes sent afterwards are checked against the event.
+pub fn start(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    _ctx: &Context,
+) -> anyhow::Result<Reply> {
+    if !is_manager(conn, event_id, user) {
+        return Err(anyhow!("not allowed"));
+    }
+    let reply = show_check_in(conn, user, event_id, None)?;
+    db::set_check_in_event(conn, user.id.0, event_id)?;
+    Ok(reply)
+}
+
+/// Ends the check-in session and returns to the event.
+pub fn stop(conn: &Connection, user: &User, event_id: u64, ctx: &Context) -> anyhow::Result<Reply> {
+    db::set_check_in_event(conn, user.id.0, 0)?;
+    show_event(conn, user, event_id, ctx, None, 0)
+}
+
+fn show_check_in(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    result: Option<String>,
+) -> anyhow::Result<Reply> {
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    let (checked_in, total) = db::get_check_in_count(conn, event_id)?;
+    let mut text = format!(
+        "{}\n{}\n\n{}",
+        format::event_title(&s.event),
+        t!(user.lang, "starts-at", start = format::ts(s.event.ts)),
+        t!(
+            user.lang,
+            "check-in",
+            checked_in = checked_in,
+            total = total
+        )
+    );
+    if let Some(result) = result {
+        text.push_str(&format!("\n\n{}", result));
+    }
+    Ok(ReplyMessage::new(text)
+        .keyboard(vec![vec![InlineKeyboardButton::callback(
+            t!(user.lang, "stop-check-in"),
+            serde_json::to_string(&CallbackQuery::StopCheckIn { event_id })?,
+        )]])
+        .into())
+}
+
+fn describe_ticket(ticket: &Ticket, lang: Lang) -> String {
+    let mut text = t!(
+        lang,
+        "checked-ticket",
+        seat = ticket.seat + 1,
+        user_id = ticket.user_id.to_string()
+    );
+    if !ticket.name.is_empty() {
+        text.push_str(&format!(", {}", html::escape(&ticket.name)));
+    }
+    text
+}
+
+fn describe_result(conn: &Connection, result: &CheckIn, lang: Lang) -> String {
+    match result {
+        CheckIn::CheckedIn { ticket } => format!(
+            "✅ {}",
+            t!(lang, "ticket-valid", ticket = describe_ticket(ticket, lang))
+        ),
+        CheckIn::AlreadyCheckedIn { ticket, ts } => format!(
+            "⚠️ {}",
+            t!(
+                lang,
+                "ticket-already-checked-in",
+                ts = format::ts(*ts),
+                ticket = describe_ticket(ticket, lang)
+            )
+        ),
+        CheckIn::Cancelled { ticket } => format!(
+            "❌ {}",
+            t!(
+                lang,
+                "ticket-cancelled",
+                ticket = describe_ticket(ticket, lang)
+            )
+        ),
+        CheckIn::WrongEvent { ticket } => format!(
+            "❌ {}",
+            t!(
+                lang,
+                "ticket-wrong-event",
+                name = db::get_event_name(conn, ticket.event_id)
+                    .map(|name| html::escape(&name))
+                    .unwrap_or_default()
+            )
+        ),
+        CheckIn::Unknown => format!("❌ {}", t!(lang, "ticket-unknown")),
+    }
+}
+
+fn check_code(conn: &Connection, user: &User, event_id: u64, code: &str) -> anyhow::Result<Reply> {
+    let result = db::check_in(conn, event_id, code)?;
+    show_check_in(
+        conn,
+        user,
+        event_id,
+        Some(describe_result(conn, &result, user.lang)),
+    )
+}
+
+/// Text message from a manager in a check-in session - try to use it as a ticket code.
+pub fn handle_text(
+    conn: &Connection,
+    user: &User,
+    data: &str,
+    _ctx: &Context,
+) -> anyhow::Result<Option<Reply>> {
+    let event_id = db::get_check_in_event(conn, user.id.0)?;
+    if event_id == 0 {
+        return Ok(None);
+    }
+    match code_from_text(data) {
+        Some(code) => check_code(conn, user, event_id, code).map(Some),
+        None => show_check_in(
+            conn,
+            user,
+            event_id,
+            Some(format!("❌ {}", t!(user.lang, "ticket-code-invalid"))),
+        )
+        .map(Some),
+    }
+}
+
+/// Scanned ticket link: checked in by a manager, otherwise shown to its owner.
+pub fn handle_link(
+    conn: &Connection,
+    user: &User,
+    code: &str,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    match db::get_check_in_event(conn, user.id.0)? {
+        0 => tickets::show_my_tickets(conn, user, None, ctx),
+        event_id => check_code(conn, user, event_id, code),
+    }
+}
+
+/// Photo from a manager in a check-in session - look for a ticket QR code on it.
+pub fn handle_photo(
+    conn: &Connection,
+    user: &User,
+    photo: &[u8],
+    _ctx: &Context,
+) -> anyhow::Result<Option<Reply>> {
+    let event_id = db::get_check_in_event(conn, user.id.0)?;
+    if event_id == 0 {
+        return Ok(None);
+    }
+    match read_qr(photo)?.as_deref().and_then(code_from_text) {
+        Some(code) => check_code(conn, user, event_id, code).map(Some),
+        None => show_check_in(
+            conn,
+            user,
+            event_id,
+            Some(format!("❌ {}", t!(user.lang, "qr-code-not-found"))),
+        )
+        .map(Some),
+    }
+}
+
+#[test]
+fn test_code_from_text() {
+    let code = "0123456789abcdef0123456789abcdef";
+    assert_eq!(code_from_text(code), Some(code));
+    assert_eq!(
+        code_from_text(&format!("https://t.me/bot?start=t{}", code)),
+        Some(code)
+    );
+    assert_eq!(code_from_text(&format!("/start t{}", code)), Some(code));
+    assert_eq!(code_from_text("hello"), None);
+}
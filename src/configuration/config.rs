@@ -1,10 +1,19 @@
 use crate::configuration::raw_config::RawConfiguration;
+use crate::types::Reminder;
 use std::collections::HashSet;
 use std::net::SocketAddr;
 
+#[derive(Clone)]
 pub struct Config {
     pub telegram_bot_token: String,
     pub payment_provider_token: String,
+    pub payment_provider: String,
+    pub checkout_url: String,
+    pub refund_url: String,
+    pub payment_webhook_secret: String,
+    pub crypto_api_url: String,
+    pub crypto_api_key: String,
+    pub payment_hold_minutes: u64,
     pub admins: HashSet<u64>,
     pub public_lists: bool,
     pub automatic_blacklisting: bool,
@@ -22,15 +31,29 @@ pub struct Config {
     pub mailing_hours_from: u64,
     pub mailing_hours_to: u64,
     pub api_socket_address: SocketAddr,
+    pub default_reminders: Vec<Reminder>,
+    /// Address the API is reachable at from outside, for calendar subscription links.
+    pub public_url: String,
+    /// Delay after the end of an event before its participants are asked to rate it, None turns it off.
+    pub feedback_after: Option<u64>,
 }
 
 impl From<RawConfiguration> for Config {
     fn from(value: RawConfiguration) -> Self {
         let mailing_hours = value.parse_mailing_hours().unwrap();
+        let default_reminders = value.parse_reminders().unwrap();
+        let feedback_after = value.parse_feedback_after().unwrap();
         Config {
             api_socket_address: value.socket_address(),
             telegram_bot_token: value.telegram_bot_token.clone(),
             payment_provider_token: value.payment_provider_token.clone(),
+            payment_provider: value.payment_provider.clone(),
+            checkout_url: value.checkout_url.clone(),
+            refund_url: value.refund_url.clone(),
+            payment_webhook_secret: value.payment_webhook_secret.clone(),
+            crypto_api_url: value.crypto_api_url.clone(),
+            crypto_api_key: value.crypto_api_key.clone(),
+            payment_hold_minutes: value.payment_hold_minutes,
             admins: value.parse_admins(),
             public_lists: value.public_lists,
             automatic_blacklisting: value.automatic_blacklisting,
@@ -47,6 +70,9 @@ impl From<RawConfiguration> for Config {
             limit_bulk_notifications_per_second: value.limit_bulk_notifications_per_second,
             mailing_hours_from: mailing_hours.0,
             mailing_hours_to: mailing_hours.1,
+            default_reminders,
+            public_url: value.public_url.trim_end_matches('/').to_string(),
+            feedback_after,
         }
     }
 }
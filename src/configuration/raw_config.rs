@@ -1,3 +1,5 @@
+use crate::types::Reminder;
+use crate::util::parse_duration;
 use chrono::DateTime;
 use regex::Regex;
 use std::collections::HashSet;
@@ -7,6 +9,20 @@ use std::net::{SocketAddr, ToSocketAddrs};
 pub struct RawConfiguration {
     pub telegram_bot_token: String,
     pub payment_provider_token: String,
+    #[serde(default)]
+    pub payment_provider: String,
+    #[serde(default)]
+    pub checkout_url: String,
+    #[serde(default)]
+    pub refund_url: String,
+    #[serde(default)]
+    pub payment_webhook_secret: String,
+    #[serde(default)]
+    pub crypto_api_url: String,
+    #[serde(default)]
+    pub crypto_api_key: String,
+    #[serde(default)]
+    pub payment_hold_minutes: u64,
     pub admin_ids: String,
     pub public_lists: bool,
     pub automatic_blacklisting: bool,
@@ -24,6 +40,12 @@ pub struct RawConfiguration {
     pub mailing_hours: String,
     pub listen_address: String,
     pub listen_port: u16,
+    #[serde(default)]
+    pub reminders: String,
+    #[serde(default)]
+    pub public_url: String,
+    #[serde(default)]
+    pub feedback_after: String,
 }
 
 impl RawConfiguration {
@@ -47,6 +69,31 @@ impl RawConfiguration {
         }
     }
 
+    pub fn parse_reminders(&self) -> Result<Vec<Reminder>, String> {
+        self.reminders
+            .split(',')
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+            .map(|part| match parse_duration(part) {
+                Some(before) => Ok(Reminder {
+                    before,
+                    template: None,
+                }),
+                None => Err(format!("Failed to parse reminder: {}.", part)),
+            })
+            .collect()
+    }
+
+    pub fn parse_feedback_after(&self) -> Result<Option<u64>, String> {
+        match self.feedback_after.trim() {
+            "" => Ok(None),
+            delay => match parse_duration(delay) {
+                Some(delay) => Ok(Some(delay)),
+                None => Err(format!("Failed to parse feedback delay: {}.", delay)),
+            },
+        }
+    }
+
     pub fn parse_admins(&self) -> HashSet<u64> {
         self.admin_ids
             .split(',')
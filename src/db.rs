@@ -1,14 +1,20 @@
 use crate::types::{
-    Booking, Connection, Event, EventState, EventType, MessageBatch, MessageType, OrderInfo,
-    Participant, Presence, ReservationState, User,
+    Answer, Attendance, AttendanceStats, Audience, Booking, CheckIn, Connection, DeliveryReport,
+    Event, EventState, EventType, FeedbackResponse, FeedbackSummary, Media, MessageBatch,
+    MessageType, OrderInfo, Participant, ParticipantAnswers, Payment, PaymentKind, PaymentTotals,
+    Presence, PromoCode, Question, Reconciliation, RefundRequest, Reminder, ReservationState,
+    Ticket, User, UserBooking,
 };
 use crate::util::{self, get_unix_time};
 use fallible_streaming_iterator::FallibleStreamingIterator;
-use rusqlite::{params, Result, Row};
+use rusqlite::{params, params_from_iter, Result, Row};
 use std::collections::HashSet;
 use url::Url;
 
+use crate::calendar::DEFAULT_DURATION;
 use crate::format;
+use crate::i18n::{Args, Lang, Text};
+use crate::templates::{self, TemplateKind};
 use anyhow::anyhow;
 
 #[cfg(test)]
@@ -18,6 +24,7 @@ pub struct Counter {
     pub reserved: u64,
     pub my_reservation: u64,
     pub my_waiting: u64,
+    pub my_pending: u64,
 }
 
 impl Counter {
@@ -25,6 +32,7 @@ impl Counter {
         reserved: Result<u64, rusqlite::Error>,
         my_reservation: Result<u64, rusqlite::Error>,
         my_waiting: Result<u64, rusqlite::Error>,
+        my_pending: Result<u64, rusqlite::Error>,
     ) -> Result<Counter, rusqlite::Error> {
         Ok(Counter {
             reserved: match reserved {
@@ -39,6 +47,7 @@ impl Counter {
                 Ok(v) => v,
                 Err(_) => 0,
             },
+            my_pending: my_pending.unwrap_or(0),
         })
     }
 }
@@ -50,45 +59,153 @@ pub struct EventStats {
     pub state: EventState,
 }
 
+/// Values of `events.state`: 0 follows the registration window, the others are set by an admin.
+pub const REGISTRATION_CLOSED: u64 = 1;
+pub const REGISTRATION_OPENED: u64 = 2;
+
 impl EventStats {
     pub fn new(row: &Row) -> Result<EventStats, rusqlite::Error> {
         let state: u64 = row.get("state")?;
+        let opens_at: u64 = row.get("registration_opens_at")?;
+        let closes_at: u64 = row.get("registration_closes_at")?;
+        let now = get_unix_time();
         Ok(EventStats {
-            event: Event {
-                id: row.get("id")?,
-                name: row.get("name")?,
-                link: row.get("link")?,
-                max_adults: row.get("max_adults")?,
-                max_children: row.get("max_children")?,
-                max_adults_per_reservation: row.get("max_adults_per_reservation")?,
-                max_children_per_reservation: row.get("max_children_per_reservation")?,
-                ts: row.get("ts")?,
-                remind: 0,
-                adult_ticket_price: row.get::<&str, u64>("adult_ticket_price")?,
-                child_ticket_price: row.get::<&str, u64>("child_ticket_price")?,
-                currency: row.get("currency")?,
-            },
+            event: event_from_row(row)?,
             adults: Counter::new(
                 row.get("adults"),
                 row.get("my_adults"),
                 row.get("my_wait_adults"),
+                row.get("my_pending_adults"),
             )?,
             children: Counter::new(
                 row.get("children"),
                 row.get("my_children"),
                 row.get("my_wait_children"),
+                row.get("my_pending_children"),
             )?,
+            // A manual open or close overrides the registration window.
             state: match state {
-                0 => EventState::Open,
-                _ => EventState::Closed,
+                REGISTRATION_CLOSED => EventState::Closed,
+                REGISTRATION_OPENED => EventState::Open,
+                _ if closes_at != 0 && now > closes_at => EventState::Closed,
+                _ if opens_at > now => EventState::Upcoming(opens_at),
+                _ => EventState::Open,
             },
         })
     }
 }
 
+pub fn event_from_row(row: &Row) -> Result<Event, rusqlite::Error> {
+    Ok(Event {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        link: row.get("link")?,
+        max_adults: row.get("max_adults")?,
+        max_children: row.get("max_children")?,
+        max_adults_per_reservation: row.get("max_adults_per_reservation")?,
+        max_children_per_reservation: row.get("max_children_per_reservation")?,
+        ts: row.get("ts")?,
+        reminders: reminders_from_row(row)?,
+        adult_ticket_price: row.get::<&str, u64>("adult_ticket_price")?,
+        child_ticket_price: row.get::<&str, u64>("child_ticket_price")?,
+        currency: row.get("currency")?,
+        registration_opens_at: row.get("registration_opens_at")?,
+        registration_closes_at: row.get("registration_closes_at")?,
+        requires_approval: row.get("requires_approval")?,
+        questions: questions_from_row(row)?,
+        named_tickets: row.get("named_tickets")?,
+        waiting_list_hold: row.get("waiting_list_hold")?,
+        refund_deadline: row.get("refund_deadline")?,
+        late_refund_percent: row.get("late_refund_percent")?,
+        venue: row.get("venue")?,
+        organization: row.get("organization")?,
+        tags: tags_from_row(row)?,
+    })
+}
+
+pub fn reminders_from_row(row: &Row) -> Result<Vec<Reminder>, rusqlite::Error> {
+    json_list_from_row(row, "reminders")
+}
+
+pub fn questions_from_row(row: &Row) -> Result<Vec<Question>, rusqlite::Error> {
+    json_list_from_row(row, "questions")
+}
+
+/// Gives questions without an id the id of the current question with the same text or a new one,
+/// so that stored answers follow reordered questions. Ids up to `last_id` are never reused.
+fn assign_question_ids(current: &[Question], questions: &mut [Question], last_id: u64) {
+    let mut used = HashSet::new();
+    for q in questions.iter_mut() {
+        if !used.insert(q.id) {
+            q.id = 0;
+        }
+    }
+    let mut next = current
+        .iter()
+        .chain(questions.iter())
+        .map(|q| q.id)
+        .fold(last_id, u64::max);
+    for q in questions.iter_mut().filter(|q| q.id == 0) {
+        q.id = match current
+            .iter()
+            .find(|c| c.text == q.text && !used.contains(&c.id))
+        {
+            Some(c) => c.id,
+            None => {
+                next += 1;
+                next
+            }
+        };
+        used.insert(q.id);
+    }
+}
+
+/// Questions of the event with ids of the stored ones.
+fn with_question_ids(conn: &Connection, e: &Event) -> Result<Vec<Question>, rusqlite::Error> {
+    let mut questions = e.questions.clone();
+    let (current, last_id) = if e.id == 0 {
+        (Vec::new(), 0)
+    } else {
+        let current = conn
+            .query_row(
+                "SELECT questions FROM events WHERE id = ?1",
+                [e.id],
+                questions_from_row,
+            )
+            .or_else(|err| match err {
+                rusqlite::Error::QueryReturnedNoRows => Ok(Vec::new()),
+                err => Err(err),
+            })?;
+        let last_id: u64 = conn.query_row(
+            "SELECT coalesce(max(question), 0) FROM answers WHERE event = ?1",
+            [e.id],
+            |row| row.get(0),
+        )?;
+        (current, last_id)
+    };
+    assign_question_ids(&current, &mut questions, last_id);
+    Ok(questions)
+}
+
+pub fn tags_from_row(row: &Row) -> Result<Vec<String>, rusqlite::Error> {
+    json_list_from_row(row, "tags")
+}
+
+fn json_list_from_row<T: serde::de::DeserializeOwned>(
+    row: &Row,
+    column: &str,
+) -> Result<Vec<T>, rusqlite::Error> {
+    match row.get::<&str, Option<String>>(column)? {
+        Some(v) => serde_json::from_str(&v).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+        }),
+        None => Ok(Vec::new()),
+    }
+}
+
 pub struct GroupMessage {
     pub sender: String,
-    pub text: String,
+    pub text: Text,
     pub ts: u64,
     pub waiting_list: u64,
 }
@@ -104,11 +221,18 @@ pub fn mutate_event(conn: &Connection, e: &Event) -> Result<u64, rusqlite::Error
             )));
         }
     }
+    let reminders = serde_json::to_string(&e.reminders)
+        .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+    let questions = serde_json::to_string(&with_question_ids(conn, e)?)
+        .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+    let tags = serde_json::to_string(&e.tags)
+        .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+    let updated = get_unix_time();
     let mut event_id = e.id;
     if e.id == 0 {
         let res = conn.execute(
-            "INSERT INTO events (name, link, max_adults, max_children, max_adults_per_reservation, max_children_per_reservation, ts, remind, adult_ticket_price, child_ticket_price, currency) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
-            params![e.name, e.link, e.max_adults, e.max_children, e.max_adults_per_reservation, e.max_children_per_reservation, e.ts, e.remind, e.adult_ticket_price, e.child_ticket_price, e.currency],
+            "INSERT INTO events (name, link, max_adults, max_children, max_adults_per_reservation, max_children_per_reservation, ts, remind, adult_ticket_price, child_ticket_price, currency, reminders, registration_opens_at, registration_closes_at, requires_approval, questions, named_tickets, waiting_list_hold, refund_deadline, late_refund_percent, venue, organization, tags, updated) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
+            params![e.name, e.link, e.max_adults, e.max_children, e.max_adults_per_reservation, e.max_children_per_reservation, e.ts, e.adult_ticket_price, e.child_ticket_price, e.currency, reminders, e.registration_opens_at, e.registration_closes_at, e.requires_approval, questions, e.named_tickets, e.waiting_list_hold, e.refund_deadline, e.late_refund_percent, e.venue, e.organization, tags, updated],
         )?;
         if res > 0 {
             let mut stmt =
@@ -120,21 +244,149 @@ pub fn mutate_event(conn: &Connection, e: &Event) -> Result<u64, rusqlite::Error
         }
     } else {
         conn.execute(
-            "UPDATE events SET name = ?1, link = ?2, max_adults = ?3, max_children = ?4, max_adults_per_reservation = ?5, max_children_per_reservation = ?6, ts = ?7, remind = ?8 \
-                WHERE id = ?9",
-            params![e.name, e.link, e.max_adults, e.max_children, e.max_adults_per_reservation, e.max_children_per_reservation, e.ts, e.remind, e.id],
+            "UPDATE events SET name = ?1, link = ?2, max_adults = ?3, max_children = ?4, max_adults_per_reservation = ?5, max_children_per_reservation = ?6, ts = ?7, reminders = ?8, registration_opens_at = ?9, registration_closes_at = ?10, requires_approval = ?11, questions = ?12, named_tickets = ?13, waiting_list_hold = ?14, refund_deadline = ?15, late_refund_percent = ?16, venue = ?17, organization = ?18, tags = ?19, updated = ?20 \
+                WHERE id = ?21",
+            params![e.name, e.link, e.max_adults, e.max_children, e.max_adults_per_reservation, e.max_children_per_reservation, e.ts, reminders, e.registration_opens_at, e.registration_closes_at, e.requires_approval, questions, e.named_tickets, e.waiting_list_hold, e.refund_deadline, e.late_refund_percent, e.venue, e.organization, tags, updated, e.id],
         )?;
         delete_enqueued_messages(conn, e.id, MessageType::Reminder)?;
     }
 
     if event_id != 0 && event_type != EventType::Announcement {
-        let text = format!(
-            "\nЗдравствуйте!\nНе забудьте, пожалуйста, что вы записались на\n<a href=\"{}\">{}</a>\
-            \nНачало: {}\nПожалуйста, вовремя откажитесь от мест, если ваши планы изменились.\n",
-            e.link,
-            e.name,
-            format::ts(e.ts),
-        );
+        schedule_reminders(conn, event_id, e)?;
+    }
+    if e.id == 0 && event_id != 0 {
+        notify_subscribers(conn, event_id, e)?;
+    } else if e.id != 0 {
+        // Follow the opening of registration until the notification starts going out.
+        conn.execute(
+            "UPDATE message_outbox SET send_at = ?1 WHERE message IN \
+            (SELECT id FROM messages WHERE event = ?2 AND type = ?3 \
+            AND id NOT IN (SELECT message FROM message_sent))",
+            params![
+                std::cmp::max(e.registration_opens_at, updated),
+                e.id,
+                MessageType::NewEvent as u64
+            ],
+        )?;
+    }
+    Ok(event_id)
+}
+
+/// Subscribers of the organization or a tag of the new event learn about it when registration opens.
+fn notify_subscribers(conn: &Connection, event_id: u64, e: &Event) -> Result<(), rusqlite::Error> {
+    let now = get_unix_time();
+    if e.ts < now || (e.organization.is_empty() && e.tags.is_empty()) {
+        return Ok(());
+    }
+    let text = text!(
+        "subscription-new-event",
+        title = format::event_title(e),
+        start = format::ts(e.ts)
+    );
+    enqueue_message(
+        conn,
+        event_id,
+        "Bot",
+        0,
+        MessageType::NewEvent,
+        &text,
+        std::cmp::max(e.registration_opens_at, now),
+    )?;
+    Ok(())
+}
+
+/// Subscribers of the event who neither booked it nor got the message, parameters come from `subscriber_params`.
+const SUBSCRIBERS: &str = "SELECT DISTINCT user FROM subscriptions \
+    WHERE ((kind = 'organization' AND value = ?1) \
+    OR (kind = 'tag' AND value IN (SELECT value FROM json_each(?2)))) \
+    AND user NOT IN (SELECT user FROM reservations WHERE event = ?3) \
+    AND user NOT IN (SELECT user FROM message_sent WHERE message = ?4)";
+
+fn subscriber_params(e: &Event) -> Result<(String, String), rusqlite::Error> {
+    let tags: Vec<String> = e.tags.iter().map(|tag| tag.to_lowercase()).collect();
+    Ok((
+        e.organization.to_lowercase(),
+        serde_json::to_string(&tags)
+            .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?,
+    ))
+}
+
+/// Organization and tags the user gets messages about new events for, with the ids to remove them.
+pub fn get_subscriptions(
+    conn: &Connection,
+    user_id: u64,
+) -> Result<Vec<(u64, Audience)>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT rowid, kind, value FROM subscriptions WHERE user = ?1 ORDER BY kind, value",
+    )?;
+    let mut rows = stmt.query([user_id])?;
+    let mut res = Vec::new();
+    while let Some(row) = rows.next()? {
+        let kind: String = row.get(1)?;
+        let value: String = row.get(2)?;
+        if let Ok(audience) = format!("{} {}", kind, value).parse() {
+            res.push((row.get(0)?, audience));
+        }
+    }
+    Ok(res)
+}
+
+/// Subscribes to the organization or tag, or unsubscribes if already subscribed.
+/// Returns whether the user is subscribed now.
+pub fn toggle_subscription(
+    conn: &Connection,
+    user_id: u64,
+    topic: &Audience,
+) -> Result<bool, rusqlite::Error> {
+    let (kind, value) = match topic {
+        Audience::Organization(organization) => ("organization", organization.to_lowercase()),
+        Audience::Tag(tag) => ("tag", tag.to_lowercase()),
+        _ => return Ok(false),
+    };
+    if conn.execute(
+        "DELETE FROM subscriptions WHERE user = ?1 AND kind = ?2 AND value = ?3",
+        params![user_id, kind, value],
+    )? > 0
+    {
+        return Ok(false);
+    }
+    conn.execute(
+        "INSERT INTO subscriptions (user, kind, value) VALUES (?1, ?2, ?3)",
+        params![user_id, kind, value],
+    )?;
+    Ok(true)
+}
+
+pub fn delete_subscription(
+    conn: &Connection,
+    user_id: u64,
+    subscription_id: u64,
+) -> Result<usize, rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM subscriptions WHERE rowid = ?1 AND user = ?2",
+        params![subscription_id, user_id],
+    )
+}
+
+/// Enqueue reminders which are still due, previously sent ones are skipped.
+fn schedule_reminders(conn: &Connection, event_id: u64, e: &Event) -> Result<(), rusqlite::Error> {
+    let now = get_unix_time();
+    for reminder in &e.reminders {
+        let send_at = e.ts.saturating_sub(reminder.before);
+        if send_at < now {
+            continue;
+        }
+        // The reminder's own template, then the event's or the common one.
+        let text = match &reminder.template {
+            Some(template) => templates::template(template, e),
+            None => templates::text(
+                conn,
+                TemplateKind::Reminder,
+                event_id,
+                e,
+                format::reminder(e),
+            )?,
+        };
         enqueue_message(
             conn,
             event_id,
@@ -142,38 +394,483 @@ pub fn mutate_event(conn: &Connection, e: &Event) -> Result<u64, rusqlite::Error
             0,
             MessageType::Reminder,
             &text,
-            e.remind,
+            send_at,
         )?;
     }
-    Ok(event_id)
+    Ok(())
 }
 
+/// Enqueue a message for the participants of the event, returns its id.
 pub fn enqueue_message(
     conn: &Connection,
     event_id: u64,
     sender: &str,
     waiting_list: u64,
     message_type: MessageType,
-    text: &str,
+    text: &Text,
+    send_at: u64,
+) -> Result<u64, rusqlite::Error> {
+    insert_message(
+        conn,
+        event_id,
+        sender,
+        waiting_list,
+        message_type,
+        text,
+        None,
+        None,
+        send_at,
+    )
+}
+
+/// Enqueue a direct message with media for the participants of the event, returns its id.
+pub fn enqueue_media_message(
+    conn: &Connection,
+    event_id: u64,
+    sender: &str,
+    waiting_list: u64,
+    text: &Text,
+    media: Option<&Media>,
+    send_at: u64,
+) -> Result<u64, rusqlite::Error> {
+    insert_message(
+        conn,
+        event_id,
+        sender,
+        waiting_list,
+        MessageType::Direct,
+        text,
+        media,
+        None,
+        send_at,
+    )
+}
+
+/// Enqueue a message for a single user.
+pub fn enqueue_personal_message(
+    conn: &Connection,
+    event_id: u64,
+    recipient: u64,
+    message_type: MessageType,
+    text: &Text,
     send_at: u64,
 ) -> Result<(), rusqlite::Error> {
+    insert_message(
+        conn,
+        event_id,
+        "Bot",
+        0,
+        message_type,
+        text,
+        None,
+        Some(recipient),
+        send_at,
+    )?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn insert_message(
+    conn: &Connection,
+    event_id: u64,
+    sender: &str,
+    waiting_list: u64,
+    message_type: MessageType,
+    text: &Text,
+    media: Option<&Media>,
+    recipient: Option<u64>,
+    send_at: u64,
+) -> Result<u64, rusqlite::Error> {
     debug!("enqueue message {} {}", util::get_unix_time(), send_at);
+    let message_id = store_message(
+        conn,
+        event_id,
+        sender,
+        waiting_list,
+        message_type,
+        text,
+        media,
+        recipient,
+        None,
+    )?;
     conn.execute(
-        "INSERT INTO messages (event, type, sender, waiting_list, text, ts) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![event_id, message_type as u64, sender, waiting_list, text, util::get_unix_time()],
+        "INSERT INTO message_outbox (message, send_at) VALUES (?1, ?2)",
+        params![message_id, send_at],
     )?;
-    let mut stmt = conn.prepare("SELECT last_insert_rowid()")?;
-    let mut rows = stmt.query([])?;
-    if let Some(row) = rows.next()? {
-        let message_id: u64 = row.get(0)?;
+    Ok(message_id)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn store_message(
+    conn: &Connection,
+    event_id: u64,
+    sender: &str,
+    waiting_list: u64,
+    message_type: MessageType,
+    text: &Text,
+    media: Option<&Media>,
+    recipient: Option<u64>,
+    audience: Option<&Audience>,
+) -> Result<u64, rusqlite::Error> {
+    // Catalog messages are rendered in the language of each recipient when sent,
+    // templates get the free seats and the name of each recipient.
+    let (text, args, template) = match text {
+        Text::Literal(text) => (text.to_string(), None, false),
+        Text::Message { key, args } => (
+            key.to_string(),
+            Some(
+                serde_json::to_string(args)
+                    .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?,
+            ),
+            false,
+        ),
+        Text::Template { template, args } => (templates::render(template, args), None, true),
+    };
+    let media = media
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+    conn.execute(
+        "INSERT INTO messages (event, type, sender, waiting_list, text, ts, recipient, args, template, audience, media) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![event_id, message_type as u64, sender, waiting_list, text, util::get_unix_time(), recipient, args, template, audience.map(|a| a.to_string()), media],
+    )?;
+    conn.query_row("SELECT last_insert_rowid()", [], |row| row.get(0))
+}
+
+/// Stores the broadcast to be sent once confirmed.
+pub fn create_broadcast(
+    conn: &Connection,
+    sender: &str,
+    audience: &Audience,
+    text: &Text,
+    media: Option<&Media>,
+) -> Result<u64, rusqlite::Error> {
+    store_message(
+        conn,
+        0,
+        sender,
+        0,
+        MessageType::Direct,
+        text,
+        media,
+        None,
+        Some(audience),
+    )
+}
+
+/// Audience and text of a broadcast.
+pub fn get_broadcast(
+    conn: &Connection,
+    message_id: u64,
+) -> Result<Option<(Audience, Text)>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT * FROM messages WHERE id = ?1 AND audience IS NOT NULL")?;
+    let mut rows = stmt.query([message_id])?;
+    match rows.next()? {
+        Some(row) => match row.get::<&str, String>("audience")?.parse::<Audience>() {
+            Ok(audience) => Ok(Some((audience, message_text(conn, row)?))),
+            Err(_) => Ok(None),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Users the broadcast would go to now.
+pub fn get_audience(conn: &Connection, audience: &Audience) -> Result<Vec<u64>, rusqlite::Error> {
+    let (query, params) = audience_query(audience);
+    let mut stmt = conn.prepare(&format!("{} ORDER BY user", query))?;
+    let rows = stmt.query_map(params_from_iter(params), |row| row.get(0))?;
+    rows.collect()
+}
+
+/// Query selecting the `user` column of the audience with its parameters.
+fn audience_query(audience: &Audience) -> (String, Vec<rusqlite::types::Value>) {
+    use rusqlite::types::Value;
+    // Everyone who has written to the bot got a language.
+    let confirmed = format!(
+        "SELECT user FROM reservations WHERE event = ?1 AND waiting_list = 0 AND state NOT IN ({}, {})",
+        ReservationState::PendingApproval as u64,
+        ReservationState::Refunded as u64
+    );
+    match audience {
+        Audience::All => (
+            "SELECT user FROM languages UNION SELECT user FROM reservations".to_string(),
+            vec![],
+        ),
+        Audience::Attended(event_id) => (
+            "SELECT user FROM presence WHERE event = ?1".to_string(),
+            vec![Value::Integer(*event_id as i64)],
+        ),
+        Audience::NoShow(event_id) => (
+            format!(
+                "{} EXCEPT SELECT user FROM presence WHERE event = ?1",
+                confirmed
+            ),
+            vec![Value::Integer(*event_id as i64)],
+        ),
+        Audience::Tag(tag) => (
+            "SELECT user FROM subscriptions WHERE kind = 'tag' AND value = ?1".to_string(),
+            vec![Value::Text(tag.to_lowercase())],
+        ),
+        Audience::Organization(organization) => (
+            "SELECT user FROM subscriptions WHERE kind = 'organization' AND value = ?1".to_string(),
+            vec![Value::Text(organization.to_lowercase())],
+        ),
+    }
+}
+
+/// Fixes the recipients of the broadcast and puts it into the outbox.
+/// Returns the number of recipients, 0 if there are none or it has already been scheduled.
+pub fn schedule_broadcast(
+    conn: &Connection,
+    message_id: u64,
+    send_at: u64,
+) -> Result<usize, rusqlite::Error> {
+    let audience = match get_broadcast(conn, message_id)? {
+        Some((audience, _)) => audience,
+        None => return Ok(0),
+    };
+    let mut stmt = conn.prepare(
+        "SELECT message FROM message_recipients WHERE message = ?1 \
+        UNION SELECT message FROM message_outbox WHERE message = ?1",
+    )?;
+    if stmt.exists([message_id])? {
+        return Ok(0);
+    }
+    let (query, mut params) = audience_query(&audience);
+    params.push(rusqlite::types::Value::Integer(message_id as i64));
+    let recipients = conn.execute(
+        &format!(
+            "INSERT INTO message_recipients (message, user) SELECT ?{}, user FROM ({})",
+            params.len(),
+            query
+        ),
+        params_from_iter(params),
+    )?;
+    if recipients > 0 {
         conn.execute(
             "INSERT INTO message_outbox (message, send_at) VALUES (?1, ?2)",
             params![message_id, send_at],
         )?;
     }
+    Ok(recipients)
+}
+
+/// Surveys the participants present at events which are over, the survey goes out `delay` after the end.
+/// Events which ended more than a day before the survey was due are skipped, e.g. when it is turned on.
+pub fn schedule_feedback(
+    conn: &Connection,
+    now: u64,
+    delay: u64,
+) -> Result<usize, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM events WHERE ts + ?1 <= ?2 AND ts + ?1 + ?3 + 86400 > ?2 \
+        AND id NOT IN (SELECT event FROM feedback_surveys) \
+        AND id IN (SELECT event FROM presence)",
+    )?;
+    let events = stmt
+        .query_map(params![DEFAULT_DURATION, now, delay], event_from_row)?
+        .collect::<Result<Vec<Event>, _>>()?;
+    let mut surveyed = 0;
+    for e in events {
+        let text = text!(
+            "feedback-request",
+            title = format::event_title(&e),
+            start = format::ts(e.ts)
+        );
+        // Recipients are fixed now, presence is gone when the event is purged.
+        let message_id = store_message(
+            conn,
+            e.id,
+            "Bot",
+            0,
+            MessageType::Feedback,
+            &text,
+            None,
+            None,
+            Some(&Audience::Attended(e.id)),
+        )?;
+        conn.execute(
+            "INSERT INTO feedback_surveys (event, name, ts, message) VALUES (?1, ?2, ?3, ?4)",
+            params![e.id, e.name, e.ts, message_id],
+        )?;
+        surveyed += schedule_broadcast(conn, message_id, e.ts + DEFAULT_DURATION + delay)?;
+    }
+    Ok(surveyed)
+}
+
+/// Rating of the event from a surveyed participant, false if the user was not asked.
+pub fn save_feedback_rating(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+    rating: u64,
+) -> Result<bool, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT f.event FROM feedback_surveys AS f \
+        JOIN message_recipients AS r ON r.message = f.message WHERE f.event = ?1 AND r.user = ?2",
+    )?;
+    if !stmt.exists([event_id, user_id])? {
+        return Ok(false);
+    }
+    conn.execute(
+        "INSERT INTO feedback (event, user, rating, ts) VALUES (?1, ?2, ?3, ?4) \
+        ON CONFLICT (event, user) DO UPDATE SET rating = excluded.rating, ts = excluded.ts",
+        params![event_id, user_id, rating, get_unix_time()],
+    )?;
+    Ok(true)
+}
+
+pub fn save_feedback_comment(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+    comment: &str,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE feedback SET comment = ?3 WHERE event = ?1 AND user = ?2",
+        params![event_id, user_id, comment],
+    )?;
+    Ok(())
+}
+
+/// Event the user rated and may comment on.
+pub fn get_feedback_session(
+    conn: &Connection,
+    user_id: u64,
+) -> Result<Option<u64>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT event FROM feedback_sessions WHERE user = ?1")?;
+    let mut rows = stmt.query([user_id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+/// Starts waiting for a comment on the event, 0 stops it.
+pub fn set_feedback_session(
+    conn: &Connection,
+    user_id: u64,
+    event_id: u64,
+) -> Result<(), rusqlite::Error> {
+    if event_id == 0 {
+        conn.execute(
+            "DELETE FROM feedback_sessions WHERE user = ?1",
+            params![user_id],
+        )?;
+    } else {
+        conn.execute(
+            "INSERT OR REPLACE INTO feedback_sessions (user, event) VALUES (?1, ?2)",
+            params![user_id, event_id],
+        )?;
+    }
     Ok(())
 }
 
+/// Surveys from the latest event on, limit 0 returns all of them.
+pub fn get_feedback_summaries(
+    conn: &Connection,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<FeedbackSummary>, rusqlite::Error> {
+    feedback_summaries(conn, 0, offset, limit)
+}
+
+pub fn get_feedback_summary(
+    conn: &Connection,
+    event_id: u64,
+) -> Result<Option<FeedbackSummary>, rusqlite::Error> {
+    Ok(feedback_summaries(conn, event_id, 0, 0)?.into_iter().next())
+}
+
+/// Surveys of the event, of all events for 0.
+fn feedback_summaries(
+    conn: &Connection,
+    event_id: u64,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<FeedbackSummary>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT f.event, f.name, f.ts, count(r.user) FROM feedback_surveys AS f \
+        LEFT JOIN message_recipients AS r ON r.message = f.message \
+        WHERE ?1 = 0 OR f.event = ?1 \
+        GROUP BY f.event ORDER BY f.ts DESC LIMIT ?2 OFFSET ?3",
+    )?;
+    let mut rows = stmt.query(params![
+        event_id,
+        if limit == 0 { -1 } else { limit as i64 },
+        offset * limit
+    ])?;
+    let mut res = Vec::new();
+    while let Some(row) = rows.next()? {
+        res.push(FeedbackSummary {
+            event_id: row.get(0)?,
+            name: row.get(1)?,
+            ts: row.get(2)?,
+            surveyed: row.get(3)?,
+            ..Default::default()
+        });
+    }
+    for s in &mut res {
+        let mut stmt = conn.prepare(
+            "SELECT rating, count(*), sum(comment != '') FROM feedback WHERE event = ?1 GROUP BY rating",
+        )?;
+        let mut rows = stmt.query([s.event_id])?;
+        let mut total = 0;
+        while let Some(row) = rows.next()? {
+            let rating: u64 = row.get(0)?;
+            let count: u64 = row.get(1)?;
+            if let Some(ratings) = s.ratings.get_mut(rating as usize - 1) {
+                *ratings = count;
+            }
+            s.responses += count;
+            s.comments += row.get::<usize, u64>(2)?;
+            total += rating * count;
+        }
+        if s.responses > 0 {
+            s.average = total as f64 / s.responses as f64;
+        }
+    }
+    Ok(res)
+}
+
+/// Ratings and comments on the event, limit 0 returns all of them.
+pub fn get_feedback_responses(
+    conn: &Connection,
+    event_id: u64,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<FeedbackResponse>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT user, rating, comment, ts FROM feedback WHERE event = ?1 ORDER BY ts LIMIT ?2 OFFSET ?3",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            event_id,
+            if limit == 0 { -1 } else { limit as i64 },
+            offset * limit
+        ],
+        |row| {
+            Ok(FeedbackResponse {
+                user_id: row.get(0)?,
+                rating: row.get(1)?,
+                comment: row.get(2)?,
+                ts: row.get(3)?,
+            })
+        },
+    )?;
+    rows.collect()
+}
+
+/// Removes a broadcast which has not been scheduled.
+pub fn delete_broadcast(conn: &Connection, message_id: u64) -> Result<usize, rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM messages WHERE id = ?1 AND audience IS NOT NULL \
+        AND id NOT IN (SELECT message FROM message_recipients) \
+        AND id NOT IN (SELECT message FROM message_outbox)",
+        [message_id],
+    )
+}
+
 pub fn delete_enqueued_messages(
     conn: &Connection,
     event_id: u64,
@@ -187,12 +884,20 @@ pub fn delete_enqueued_messages(
             "DELETE FROM message_outbox WHERE message = ?1",
             params![message_id],
         )?;
+        conn.execute(
+            "DELETE FROM message_sent WHERE message = ?1",
+            params![message_id],
+        )?;
         conn.execute("DELETE FROM messages WHERE id = ?1", params![message_id])?;
     }
     Ok(())
 }
 
 pub fn prompt_waiting_list(conn: &Connection, event_id: u64) -> Result<(), rusqlite::Error> {
+    let s = get_event(conn, event_id, 0)?;
+    if s.event.waiting_list_hold != 0 || s.event.get_type() == EventType::Paid {
+        return promote_waiting_list(conn, &s.event);
+    }
     if have_vacancies(conn, event_id)? == false {
         debug!("prompt_waiting_list - no tickets, event {}", event_id);
         return Ok(());
@@ -209,30 +914,272 @@ pub fn prompt_waiting_list(conn: &Connection, event_id: u64) -> Result<(), rusql
         )?;
     } else {
         if let Ok(event_name) = get_event_name(conn, event_id) {
-            enqueue_message(conn,
-                            event_id,
-                            "Bot",
-                            1,
-                            MessageType::WaitingListPrompt,
-                            &format!("Кто-то отменил бронирование на мероприятие: \"{}\".\nВы можете попробовать записаться.", event_name),
-                            send_at,
+            let text = templates::text(
+                conn,
+                TemplateKind::WaitingList,
+                event_id,
+                &s.event,
+                text!("waiting-list-prompt", name = event_name),
+            )?;
+            enqueue_message(
+                conn,
+                event_id,
+                "Bot",
+                1,
+                MessageType::WaitingListPrompt,
+                &text,
+                send_at,
             )?;
         }
     }
     Ok(())
 }
 
-pub fn blacklist_absent_participants(
-    conn: &Connection,
-    event_id: u64,
+const DEFAULT_PAYMENT_HOLD: u64 = 60 * 60;
+
+/// Hands vacant seats to waiting reservations in the order of sign-up and holds them
+/// for the event's hold time. A reservation which doesn't fit blocks later ones of the same category.
+/// Paid events always hold freed seats for payment, by default for an hour.
+fn promote_waiting_list(conn: &Connection, e: &Event) -> Result<(), rusqlite::Error> {
+    let (mut vacant_adults, mut vacant_children) = get_vacancies(conn, e.id)?;
+    let mut stmt = conn.prepare(
+        "SELECT r.id, r.user, r.adults, r.children, \
+        (SELECT coalesce(sum(c.adults), 0) FROM reservations AS c WHERE c.event = r.event AND c.user = r.user AND c.waiting_list = 0 AND c.state NOT IN (?2, ?3)), \
+        (SELECT coalesce(sum(c.children), 0) FROM reservations AS c WHERE c.event = r.event AND c.user = r.user AND c.waiting_list = 0 AND c.state NOT IN (?2, ?3)) \
+        FROM reservations AS r WHERE r.event = ?1 AND r.waiting_list = 1 AND r.state != ?2 ORDER BY r.ts, r.id",
+    )?;
+    let mut rows = stmt.query(params![
+        e.id,
+        ReservationState::PendingApproval as u64,
+        ReservationState::Refunded as u64
+    ])?;
+    let mut waiting: Vec<(u64, u64, u64, u64, u64, u64)> = Vec::new();
+    while let Some(row) = rows.next()? {
+        waiting.push((
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get(5)?,
+        ));
+    }
+    let hold_until = get_hold_until(e, get_unix_time());
+    let (mut adults_blocked, mut children_blocked) = (false, false);
+    for (id, user_id, adults, children, my_adults, my_children) in waiting {
+        if my_adults + adults > e.max_adults_per_reservation
+            || my_children + children > e.max_children_per_reservation
+        {
+            continue;
+        }
+        if adults > vacant_adults
+            || children > vacant_children
+            || (adults > 0 && adults_blocked)
+            || (children > 0 && children_blocked)
+        {
+            adults_blocked |= adults > 0;
+            children_blocked |= children > 0;
+            continue;
+        }
+        conn.execute(
+            "UPDATE reservations SET waiting_list = 0, state = ?2, hold_until = ?3 WHERE id = ?1",
+            params![id, ReservationState::Offered as u64, hold_until],
+        )?;
+        vacant_adults -= adults;
+        vacant_children -= children;
+        enqueue_personal_message(
+            conn,
+            e.id,
+            user_id,
+            MessageType::Notification,
+            &text!(
+                "seat-offered",
+                title = format::event_title(e),
+                start = format::ts(e.ts),
+                hold_until = format::ts(hold_until),
+                paid = if e.get_type() == EventType::Paid {
+                    "yes"
+                } else {
+                    "no"
+                }
+            ),
+            get_unix_time(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Deadline for confirming or paying seats held from now.
+fn get_hold_until(e: &Event, now: u64) -> u64 {
+    now + match e.waiting_list_hold {
+        0 => DEFAULT_PAYMENT_HOLD,
+        hold => hold,
+    }
+}
+
+/// Seats held for the user: adults, children and the hold deadline.
+pub fn get_offer(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+) -> Result<(u64, u64, u64), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT coalesce(sum(adults), 0), coalesce(sum(children), 0), coalesce(min(hold_until), 0) FROM reservations WHERE event = ?1 AND user = ?2 AND state = ?3",
+    )?;
+    stmt.query_row(
+        params![event_id, user_id, ReservationState::Offered as u64],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+}
+
+/// Confirms seats held for the user in a free event, paid events confirm them by payment.
+pub fn accept_offer(conn: &Connection, event_id: u64, user_id: u64) -> anyhow::Result<()> {
+    if get_event(conn, event_id, user_id)?.event.get_type() == EventType::Paid {
+        return Err(text!("please-pay").into());
+    }
+    let updated = conn.execute(
+        "UPDATE reservations SET state = ?3, hold_until = 0 WHERE event = ?1 AND user = ?2 AND state = ?4 AND hold_until >= ?5",
+        params![
+            event_id,
+            user_id,
+            ReservationState::Free as u64,
+            ReservationState::Offered as u64,
+            get_unix_time()
+        ],
+    )?;
+    if updated == 0 {
+        return Err(text!("hold-expired").into());
+    }
+    Ok(())
+}
+
+/// Checks the payment for seats held from the waiting list, they are paid in one go.
+/// The hold is extended for `hold` seconds so that the payment can complete.
+pub fn check_offered_booking(
+    conn: &Connection,
+    booking: &Booking,
+    amount: u64,
+    hold: u64,
+) -> anyhow::Result<()> {
+    let s = get_event(conn, booking.event_id, booking.user_id)?;
+    if get_booking_amount(
+        conn,
+        &s.event,
+        booking.user_id,
+        booking.adults,
+        booking.children,
+    )? != amount
+    {
+        return Err(anyhow!("Wrong tranaction amount"));
+    }
+    let (adults, children, hold_until) = get_offer(conn, booking.event_id, booking.user_id)?;
+    if (adults, children) != (booking.adults, booking.children) || hold_until < get_unix_time() {
+        return Err(text!("hold-expired").into());
+    }
+    conn.execute(
+        "UPDATE reservations SET hold_until = max(hold_until, ?3) WHERE event = ?1 AND user = ?2 AND state = ?4",
+        params![
+            booking.event_id,
+            booking.user_id,
+            get_unix_time() + hold,
+            ReservationState::Offered as u64
+        ],
+    )?;
+    save_checkout(conn, booking, amount)?;
+    Ok(())
+}
+
+/// Leaves the waiting list and gives up held seats, which pass to the next in line.
+pub fn leave_waiting_list(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+) -> Result<(), rusqlite::Error> {
+    let released = conn.execute(
+        "DELETE FROM reservations WHERE event = ?1 AND user = ?2 AND state = ?3",
+        params![event_id, user_id, ReservationState::Offered as u64],
+    )?;
+    conn.execute(
+        "DELETE FROM reservations WHERE event = ?1 AND user = ?2 AND waiting_list = 1",
+        params![event_id, user_id],
+    )?;
+    trim_tickets(conn, event_id, user_id)?;
+    if released > 0 {
+        prompt_waiting_list(conn, event_id)
+    } else {
+        Ok(())
+    }
+}
+
+/// Releases held and approved seats which were not confirmed or paid in time
+/// and offers them to the next in line.
+pub fn expire_offers(conn: &Connection, ts: u64) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT event, user, state FROM reservations WHERE state IN (?1, ?2) AND hold_until < ?3",
+    )?;
+    let expired = stmt
+        .query_map(
+            params![
+                ReservationState::Offered as u64,
+                ReservationState::Approved as u64,
+                ts
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, u64>(0)?,
+                    row.get::<_, u64>(1)?,
+                    row.get::<_, u64>(2)?,
+                ))
+            },
+        )?
+        .collect::<Result<Vec<(u64, u64, u64)>, _>>()?;
+    let mut events = Vec::new();
+    for (event_id, user_id, state) in expired {
+        conn.execute(
+            "DELETE FROM reservations WHERE event = ?1 AND user = ?2 AND state = ?3 AND hold_until < ?4",
+            params![event_id, user_id, state, ts],
+        )?;
+        trim_tickets(conn, event_id, user_id)?;
+        if let Ok(event_name) = get_event_name(conn, event_id) {
+            let text = if state == ReservationState::Approved as u64 {
+                text!("approval-expired", name = event_name)
+            } else {
+                text!("offer-expired", name = event_name)
+            };
+            enqueue_personal_message(
+                conn,
+                event_id,
+                user_id,
+                MessageType::Notification,
+                &text,
+                ts,
+            )?;
+        }
+        if !events.contains(&event_id) {
+            events.push(event_id);
+        }
+    }
+    for event_id in events {
+        prompt_waiting_list(conn, event_id)?;
+    }
+    Ok(())
+}
+
+pub fn blacklist_absent_participants(
+    conn: &Connection,
+    event_id: u64,
     admins: &HashSet<u64>,
     cancel_future_reservations: bool,
 ) -> Result<(), rusqlite::Error> {
     let mut stmt = conn.prepare(
-        "select r.*, p.user from (select event, user, user_name1, user_name2, count(user) as count from reservations where event = ?1 and waiting_list = 0 group by user) as r
+        "select r.*, p.user from (select event, user, user_name1, user_name2, count(user) as count from reservations where event = ?1 and waiting_list = 0 and state not in (?2, ?3) group by user) as r
         left join presence as p on r.event = p.event and r.user = p.user"
     )?;
-    let mut rows = stmt.query(params![event_id])?;
+    let mut rows = stmt.query(params![
+        event_id,
+        ReservationState::PendingApproval as u64,
+        ReservationState::Refunded as u64
+    ])?;
     let mut list: Vec<Presence> = Vec::new();
     let mut presence_checked = false;
     while let Some(row) = rows.next()? {
@@ -244,6 +1191,7 @@ pub fn blacklist_absent_participants(
                 user_name2: row.get(3)?,
                 reserved: row.get(4)?,
                 attachment: None,
+                attendees: Vec::new(),
             });
         } else {
             presence_checked = true;
@@ -315,13 +1263,44 @@ pub fn delete_event(
     if let Err(e) = conn.execute("DELETE FROM presence WHERE event=?1", params![event_id]) {
         error!("{}", e);
     }
+    if let Err(e) = conn.execute("DELETE FROM answers WHERE event=?1", params![event_id]) {
+        error!("{}", e);
+    }
+    if let Err(e) = conn.execute("DELETE FROM tickets WHERE event=?1", params![event_id]) {
+        error!("{}", e);
+    }
+    if let Err(e) = conn.execute(
+        "DELETE FROM revoked_tickets WHERE event=?1",
+        params![event_id],
+    ) {
+        error!("{}", e);
+    }
+    if let Err(e) = conn.execute("DELETE FROM checkouts WHERE event=?1", params![event_id]) {
+        error!("{}", e);
+    }
+    if let Err(e) = conn.execute(
+        "DELETE FROM refund_requests WHERE event=?1",
+        params![event_id],
+    ) {
+        error!("{}", e);
+    }
     if let Err(e) = conn.execute(
         "DELETE FROM group_leaders WHERE event=?1",
         params![event_id],
     ) {
         error!("{}", e);
     }
-    if let Err(e) = conn.execute("DELETE FROM messages WHERE event=?1", params![event_id]) {
+    // Feedback surveys go out after the event is over and outlive it.
+    if let Err(e) = conn.execute(
+        "DELETE FROM message_sent WHERE message IN (SELECT id FROM messages WHERE event=?1 AND type != ?2)",
+        params![event_id, MessageType::Feedback as u64],
+    ) {
+        error!("{}", e);
+    }
+    if let Err(e) = conn.execute(
+        "DELETE FROM messages WHERE event=?1 AND type != ?2",
+        params![event_id, MessageType::Feedback as u64],
+    ) {
         error!("{}", e);
     }
     Ok(())
@@ -353,15 +1332,20 @@ pub fn sign_up(
     let event_type = s.event.get_type();
 
     if ts > s.event.ts || (s.state != EventState::Open && user.is_admin == false) {
-        return Err(anyhow!("Запись остановлена."));
+        return Err(match s.state {
+            EventState::Upcoming(opens_at) => {
+                text!("registration-opens", opens = format::ts(opens_at)).into()
+            }
+            _ => text!("registration-closed").into(),
+        });
     }
 
     // Check event limits
-    if (wait == 0 || event_type == EventType::Paid)
+    if wait == 0
         && (adults as i64 > s.event.max_adults as i64 - s.adults.reserved as i64
             || children as i64 > s.event.max_children as i64 - s.children.reserved as i64)
     {
-        return Err(anyhow!("К сожалению, свободные места закончились."));
+        return Err(text!("no-seats-left").into());
     }
 
     let state = match event_type {
@@ -377,16 +1361,26 @@ pub fn sign_up(
                 .prepare("select events.id from events join reservations as r on events.id = r.event where events.ts = ?1 and r.user = ?2 and events.id != ?3")?;
             let mut rows = stmt.query(params![s.event.ts, user_id, s.event.id])?;
             if let Some(_) = rows.next()? {
-                return Err(anyhow!(
-                    "Вы уже записаны на другое мероприятие в это время."
-                ));
+                return Err(text!("time-conflict").into());
             }
             ReservationState::Free
         }
+        EventType::Paid if wait != 0 && !s.event.requires_approval => {
+            // Waiting list, paid once a seat is offered.
+            ReservationState::Free
+        }
+        EventType::Paid if s.event.requires_approval => {
+            // Payment follows the approval.
+            if s.adults.my_pending + s.children.my_pending > 0
+                || get_approved_booking(conn, event_id, user_id)? != (0, 0)
+            {
+                return Err(text!("application-exists").into());
+            }
+            ReservationState::PendingApproval
+        }
         EventType::Paid => {
             // pre checkout?
-            if s.event.adult_ticket_price * adults + s.event.child_ticket_price * children != amount
-            {
+            if get_booking_amount(conn, &s.event, user_id, adults, children)? != amount {
                 return Err(anyhow!("Wrong tranaction amount"));
             }
             ReservationState::PaymentPending
@@ -395,20 +1389,34 @@ pub fn sign_up(
             return Err(anyhow!("Wrong event type"));
         }
     };
+    let state = match state {
+        ReservationState::Free if s.event.requires_approval => ReservationState::PendingApproval,
+        state => state,
+    };
 
-    // Check user limits
-    if s.adults.my_reservation + s.adults.my_waiting + adults > s.event.max_adults_per_reservation {
-        if s.adults.my_reservation + adults > s.event.max_adults_per_reservation {
+    // Check user limits, a purchase doesn't count seats in the waiting list
+    let (my_waiting_adults, my_waiting_children) = match state {
+        ReservationState::PaymentPending => (0, 0),
+        _ => (s.adults.my_waiting, s.children.my_waiting),
+    };
+    if s.adults.my_reservation + my_waiting_adults + s.adults.my_pending + adults
+        > s.event.max_adults_per_reservation
+    {
+        if s.adults.my_reservation + s.adults.my_pending + adults
+            > s.event.max_adults_per_reservation
+        {
             return Ok((0, false));
         } else {
             move_from_waiting_list(conn, event_id, user_id, 1, 0)?;
             return Ok((1, false));
         }
     }
-    if s.children.my_reservation + s.children.my_waiting + children
+    if s.children.my_reservation + my_waiting_children + s.children.my_pending + children
         > s.event.max_children_per_reservation
     {
-        if s.children.my_reservation + children > s.event.max_children_per_reservation {
+        if s.children.my_reservation + s.children.my_pending + children
+            > s.event.max_children_per_reservation
+        {
             return Ok((0, false));
         } else {
             move_from_waiting_list(conn, event_id, user_id, 0, 1)?;
@@ -416,33 +1424,59 @@ pub fn sign_up(
         }
     }
 
-    Ok((conn.execute(
+    let pending = matches!(state, ReservationState::PaymentPending);
+    let inserted = conn.execute(
         "INSERT INTO reservations (event, user, user_name1, user_name2, adults, children, waiting_list, ts, state) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
         params![event_id, user_id, user.user_name1, user.user_name2, adults, children, wait, ts, state as u64],
-    )?, false))
+    )?;
+    if pending {
+        save_checkout(
+            conn,
+            &Booking {
+                event_id,
+                adults,
+                children,
+                user_id,
+            },
+            amount,
+        )?;
+    }
+    Ok((inserted, false))
 }
 
 pub fn checkout(conn: &Connection, booking: &Booking, order_info: OrderInfo) -> anyhow::Result<()> {
     let s = get_event(conn, booking.event_id, booking.user_id)?;
-    if s.event.adult_ticket_price * booking.adults + s.event.child_ticket_price * booking.children
-        != order_info.amount
-    {
+    // The amount validated at pre-checkout, the promo code may have expired since.
+    let amount = match get_checkout(conn, booking)? {
+        Some((amount, _)) => amount,
+        None => get_booking_amount(
+            conn,
+            &s.event,
+            booking.user_id,
+            booking.adults,
+            booking.children,
+        )?,
+    };
+    if amount != order_info.amount {
         return Err(anyhow!("Wrong tranaction amount"));
     }
 
     let mut stmt = conn
-        .prepare("select id from reservations where event = ?1 and user = ?2 and state = ?3 and adults = ?4 and children = ?5 limit 1")?;
+        .prepare("select id from reservations where event = ?1 and user = ?2 and state in (?3, ?6) and adults = ?4 and children = ?5 limit 1")?;
     let mut rows = stmt.query(params![
         booking.event_id,
         booking.user_id,
         ReservationState::PaymentPending as u64,
         booking.adults,
-        booking.children
+        booking.children,
+        ReservationState::Approved as u64,
     ])?;
+    let (offered_adults, offered_children, _) = get_offer(conn, booking.event_id, booking.user_id)?;
+    let offered = (offered_adults, offered_children);
     if let Some(row) = rows.next()? {
         let id: u64 = row.get("id")?;
         conn.execute(
-            "UPDATE reservations SET state = ?1, payment = ?2, user_name1 = ?3 WHERE id = ?4",
+            "UPDATE reservations SET state = ?1, payment = ?2, user_name1 = COALESCE(NULLIF(?3, ''), user_name1), hold_until = 0 WHERE id = ?4",
             params![
                 ReservationState::PaymentCompleted as u64,
                 serde_json::to_string(&order_info)?,
@@ -450,6 +1484,29 @@ pub fn checkout(conn: &Connection, booking: &Booking, order_info: OrderInfo) ->
                 id
             ],
         )?;
+        if s.event.named_tickets && !order_info.name.is_empty() {
+            name_first_free_seat(conn, booking.event_id, booking.user_id, &order_info.name)?;
+        }
+        use_promo_code(conn, booking)?;
+        Ok(())
+    } else if offered_adults + offered_children > 0 && offered == (booking.adults, booking.children)
+    {
+        // Seats held from the waiting list.
+        conn.execute(
+            "UPDATE reservations SET state = ?1, payment = ?2, user_name1 = COALESCE(NULLIF(?3, ''), user_name1), hold_until = 0 WHERE event = ?4 AND user = ?5 AND state = ?6",
+            params![
+                ReservationState::PaymentCompleted as u64,
+                serde_json::to_string(&order_info)?,
+                order_info.name,
+                booking.event_id,
+                booking.user_id,
+                ReservationState::Offered as u64
+            ],
+        )?;
+        if s.event.named_tickets && !order_info.name.is_empty() {
+            name_first_free_seat(conn, booking.event_id, booking.user_id, &order_info.name)?;
+        }
+        use_promo_code(conn, booking)?;
         Ok(())
     } else {
         Err(anyhow!(
@@ -460,104 +1517,620 @@ pub fn checkout(conn: &Connection, booking: &Booking, order_info: OrderInfo) ->
     }
 }
 
-fn move_from_waiting_list(
-    conn: &Connection,
-    event_id: u64,
-    user_id: u64,
-    adults: u64,
-    children: u64,
-) -> Result<(), rusqlite::Error> {
-    conn.execute("UPDATE reservations SET waiting_list = 0  WHERE id in \
-        (SELECT id FROM reservations where event = ?1 and user = ?2 and waiting_list = 1 and adults = ?3 and children = ?4 order by ts limit 1)",
-                 params![event_id, user_id, adults, children],
+/// Adds an entry to the payments ledger.
+pub fn record_payment(conn: &Connection, p: &Payment) -> Result<u64, rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO payments (ts, kind, event, user, amount, currency, telegram_charge_id, provider_charge_id, payload, promo_code) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            p.ts,
+            p.kind as u64,
+            p.event_id,
+            p.user_id,
+            p.amount,
+            p.currency,
+            p.charge_id,
+            p.provider_charge_id,
+            p.payload,
+            p.promo_code
+        ],
     )?;
-    Ok(())
+    Ok(conn.last_insert_rowid() as u64)
 }
 
-pub fn add_attachment(
+fn payment_from_row(row: &Row) -> Result<Payment, rusqlite::Error> {
+    let kind: u64 = row.get("kind")?;
+    Ok(Payment {
+        id: row.get("id")?,
+        ts: row.get("ts")?,
+        kind: PaymentKind::from_u64(kind).ok_or_else(|| {
+            rusqlite::Error::InvalidParameterName(format!("Unknown payment kind {}", kind))
+        })?,
+        event_id: row.get("event")?,
+        user_id: row.get("user")?,
+        amount: row.get("amount")?,
+        currency: row.get("currency")?,
+        charge_id: row.get("telegram_charge_id")?,
+        provider_charge_id: row.get("provider_charge_id")?,
+        payload: row.get("payload")?,
+        promo_code: row.get("promo_code")?,
+    })
+}
+
+/// Ledger entries of the event in order of arrival, event 0 holds donations.
+pub fn get_payments(conn: &Connection, event_id: u64) -> Result<Vec<Payment>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT * FROM payments WHERE event = ?1 ORDER BY id")?;
+    let rows = stmt.query_map(params![event_id], payment_from_row)?;
+    rows.collect()
+}
+
+/// Whether the charge is already in the ledger, providers may deliver a notification more than once.
+pub fn is_payment_recorded(conn: &Connection, charge_id: &str) -> Result<bool, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT id FROM payments WHERE telegram_charge_id = ?1")?;
+    let mut rows = stmt.query(params![charge_id])?;
+    Ok(rows.next()?.is_some())
+}
+
+pub fn get_payment_totals(
     conn: &Connection,
     event_id: u64,
-    user: u64,
-    attachment: &str,
-) -> Result<usize, rusqlite::Error> {
-    let msg = if attachment.len() < 256 {
-        format!("{}...", attachment.chars().take(256).collect::<String>())
-    } else {
-        attachment.to_string()
+) -> Result<PaymentTotals, rusqlite::Error> {
+    let mut totals = PaymentTotals {
+        event_id,
+        ..Default::default()
     };
-
-    let s = get_event(conn, event_id, user)?;
-    if s.adults.my_reservation > 0
-        || s.adults.my_waiting > 0
-        || s.children.my_reservation > 0
-        || s.children.my_waiting > 0
-    {
-        conn.execute(
-            "INSERT INTO attachments (event, user, attachment) VALUES (?1, ?2, ?3) ON CONFLICT (event, user) DO \
-            UPDATE SET attachment=excluded.attachment",
-            params![event_id, user, msg],
-        )
-    } else {
-        Ok(0)
+    for p in get_payments(conn, event_id)? {
+        let (count, amount) = match p.kind {
+            PaymentKind::Ticket => (&mut totals.tickets, &mut totals.ticket_amount),
+            PaymentKind::Refund => (&mut totals.refunds, &mut totals.refund_amount),
+            PaymentKind::Donation => (&mut totals.donations, &mut totals.donation_amount),
+        };
+        *count += 1;
+        *amount += p.amount;
+        totals.currency = p.currency;
     }
+    Ok(totals)
 }
 
-pub fn cancel(
+/// Matches ticket payments of the ledger with paid reservations by Telegram charge id.
+pub fn reconcile_payments(
     conn: &Connection,
     event_id: u64,
-    user: u64,
-    adults: u64,
-) -> Result<(), rusqlite::Error> {
-    let state_changed = have_vacancies(conn, event_id)? == false;
-    conn.execute(
-        "DELETE FROM reservations WHERE id IN (SELECT id FROM reservations WHERE event=?1 AND user=?2 AND adults = ?3 ORDER BY waiting_list DESC LIMIT 1)",
-        params![event_id, user, adults],
+) -> Result<Reconciliation, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT payment FROM reservations WHERE event = ?1 AND state IN (?2, ?3) AND payment IS NOT NULL",
     )?;
-    if state_changed {
-        prompt_waiting_list(conn, event_id)
-    } else {
-        Ok(())
+    let charges = stmt
+        .query_map(
+            params![
+                event_id,
+                ReservationState::PaymentCompleted as u64,
+                ReservationState::Refunded as u64
+            ],
+            |row| row.get::<_, String>(0),
+        )?
+        .collect::<Result<Vec<String>, _>>()?
+        .iter()
+        .filter_map(|payment| serde_json::from_str::<OrderInfo>(payment).ok())
+        .map(|order_info| order_info.id)
+        .collect::<HashSet<String>>();
+    let payments = get_payments(conn, event_id)?
+        .into_iter()
+        .filter(|p| p.kind == PaymentKind::Ticket)
+        .collect::<Vec<Payment>>();
+    let mut unrecorded_charges = charges
+        .iter()
+        .filter(|id| !payments.iter().any(|p| &p.charge_id == *id))
+        .cloned()
+        .collect::<Vec<String>>();
+    unrecorded_charges.sort();
+    Ok(Reconciliation {
+        unmatched_payments: payments
+            .into_iter()
+            .filter(|p| !charges.contains(&p.charge_id))
+            .collect(),
+        unrecorded_charges,
+    })
+}
+
+/// Paid seats of the user which can be refunded and the amount paid for them.
+/// Seats paid together share the payment, seats without one are counted at full price.
+fn get_paid_seats(
+    conn: &Connection,
+    e: &Event,
+    user_id: u64,
+) -> Result<(u64, u64), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT adults, children, payment FROM reservations WHERE event = ?1 AND user = ?2 AND state = ?3 AND waiting_list = 0",
+    )?;
+    let mut rows = stmt.query(params![
+        e.id,
+        user_id,
+        ReservationState::PaymentCompleted as u64
+    ])?;
+    let mut seats = 0;
+    let mut amount = 0;
+    let mut payments = HashSet::new();
+    while let Some(row) = rows.next()? {
+        let adults: u64 = row.get(0)?;
+        let children: u64 = row.get(1)?;
+        seats += adults + children;
+        match row
+            .get::<_, Option<String>>(2)?
+            .and_then(|payment| serde_json::from_str::<OrderInfo>(&payment).ok())
+        {
+            Some(order_info) => {
+                if payments.insert(order_info.id) {
+                    amount += order_info.amount;
+                }
+            }
+            None => amount += e.adult_ticket_price * adults + e.child_ticket_price * children,
+        }
     }
+    Ok((seats, amount))
 }
 
-pub fn wontgo(conn: &Connection, event_id: u64, user: u64) -> Result<(), rusqlite::Error> {
-    let state_changed = have_vacancies(conn, event_id)? == false;
+/// Requests a refund of the paid seats according to the event refund policy.
+/// Returns the amount to be refunded.
+pub fn request_refund(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+    ts: u64,
+) -> anyhow::Result<u64> {
+    let s = get_event(conn, event_id, user_id)?;
+    let (seats, paid) = get_paid_seats(conn, &s.event, user_id)?;
+    if seats == 0 {
+        return Err(text!("no-paid-tickets").into());
+    }
+    let percent = s.event.refund_percent(ts);
+    if percent == 0 {
+        return Err(text!("refund-impossible").into());
+    }
+    let amount = paid * percent / 100;
     conn.execute(
-        "DELETE FROM reservations WHERE event=?1 AND user=?2",
-        params![event_id, user],
+        "INSERT OR REPLACE INTO refund_requests (event, user, ts, amount) VALUES (?1, ?2, ?3, ?4)",
+        params![event_id, user_id, ts, amount],
     )?;
-    if state_changed {
-        prompt_waiting_list(conn, event_id)
-    } else {
-        Ok(())
-    }
+    Ok(amount)
 }
 
-fn have_vacancies(conn: &Connection, event_id: u64) -> Result<bool, rusqlite::Error> {
-    let (vacant_adults, vacant_children) = get_vacancies(conn, event_id)?;
-    if vacant_adults + vacant_children > 0 {
-        Ok(true)
-    } else {
-        Ok(false)
+/// Amount of the user's pending refund request, if any.
+pub fn get_refund_request(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+) -> Result<Option<u64>, rusqlite::Error> {
+    let mut stmt =
+        conn.prepare("SELECT amount FROM refund_requests WHERE event = ?1 AND user = ?2")?;
+    let mut rows = stmt.query(params![event_id, user_id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
     }
 }
 
-fn get_vacancies(conn: &Connection, event_id: u64) -> Result<(u64, u64), rusqlite::Error> {
-    let mut vacant_adults: u64 = 0;
-    let mut vacant_children: u64 = 0;
+pub fn get_refund_requests(
+    conn: &Connection,
+    event_id: u64,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<RefundRequest>, rusqlite::Error> {
     let mut stmt = conn.prepare(
-        "SELECT a.max_adults, a.max_children, b.adults, b.children, a.id FROM events as a \
-        LEFT JOIN (SELECT sum(adults) as adults, sum(children) as children, event FROM reservations WHERE event = ?1 AND waiting_list = 0 group by event) as b \
-        ON a.id = b.event WHERE id = ?1 group by id"
+        "SELECT q.user, q.amount, coalesce(sum(r.adults), 0), coalesce(sum(r.children), 0), \
+        coalesce(min(r.user_name1), ''), coalesce(min(r.user_name2), '') FROM refund_requests AS q \
+        LEFT JOIN reservations AS r ON r.event = q.event AND r.user = q.user AND r.state = ?2 AND r.waiting_list = 0 \
+        WHERE q.event = ?1 GROUP BY q.user ORDER BY q.ts LIMIT ?3 OFFSET ?4",
     )?;
-    let mut rows = stmt.query(params![event_id])?;
-    if let Some(row) = rows.next()? {
-        let max_adults: u64 = row.get(0)?;
-        let max_children: u64 = row.get(1)?;
-        let reserved_adults: u64 = match row.get(2) {
-            Ok(v) => v,
-            Err(_) => 0,
-        };
+    let mut rows = stmt.query(params![
+        event_id,
+        ReservationState::PaymentCompleted as u64,
+        limit,
+        offset * limit
+    ])?;
+    let mut res = Vec::new();
+    while let Some(row) = rows.next()? {
+        res.push(RefundRequest {
+            user_id: row.get(0)?,
+            amount: row.get(1)?,
+            adults: row.get(2)?,
+            children: row.get(3)?,
+            user_name1: row.get(4)?,
+            user_name2: row.get(5)?,
+        });
+    }
+    Ok(res)
+}
+
+/// Releases the refunded seats and records the refund in the payments ledger.
+/// The amount is recomputed from the seats paid now with the percent at the time of the request.
+pub fn approve_refund(conn: &Connection, event_id: u64, user_id: u64) -> anyhow::Result<u64> {
+    let s = get_event(conn, event_id, 0)?;
+    let mut stmt = conn.prepare("SELECT ts FROM refund_requests WHERE event = ?1 AND user = ?2")?;
+    let mut rows = stmt.query(params![event_id, user_id])?;
+    let ts: u64 = match rows.next()? {
+        Some(row) => row.get(0)?,
+        None => {
+            return Err(anyhow!(
+                "Failed to find refund request of user {}.",
+                user_id
+            ));
+        }
+    };
+    let (seats, paid) = get_paid_seats(conn, &s.event, user_id)?;
+    if seats == 0 {
+        return Err(text!("no-paid-tickets").into());
+    }
+    let amount = paid * s.event.refund_percent(ts) / 100;
+    let state_changed = !have_vacancies(conn, event_id)?;
+    conn.execute(
+        "UPDATE reservations SET state = ?1 WHERE event = ?2 AND user = ?3 AND state = ?4 AND waiting_list = 0",
+        params![
+            ReservationState::Refunded as u64,
+            event_id,
+            user_id,
+            ReservationState::PaymentCompleted as u64
+        ],
+    )?;
+    conn.execute(
+        "DELETE FROM refund_requests WHERE event = ?1 AND user = ?2",
+        params![event_id, user_id],
+    )?;
+    record_payment(
+        conn,
+        &Payment {
+            id: 0,
+            ts: get_unix_time(),
+            kind: PaymentKind::Refund,
+            event_id,
+            user_id,
+            amount,
+            currency: s.event.currency.clone(),
+            charge_id: "".to_string(),
+            provider_charge_id: "".to_string(),
+            payload: "".to_string(),
+            promo_code: "".to_string(),
+        },
+    )?;
+    trim_tickets(conn, event_id, user_id)?;
+    enqueue_personal_message(
+        conn,
+        event_id,
+        user_id,
+        MessageType::Notification,
+        &text!(
+            "refund-approved",
+            title = format::event_title(&s.event),
+            start = format::ts(s.event.ts),
+            amount = format::price(amount, &s.event.currency)
+        ),
+        get_unix_time(),
+    )?;
+    if state_changed {
+        prompt_waiting_list(conn, event_id)?;
+    }
+    Ok(amount)
+}
+
+pub fn reject_refund(conn: &Connection, event_id: u64, user_id: u64) -> anyhow::Result<()> {
+    let s = get_event(conn, event_id, 0)?;
+    let deleted = conn.execute(
+        "DELETE FROM refund_requests WHERE event = ?1 AND user = ?2",
+        params![event_id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(anyhow!(
+            "Failed to find refund request of user {}.",
+            user_id
+        ));
+    }
+    enqueue_personal_message(
+        conn,
+        event_id,
+        user_id,
+        MessageType::Notification,
+        &text!(
+            "refund-rejected",
+            title = format::event_title(&s.event),
+            start = format::ts(s.event.ts)
+        ),
+        get_unix_time(),
+    )?;
+    Ok(())
+}
+
+/// Confirms pending applications of the user. Paid events proceed to payment.
+pub fn approve_reservation(conn: &Connection, event_id: u64, user_id: u64) -> anyhow::Result<()> {
+    let s = get_event(conn, event_id, 0)?;
+    let mut stmt = conn.prepare(
+        "SELECT sum(adults), sum(children) FROM reservations WHERE event = ?1 AND user = ?2 AND state = ?3 AND waiting_list = 0",
+    )?;
+    let (adults, children): (Option<u64>, Option<u64>) = stmt.query_row(
+        params![event_id, user_id, ReservationState::PendingApproval as u64],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let (vacant_adults, vacant_children) = get_vacancies(conn, event_id)?;
+    if adults.unwrap_or(0) > vacant_adults || children.unwrap_or(0) > vacant_children {
+        return Err(text!("no-seats-left").into());
+    }
+
+    // Paid seats are held until the payment deadline like seats offered from the waiting list.
+    let is_paid = s.event.get_type() == EventType::Paid;
+    let (state, hold_until) = if is_paid {
+        (
+            ReservationState::Approved,
+            get_hold_until(&s.event, get_unix_time()),
+        )
+    } else {
+        (ReservationState::Free, 0)
+    };
+    let updated = conn.execute(
+        "UPDATE reservations SET state = ?1, hold_until = ?2 WHERE event = ?3 AND user = ?4 AND state = ?5 AND waiting_list = 0",
+        params![
+            state as u64,
+            hold_until,
+            event_id,
+            user_id,
+            ReservationState::PendingApproval as u64
+        ],
+    )?;
+    if updated == 0 {
+        return Err(anyhow!("Failed to find application for user {}.", user_id));
+    }
+
+    // Free seats are confirmed right away, their tickets follow the message.
+    enqueue_personal_message(
+        conn,
+        event_id,
+        user_id,
+        if is_paid {
+            MessageType::Notification
+        } else {
+            MessageType::Tickets
+        },
+        &text!(
+            "application-approved",
+            title = format::event_title(&s.event),
+            start = format::ts(s.event.ts),
+            paid = if is_paid { "yes" } else { "no" },
+            hold_until = format::ts(hold_until),
+        ),
+        get_unix_time(),
+    )?;
+    Ok(())
+}
+
+pub fn reject_reservation(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+    reason: Option<&str>,
+) -> anyhow::Result<()> {
+    let s = get_event(conn, event_id, 0)?;
+    let deleted = conn.execute(
+        "DELETE FROM reservations WHERE event = ?1 AND user = ?2 AND state = ?3",
+        params![event_id, user_id, ReservationState::PendingApproval as u64],
+    )?;
+    if deleted == 0 {
+        return Err(anyhow!("Failed to find application for user {}.", user_id));
+    }
+    trim_tickets(conn, event_id, user_id)?;
+
+    let text = match reason {
+        Some(reason) => text!("application-rejected-reason", reason = reason),
+        None => text!("application-rejected"),
+    }
+    .arg("title", format::event_title(&s.event))
+    .arg("start", format::ts(s.event.ts));
+    enqueue_personal_message(
+        conn,
+        event_id,
+        user_id,
+        MessageType::Notification,
+        &text,
+        get_unix_time(),
+    )?;
+    Ok(())
+}
+
+/// Application the manager is asked to give a rejection reason for: event, applicant and
+/// the approval queue page to return to.
+pub fn get_rejection_session(
+    conn: &Connection,
+    user_id: u64,
+) -> Result<Option<(u64, u64, u64)>, rusqlite::Error> {
+    let mut stmt =
+        conn.prepare("SELECT event, applicant, page FROM rejection_sessions WHERE user = ?1")?;
+    let mut rows = stmt.query([user_id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some((row.get(0)?, row.get(1)?, row.get(2)?))),
+        None => Ok(None),
+    }
+}
+
+/// Starts waiting for a rejection reason, event 0 stops it.
+pub fn set_rejection_session(
+    conn: &Connection,
+    user_id: u64,
+    event_id: u64,
+    applicant: u64,
+    offset: u64,
+) -> Result<(), rusqlite::Error> {
+    if event_id == 0 {
+        conn.execute(
+            "DELETE FROM rejection_sessions WHERE user = ?1",
+            params![user_id],
+        )?;
+    } else {
+        conn.execute(
+            "INSERT OR REPLACE INTO rejection_sessions (user, event, applicant, page) VALUES (?1, ?2, ?3, ?4)",
+            params![user_id, event_id, applicant, offset],
+        )?;
+    }
+    Ok(())
+}
+
+/// Seats approved for the user and waiting for payment.
+pub fn get_approved_booking(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+) -> Result<(u64, u64), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT sum(adults), sum(children) FROM reservations WHERE event = ?1 AND user = ?2 AND state = ?3",
+    )?;
+    let (adults, children): (Option<u64>, Option<u64>) = stmt.query_row(
+        params![event_id, user_id, ReservationState::Approved as u64],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok((adults.unwrap_or(0), children.unwrap_or(0)))
+}
+
+/// Approved seats are paid in one go before the deadline, which is extended for `hold` seconds
+/// so that the payment can complete.
+pub fn check_approved_booking(
+    conn: &Connection,
+    booking: &Booking,
+    amount: u64,
+    hold: u64,
+) -> anyhow::Result<()> {
+    let s = get_event(conn, booking.event_id, booking.user_id)?;
+    if get_booking_amount(
+        conn,
+        &s.event,
+        booking.user_id,
+        booking.adults,
+        booking.children,
+    )? != amount
+    {
+        return Err(anyhow!("Wrong tranaction amount"));
+    }
+    if get_approved_booking(conn, booking.event_id, booking.user_id)?
+        != (booking.adults, booking.children)
+    {
+        return Err(text!("application-not-approved").into());
+    }
+    let now = get_unix_time();
+    let extended = conn.execute(
+        "UPDATE reservations SET hold_until = max(hold_until, ?3) WHERE event = ?1 AND user = ?2 AND state = ?4 AND hold_until >= ?5",
+        params![
+            booking.event_id,
+            booking.user_id,
+            now + hold,
+            ReservationState::Approved as u64,
+            now
+        ],
+    )?;
+    if extended == 0 {
+        return Err(text!("hold-expired").into());
+    }
+    save_checkout(conn, booking, amount)?;
+    Ok(())
+}
+
+fn move_from_waiting_list(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+    adults: u64,
+    children: u64,
+) -> Result<(), rusqlite::Error> {
+    conn.execute("UPDATE reservations SET waiting_list = 0  WHERE id in \
+        (SELECT id FROM reservations where event = ?1 and user = ?2 and waiting_list = 1 and adults = ?3 and children = ?4 order by ts limit 1)",
+                 params![event_id, user_id, adults, children],
+    )?;
+    Ok(())
+}
+
+pub fn add_attachment(
+    conn: &Connection,
+    event_id: u64,
+    user: u64,
+    attachment: &str,
+) -> Result<usize, rusqlite::Error> {
+    let msg = if attachment.len() < 256 {
+        format!("{}...", attachment.chars().take(256).collect::<String>())
+    } else {
+        attachment.to_string()
+    };
+
+    let s = get_event(conn, event_id, user)?;
+    if s.adults.my_reservation > 0
+        || s.adults.my_waiting > 0
+        || s.children.my_reservation > 0
+        || s.children.my_waiting > 0
+    {
+        conn.execute(
+            "INSERT INTO attachments (event, user, attachment) VALUES (?1, ?2, ?3) ON CONFLICT (event, user) DO \
+            UPDATE SET attachment=excluded.attachment",
+            params![event_id, user, msg],
+        )
+    } else {
+        Ok(0)
+    }
+}
+
+pub fn cancel(
+    conn: &Connection,
+    event_id: u64,
+    user: u64,
+    adults: u64,
+) -> Result<(), rusqlite::Error> {
+    let state_changed = have_vacancies(conn, event_id)? == false;
+    conn.execute(
+        "DELETE FROM reservations WHERE id IN (SELECT id FROM reservations WHERE event=?1 AND user=?2 AND adults = ?3 ORDER BY state = ?4 DESC, waiting_list DESC LIMIT 1)",
+        params![event_id, user, adults, ReservationState::PendingApproval as u64],
+    )?;
+    trim_tickets(conn, event_id, user)?;
+    if state_changed {
+        prompt_waiting_list(conn, event_id)
+    } else {
+        Ok(())
+    }
+}
+
+pub fn wontgo(conn: &Connection, event_id: u64, user: u64) -> Result<(), rusqlite::Error> {
+    let state_changed = have_vacancies(conn, event_id)? == false;
+    conn.execute(
+        "DELETE FROM reservations WHERE event=?1 AND user=?2",
+        params![event_id, user],
+    )?;
+    trim_tickets(conn, event_id, user)?;
+    if state_changed {
+        prompt_waiting_list(conn, event_id)
+    } else {
+        Ok(())
+    }
+}
+
+fn have_vacancies(conn: &Connection, event_id: u64) -> Result<bool, rusqlite::Error> {
+    let (vacant_adults, vacant_children) = get_vacancies(conn, event_id)?;
+    if vacant_adults + vacant_children > 0 {
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+pub fn get_vacancies(conn: &Connection, event_id: u64) -> Result<(u64, u64), rusqlite::Error> {
+    let mut vacant_adults: u64 = 0;
+    let mut vacant_children: u64 = 0;
+    let mut stmt = conn.prepare(
+        "SELECT a.max_adults, a.max_children, b.adults, b.children, a.id FROM events as a \
+        LEFT JOIN (SELECT sum(adults) as adults, sum(children) as children, event FROM reservations WHERE event = ?1 AND waiting_list = 0 AND state NOT IN (?2, ?3) group by event) as b \
+        ON a.id = b.event WHERE id = ?1 group by id"
+    )?;
+    let mut rows = stmt.query(params![
+        event_id,
+        ReservationState::PendingApproval as u64,
+        ReservationState::Refunded as u64
+    ])?;
+    if let Some(row) = rows.next()? {
+        let max_adults: u64 = row.get(0)?;
+        let max_children: u64 = row.get(1)?;
+        let reserved_adults: u64 = match row.get(2) {
+            Ok(v) => v,
+            Err(_) => 0,
+        };
         let reserved_children: u64 = match row.get(3) {
             Ok(v) => v,
             Err(_) => 0,
@@ -591,13 +2164,20 @@ pub fn get_events(
     limit: u64,
 ) -> Result<Vec<EventStats>, rusqlite::Error> {
     let mut stmt = conn.prepare(
-        "select a.*, b.my_adults, b.my_children, c.my_wait_adults, c.my_wait_children FROM \
-        (SELECT events.id, events.name, events.link, events.max_adults, events.max_children, events.max_adults_per_reservation, events.max_children_per_reservation, events.ts, r.adults, r.children, events.state, events.adult_ticket_price, events.child_ticket_price, events.currency FROM events \
-        LEFT JOIN (SELECT sum(adults) as adults, sum(children) as children, event FROM reservations WHERE waiting_list = 0 GROUP BY event) as r ON events.id = r.event ORDER BY ts LIMIT ?2 OFFSET ?3) as a \
-        LEFT JOIN (SELECT sum(adults) as my_adults, sum(children) as my_children, event FROM reservations WHERE waiting_list = 0 AND user = ?1 GROUP BY event) as b ON a.id = b.event \
-        LEFT JOIN (SELECT sum(adults) as my_wait_adults, sum(children) as my_wait_children, event FROM reservations WHERE waiting_list = 1 AND user = ?1 GROUP BY event) as c ON a.id = c.event"
+        "select a.*, b.my_adults, b.my_children, c.my_wait_adults, c.my_wait_children, d.my_pending_adults, d.my_pending_children FROM \
+        (SELECT events.*, r.adults, r.children FROM events \
+        LEFT JOIN (SELECT sum(adults) as adults, sum(children) as children, event FROM reservations WHERE waiting_list = 0 AND state NOT IN (?4, ?5) GROUP BY event) as r ON events.id = r.event ORDER BY ts LIMIT ?2 OFFSET ?3) as a \
+        LEFT JOIN (SELECT sum(adults) as my_adults, sum(children) as my_children, event FROM reservations WHERE waiting_list = 0 AND state NOT IN (?4, ?5) AND user = ?1 GROUP BY event) as b ON a.id = b.event \
+        LEFT JOIN (SELECT sum(adults) as my_wait_adults, sum(children) as my_wait_children, event FROM reservations WHERE waiting_list = 1 AND state NOT IN (?4, ?5) AND user = ?1 GROUP BY event) as c ON a.id = c.event \
+        LEFT JOIN (SELECT sum(adults) as my_pending_adults, sum(children) as my_pending_children, event FROM reservations WHERE state = ?4 AND user = ?1 GROUP BY event) as d ON a.id = d.event"
     )?;
-    let mut rows = stmt.query([user, limit, offset * limit])?;
+    let mut rows = stmt.query([
+        user,
+        limit,
+        offset * limit,
+        ReservationState::PendingApproval as u64,
+        ReservationState::Refunded as u64,
+    ])?;
     let mut res = Vec::new();
     while let Some(row) = rows.next()? {
         res.push(EventStats::new(row)?);
@@ -605,19 +2185,150 @@ pub fn get_events(
     Ok(res)
 }
 
+/// Upcoming events the user has seats for, with past events they attended if `past` is set.
+pub fn get_user_bookings(
+    conn: &Connection,
+    user: u64,
+    now: u64,
+    past: bool,
+) -> Result<Vec<UserBooking>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT e.id, e.name, e.link, e.ts, e.venue, e.adult_ticket_price, e.child_ticket_price, \
+        e.max_adults = 0 OR e.max_children = 0, \
+        sum(CASE WHEN r.waiting_list = 0 AND r.state IN (?4, ?5) THEN r.adults ELSE 0 END), \
+        sum(CASE WHEN r.waiting_list = 0 AND r.state IN (?4, ?5) THEN r.children ELSE 0 END), \
+        sum(CASE WHEN r.waiting_list = 1 AND r.state != ?6 THEN r.adults ELSE 0 END), \
+        sum(CASE WHEN r.waiting_list = 1 AND r.state != ?6 THEN r.children ELSE 0 END), \
+        sum(CASE WHEN r.state = ?6 THEN r.adults ELSE 0 END), \
+        sum(CASE WHEN r.state = ?6 THEN r.children ELSE 0 END), \
+        sum(CASE WHEN r.waiting_list = 0 AND r.state IN (?7, ?8, ?9) THEN r.adults ELSE 0 END), \
+        sum(CASE WHEN r.waiting_list = 0 AND r.state IN (?7, ?8, ?9) THEN r.children ELSE 0 END), \
+        a.attachment, p.user IS NOT NULL \
+        FROM reservations AS r JOIN events AS e ON r.event = e.id \
+        LEFT JOIN attachments AS a ON a.event = r.event AND a.user = r.user \
+        LEFT JOIN presence AS p ON p.event = r.event AND p.user = r.user \
+        WHERE r.user = ?1 AND r.state != ?10 AND (e.ts >= ?2 OR (?3 AND p.user IS NOT NULL)) \
+        GROUP BY e.id ORDER BY e.ts",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            user,
+            now,
+            past,
+            ReservationState::Free as u64,
+            ReservationState::PaymentCompleted as u64,
+            ReservationState::PendingApproval as u64,
+            ReservationState::PaymentPending as u64,
+            ReservationState::Approved as u64,
+            ReservationState::Offered as u64,
+            ReservationState::Refunded as u64,
+        ],
+        |row| {
+            Ok(UserBooking {
+                event_id: row.get(0)?,
+                name: row.get(1)?,
+                link: row.get(2)?,
+                ts: row.get(3)?,
+                venue: row.get(4)?,
+                is_paid: row.get::<_, u64>(5)? != 0 || row.get::<_, u64>(6)? != 0,
+                no_age_distinction: row.get(7)?,
+                adults: row.get(8)?,
+                children: row.get(9)?,
+                waiting_adults: row.get(10)?,
+                waiting_children: row.get(11)?,
+                pending_adults: row.get(12)?,
+                pending_children: row.get(13)?,
+                unpaid_adults: row.get(14)?,
+                unpaid_children: row.get(15)?,
+                attachment: row.get(16)?,
+                attended: row.get(17)?,
+            })
+        },
+    )?;
+    rows.collect()
+}
+
+/// Events the user has confirmed seats for, for the calendar feed.
+/// Last time the list of upcoming events changed: an event was saved or one started.
+pub fn get_events_modified(conn: &Connection, now: u64) -> Result<u64, rusqlite::Error> {
+    conn.query_row(
+        "SELECT max(ifnull(max(updated), 0), ifnull((SELECT max(ts) FROM events WHERE ts <= ?1), 0)) FROM events",
+        [now],
+        |row| row.get(0),
+    )
+}
+
+pub fn find_event(conn: &Connection, event_id: u64) -> Result<Option<Event>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT * FROM events WHERE id = ?1")?;
+    let mut rows = stmt.query([event_id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(event_from_row(row)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn get_confirmed_events(conn: &Connection, user: u64) -> Result<Vec<Event>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM events WHERE id IN \
+        (SELECT event FROM reservations WHERE user = ?1 AND waiting_list = 0 AND state IN (?2, ?3)) \
+        ORDER BY ts",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            user,
+            ReservationState::Free as u64,
+            ReservationState::PaymentCompleted as u64
+        ],
+        event_from_row,
+    )?;
+    rows.collect()
+}
+
+/// Secret token of the user's calendar feed, issued on first use or anew if `reset` is set.
+pub fn get_calendar_token(conn: &Connection, user: u64, reset: bool) -> anyhow::Result<String> {
+    if !reset {
+        let mut stmt = conn.prepare("SELECT token FROM calendar_tokens WHERE user = ?1")?;
+        let mut rows = stmt.query(params![user])?;
+        if let Some(row) = rows.next()? {
+            return Ok(row.get(0)?);
+        }
+    }
+    let token = util::generate_code()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO calendar_tokens (user, token) VALUES (?1, ?2)",
+        params![user, token],
+    )?;
+    Ok(token)
+}
+
+pub fn get_calendar_user(conn: &Connection, token: &str) -> Result<Option<u64>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT user FROM calendar_tokens WHERE token = ?1")?;
+    let mut rows = stmt.query(params![token])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
 pub fn get_event(
     conn: &Connection,
     event_id: u64,
     user: u64,
 ) -> Result<EventStats, rusqlite::Error> {
     let mut stmt = conn.prepare(
-        "select a.*, b.my_adults, b.my_children, c.my_wait_adults, c.my_wait_children FROM \
-        (SELECT events.id, events.name, events.link, events.max_adults, events.max_children, events.max_adults_per_reservation, events.max_children_per_reservation, events.ts, r.adults, r.children, events.state, events.adult_ticket_price, events.child_ticket_price, events.currency FROM events \
-        LEFT JOIN (SELECT sum(adults) as adults, sum(children) as children, event FROM reservations WHERE waiting_list = 0 GROUP BY event) as r ON events.id = r.event) as a \
-        LEFT JOIN (SELECT sum(adults) as my_adults, sum(children) as my_children, event FROM reservations WHERE waiting_list = 0 AND user = ?1 GROUP BY event) as b ON a.id = b.event \
-        LEFT JOIN (SELECT sum(adults) as my_wait_adults, sum(children) as my_wait_children, event FROM reservations WHERE waiting_list = 1 AND user = ?1 GROUP BY event) as c ON a.id = c.event WHERE a.id = ?2"
+        "select a.*, b.my_adults, b.my_children, c.my_wait_adults, c.my_wait_children, d.my_pending_adults, d.my_pending_children FROM \
+        (SELECT events.*, r.adults, r.children FROM events \
+        LEFT JOIN (SELECT sum(adults) as adults, sum(children) as children, event FROM reservations WHERE waiting_list = 0 AND state NOT IN (?3, ?4) GROUP BY event) as r ON events.id = r.event) as a \
+        LEFT JOIN (SELECT sum(adults) as my_adults, sum(children) as my_children, event FROM reservations WHERE waiting_list = 0 AND state NOT IN (?3, ?4) AND user = ?1 GROUP BY event) as b ON a.id = b.event \
+        LEFT JOIN (SELECT sum(adults) as my_wait_adults, sum(children) as my_wait_children, event FROM reservations WHERE waiting_list = 1 AND state NOT IN (?3, ?4) AND user = ?1 GROUP BY event) as c ON a.id = c.event \
+        LEFT JOIN (SELECT sum(adults) as my_pending_adults, sum(children) as my_pending_children, event FROM reservations WHERE state = ?3 AND user = ?1 GROUP BY event) as d ON a.id = d.event WHERE a.id = ?2"
     )?;
-    let mut rows = stmt.query([user, event_id])?;
+    let mut rows = stmt.query([
+        user,
+        event_id,
+        ReservationState::PendingApproval as u64,
+        ReservationState::Refunded as u64,
+    ])?;
     if let Some(row) = rows.next()? {
         set_current_event(conn, user, event_id)?;
         Ok(EventStats::new(row)?)
@@ -657,114 +2368,949 @@ pub fn get_participants(
             "SELECT a.*, b.attachment FROM (SELECT sum(adults) as adults, sum(children) as children, user, user_name1, user_name2, event, ts FROM reservations WHERE waiting_list = ?1 AND event = ?2 AND state = ?3 group by event, user ORDER BY ts) as a \
         LEFT JOIN attachments as b ON a.event = b.event and a.user = b.user"
         )?;
-        stmt.query([waiting_list, event_id, state as u64])?
+        stmt.query([waiting_list, event_id, state as u64])?
+    } else {
+        stmt = conn.prepare(
+            "SELECT a.*, b.attachment FROM (SELECT sum(adults) as adults, sum(children) as children, user, user_name1, user_name2, event, ts FROM reservations WHERE waiting_list = ?1 AND event = ?2 AND state = ?3 group by event, user ORDER BY ts LIMIT ?4 OFFSET ?5) as a \
+            LEFT JOIN attachments as b ON a.event = b.event and a.user = b.user"
+        )?;
+        stmt.query([waiting_list, event_id, state as u64, limit, offset * limit])?
+    };
+    let mut res = Vec::new();
+    while let Some(row) = rows.next()? {
+        res.push(Participant {
+            adults: row.get(0)?,
+            children: row.get(1)?,
+            user_id: row.get(2)?,
+            user_name1: row.get(3)?,
+            user_name2: row.get(4)?,
+            attachment: match row.get(7) {
+                Ok(v) => Some(v),
+                Err(_) => None,
+            },
+        });
+    }
+    Ok(res)
+}
+
+pub fn get_approval_queue(
+    conn: &Connection,
+    event_id: u64,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<Participant>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT a.*, b.attachment FROM (SELECT sum(adults) as adults, sum(children) as children, user, user_name1, user_name2, event, min(ts) as ts FROM reservations WHERE event = ?1 AND state = ?2 group by event, user ORDER BY ts LIMIT ?3 OFFSET ?4) as a \
+        LEFT JOIN attachments as b ON a.event = b.event and a.user = b.user",
+    )?;
+    let mut rows = stmt.query([
+        event_id,
+        ReservationState::PendingApproval as u64,
+        limit,
+        offset * limit,
+    ])?;
+    let mut res = Vec::new();
+    while let Some(row) = rows.next()? {
+        res.push(Participant {
+            adults: row.get(0)?,
+            children: row.get(1)?,
+            user_id: row.get(2)?,
+            user_name1: row.get(3)?,
+            user_name2: row.get(4)?,
+            attachment: row.get(7).ok(),
+        });
+    }
+    Ok(res)
+}
+
+/// Participants whose presence is not confirmed yet.
+pub fn get_presence_list(
+    conn: &Connection,
+    event_id: u64,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<Presence>, rusqlite::Error> {
+    get_participants_by_presence(conn, event_id, false, offset, limit)
+}
+
+/// Participants already marked present.
+pub fn get_present_list(
+    conn: &Connection,
+    event_id: u64,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<Presence>, rusqlite::Error> {
+    get_participants_by_presence(conn, event_id, true, offset, limit)
+}
+
+fn get_participants_by_presence(
+    conn: &Connection,
+    event_id: u64,
+    present: bool,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<Presence>, rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!(
+        "select r.*, p.user, a.attachment from (select event, user, user_name1, user_name2, count(user) from reservations where event = ?1 and waiting_list = 0 and state not in (?4, ?5) group by user) as r \
+            left join presence as p on r.event = p.event and r.user = p.user \
+            left join attachments as a on r.event = a.event and r.user = a.user \
+            where p.user IS {} order by r.user_name1 LIMIT ?2 OFFSET ?3",
+        if present { "NOT NULL" } else { "NULL" }
+    ))?;
+    let mut rows = stmt.query([
+        event_id,
+        limit,
+        offset * limit,
+        ReservationState::PendingApproval as u64,
+        ReservationState::Refunded as u64,
+    ])?;
+    let mut res = Vec::new();
+    while let Some(row) = rows.next()? {
+        res.push(Presence {
+            user_id: row.get(1)?,
+            user_name1: row.get(2)?,
+            user_name2: row.get(3)?,
+            reserved: row.get(4)?,
+            attachment: match row.get(6) {
+                Ok(v) => Some(v),
+                Err(_) => None,
+            },
+            attendees: Vec::new(),
+        });
+    }
+    for p in &mut res {
+        p.attendees = get_tickets(conn, event_id, p.user_id)?
+            .into_iter()
+            .filter(|t| !t.name.is_empty())
+            .map(|t| t.name)
+            .collect();
+    }
+    Ok(res)
+}
+
+pub fn confirm_presence(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "insert or ignore into presence (event, user) values (?1, ?2)",
+        params![event_id, user_id],
+    )?;
+    Ok(())
+}
+
+pub fn is_group_leader(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+) -> Result<bool, rusqlite::Error> {
+    let mut stmt =
+        conn.prepare("SELECT event FROM group_leaders WHERE event = ?1 AND user = ?2")?;
+    let mut rows = stmt.query(params![event_id, user_id])?;
+    if let Some(_) = rows.next()? {
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+pub fn set_group_leader(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "insert into group_leaders (event, user) values (?1, ?2)",
+        params![event_id, user_id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_reservation(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+) -> Result<(), rusqlite::Error> {
+    let state_changed = have_vacancies(conn, event_id)? == false;
+    conn.execute(
+        "delete from reservations where event = ?1 and user = ?2",
+        params![event_id, user_id],
+    )?;
+    conn.execute(
+        "delete from answers where event = ?1 and user = ?2",
+        params![event_id, user_id],
+    )?;
+    conn.execute(
+        "delete from refund_requests where event = ?1 and user = ?2",
+        params![event_id, user_id],
+    )?;
+    trim_tickets(conn, event_id, user_id)?;
+    if state_changed {
+        prompt_waiting_list(conn, event_id)
+    } else {
+        Ok(())
+    }
+}
+
+/// Number of seats booked by the user, including waiting list and pending applications.
+pub fn get_seat_count(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+) -> Result<u64, rusqlite::Error> {
+    conn.query_row(
+        "SELECT coalesce(sum(adults + children), 0) FROM reservations WHERE event = ?1 AND user = ?2 AND state != ?3",
+        params![event_id, user_id, ReservationState::Refunded as u64],
+        |row| row.get(0),
+    )
+}
+
+/// Drops names of seats which are no longer booked and revokes their codes.
+/// Revoked codes are kept so that check-in can tell them from unknown ones.
+fn trim_tickets(conn: &Connection, event_id: u64, user_id: u64) -> Result<(), rusqlite::Error> {
+    revoke_tickets(conn, event_id, user_id)?;
+    conn.execute(
+        "DELETE FROM tickets WHERE event = ?1 AND user = ?2 AND code IS NULL AND seat >= \
+        (SELECT coalesce(sum(adults + children), 0) FROM reservations WHERE event = ?1 AND user = ?2 AND state != ?3)",
+        params![event_id, user_id, ReservationState::Refunded as u64],
+    )?;
+    conn.execute(
+        "UPDATE tickets SET name = '', email = NULL WHERE event = ?1 AND user = ?2 AND seat >= \
+        (SELECT coalesce(sum(adults + children), 0) FROM reservations WHERE event = ?1 AND user = ?2 AND state != ?3)",
+        params![event_id, user_id, ReservationState::Refunded as u64],
+    )?;
+    Ok(())
+}
+
+pub fn set_ticket_name(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+    seat: u64,
+    name: &str,
+    email: Option<&str>,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO tickets (event, user, seat, name, email) VALUES (?1, ?2, ?3, ?4, ?5) \
+        ON CONFLICT (event, user, seat) DO UPDATE SET name = excluded.name, email = excluded.email",
+        params![event_id, user_id, seat, name, email],
+    )?;
+    Ok(())
+}
+
+/// Generates entrance codes for confirmed seats which have none yet and returns the new tickets.
+/// Codes of cancelled or unconfirmed reservations are revoked first.
+pub fn issue_tickets(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+) -> anyhow::Result<Vec<Ticket>> {
+    revoke_tickets(conn, event_id, user_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT r.id, r.adults + r.children - (SELECT count(*) FROM tickets AS t WHERE t.reservation = r.id) \
+        FROM reservations AS r WHERE r.event = ?1 AND r.user = ?2 AND r.waiting_list = 0 AND r.state IN (?3, ?4) ORDER BY r.id",
+    )?;
+    let mut rows = stmt.query(params![
+        event_id,
+        user_id,
+        ReservationState::Free as u64,
+        ReservationState::PaymentCompleted as u64
+    ])?;
+    let mut missing: Vec<u64> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let reservation: u64 = row.get(0)?;
+        let count: i64 = row.get(1)?;
+        missing.extend((0..count).map(|_| reservation));
+    }
+    let mut stmt = conn.prepare(
+        "SELECT seat FROM tickets WHERE event = ?1 AND user = ?2 AND reservation IS NOT NULL",
+    )?;
+    let mut taken = stmt
+        .query_map(params![event_id, user_id], |row| row.get::<_, u64>(0))?
+        .collect::<Result<Vec<u64>, _>>()?;
+    let mut issued = Vec::new();
+    for reservation in missing {
+        let seat = (0..).find(|seat| !taken.contains(seat)).unwrap_or(0);
+        taken.push(seat);
+        let code = util::generate_code()?;
+        // The revoked code of the seat is kept so that check-in still reports it as cancelled.
+        conn.execute(
+            "INSERT OR IGNORE INTO revoked_tickets (code, event, user, seat, name, email) \
+            SELECT code, event, user, seat, name, email FROM tickets \
+            WHERE event = ?1 AND user = ?2 AND seat = ?3 AND code IS NOT NULL",
+            params![event_id, user_id, seat],
+        )?;
+        conn.execute(
+            "INSERT INTO tickets (event, user, seat, name, reservation, code) VALUES (?1, ?2, ?3, '', ?4, ?5) \
+            ON CONFLICT (event, user, seat) DO UPDATE SET reservation = excluded.reservation, code = excluded.code, checked_in = 0",
+            params![event_id, user_id, seat, reservation, code],
+        )?;
+        issued.push(seat);
+    }
+    Ok(get_tickets(conn, event_id, user_id)?
+        .into_iter()
+        .filter(|t| issued.contains(&t.seat))
+        .collect())
+}
+
+/// Issues missing tickets in all upcoming events the user has booked.
+pub fn issue_user_tickets(conn: &Connection, user_id: u64) -> anyhow::Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT r.event FROM reservations AS r JOIN events AS e ON e.id = r.event WHERE r.user = ?1 AND e.ts > ?2",
+    )?;
+    let mut rows = stmt.query(params![user_id, get_unix_time()])?;
+    while let Some(row) = rows.next()? {
+        issue_tickets(conn, row.get(0)?, user_id)?;
+    }
+    Ok(())
+}
+
+/// Detaches codes from reservations which are gone or no longer confirmed.
+fn revoke_tickets(conn: &Connection, event_id: u64, user_id: u64) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE tickets SET reservation = NULL WHERE event = ?1 AND user = ?2 AND code IS NOT NULL AND reservation NOT IN \
+        (SELECT id FROM reservations WHERE event = ?1 AND user = ?2 AND waiting_list = 0 AND state IN (?3, ?4))",
+        params![
+            event_id,
+            user_id,
+            ReservationState::Free as u64,
+            ReservationState::PaymentCompleted as u64
+        ],
+    )?;
+    Ok(())
+}
+
+/// Reverts a mistaken presence confirmation together with ticket check-ins.
+pub fn unconfirm_presence(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM presence WHERE event = ?1 AND user = ?2",
+        params![event_id, user_id],
+    )?;
+    conn.execute(
+        "UPDATE tickets SET checked_in = 0 WHERE event = ?1 AND user = ?2",
+        params![event_id, user_id],
+    )?;
+    Ok(())
+}
+
+/// Reserved, present and absent participants and seats of confirmed reservations.
+pub fn get_attendance_stats(
+    conn: &Connection,
+    event_id: u64,
+) -> Result<AttendanceStats, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT count(DISTINCT r.user), count(DISTINCT p.user), \
+        coalesce(sum(r.adults), 0), coalesce(sum(CASE WHEN p.user IS NULL THEN 0 ELSE r.adults END), 0), \
+        coalesce(sum(r.children), 0), coalesce(sum(CASE WHEN p.user IS NULL THEN 0 ELSE r.children END), 0) \
+        FROM reservations AS r LEFT JOIN presence AS p ON p.event = r.event AND p.user = r.user \
+        WHERE r.event = ?1 AND r.waiting_list = 0 AND r.state NOT IN (?2, ?3)",
+    )?;
+    stmt.query_row(
+        params![
+            event_id,
+            ReservationState::PendingApproval as u64,
+            ReservationState::Refunded as u64
+        ],
+        |row| {
+            let attendance = |reserved: u64, present: u64| Attendance {
+                reserved,
+                present,
+                no_show: reserved - present,
+            };
+            Ok(AttendanceStats {
+                event_id,
+                participants: attendance(row.get(0)?, row.get(1)?),
+                adults: attendance(row.get(2)?, row.get(3)?),
+                children: attendance(row.get(4)?, row.get(5)?),
+            })
+        },
+    )
+}
+
+/// Validates an entrance code for the event and marks the seat and its owner present.
+pub fn check_in(conn: &Connection, event_id: u64, code: &str) -> Result<CheckIn, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT t.event, t.user, t.seat, t.name, t.email, t.code, t.checked_in, r.id FROM tickets AS t \
+        LEFT JOIN reservations AS r ON r.id = t.reservation AND r.waiting_list = 0 AND r.state IN (?2, ?3) \
+        WHERE t.code = ?1",
+    )?;
+    let mut rows = stmt.query(params![
+        code,
+        ReservationState::Free as u64,
+        ReservationState::PaymentCompleted as u64
+    ])?;
+    let row = match rows.next()? {
+        Some(row) => row,
+        None => return check_revoked(conn, event_id, code),
+    };
+    let checked_in: u64 = row.get(6)?;
+    let reservation: Option<u64> = row.get(7)?;
+    let ticket = Ticket {
+        event_id: row.get(0)?,
+        user_id: row.get(1)?,
+        seat: row.get(2)?,
+        name: row.get(3)?,
+        email: row.get(4)?,
+        code: row.get(5)?,
+    };
+    if ticket.event_id != event_id {
+        Ok(CheckIn::WrongEvent { ticket })
+    } else if reservation.is_none() {
+        Ok(CheckIn::Cancelled { ticket })
+    } else if checked_in != 0 {
+        Ok(CheckIn::AlreadyCheckedIn {
+            ticket,
+            ts: checked_in,
+        })
+    } else {
+        conn.execute(
+            "UPDATE tickets SET checked_in = ?2 WHERE code = ?1",
+            params![code, get_unix_time()],
+        )?;
+        confirm_presence(conn, event_id, ticket.user_id)?;
+        Ok(CheckIn::CheckedIn { ticket })
+    }
+}
+
+/// Codes replaced by newly issued tickets are only known as cancelled ones.
+fn check_revoked(conn: &Connection, event_id: u64, code: &str) -> Result<CheckIn, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT event, user, seat, name, email, code FROM revoked_tickets WHERE code = ?1",
+    )?;
+    let rows = stmt.query([code])?;
+    Ok(match tickets_from_rows(rows)?.pop() {
+        Some(ticket) if ticket.event_id != event_id => CheckIn::WrongEvent { ticket },
+        Some(ticket) => CheckIn::Cancelled { ticket },
+        None => CheckIn::Unknown,
+    })
+}
+
+/// Checked in seats and all valid tickets of the event.
+pub fn get_check_in_count(conn: &Connection, event_id: u64) -> Result<(u64, u64), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT coalesce(sum(t.checked_in != 0), 0), count(*) FROM tickets AS t \
+        JOIN reservations AS r ON r.id = t.reservation AND r.waiting_list = 0 AND r.state IN (?2, ?3) \
+        WHERE t.event = ?1",
+    )?;
+    stmt.query_row(
+        params![
+            event_id,
+            ReservationState::Free as u64,
+            ReservationState::PaymentCompleted as u64
+        ],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+}
+
+/// Event whose tickets the manager is checking, 0 if none.
+/// The session ends as soon as the manager opens another event.
+pub fn get_check_in_event(conn: &Connection, user_id: u64) -> Result<u64, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT s.event FROM check_in_sessions AS s \
+        JOIN current_events AS c ON c.user = s.user AND c.event = s.event WHERE s.user = ?1",
+    )?;
+    let mut rows = stmt.query([user_id])?;
+    if let Some(row) = rows.next()? {
+        row.get(0)
+    } else {
+        Ok(0)
+    }
+}
+
+fn promo_code_from_row(row: &Row) -> Result<PromoCode, rusqlite::Error> {
+    Ok(PromoCode {
+        code: row.get("code")?,
+        event_id: row.get("event")?,
+        percent: row.get("percent")?,
+        amount: row.get("amount")?,
+        max_uses: row.get("max_uses")?,
+        uses: row.get("uses")?,
+        expires_at: row.get("expires_at")?,
+    })
+}
+
+/// Adds a promo code or changes an existing one keeping its usage count.
+pub fn add_promo_code(conn: &Connection, p: &PromoCode) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO promo_codes (code, event, percent, amount, max_uses, expires_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6) \
+        ON CONFLICT (code) DO UPDATE SET event = ?2, percent = ?3, amount = ?4, max_uses = ?5, expires_at = ?6",
+        params![
+            p.code.to_uppercase(),
+            p.event_id,
+            p.percent,
+            p.amount,
+            p.max_uses,
+            p.expires_at
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn delete_promo_code(conn: &Connection, code: &str) -> Result<usize, rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM promo_codes WHERE code = ?1",
+        params![code.to_uppercase()],
+    )
+}
+
+pub fn get_promo_codes(conn: &Connection) -> Result<Vec<PromoCode>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT * FROM promo_codes ORDER BY event, code")?;
+    let rows = stmt.query_map([], promo_code_from_row)?;
+    rows.collect()
+}
+
+fn get_promo_code(conn: &Connection, code: &str) -> Result<Option<PromoCode>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT * FROM promo_codes WHERE code = ?1")?;
+    let mut rows = stmt.query(params![code.to_uppercase()])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(promo_code_from_row(row)?)),
+        None => Ok(None),
+    }
+}
+
+/// Remembers the promo code for the user's next payment for the event.
+pub fn apply_promo_code(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+    code: &str,
+) -> anyhow::Result<PromoCode> {
+    match get_promo_code(conn, code.trim())? {
+        Some(p) if p.is_valid(event_id, get_unix_time()) => {
+            conn.execute(
+                "INSERT OR REPLACE INTO applied_promo_codes (event, user, code) VALUES (?1, ?2, ?3)",
+                params![event_id, user_id, p.code],
+            )?;
+            Ok(p)
+        }
+        _ => Err(text!("promo-code-invalid").into()),
+    }
+}
+
+/// Promo code applied by the user to the event, if it's still valid.
+pub fn get_applied_promo_code(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+) -> Result<Option<PromoCode>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT p.* FROM applied_promo_codes AS a JOIN promo_codes AS p ON p.code = a.code \
+        WHERE a.event = ?1 AND a.user = ?2",
+    )?;
+    let mut rows = stmt.query(params![event_id, user_id])?;
+    match rows.next()? {
+        Some(row) => {
+            let p = promo_code_from_row(row)?;
+            Ok(if p.is_valid(event_id, get_unix_time()) {
+                Some(p)
+            } else {
+                None
+            })
+        }
+        None => Ok(None),
+    }
+}
+
+/// Order total with the user's promo code applied.
+pub fn get_booking_amount(
+    conn: &Connection,
+    e: &Event,
+    user_id: u64,
+    adults: u64,
+    children: u64,
+) -> Result<u64, rusqlite::Error> {
+    let total = e.adult_ticket_price * adults + e.child_ticket_price * children;
+    Ok(match get_applied_promo_code(conn, e.id, user_id)? {
+        Some(p) => p.apply(total),
+        None => total,
+    })
+}
+
+/// Counts the use of the order's promo code once the order is paid.
+fn use_promo_code(conn: &Connection, booking: &Booking) -> Result<(), rusqlite::Error> {
+    let code = get_order_promo_code(conn, booking)?;
+    if !code.is_empty() {
+        conn.execute(
+            "UPDATE promo_codes SET uses = uses + 1 WHERE code = ?1",
+            params![code],
+        )?;
+    }
+    conn.execute(
+        "DELETE FROM applied_promo_codes WHERE event = ?1 AND user = ?2",
+        params![booking.event_id, booking.user_id],
+    )?;
+    conn.execute(
+        "DELETE FROM checkouts WHERE event = ?1 AND user = ?2",
+        params![booking.event_id, booking.user_id],
+    )?;
+    Ok(())
+}
+
+/// Remembers the amount and the promo code validated at pre-checkout,
+/// the payment is checked against them even if the promo code expires meanwhile.
+fn save_checkout(conn: &Connection, booking: &Booking, amount: u64) -> Result<(), rusqlite::Error> {
+    let code = get_applied_promo_code(conn, booking.event_id, booking.user_id)?
+        .map(|p| p.code)
+        .unwrap_or_default();
+    conn.execute(
+        "INSERT OR REPLACE INTO checkouts (event, user, adults, children, amount, promo_code) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            booking.event_id,
+            booking.user_id,
+            booking.adults,
+            booking.children,
+            amount,
+            code
+        ],
+    )?;
+    Ok(())
+}
+
+/// Amount and promo code of the order validated at pre-checkout.
+fn get_checkout(
+    conn: &Connection,
+    booking: &Booking,
+) -> Result<Option<(u64, String)>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT amount, promo_code FROM checkouts WHERE event = ?1 AND user = ?2 AND adults = ?3 AND children = ?4",
+    )?;
+    let mut rows = stmt.query(params![
+        booking.event_id,
+        booking.user_id,
+        booking.adults,
+        booking.children
+    ])?;
+    match rows.next()? {
+        Some(row) => Ok(Some((row.get(0)?, row.get(1)?))),
+        None => Ok(None),
+    }
+}
+
+/// Promo code the order is paid with, empty if none.
+pub fn get_order_promo_code(
+    conn: &Connection,
+    booking: &Booking,
+) -> Result<String, rusqlite::Error> {
+    match get_checkout(conn, booking)? {
+        Some((_, code)) => Ok(code),
+        None => Ok(
+            get_applied_promo_code(conn, booking.event_id, booking.user_id)?
+                .map(|p| p.code)
+                .unwrap_or_default(),
+        ),
+    }
+}
+
+/// Event and order the user is entering a promo code for, valid while the event is open.
+pub fn get_promo_code_session(
+    conn: &Connection,
+    user_id: u64,
+) -> Result<Option<(u64, u64, u64)>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT s.event, s.adults, s.children FROM promo_code_sessions AS s \
+        JOIN current_events AS c ON c.user = s.user AND c.event = s.event WHERE s.user = ?1",
+    )?;
+    let mut rows = stmt.query([user_id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some((row.get(0)?, row.get(1)?, row.get(2)?))),
+        None => Ok(None),
+    }
+}
+
+/// Starts waiting for a promo code for the event, 0 stops it.
+pub fn set_promo_code_session(
+    conn: &Connection,
+    user_id: u64,
+    event_id: u64,
+    adults: u64,
+    children: u64,
+) -> Result<(), rusqlite::Error> {
+    if event_id == 0 {
+        conn.execute(
+            "DELETE FROM promo_code_sessions WHERE user = ?1",
+            params![user_id],
+        )?;
+    } else {
+        conn.execute(
+            "INSERT OR REPLACE INTO promo_code_sessions (user, event, adults, children) VALUES (?1, ?2, ?3, ?4)",
+            params![user_id, event_id, adults, children],
+        )?;
+    }
+    Ok(())
+}
+
+/// Language of the user, the one of the Telegram client is kept unless the user chose another.
+pub fn resolve_language(
+    conn: &Connection,
+    user_id: u64,
+    detected: Lang,
+) -> Result<Lang, rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO languages (user, lang, manual) VALUES (?1, ?2, 0) \
+        ON CONFLICT(user) DO UPDATE SET lang = excluded.lang WHERE manual = 0 AND lang != excluded.lang",
+        params![user_id, detected.code()],
+    )?;
+    get_language(conn, user_id)
+}
+
+/// Language chosen with /language.
+pub fn set_language(conn: &Connection, user_id: u64, lang: Lang) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT OR REPLACE INTO languages (user, lang, manual) VALUES (?1, ?2, 1)",
+        params![user_id, lang.code()],
+    )?;
+    Ok(())
+}
+
+pub fn get_language(conn: &Connection, user_id: u64) -> Result<Lang, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT lang FROM languages WHERE user = ?1")?;
+    let mut rows = stmt.query([user_id])?;
+    match rows.next()? {
+        Some(row) => Ok(Lang::parse(&row.get::<_, String>(0)?).unwrap_or_default()),
+        None => Ok(Lang::default()),
+    }
+}
+
+/// Template of the kind for the event, 0 for all events.
+pub fn get_template(
+    conn: &Connection,
+    event_id: u64,
+    kind: &str,
+) -> Result<Option<String>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT text FROM templates WHERE event = ?1 AND kind = ?2")?;
+    let mut rows = stmt.query(params![event_id, kind])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn set_template(
+    conn: &Connection,
+    event_id: u64,
+    kind: &str,
+    text: &str,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT OR REPLACE INTO templates (event, kind, text) VALUES (?1, ?2, ?3)",
+        params![event_id, kind, text],
+    )?;
+    Ok(())
+}
+
+pub fn delete_template(
+    conn: &Connection,
+    event_id: u64,
+    kind: &str,
+) -> Result<usize, rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM templates WHERE event = ?1 AND kind = ?2",
+        params![event_id, kind],
+    )
+}
+
+/// Name the participant signed up for the event with.
+pub fn get_participant_name(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+) -> Result<Option<String>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT user_name1 FROM reservations WHERE event = ?1 AND user = ?2 ORDER BY ts LIMIT 1",
+    )?;
+    let mut rows = stmt.query(params![event_id, user_id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+/// Starts a check-in session for the event, 0 ends it.
+pub fn set_check_in_event(
+    conn: &Connection,
+    user_id: u64,
+    event_id: u64,
+) -> Result<(), rusqlite::Error> {
+    if event_id == 0 {
+        conn.execute(
+            "DELETE FROM check_in_sessions WHERE user = ?1",
+            params![user_id],
+        )?;
     } else {
-        stmt = conn.prepare(
-            "SELECT a.*, b.attachment FROM (SELECT sum(adults) as adults, sum(children) as children, user, user_name1, user_name2, event, ts FROM reservations WHERE waiting_list = ?1 AND event = ?2 AND state = ?3 group by event, user ORDER BY ts LIMIT ?4 OFFSET ?5) as a \
-            LEFT JOIN attachments as b ON a.event = b.event and a.user = b.user"
+        conn.execute(
+            "INSERT OR REPLACE INTO check_in_sessions (user, event) VALUES (?1, ?2)",
+            params![user_id, event_id],
         )?;
-        stmt.query([waiting_list, event_id, state as u64, limit, offset * limit])?
-    };
-    let mut res = Vec::new();
-    while let Some(row) = rows.next()? {
-        res.push(Participant {
-            adults: row.get(0)?,
-            children: row.get(1)?,
-            user_id: row.get(2)?,
-            user_name1: row.get(3)?,
-            user_name2: row.get(4)?,
-            attachment: match row.get(7) {
-                Ok(v) => Some(v),
-                Err(_) => None,
-            },
-        });
     }
-    Ok(res)
+    Ok(())
 }
 
-pub fn get_presence_list(
+/// Tickets with entrance codes of confirmed reservations in upcoming events.
+pub fn get_valid_tickets(
     conn: &Connection,
-    event_id: u64,
-    offset: u64,
-    limit: u64,
-) -> Result<Vec<Presence>, rusqlite::Error> {
+    user_id: u64,
+    event_id: Option<u64>,
+) -> Result<Vec<Ticket>, rusqlite::Error> {
     let mut stmt = conn.prepare(
-        "select r.*, p.user, a.attachment from (select event, user, user_name1, user_name2, count(user) from reservations where event = ?1 and waiting_list = 0 group by user) as r \
-            left join presence as p on r.event = p.event and r.user = p.user \
-            left join attachments as a on r.event = a.event and r.user = a.user \
-            where p.user IS NULL order by r.user_name1 LIMIT ?2 OFFSET ?3"
+        "SELECT t.event, t.user, t.seat, t.name, t.email, t.code FROM tickets AS t \
+        JOIN reservations AS r ON r.id = t.reservation AND r.waiting_list = 0 AND r.state IN (?3, ?4) \
+        JOIN events AS e ON e.id = t.event \
+        WHERE t.user = ?1 AND t.code IS NOT NULL AND (?2 IS NULL OR t.event = ?2) AND e.ts > ?5 \
+        ORDER BY e.ts, t.seat",
     )?;
-    let mut rows = stmt.query([event_id, limit, offset * limit])?;
-    let mut res = Vec::new();
-    while let Some(row) = rows.next()? {
-        res.push(Presence {
-            user_id: row.get(1)?,
-            user_name1: row.get(2)?,
-            user_name2: row.get(3)?,
-            reserved: row.get(4)?,
-            attachment: match row.get(6) {
-                Ok(v) => Some(v),
-                Err(_) => None,
-            },
-        });
+    let rows = stmt.query(params![
+        user_id,
+        event_id,
+        ReservationState::Free as u64,
+        ReservationState::PaymentCompleted as u64,
+        get_unix_time()
+    ])?;
+    tickets_from_rows(rows)
+}
+
+fn name_first_free_seat(
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+    name: &str,
+) -> Result<(), rusqlite::Error> {
+    let seats = get_seat_count(conn, event_id, user_id)?;
+    let tickets = get_tickets(conn, event_id, user_id)?;
+    if let Some(seat) = (0..seats).find(|seat| {
+        !tickets
+            .iter()
+            .any(|t| t.seat == *seat && !t.name.is_empty())
+    }) {
+        set_ticket_name(conn, event_id, user_id, seat, name, None)?;
     }
-    Ok(res)
+    Ok(())
 }
 
-pub fn confirm_presence(
+/// Forgets attendee names, entrance codes stay valid.
+pub fn clear_tickets(
     conn: &Connection,
     event_id: u64,
     user_id: u64,
 ) -> Result<(), rusqlite::Error> {
     conn.execute(
-        "insert into presence (event, user) values (?1, ?2)",
+        "DELETE FROM tickets WHERE event = ?1 AND user = ?2 AND code IS NULL",
+        params![event_id, user_id],
+    )?;
+    conn.execute(
+        "UPDATE tickets SET name = '', email = NULL WHERE event = ?1 AND user = ?2",
         params![event_id, user_id],
     )?;
     Ok(())
 }
 
-pub fn is_group_leader(
+pub fn get_tickets(
     conn: &Connection,
     event_id: u64,
     user_id: u64,
-) -> Result<bool, rusqlite::Error> {
-    let mut stmt =
-        conn.prepare("SELECT event FROM group_leaders WHERE event = ?1 AND user = ?2")?;
-    let mut rows = stmt.query(params![event_id, user_id])?;
-    if let Some(_) = rows.next()? {
-        Ok(true)
-    } else {
-        Ok(false)
+) -> Result<Vec<Ticket>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT event, user, seat, name, email, code FROM tickets WHERE event = ?1 AND user = ?2 ORDER BY seat",
+    )?;
+    let rows = stmt.query(params![event_id, user_id])?;
+    tickets_from_rows(rows)
+}
+
+/// Named seats of all participants, for presence lists and exports.
+pub fn get_event_tickets(conn: &Connection, event_id: u64) -> Result<Vec<Ticket>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT event, user, seat, name, email, code FROM tickets WHERE event = ?1 AND name != '' ORDER BY user, seat",
+    )?;
+    let rows = stmt.query(params![event_id])?;
+    tickets_from_rows(rows)
+}
+
+fn tickets_from_rows(mut rows: rusqlite::Rows) -> Result<Vec<Ticket>, rusqlite::Error> {
+    let mut res = Vec::new();
+    while let Some(row) = rows.next()? {
+        res.push(Ticket {
+            event_id: row.get(0)?,
+            user_id: row.get(1)?,
+            seat: row.get(2)?,
+            name: row.get(3)?,
+            email: row.get(4)?,
+            code: row.get(5)?,
+        });
     }
+    Ok(res)
 }
 
-pub fn set_group_leader(
+pub fn save_answer(
     conn: &Connection,
     event_id: u64,
     user_id: u64,
+    question: u64,
+    answer: &str,
+    complete: bool,
 ) -> Result<(), rusqlite::Error> {
     conn.execute(
-        "insert into group_leaders (event, user) values (?1, ?2)",
-        params![event_id, user_id],
+        "INSERT INTO answers (event, user, question, answer, complete) VALUES (?1, ?2, ?3, ?4, ?5) \
+        ON CONFLICT (event, user, question) DO UPDATE SET answer = excluded.answer, complete = excluded.complete",
+        params![event_id, user_id, question, answer, complete],
     )?;
     Ok(())
 }
 
-pub fn delete_reservation(
+pub fn get_answers(
     conn: &Connection,
     event_id: u64,
     user_id: u64,
-) -> Result<(), rusqlite::Error> {
-    let state_changed = have_vacancies(conn, event_id)? == false;
-    conn.execute(
-        "delete from reservations where event = ?1 and user = ?2",
-        params![event_id, user_id],
+) -> Result<Vec<Answer>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT question, answer, complete FROM answers WHERE event = ?1 AND user = ?2 ORDER BY question",
     )?;
-    if state_changed {
-        prompt_waiting_list(conn, event_id)
-    } else {
-        Ok(())
+    let mut rows = stmt.query(params![event_id, user_id])?;
+    let mut res = Vec::new();
+    while let Some(row) = rows.next()? {
+        res.push(Answer {
+            question: row.get(0)?,
+            answer: row.get(1)?,
+            complete: row.get(2)?,
+        });
+    }
+    Ok(res)
+}
+
+/// Questionnaires of participants with active or pending reservations.
+pub fn get_event_answers(
+    conn: &Connection,
+    event_id: u64,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<ParticipantAnswers>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT user, user_name1, user_name2 FROM reservations WHERE event = ?1 GROUP BY user ORDER BY min(ts) LIMIT ?2 OFFSET ?3",
+    )?;
+    let mut rows = stmt.query(params![
+        event_id,
+        if limit == 0 { -1 } else { limit as i64 },
+        offset * limit
+    ])?;
+    let mut res = Vec::new();
+    while let Some(row) = rows.next()? {
+        res.push(ParticipantAnswers {
+            user_id: row.get(0)?,
+            user_name1: row.get(1)?,
+            user_name2: row.get(2)?,
+            answers: Vec::new(),
+        });
     }
+    for p in &mut res {
+        p.answers = get_answers(conn, event_id, p.user_id)?
+            .into_iter()
+            .filter(|a| a.complete)
+            .collect();
+    }
+    Ok(res)
 }
 
 pub fn get_pending_messages(
@@ -774,10 +3320,10 @@ pub fn get_pending_messages(
 ) -> Result<Vec<MessageBatch>, rusqlite::Error> {
     //debug!("get_pending_messages {}", ts);
     let mut stmt = conn.prepare(
-        "SELECT m.*, o.send_at, e.adult_ticket_price, e.child_ticket_price FROM message_outbox as o \
+        "SELECT m.*, o.send_at, ifnull(e.adult_ticket_price, 0) as adult_ticket_price, ifnull(e.child_ticket_price, 0) as child_ticket_price FROM message_outbox as o \
         JOIN messages as m ON o.message = m.id \
-        JOIN events as e ON m.event = e.id \
-        WHERE o.send_at < ?1",
+        LEFT JOIN events as e ON m.event = e.id \
+        WHERE o.send_at < ?1 AND (e.id IS NOT NULL OR m.audience IS NOT NULL)",
     )?;
     let mut rows = stmt.query([ts])?;
     let mut res = Vec::new();
@@ -789,10 +3335,11 @@ pub fn get_pending_messages(
             sender: row.get("sender")?,
             message_type: num::FromPrimitive::from_u64(message_type).unwrap(),
             waiting_list: row.get("waiting_list")?,
-            text: row.get("text")?,
+            text: message_text(conn, row)?,
             is_paid: row.get::<&str, u64>("adult_ticket_price")? != 0
                 || row.get::<&str, u64>("child_ticket_price")? != 0,
             recipients: Vec::new(),
+            media: media_from_row(row)?,
         };
         res.push(batch);
 
@@ -804,10 +3351,56 @@ pub fn get_pending_messages(
             collect_users = false;
         }
 
-        if collect_users {
+        if let Some(recipient) = row.get::<&str, Option<u64>>("recipient")? {
+            // Personal message.
+            let mut stmt =
+                conn.prepare("SELECT user FROM message_sent WHERE message = ?1 AND user = ?2")?;
+            if !stmt.exists([batch.message_id, recipient])? {
+                batch.recipients.push(recipient);
+                max_messages -= 1;
+                if max_messages == 0 {
+                    return Ok(res);
+                }
+            }
+        } else if row.get::<&str, Option<String>>("audience")?.is_some() {
+            // Broadcast to the recipients fixed when it was scheduled.
+            let mut stmt = conn.prepare(
+                "SELECT r.user FROM message_recipients as r \
+                LEFT JOIN message_sent as s ON s.message = r.message AND s.user = r.user \
+                WHERE r.message = ?1 AND s.user IS NULL ORDER BY r.user LIMIT ?2",
+            )?;
+            let mut rows = stmt.query([batch.message_id, max_messages])?;
+            while let Some(row) = rows.next()? {
+                batch.recipients.push(row.get("user")?);
+                max_messages -= 1;
+                if max_messages == 0 {
+                    return Ok(res);
+                }
+            }
+        } else if batch.message_type == MessageType::NewEvent {
+            // Subscribers at the time of sending.
+            if let Some(e) = find_event(conn, batch.event_id)? {
+                let (organization, tags) = subscriber_params(&e)?;
+                let mut stmt = conn.prepare(&format!("{} ORDER BY user LIMIT ?5", SUBSCRIBERS))?;
+                let mut rows = stmt.query(params![
+                    organization,
+                    tags,
+                    batch.event_id,
+                    batch.message_id,
+                    max_messages
+                ])?;
+                while let Some(row) = rows.next()? {
+                    batch.recipients.push(row.get("user")?);
+                    max_messages -= 1;
+                    if max_messages == 0 {
+                        return Ok(res);
+                    }
+                }
+            }
+        } else if collect_users {
             let mut stmt = conn.prepare(
                 "SELECT r.user, s.message as sent FROM \
-                        (select user, ts from reservations WHERE event = ?1 AND waiting_list = ?2 GROUP BY user) as r
+                        (select user, ts from reservations WHERE event = ?1 AND waiting_list = ?2 AND state NOT IN (?5, ?6) GROUP BY user) as r
                         LEFT JOIN (select user, message from message_sent where message = ?3) as s
                         ON r.user = s.user
                         WHERE sent is null ORDER BY r.ts LIMIT ?4"
@@ -817,6 +3410,8 @@ pub fn get_pending_messages(
                 batch.waiting_list,
                 batch.message_id,
                 max_messages,
+                ReservationState::PendingApproval as u64,
+                ReservationState::Refunded as u64,
             ])?;
 
             while let Some(row) = rows.next()? {
@@ -839,10 +3434,14 @@ pub fn get_pending_messages(
                 "DELETE FROM message_outbox WHERE message = ?1",
                 params![batch.message_id],
             )?;
-            conn.execute(
-                "DELETE FROM message_sent WHERE message = ?1",
-                params![batch.message_id],
-            )?;
+            // Waiting list prompts are queued again when seats free up,
+            // receipts of other messages are kept for delivery reports.
+            if batch.message_type == MessageType::WaitingListPrompt {
+                conn.execute(
+                    "DELETE FROM message_sent WHERE message = ?1",
+                    params![batch.message_id],
+                )?;
+            }
         }
     }
     Ok(res)
@@ -857,6 +3456,11 @@ fn set_current_event(
         "insert or replace into current_events (user, event) values (?1, ?2)",
         params![user_id, event_id],
     )?;
+    // Another event is viewed, the next message is not a comment on the rated one.
+    conn.execute(
+        "DELETE FROM feedback_sessions WHERE user = ?1 AND event != ?2",
+        params![user_id, event_id],
+    )?;
     Ok(())
 }
 
@@ -1031,6 +3635,297 @@ pub fn create(conn: &Connection) -> Result<(), rusqlite::Error> {
             error!("Failed to query db.");
         }
     }
+    migrate(conn)
+}
+
+/// Schema changes on top of the initial layout, applied to new and existing databases.
+fn migrate(conn: &Connection) -> Result<(), rusqlite::Error> {
+    add_column(conn, "messages", "args", "TEXT DEFAULT NULL")?;
+    add_column(conn, "events", "reminders", "TEXT DEFAULT NULL")?;
+    // Events created before `reminders` keep their single `remind` time.
+    conn.execute(
+        "UPDATE events SET reminders = CASE WHEN remind > 0 AND remind < ts \
+        THEN json_array(json_object('before', ts - remind)) ELSE '[]' END \
+        WHERE reminders IS NULL",
+        [],
+    )?;
+    add_column(conn, "events", "registration_opens_at", "INTEGER DEFAULT 0")?;
+    add_column(
+        conn,
+        "events",
+        "registration_closes_at",
+        "INTEGER DEFAULT 0",
+    )?;
+    add_column(conn, "events", "requires_approval", "INTEGER DEFAULT 0")?;
+    add_column(conn, "messages", "recipient", "INTEGER DEFAULT NULL")?;
+    add_column(conn, "events", "questions", "TEXT DEFAULT NULL")?;
+    add_column(conn, "events", "named_tickets", "INTEGER DEFAULT 0")?;
+    add_column(conn, "events", "waiting_list_hold", "INTEGER DEFAULT 0")?;
+    add_column(conn, "reservations", "hold_until", "INTEGER DEFAULT 0")?;
+    // Applications approved before the payment deadline was introduced get one from now.
+    conn.execute(
+        "UPDATE reservations SET hold_until = ?1 WHERE state = ?2 AND hold_until = 0",
+        params![
+            get_unix_time() + DEFAULT_PAYMENT_HOLD,
+            ReservationState::Approved as u64
+        ],
+    )?;
+    add_column(conn, "events", "refund_deadline", "INTEGER DEFAULT 0")?;
+    add_column(conn, "events", "late_refund_percent", "INTEGER DEFAULT 0")?;
+    add_column(conn, "events", "venue", "TEXT DEFAULT ''")?;
+    add_column(conn, "events", "organization", "TEXT DEFAULT ''")?;
+    add_column(conn, "events", "tags", "TEXT DEFAULT NULL")?;
+    add_column(conn, "events", "updated", "INTEGER DEFAULT 0")?;
+    add_column(conn, "messages", "template", "INTEGER DEFAULT 0")?;
+    add_column(conn, "messages", "audience", "TEXT DEFAULT NULL")?;
+    add_column(conn, "messages", "media", "TEXT DEFAULT NULL")?;
+    add_column(conn, "message_sent", "error", "TEXT DEFAULT NULL")?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS message_recipients (
+            message         INTEGER NOT NULL,
+            user            INTEGER NOT NULL,
+            PRIMARY KEY (message, user)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS feedback_surveys (
+            event           INTEGER NOT NULL PRIMARY KEY,
+            name            TEXT NOT NULL,
+            ts              INTEGER NOT NULL,
+            message         INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS feedback (
+            event           INTEGER NOT NULL,
+            user            INTEGER NOT NULL,
+            rating          INTEGER NOT NULL,
+            comment         TEXT NOT NULL DEFAULT '',
+            ts              INTEGER NOT NULL,
+            PRIMARY KEY (event, user)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS feedback_sessions (
+            user            INTEGER NOT NULL PRIMARY KEY,
+            event           INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS subscriptions (
+            user            INTEGER NOT NULL,
+            kind            TEXT NOT NULL,
+            value           TEXT NOT NULL,
+            PRIMARY KEY (user, kind, value)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS calendar_tokens (
+            user            INTEGER NOT NULL PRIMARY KEY,
+            token           TEXT NOT NULL UNIQUE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS templates (
+            event           INTEGER NOT NULL DEFAULT 0,
+            kind            TEXT NOT NULL,
+            text            TEXT NOT NULL,
+            PRIMARY KEY (event, kind)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tickets (
+            event           INTEGER NOT NULL,
+            user            INTEGER NOT NULL,
+            seat            INTEGER NOT NULL,
+            name            TEXT NOT NULL,
+            email           TEXT DEFAULT NULL,
+            PRIMARY KEY (event, user, seat)
+        )",
+        [],
+    )?;
+    add_column(conn, "tickets", "reservation", "INTEGER DEFAULT NULL")?;
+    add_column(conn, "tickets", "code", "TEXT DEFAULT NULL")?;
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS tickets_code_index ON tickets (code)",
+        [],
+    )?;
+    add_column(conn, "tickets", "checked_in", "INTEGER DEFAULT 0")?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS check_in_sessions (
+            user            INTEGER NOT NULL PRIMARY KEY,
+            event           INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS payments (
+            id              INTEGER PRIMARY KEY AUTOINCREMENT,
+            ts              INTEGER NOT NULL,
+            kind            INTEGER NOT NULL,
+            event           INTEGER NOT NULL,
+            user            INTEGER NOT NULL,
+            amount          INTEGER NOT NULL,
+            currency        TEXT NOT NULL
+        )",
+        [],
+    )?;
+    add_column(conn, "payments", "telegram_charge_id", "TEXT DEFAULT ''")?;
+    add_column(conn, "payments", "provider_charge_id", "TEXT DEFAULT ''")?;
+    add_column(conn, "payments", "payload", "TEXT DEFAULT ''")?;
+    add_column(conn, "payments", "promo_code", "TEXT DEFAULT ''")?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS promo_codes (
+            code            TEXT NOT NULL PRIMARY KEY,
+            event           INTEGER NOT NULL DEFAULT 0,
+            percent         INTEGER NOT NULL DEFAULT 0,
+            amount          INTEGER NOT NULL DEFAULT 0,
+            max_uses        INTEGER NOT NULL DEFAULT 0,
+            uses            INTEGER NOT NULL DEFAULT 0,
+            expires_at      INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS applied_promo_codes (
+            event           INTEGER NOT NULL,
+            user            INTEGER NOT NULL,
+            code            TEXT NOT NULL,
+            PRIMARY KEY (event, user)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS languages (
+            user            INTEGER NOT NULL PRIMARY KEY,
+            lang            TEXT NOT NULL,
+            manual          INTEGER NOT NULL
+            )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS promo_code_sessions (
+            user            INTEGER NOT NULL PRIMARY KEY,
+            event           INTEGER NOT NULL,
+            adults          INTEGER NOT NULL,
+            children        INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS refund_requests (
+            event           INTEGER NOT NULL,
+            user            INTEGER NOT NULL,
+            ts              INTEGER NOT NULL,
+            amount          INTEGER NOT NULL,
+            PRIMARY KEY (event, user)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS answers (
+            event           INTEGER NOT NULL,
+            user            INTEGER NOT NULL,
+            question        INTEGER NOT NULL,
+            answer          TEXT NOT NULL,
+            complete        INTEGER NOT NULL DEFAULT 1,
+            PRIMARY KEY (event, user, question)
+        )",
+        [],
+    )?;
+    migrate_question_ids(conn)?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS rejection_sessions (
+            user            INTEGER NOT NULL PRIMARY KEY,
+            event           INTEGER NOT NULL,
+            applicant       INTEGER NOT NULL,
+            page            INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS revoked_tickets (
+            code            TEXT NOT NULL PRIMARY KEY,
+            event           INTEGER NOT NULL,
+            user            INTEGER NOT NULL,
+            seat            INTEGER NOT NULL,
+            name            TEXT NOT NULL,
+            email           TEXT DEFAULT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS checkouts (
+            event           INTEGER NOT NULL,
+            user            INTEGER NOT NULL,
+            adults          INTEGER NOT NULL,
+            children        INTEGER NOT NULL,
+            amount          INTEGER NOT NULL,
+            promo_code      TEXT NOT NULL,
+            PRIMARY KEY (event, user)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Answers used to refer to the question index, questions get ids from their positions.
+fn migrate_question_ids(conn: &Connection) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT id, questions FROM events WHERE questions IS NOT NULL")?;
+    let events = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, u64>("id")?, questions_from_row(row)?))
+        })?
+        .collect::<Result<Vec<(u64, Vec<Question>)>, _>>()?;
+    for (event_id, mut questions) in events {
+        if questions.is_empty() || questions.iter().any(|q| q.id != 0) {
+            continue;
+        }
+        for (i, q) in questions.iter_mut().enumerate() {
+            q.id = i as u64 + 1;
+        }
+        let questions = serde_json::to_string(&questions)
+            .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+        conn.execute(
+            "UPDATE events SET questions = ?1 WHERE id = ?2",
+            params![questions, event_id],
+        )?;
+        // In two steps to keep the keys unique.
+        conn.execute(
+            "UPDATE answers SET question = -question - 1 WHERE event = ?1",
+            [event_id],
+        )?;
+        conn.execute(
+            "UPDATE answers SET question = -question WHERE event = ?1",
+            [event_id],
+        )?;
+    }
+    Ok(())
+}
+
+fn add_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT name FROM pragma_table_info('{}') WHERE name = ?1",
+        table
+    ))?;
+    if !stmt.exists([column])? {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
     Ok(())
 }
 
@@ -1042,6 +3937,100 @@ pub fn save_receipt(conn: &Connection, message_id: u64, user: u64) -> Result<(),
     Ok(())
 }
 
+/// Records that the message can't be delivered to the user, it is not retried.
+pub fn save_failure(
+    conn: &Connection,
+    message_id: u64,
+    user: u64,
+    reason: &str,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO message_sent (message, user, ts, error) VALUES (?1, ?2, ?3, ?4)",
+        params![message_id, user, util::get_unix_time(), reason],
+    )?;
+    Ok(())
+}
+
+/// Delivered, failed and pending recipients of the message, None if there is no such message.
+pub fn get_delivery_report(
+    conn: &Connection,
+    message_id: u64,
+) -> Result<Option<DeliveryReport>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT m.*, EXISTS (SELECT 1 FROM message_outbox WHERE message = m.id) as queued \
+        FROM messages as m WHERE m.id = ?1",
+    )?;
+    let mut rows = stmt.query([message_id])?;
+    let row = match rows.next()? {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    let mut report = DeliveryReport {
+        message_id,
+        event_id: row.get("event")?,
+        ..Default::default()
+    };
+
+    let mut stmt =
+        conn.prepare("SELECT error, count(*) FROM message_sent WHERE message = ?1 GROUP BY error")?;
+    let mut receipts = stmt.query([message_id])?;
+    while let Some(receipt) = receipts.next()? {
+        let count: u64 = receipt.get(1)?;
+        match receipt.get::<usize, Option<String>>(0)? {
+            Some(reason) => {
+                report.failed += count;
+                report.failures.insert(reason, count);
+            }
+            None => report.delivered += count,
+        }
+    }
+
+    // Recipients of a message still in the outbox which have no receipt yet.
+    report.pending = if let Some(recipient) = row.get::<&str, Option<u64>>("recipient")? {
+        let mut stmt =
+            conn.prepare("SELECT user FROM message_sent WHERE message = ?1 AND user = ?2")?;
+        (row.get::<&str, bool>("queued")? && !stmt.exists([message_id, recipient])?) as u64
+    } else if row.get::<&str, Option<String>>("audience")?.is_some() {
+        conn.query_row(
+            "SELECT count(*) FROM message_recipients as r \
+            LEFT JOIN message_sent as s ON s.message = r.message AND s.user = r.user \
+            WHERE r.message = ?1 AND s.user IS NULL",
+            [message_id],
+            |row| row.get(0),
+        )?
+    } else if row.get::<&str, u64>("type")? == MessageType::NewEvent as u64 {
+        match find_event(conn, report.event_id)? {
+            Some(e) if row.get::<&str, bool>("queued")? => {
+                let (organization, tags) = subscriber_params(&e)?;
+                conn.query_row(
+                    &format!("SELECT count(*) FROM ({})", SUBSCRIBERS),
+                    params![organization, tags, report.event_id, message_id],
+                    |row| row.get(0),
+                )?
+            }
+            _ => 0,
+        }
+    } else if row.get::<&str, bool>("queued")? {
+        conn.query_row(
+            "SELECT count(DISTINCT user) FROM reservations \
+            WHERE event = ?1 AND waiting_list = ?2 AND state NOT IN (?3, ?4) \
+            AND user NOT IN (SELECT user FROM message_sent WHERE message = ?5)",
+            params![
+                report.event_id,
+                row.get::<&str, u64>("waiting_list")?,
+                ReservationState::PendingApproval as u64,
+                ReservationState::Refunded as u64,
+                message_id
+            ],
+            |row| row.get(0),
+        )?
+    } else {
+        0
+    };
+    report.targeted = report.delivered + report.failed + report.pending;
+    Ok(Some(report))
+}
+
 pub fn add_to_black_list(
     conn: &Connection,
     user: u64,
@@ -1110,6 +4099,7 @@ pub fn get_black_list(
             user_name1: row.get(1)?,
             user_name2: row.get(2)?,
             is_admin: false,
+            lang: Lang::default(),
         });
     }
     Ok(res)
@@ -1130,6 +4120,23 @@ pub fn clear_black_list(conn: &Connection, ts: u64) -> Result<(), rusqlite::Erro
     Ok(())
 }
 
+/// Releases seats booked for an invoice which could not be created.
+/// Seats held from the waiting list or approved stay held until their deadline.
+pub fn cancel_pending_payment(conn: &Connection, booking: &Booking) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM reservations WHERE id = (SELECT id FROM reservations \
+        WHERE event = ?1 AND user = ?2 AND state = ?3 AND adults = ?4 AND children = ?5 ORDER BY id DESC LIMIT 1)",
+        params![
+            booking.event_id,
+            booking.user_id,
+            ReservationState::PaymentPending as u64,
+            booking.adults,
+            booking.children
+        ],
+    )?;
+    Ok(())
+}
+
 pub fn clear_failed_payments(conn: &Connection, ts: u64) -> Result<(), rusqlite::Error> {
     conn.execute(
         "DELETE FROM reservations WHERE state = ?1 AND ts < ?2",
@@ -1168,6 +4175,42 @@ pub fn set_event_limits(
     }
 }
 
+fn media_from_row(row: &Row) -> Result<Option<Media>, rusqlite::Error> {
+    match row.get::<&str, Option<String>>("media")? {
+        Some(v) => serde_json::from_str(&v).map(Some).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+        }),
+        None => Ok(None),
+    }
+}
+
+/// Text of a queued message, older messages and literal texts have no arguments.
+/// Templates get the current number of free seats.
+fn message_text(conn: &Connection, row: &Row) -> Result<Text, rusqlite::Error> {
+    let text: String = row.get("text")?;
+    if row.get::<&str, bool>("template")? {
+        let (adults, children) = get_vacancies(conn, row.get("event")?)?;
+        return Ok(Text::Template {
+            template: text,
+            args: Args::new(),
+        }
+        .arg("seats", adults + children));
+    }
+    match row.get::<&str, Option<String>>("args")? {
+        Some(args) => Ok(Text::Message {
+            key: text,
+            args: serde_json::from_str(&args).map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    0,
+                    rusqlite::types::Type::Text,
+                    Box::new(err),
+                )
+            })?,
+        }),
+        None => Ok(Text::Literal(text)),
+    }
+}
+
 pub fn get_group_messages(
     conn: &Connection,
     event_id: u64,
@@ -1176,12 +4219,12 @@ pub fn get_group_messages(
     let mut stmt;
     let mut rows = if let Some(waiting_list) = waiting_list {
         stmt = conn.prepare(
-            "SELECT sender, text, ts, waiting_list FROM messages WHERE event = ?1 AND type = 0 AND waiting_list = ?2 ORDER BY ts DESC LIMIT 3"
+            "SELECT event, sender, text, args, template, ts, waiting_list FROM messages WHERE event = ?1 AND type = 0 AND waiting_list = ?2 ORDER BY ts DESC LIMIT 3"
         )?;
         stmt.query(params![event_id, waiting_list])?
     } else {
         stmt = conn.prepare(
-            "SELECT sender, text, ts, waiting_list FROM messages WHERE event = ?1 AND type = 0 ORDER BY ts DESC LIMIT 3",
+            "SELECT event, sender, text, args, template, ts, waiting_list FROM messages WHERE event = ?1 AND type = 0 ORDER BY ts DESC LIMIT 3",
         )?;
         stmt.query(params![event_id])?
     };
@@ -1189,7 +4232,11 @@ pub fn get_group_messages(
     while let Some(row) = rows.next()? {
         let msg = GroupMessage {
             sender: row.get("sender")?,
-            text: row.get("text")?,
+            text: match message_text(conn, row)? {
+                // Shown to everyone, so without a recipient name.
+                text @ Text::Template { .. } => text.arg("user", ""),
+                text => text,
+            },
             ts: row.get("ts")?,
             waiting_list: row.get("waiting_list")?,
         };
@@ -1,33 +1,77 @@
 #[cfg(test)]
 mod tests {
     use crate::db::*;
+    use crate::payment_providers::*;
+    use crate::reply::*;
+    use crate::types::{Charge, QuestionKind};
     use r2d2_sqlite::SqliteConnectionManager;
     use teloxide::types::UserId;
 
-    #[test]
-    fn test_db() -> Result<(), rusqlite::Error> {
-        let db_file = "./test.db3";
-        let _ = std::fs::remove_file(db_file);
-        let manager = SqliteConnectionManager::file(db_file);
-        let pool = r2d2::Pool::new(manager).unwrap();
+    /// Connection to a new in-memory database.
+    fn connection() -> r2d2::PooledConnection<SqliteConnectionManager> {
+        let pool = r2d2::Pool::builder()
+            .max_size(1)
+            .build(SqliteConnectionManager::memory())
+            .unwrap();
         let conn = pool.get().unwrap();
         create(&conn).expect("Failed to create db.");
+        conn
+    }
 
-        let ts = 1650445814;
-
-        let e = Event {
+    /// Free event for ten adults, one seat per reservation, tests change what they check.
+    fn event(name: &str, ts: u64) -> Event {
+        Event {
             id: 0,
-            name: "test event 1".to_string(),
+            name: name.to_string(),
             link: "https://example.com/1".to_string(),
-            max_adults: 2,
-            max_children: 2,
+            max_adults: 10,
+            max_children: 0,
             max_adults_per_reservation: 1,
-            max_children_per_reservation: 3,
-            ts: ts,
-            remind: ts - 10,
+            max_children_per_reservation: 0,
+            ts,
+            reminders: vec![],
             adult_ticket_price: 0,
             child_ticket_price: 0,
             currency: "".to_string(),
+            registration_opens_at: 0,
+            registration_closes_at: 0,
+            requires_approval: false,
+            questions: vec![],
+            named_tickets: false,
+            waiting_list_hold: 0,
+            refund_deadline: 0,
+            late_refund_percent: 0,
+            venue: "".to_string(),
+            organization: "".to_string(),
+            tags: vec![],
+        }
+    }
+
+    fn user(id: u64) -> User {
+        User {
+            id: UserId(id),
+            user_name1: "".to_string(),
+            user_name2: "".to_string(),
+            is_admin: false,
+            lang: Lang::Ru,
+        }
+    }
+
+    #[test]
+    fn test_db() -> Result<(), rusqlite::Error> {
+        let conn = connection();
+
+        let ts = 1650445814;
+
+        let e = Event {
+            max_adults: 2,
+            max_children: 2,
+            max_children_per_reservation: 3,
+            reminders: vec![Reminder {
+                before: 10,
+                template: None,
+            }],
+            ..event("test event 1", ts)
         };
         let event_id = 1;
 
@@ -41,10 +85,9 @@ mod tests {
                     &conn,
                     event_id,
                     &User {
-                        id: UserId(1000),
                         user_name1: "user_name1_1000".to_string(),
                         user_name2: "user_name2_1000".to_string(),
-                        is_admin: false
+                        ..user(1000)
                     },
                     0,
                     1,
@@ -70,10 +113,9 @@ mod tests {
                 &conn,
                 event_id,
                 &User {
-                    id: UserId(2000),
                     user_name1: "user_name1_2000".to_string(),
                     user_name2: "user_name1_2000".to_string(),
-                    is_admin: false
+                    ..user(2000)
                 },
                 0,
                 1,
@@ -109,28 +151,18 @@ mod tests {
     #[test]
     #[ignore]
     fn test_waiting_list() -> Result<(), rusqlite::Error> {
-        let db_file = "./test1.db3";
-        let _ = std::fs::remove_file(db_file);
-        let manager = SqliteConnectionManager::file(db_file);
-        let pool = r2d2::Pool::new(manager).unwrap();
-        let conn = pool.get().unwrap();
-        create(&conn).expect("Failed to create db.");
+        let conn = connection();
 
         let ts = 1650445814;
 
         let e = Event {
-            id: 0,
-            name: "test event 1".to_string(),
-            link: "https://example.com/1".to_string(),
             max_adults: 1,
-            max_children: 0,
-            max_adults_per_reservation: 1,
             max_children_per_reservation: 3,
-            ts: ts,
-            remind: ts - 10,
-            adult_ticket_price: 0,
-            child_ticket_price: 0,
-            currency: "".to_string(),
+            reminders: vec![Reminder {
+                before: 10,
+                template: None,
+            }],
+            ..event("test event 1", ts)
         };
         let event_id = 1;
 
@@ -139,62 +171,17 @@ mod tests {
 
         // sign up
         assert_eq!(
-            sign_up(
-                &conn,
-                event_id,
-                &User {
-                    id: UserId(10),
-                    user_name1: "".to_string(),
-                    user_name2: "".to_string(),
-                    is_admin: false
-                },
-                1,
-                0,
-                0,
-                e.ts - 30,
-                0,
-            )
-            .unwrap(),
+            sign_up(&conn, event_id, &user(10), 1, 0, 0, e.ts - 30, 0,).unwrap(),
             (1, false)
         );
 
         // add to waiting list
         assert_eq!(
-            sign_up(
-                &conn,
-                event_id,
-                &User {
-                    id: UserId(20),
-                    user_name1: "".to_string(),
-                    user_name2: "".to_string(),
-                    is_admin: false
-                },
-                1,
-                0,
-                1,
-                e.ts - 20,
-                0,
-            )
-            .unwrap(),
+            sign_up(&conn, event_id, &user(20), 1, 0, 1, e.ts - 20, 0,).unwrap(),
             (1, false)
         );
         assert_eq!(
-            sign_up(
-                &conn,
-                event_id,
-                &User {
-                    id: UserId(30),
-                    user_name1: "".to_string(),
-                    user_name2: "".to_string(),
-                    is_admin: false
-                },
-                1,
-                0,
-                1,
-                e.ts - 10,
-                0,
-            )
-            .unwrap(),
+            sign_up(&conn, event_id, &user(30), 1, 0, 1, e.ts - 10, 0,).unwrap(),
             (1, false)
         );
 
@@ -247,4 +234,1693 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_reminders() -> Result<(), rusqlite::Error> {
+        let conn = connection();
+
+        let ts = get_unix_time() + 3 * 24 * 60 * 60;
+        let mut e = Event {
+            max_adults: 1,
+            reminders: vec![
+                Reminder {
+                    before: 7 * 24 * 60 * 60,
+                    template: None,
+                },
+                Reminder {
+                    before: 24 * 60 * 60,
+                    template: Some("{name} - {start}".to_string()),
+                },
+                Reminder {
+                    before: 2 * 60 * 60,
+                    template: None,
+                },
+            ],
+            ..event("test event 1", ts)
+        };
+
+        let get_reminders = |conn: &Connection| -> Result<Vec<(u64, String)>, rusqlite::Error> {
+            let mut stmt = conn.prepare(
+                "SELECT o.send_at, m.text FROM message_outbox as o JOIN messages as m ON o.message = m.id \
+                WHERE m.type = ?1 ORDER BY o.send_at",
+            )?;
+            let mut rows = stmt.query([MessageType::Reminder as u64])?;
+            let mut res = Vec::new();
+            while let Some(row) = rows.next()? {
+                res.push((row.get(0)?, row.get(1)?));
+            }
+            Ok(res)
+        };
+
+        // the reminder a week before is already due and gets skipped
+        e.id = mutate_event(&conn, &e)?;
+        let reminders = get_reminders(&conn)?;
+        assert_eq!(reminders.len(), 2);
+        assert_eq!(reminders[0].0, ts - 24 * 60 * 60);
+        assert_eq!(reminders[0].1, format!("test event 1 - {}", format::ts(ts)));
+        assert_eq!(reminders[1].0, ts - 2 * 60 * 60);
+
+        // reminders follow the event start
+        e.ts = ts + 60 * 60;
+        mutate_event(&conn, &e)?;
+        let reminders = get_reminders(&conn)?;
+        assert_eq!(reminders.len(), 2);
+        assert_eq!(reminders[0].0, e.ts - 24 * 60 * 60);
+        assert_eq!(reminders[1].0, e.ts - 2 * 60 * 60);
+
+        let s = get_event(&conn, e.id, 0)?;
+        assert_eq!(s.event.reminders, e.reminders);
+
+        // events from before `reminders` keep their single reminder
+        conn.execute(
+            "UPDATE events SET reminders = NULL, remind = ts - 3600 WHERE id = ?1",
+            [e.id],
+        )?;
+        migrate(&conn)?;
+        let s = get_event(&conn, e.id, 0)?;
+        assert_eq!(
+            s.event.reminders,
+            vec![Reminder {
+                before: 3600,
+                template: None
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_registration_period() -> Result<(), rusqlite::Error> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_adults_per_reservation: 10,
+            registration_opens_at: now + 60 * 60,
+            ..event("test event 1", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+        let user = user(10);
+
+        // not open yet
+        let s = get_event(&conn, e.id, 10)?;
+        assert!(s.state == EventState::Upcoming(e.registration_opens_at));
+        assert!(sign_up(&conn, e.id, &user, 1, 0, 0, now, 0).is_err());
+
+        // open
+        e.registration_opens_at = now - 60 * 60;
+        e.registration_closes_at = now + 60 * 60;
+        mutate_event(&conn, &e)?;
+        assert!(get_event(&conn, e.id, 10)?.state == EventState::Open);
+        assert_eq!(
+            sign_up(&conn, e.id, &user, 1, 0, 0, now, 0).unwrap(),
+            (1, false)
+        );
+
+        // closed
+        e.registration_closes_at = now - 60;
+        mutate_event(&conn, &e)?;
+        assert!(get_event(&conn, e.id, 10)?.state == EventState::Closed);
+        assert!(sign_up(&conn, e.id, &user, 1, 0, 0, now, 0).is_err());
+
+        // reopened by an admin after the deadline, then closed again
+        change_event_state(&conn, e.id, REGISTRATION_OPENED)?;
+        assert!(get_event(&conn, e.id, 10)?.state == EventState::Open);
+        change_event_state(&conn, e.id, REGISTRATION_CLOSED)?;
+        assert!(get_event(&conn, e.id, 10)?.state == EventState::Closed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_approval() -> Result<(), rusqlite::Error> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_adults_per_reservation: 10,
+            requires_approval: true,
+            ..event("test event 1", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+
+        // pending applications do not take seats
+        sign_up(&conn, e.id, &user(10), 2, 0, 0, now, 0).unwrap();
+        let s = get_event(&conn, e.id, 10)?;
+        assert_eq!(s.adults.reserved, 0);
+        assert_eq!(s.adults.my_pending, 2);
+        assert_eq!(get_approval_queue(&conn, e.id, 0, 10)?.len(), 1);
+
+        // approve
+        approve_reservation(&conn, e.id, 10).unwrap();
+        let s = get_event(&conn, e.id, 10)?;
+        assert_eq!(s.adults.reserved, 2);
+        assert_eq!(s.adults.my_pending, 0);
+        assert_eq!(get_approval_queue(&conn, e.id, 0, 10)?.len(), 0);
+        // tickets for free seats follow the notification
+        let notifications: u64 = conn.query_row(
+            "SELECT count(*) FROM messages WHERE type = ?1 AND recipient = ?2",
+            params![MessageType::Tickets as u64, 10],
+            |row| row.get(0),
+        )?;
+        assert_eq!(notifications, 1);
+
+        // reject
+        sign_up(&conn, e.id, &user(11), 1, 0, 0, now, 0).unwrap();
+        reject_reservation(&conn, e.id, 11, Some("мест нет")).unwrap();
+        let s = get_event(&conn, e.id, 11)?;
+        assert_eq!(s.adults.my_pending, 0);
+        assert_eq!(s.adults.reserved, 2);
+        assert!(reject_reservation(&conn, e.id, 11, None).is_err());
+
+        // approved paid seats are released when not paid in time
+        e.id = 0;
+        e.name = "test event 2".to_string();
+        e.adult_ticket_price = 1000;
+        e.currency = "EUR".to_string();
+        e.id = mutate_event(&conn, &e)?;
+        sign_up(&conn, e.id, &user(11), 1, 0, 0, now, 0).unwrap();
+        approve_reservation(&conn, e.id, 11).unwrap();
+        assert_eq!(get_approved_booking(&conn, e.id, 11)?, (1, 0));
+        assert_eq!(get_event(&conn, e.id, 11)?.adults.reserved, 1);
+        expire_offers(&conn, now + 60)?;
+        assert_eq!(get_approved_booking(&conn, e.id, 11)?, (1, 0));
+        expire_offers(&conn, now + 2 * 60 * 60)?;
+        assert_eq!(get_approved_booking(&conn, e.id, 11)?, (0, 0));
+        assert_eq!(get_event(&conn, e.id, 11)?.adults.reserved, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_questionnaire() -> Result<(), rusqlite::Error> {
+        let conn = connection();
+
+        let questions: Vec<Question> = serde_json::from_str(
+            r#"[{"text": "Телефон", "kind": "phone", "required": true},
+                {"text": "Размер", "kind": "single_choice", "options": ["S", "M"]},
+                {"text": "Комментарий"}]"#,
+        )
+        .unwrap();
+        assert!(questions.iter().all(Question::is_valid));
+        assert_eq!(questions[2].kind, QuestionKind::Text);
+        assert!(!Question {
+            id: 0,
+            text: "Размер".to_string(),
+            kind: QuestionKind::MultipleChoice,
+            options: vec![],
+            required: false,
+        }
+        .is_valid());
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_adults_per_reservation: 10,
+            questions,
+            ..event("test event 1", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+        let stored = get_event(&conn, e.id, 0)?.event.questions;
+        assert_eq!(stored.iter().map(|q| q.id).collect::<Vec<u64>>(), [1, 2, 3]);
+        assert_eq!(stored[0].text, e.questions[0].text);
+
+        let user = User {
+            user_name1: "user 10".to_string(),
+            ..user(10)
+        };
+        sign_up(&conn, e.id, &user, 1, 0, 0, now, 0).unwrap();
+        save_answer(&conn, e.id, 10, 1, "+43 660 1234567", true)?;
+        save_answer(&conn, e.id, 10, 2, "M", false)?;
+        save_answer(&conn, e.id, 10, 2, "S", true)?;
+        let answers = get_answers(&conn, e.id, 10)?;
+        assert_eq!(answers.len(), 2);
+        assert_eq!(answers[1].answer, "S");
+        assert!(answers[1].complete);
+
+        // answers follow reordered questions, removed ids are not reused
+        e.questions = vec![
+            e.questions[1].clone(),
+            Question {
+                id: 0,
+                text: "Город".to_string(),
+                kind: QuestionKind::Text,
+                options: vec![],
+                required: false,
+            },
+            e.questions[0].clone(),
+        ];
+        mutate_event(&conn, &e)?;
+        let stored = get_event(&conn, e.id, 0)?.event.questions;
+        assert_eq!(stored.iter().map(|q| q.id).collect::<Vec<u64>>(), [2, 4, 1]);
+
+        // answers of questionnaires from before the ids refer to the position
+        conn.execute(
+            "UPDATE events SET questions = ?1 WHERE id = ?2",
+            params![
+                r#"[{"text": "Телефон", "kind": "phone"}, {"text": "Размер", "kind": "single_choice", "options": ["S", "M"]}]"#,
+                e.id
+            ],
+        )?;
+        conn.execute(
+            "UPDATE answers SET question = question - 1 WHERE event = ?1",
+            [e.id],
+        )?;
+        migrate(&conn)?;
+        let stored = get_event(&conn, e.id, 0)?.event.questions;
+        assert_eq!(stored.iter().map(|q| q.id).collect::<Vec<u64>>(), [1, 2]);
+        let answers = get_answers(&conn, e.id, 10)?;
+        assert_eq!((answers[0].question, answers[1].question), (1, 2));
+        assert_eq!(answers[1].answer, "S");
+
+        // incomplete answers are not exported
+        save_answer(&conn, e.id, 10, 3, "draft", false)?;
+        let exported = get_event_answers(&conn, e.id, 0, 0)?;
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].user_name1, "user 10");
+        assert_eq!(exported[0].answers.len(), 2);
+
+        delete_reservation(&conn, e.id, 10)?;
+        assert!(get_answers(&conn, e.id, 10)?.is_empty());
+        assert!(get_event_answers(&conn, e.id, 0, 10)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tickets() -> Result<(), rusqlite::Error> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_children: 10,
+            max_adults_per_reservation: 10,
+            max_children_per_reservation: 10,
+            named_tickets: true,
+            ..event("test event 1", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+        let user = user(10);
+        sign_up(&conn, e.id, &user, 1, 0, 0, now, 0).unwrap();
+        sign_up(&conn, e.id, &user, 0, 1, 0, now, 0).unwrap();
+        assert_eq!(get_seat_count(&conn, e.id, 10)?, 2);
+
+        set_ticket_name(&conn, e.id, 10, 0, "Иван Петров", Some("ivan@example.com"))?;
+        set_ticket_name(&conn, e.id, 10, 1, "Маша", None)?;
+        let tickets = get_tickets(&conn, e.id, 10)?;
+        assert_eq!(tickets.len(), 2);
+        assert_eq!(tickets[0].email.as_deref(), Some("ivan@example.com"));
+        assert_eq!(
+            get_presence_list(&conn, e.id, 0, 10)?[0].attendees,
+            vec!["Иван Петров".to_string(), "Маша".to_string()]
+        );
+
+        // names of cancelled seats are dropped
+        cancel(&conn, e.id, 10, 0)?;
+        assert_eq!(get_seat_count(&conn, e.id, 10)?, 1);
+        assert_eq!(get_tickets(&conn, e.id, 10)?.len(), 1);
+        assert_eq!(get_event_tickets(&conn, e.id)?.len(), 1);
+
+        clear_tickets(&conn, e.id, 10)?;
+        assert!(get_tickets(&conn, e.id, 10)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ticket_codes() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_adults: 1,
+            max_adults_per_reservation: 10,
+            max_children_per_reservation: 10,
+            named_tickets: true,
+            ..event("test event 1", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+        let user = user(10);
+        sign_up(&conn, e.id, &user, 1, 0, 0, now, 0).unwrap();
+        // the second seat is in the waiting list and gets no code
+        sign_up(&conn, e.id, &user, 1, 0, 1, now, 0).unwrap();
+        let issued = issue_tickets(&conn, e.id, 10)?;
+        assert_eq!(issued.len(), 1);
+        assert_eq!(issued[0].code.as_ref().unwrap().len(), 32);
+        assert!(issue_tickets(&conn, e.id, 10)?.is_empty());
+        assert_eq!(get_valid_tickets(&conn, 10, Some(e.id))?.len(), 1);
+        assert_eq!(get_valid_tickets(&conn, 10, None)?.len(), 1);
+
+        // names can be changed without invalidating codes
+        set_ticket_name(&conn, e.id, 10, 0, "Иван Петров", None)?;
+        clear_tickets(&conn, e.id, 10)?;
+        assert_eq!(get_valid_tickets(&conn, 10, None)?.len(), 1);
+
+        // cancelled seats lose their codes
+        cancel(&conn, e.id, 10, 1)?;
+        cancel(&conn, e.id, 10, 1)?;
+        assert!(get_valid_tickets(&conn, 10, None)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_in() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_children: 10,
+            max_adults_per_reservation: 10,
+            max_children_per_reservation: 10,
+            ..event("test event 1", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+        let other_event = mutate_event(
+            &conn,
+            &Event {
+                id: 0,
+                link: "https://example.com/2".to_string(),
+                ..e.clone()
+            },
+        )?;
+        for id in [10, 11] {
+            let user = user(id);
+            sign_up(&conn, e.id, &user, 1, 0, 0, now, 0).unwrap();
+        }
+        let code = issue_tickets(&conn, e.id, 10)?[0].code.clone().unwrap();
+        let cancelled = issue_tickets(&conn, e.id, 11)?[0].code.clone().unwrap();
+        cancel(&conn, e.id, 11, 1)?;
+
+        assert!(matches!(
+            check_in(&conn, other_event, &code)?,
+            CheckIn::WrongEvent { .. }
+        ));
+        assert!(matches!(
+            check_in(&conn, e.id, &code)?,
+            CheckIn::CheckedIn { .. }
+        ));
+        assert!(matches!(
+            check_in(&conn, e.id, &code)?,
+            CheckIn::AlreadyCheckedIn { .. }
+        ));
+        assert!(matches!(
+            check_in(&conn, e.id, &cancelled)?,
+            CheckIn::Cancelled { .. }
+        ));
+        assert!(matches!(check_in(&conn, e.id, "x")?, CheckIn::Unknown));
+        assert_eq!(get_check_in_count(&conn, e.id)?, (1, 1));
+
+        // the seat booked again gets a new code, the old one stays cancelled
+        let user = user(11);
+        sign_up(&conn, e.id, &user, 1, 0, 0, now, 0).unwrap();
+        let reissued = issue_tickets(&conn, e.id, 11)?[0].code.clone().unwrap();
+        assert_ne!(reissued, cancelled);
+        assert!(matches!(
+            check_in(&conn, e.id, &cancelled)?,
+            CheckIn::Cancelled { .. }
+        ));
+        assert!(matches!(
+            check_in(&conn, other_event, &cancelled)?,
+            CheckIn::WrongEvent { .. }
+        ));
+        assert!(matches!(
+            check_in(&conn, e.id, &reissued)?,
+            CheckIn::CheckedIn { .. }
+        ));
+        assert_eq!(get_check_in_count(&conn, e.id)?, (2, 2));
+        // the owner is marked present
+        assert!(get_presence_list(&conn, e.id, 0, 10)?.is_empty());
+
+        // check-in session ends when the manager opens another event
+        get_event(&conn, e.id, 1)?;
+        set_check_in_event(&conn, 1, e.id)?;
+        assert_eq!(get_check_in_event(&conn, 1)?, e.id);
+        get_event(&conn, other_event, 1)?;
+        assert_eq!(get_check_in_event(&conn, 1)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_attendance() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_children: 10,
+            max_adults_per_reservation: 10,
+            max_children_per_reservation: 10,
+            ..event("test event 1", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+        for id in [10, 11] {
+            let user = User {
+                user_name1: format!("user{}", id),
+                ..user(id)
+            };
+            sign_up(&conn, e.id, &user, 1, 0, 0, now, 0).unwrap();
+            sign_up(&conn, e.id, &user, 0, 1, 0, now, 0).unwrap();
+        }
+        let code = issue_tickets(&conn, e.id, 10)?[0].code.clone().unwrap();
+        check_in(&conn, e.id, &code)?;
+
+        let stats = get_attendance_stats(&conn, e.id)?;
+        assert_eq!(
+            stats.participants,
+            Attendance {
+                reserved: 2,
+                present: 1,
+                no_show: 1
+            }
+        );
+        assert_eq!(stats.adults.present, 1);
+        assert_eq!(stats.children.no_show, 1);
+        assert_eq!(get_present_list(&conn, e.id, 0, 10)?.len(), 1);
+        assert_eq!(get_presence_list(&conn, e.id, 0, 10)?.len(), 1);
+
+        // a mistaken confirmation can be reverted
+        unconfirm_presence(&conn, e.id, 10)?;
+        assert!(get_present_list(&conn, e.id, 0, 10)?.is_empty());
+        assert_eq!(get_attendance_stats(&conn, e.id)?.participants.present, 0);
+        assert_eq!(get_check_in_count(&conn, e.id)?, (0, 2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_waiting_list_promotion() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_adults: 1,
+            waiting_list_hold: 60 * 60,
+            ..event("test event 1", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+        sign_up(&conn, e.id, &user(10), 1, 0, 0, now, 0).unwrap();
+        // user 12 joined the waiting list before user 11
+        sign_up(&conn, e.id, &user(11), 1, 0, 1, now + 2, 0).unwrap();
+        sign_up(&conn, e.id, &user(12), 1, 0, 1, now + 1, 0).unwrap();
+
+        // the freed seat is held for the first in line
+        cancel(&conn, e.id, 10, 1)?;
+        assert_eq!(get_offer(&conn, e.id, 12)?.0, 1);
+        assert_eq!(get_offer(&conn, e.id, 11)?.0, 0);
+        assert!(sign_up(&conn, e.id, &user(13), 1, 0, 0, now, 0).is_err());
+        accept_offer(&conn, e.id, 12)?;
+        assert_eq!(get_offer(&conn, e.id, 12)?.0, 0);
+        assert_eq!(get_event(&conn, e.id, 12)?.adults.my_reservation, 1);
+        assert!(accept_offer(&conn, e.id, 12).is_err());
+
+        // an unconfirmed seat goes to the next in line
+        wontgo(&conn, e.id, 12)?;
+        assert_eq!(get_offer(&conn, e.id, 11)?.0, 1);
+        expire_offers(&conn, now + 2 * 60 * 60)?;
+        assert_eq!(get_offer(&conn, e.id, 11)?.0, 0);
+        assert_eq!(get_event(&conn, e.id, 11)?.adults.my_reservation, 0);
+        assert_eq!(get_event(&conn, e.id, 0)?.adults.reserved, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_paid_waiting_list() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_adults: 1,
+            adult_ticket_price: 1000,
+            currency: "RUB".to_string(),
+            ..event("paid event", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+        let booking = |user_id| Booking {
+            event_id: e.id,
+            adults: 1,
+            children: 0,
+            user_id,
+        };
+        let order = |id: &str| OrderInfo {
+            id: id.to_string(),
+            name: "".to_string(),
+            amount: 1000,
+        };
+        sign_up(&conn, e.id, &user(10), 1, 0, 0, now, 1000).unwrap();
+        checkout(&conn, &booking(10), order("1"))?;
+        assert!(sign_up(&conn, e.id, &user(11), 1, 0, 0, now, 1000).is_err());
+        sign_up(&conn, e.id, &user(11), 1, 0, 1, now, 0).unwrap();
+        sign_up(&conn, e.id, &user(12), 1, 0, 1, now + 1, 0).unwrap();
+        assert_eq!(get_event(&conn, e.id, 11)?.adults.my_waiting, 1);
+
+        // the freed seat is held for payment
+        wontgo(&conn, e.id, 10)?;
+        assert_eq!(get_offer(&conn, e.id, 11)?.0, 1);
+        assert!(check_offered_booking(&conn, &booking(11), 500, 5 * 60).is_err());
+        check_offered_booking(&conn, &booking(11), 1000, 5 * 60)?;
+        checkout(&conn, &booking(11), order("2"))?;
+        assert_eq!(get_offer(&conn, e.id, 11)?.0, 0);
+        assert_eq!(get_event(&conn, e.id, 11)?.adults.my_reservation, 1);
+
+        // an unpaid seat expires and goes to the next in line
+        wontgo(&conn, e.id, 11)?;
+        assert_eq!(get_offer(&conn, e.id, 12)?.0, 1);
+        expire_offers(&conn, now + 2 * 60 * 60)?;
+        assert_eq!(get_offer(&conn, e.id, 12)?.0, 0);
+        assert!(check_offered_booking(&conn, &booking(12), 1000, 5 * 60).is_err());
+        assert_eq!(get_event(&conn, e.id, 0)?.adults.reserved, 0);
+
+        // leaving the waiting list
+        sign_up(&conn, e.id, &user(13), 1, 0, 0, now, 1000).unwrap();
+        checkout(&conn, &booking(13), order("3"))?;
+        sign_up(&conn, e.id, &user(14), 1, 0, 1, now, 0).unwrap();
+        leave_waiting_list(&conn, e.id, 14)?;
+        assert_eq!(get_event(&conn, e.id, 14)?.adults.my_waiting, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_refunds() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_adults: 1,
+            adult_ticket_price: 1000,
+            currency: "EUR".to_string(),
+            refund_deadline: 2 * 24 * 60 * 60,
+            late_refund_percent: 50,
+            ..event("paid event", now + 24 * 60 * 60)
+        };
+        assert_eq!(e.refund_percent(now - 24 * 60 * 60), 100);
+        assert_eq!(e.refund_percent(now), 50);
+        assert_eq!(e.refund_percent(e.ts), 0);
+        e.id = mutate_event(&conn, &e)?;
+        let booking = |user_id| Booking {
+            event_id: e.id,
+            adults: 1,
+            children: 0,
+            user_id,
+        };
+        assert!(request_refund(&conn, e.id, 10, now).is_err());
+        sign_up(&conn, e.id, &user(10), 1, 0, 0, now, 1000).unwrap();
+        checkout(
+            &conn,
+            &booking(10),
+            OrderInfo {
+                id: "1".to_string(),
+                name: "".to_string(),
+                amount: 1000,
+            },
+        )?;
+        sign_up(&conn, e.id, &user(11), 1, 0, 1, now, 0).unwrap();
+
+        // rejected request keeps the seat
+        assert_eq!(request_refund(&conn, e.id, 10, now)?, 500);
+        assert_eq!(get_refund_request(&conn, e.id, 10)?, Some(500));
+        assert_eq!(get_refund_requests(&conn, e.id, 0, 10)?[0].adults, 1);
+        reject_refund(&conn, e.id, 10)?;
+        assert_eq!(get_refund_request(&conn, e.id, 10)?, None);
+        assert!(approve_refund(&conn, e.id, 10).is_err());
+        assert_eq!(get_event(&conn, e.id, 10)?.adults.my_reservation, 1);
+
+        // approved refund releases the seat to the waiting list,
+        // the amount follows the paid bookings rather than the request
+        request_refund(&conn, e.id, 10, now)?;
+        conn.execute(
+            "UPDATE refund_requests SET amount = 900 WHERE event = ?1",
+            [e.id],
+        )?;
+        assert_eq!(approve_refund(&conn, e.id, 10)?, 500);
+        assert_eq!(get_event(&conn, e.id, 10)?.adults.my_reservation, 0);
+        assert_eq!(get_offer(&conn, e.id, 11)?.0, 1);
+        assert!(request_refund(&conn, e.id, 10, now).is_err());
+        let ledger = get_payments(&conn, e.id)?;
+        assert_eq!(ledger.len(), 1);
+        assert_eq!(ledger[0].kind, PaymentKind::Refund);
+        assert_eq!(ledger[0].amount, 500);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payments() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            adult_ticket_price: 1000,
+            currency: "EUR".to_string(),
+            ..event("paid event", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+        let payment = |kind, event_id, user_id, charge: &str| Payment {
+            id: 0,
+            ts: now,
+            kind,
+            event_id,
+            user_id,
+            amount: 1000,
+            currency: "EUR".to_string(),
+            charge_id: charge.to_string(),
+            provider_charge_id: format!("p{}", charge),
+            payload: "".to_string(),
+            promo_code: "".to_string(),
+        };
+        let checkout_as = |user_id, charge: &str| {
+            checkout(
+                &conn,
+                &Booking {
+                    event_id: e.id,
+                    adults: 1,
+                    children: 0,
+                    user_id,
+                },
+                OrderInfo {
+                    id: charge.to_string(),
+                    name: "".to_string(),
+                    amount: 1000,
+                },
+            )
+        };
+
+        // recorded and booked
+        sign_up(&conn, e.id, &user(10), 1, 0, 0, now, 1000).unwrap();
+        record_payment(&conn, &payment(PaymentKind::Ticket, e.id, 10, "1"))?;
+        checkout_as(10, "1")?;
+        // charged, but the booking failed
+        record_payment(&conn, &payment(PaymentKind::Ticket, e.id, 11, "2"))?;
+        assert!(checkout_as(11, "2").is_err());
+        // booked before the ledger existed
+        sign_up(&conn, e.id, &user(12), 1, 0, 0, now, 1000).unwrap();
+        checkout_as(12, "3")?;
+        record_payment(&conn, &payment(PaymentKind::Donation, 0, 13, "4"))?;
+
+        let payments = get_payments(&conn, e.id)?;
+        assert_eq!(payments.len(), 2);
+        assert_eq!(payments[0].provider_charge_id, "p1");
+        let totals = get_payment_totals(&conn, e.id)?;
+        assert_eq!((totals.tickets, totals.ticket_amount), (2, 2000));
+        assert_eq!(totals.currency, "EUR");
+        assert_eq!(get_payment_totals(&conn, 0)?.donation_amount, 1000);
+
+        let r = reconcile_payments(&conn, e.id)?;
+        assert_eq!(r.unmatched_payments.len(), 1);
+        assert_eq!(r.unmatched_payments[0].charge_id, "2");
+        assert_eq!(r.unrecorded_charges, vec!["3".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_promo_codes() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_adults_per_reservation: 2,
+            adult_ticket_price: 1000,
+            currency: "EUR".to_string(),
+            refund_deadline: 2 * 24 * 60 * 60,
+            late_refund_percent: 100,
+            ..event("paid event", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+        let code = |code: &str, event_id, percent, amount, max_uses, expires_at| PromoCode {
+            code: code.to_string(),
+            event_id,
+            percent,
+            amount,
+            max_uses,
+            uses: 0,
+            expires_at,
+        };
+        add_promo_code(&conn, &code("once", 0, 50, 0, 1, 0))?;
+        add_promo_code(&conn, &code("OTHER", e.id + 1, 10, 0, 0, 0))?;
+        add_promo_code(&conn, &code("OLD", 0, 10, 0, 0, now - 1))?;
+        add_promo_code(&conn, &code("FIXED", e.id, 0, 300, 0, 0))?;
+        assert_eq!(get_promo_codes(&conn)?.len(), 4);
+
+        assert!(apply_promo_code(&conn, e.id, 10, "unknown").is_err());
+        assert!(apply_promo_code(&conn, e.id, 10, "OTHER").is_err());
+        assert!(apply_promo_code(&conn, e.id, 10, "OLD").is_err());
+        assert_eq!(apply_promo_code(&conn, e.id, 10, " Once ")?.code, "ONCE");
+        assert_eq!(get_booking_amount(&conn, &e, 10, 2, 0)?, 1000);
+        assert_eq!(get_booking_amount(&conn, &e, 11, 2, 0)?, 2000);
+
+        // the discounted total is checked on booking and payment
+        assert!(sign_up(&conn, e.id, &user(10), 2, 0, 0, now, 2000).is_err());
+        sign_up(&conn, e.id, &user(10), 2, 0, 0, now, 1000).unwrap();
+        checkout(
+            &conn,
+            &Booking {
+                event_id: e.id,
+                adults: 2,
+                children: 0,
+                user_id: 10,
+            },
+            OrderInfo {
+                id: "1".to_string(),
+                name: "".to_string(),
+                amount: 1000,
+            },
+        )?;
+        assert!(get_applied_promo_code(&conn, e.id, 10)?.is_none());
+        // the refund returns what was paid
+        assert_eq!(request_refund(&conn, e.id, 10, now)?, 1000);
+
+        // the usage limit is reached
+        assert!(apply_promo_code(&conn, e.id, 11, "ONCE").is_err());
+        apply_promo_code(&conn, e.id, 11, "fixed")?;
+        assert_eq!(get_booking_amount(&conn, &e, 11, 1, 0)?, 700);
+        assert_eq!(delete_promo_code(&conn, "FIXED")?, 1);
+        assert_eq!(get_booking_amount(&conn, &e, 11, 1, 0)?, 1000);
+
+        // the code expires between pre-checkout and payment
+        add_promo_code(&conn, &code("SOON", e.id, 20, 0, 0, now + 60))?;
+        apply_promo_code(&conn, e.id, 12, "SOON")?;
+        let named = User {
+            user_name1: "user 12".to_string(),
+            ..user(12)
+        };
+        sign_up(&conn, e.id, &named, 1, 0, 0, now, 800).unwrap();
+        add_promo_code(&conn, &code("SOON", e.id, 20, 0, 0, now - 1))?;
+        assert_eq!(get_booking_amount(&conn, &e, 12, 1, 0)?, 1000);
+        checkout(
+            &conn,
+            &Booking {
+                event_id: e.id,
+                adults: 1,
+                children: 0,
+                user_id: 12,
+            },
+            OrderInfo {
+                id: "2".to_string(),
+                name: "".to_string(),
+                amount: 800,
+            },
+        )?;
+        let soon = get_promo_codes(&conn)?
+            .into_iter()
+            .find(|p| p.code == "SOON")
+            .unwrap();
+        assert_eq!(soon.uses, 1);
+        // payments without a name, e.g. from webhooks, keep the booked one
+        let name: String = conn.query_row(
+            "SELECT user_name1 FROM reservations WHERE event = ?1 AND user = 12",
+            [e.id],
+            |row| row.get(0),
+        )?;
+        assert_eq!(name, "user 12");
+
+        Ok(())
+    }
+
+    /// Local stand-in for a payment service: any invoice is paid, a webhook is valid when signed "ok".
+    struct FakeProvider;
+
+    impl PaymentProvider for FakeProvider {
+        fn create_invoice(
+            &self,
+            conn: &Connection,
+            user: &User,
+            invoice: Invoice,
+        ) -> anyhow::Result<Reply> {
+            let booking: Booking = serde_json::from_str(&invoice.payload)?;
+            self.pre_checkout(conn, user, &booking, invoice.amount)?;
+            Ok(ReplyMessage::new("pay").into())
+        }
+
+        fn verify_webhook(&self, body: &[u8], signature: &str) -> anyhow::Result<Option<Charge>> {
+            if signature != "ok" {
+                return Err(anyhow::anyhow!("Invalid signature"));
+            }
+            Ok(Some(serde_json::from_slice(body)?))
+        }
+
+        fn refund(&self, payment: &Payment, amount: u64) -> anyhow::Result<Option<String>> {
+            Ok(Some(format!("refund:{}:{}", payment.charge_id, amount)))
+        }
+    }
+
+    #[test]
+    fn test_payment_providers() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_adults: 2,
+            max_adults_per_reservation: 2,
+            adult_ticket_price: 1000,
+            currency: "EUR".to_string(),
+            late_refund_percent: 100,
+            ..event("paid event", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+        let booking = |user_id| Booking {
+            event_id: e.id,
+            adults: 2,
+            children: 0,
+            user_id,
+        };
+        let invoice = |user_id| Invoice {
+            title: "Билеты: 2".to_string(),
+            description: "paid event".to_string(),
+            payload: serde_json::to_string(&booking(user_id)).unwrap(),
+            currency: "EUR".to_string(),
+            amount: 2000,
+        };
+        let provider = FakeProvider;
+
+        // the seats are held when the invoice is created
+        provider.create_invoice(&conn, &user(20), invoice(20))?;
+        assert!(provider
+            .create_invoice(&conn, &user(21), invoice(21))
+            .is_err());
+
+        let charge = Charge {
+            payload: serde_json::to_string(&booking(20))?,
+            name: "Test".to_string(),
+            amount: 2000,
+            currency: "EUR".to_string(),
+            charge_id: "c1".to_string(),
+            provider_charge_id: "".to_string(),
+        };
+        let body = serde_json::to_vec(&charge)?;
+        assert!(provider.verify_webhook(&body, "bad").is_err());
+        let charge = provider.verify_webhook(&body, "ok")?.unwrap();
+        assert_eq!(provider.complete(&conn, &charge)?.user_id, 20);
+        // a repeated notification is not recorded twice
+        provider.complete(&conn, &charge)?;
+        assert_eq!(get_payments(&conn, e.id)?.len(), 1);
+        assert!(reconcile_payments(&conn, e.id)?
+            .unmatched_payments
+            .is_empty());
+
+        request_refund(&conn, e.id, 20, now)?;
+        approve_refund(&conn, e.id, 20)?;
+        assert_eq!(
+            refund_links(&provider, &conn, e.id, 20, 2000)?,
+            vec!["refund:c1:2000".to_string()]
+        );
+        assert!(refund_links(&TelegramPayments, &conn, e.id, 20, 2000)?.is_empty());
+        // payments returned by an earlier refund are skipped
+        record_payment(
+            &conn,
+            &Payment {
+                charge_id: "c2".to_string(),
+                amount: 1000,
+                ..get_payments(&conn, e.id)?[0].clone()
+            },
+        )?;
+        conn.execute(
+            "UPDATE reservations SET state = ?1 WHERE event = ?2 AND user = 20",
+            params![ReservationState::PaymentCompleted as u64, e.id],
+        )?;
+        request_refund(&conn, e.id, 20, now)?;
+        approve_refund(&conn, e.id, 20)?;
+        assert_eq!(
+            refund_links(&provider, &conn, e.id, 20, 2000)?,
+            vec!["refund:c2:1000".to_string()]
+        );
+
+        // external checkout signs links and checks webhook signatures
+        let external = ExternalCheckout {
+            checkout_url: "https://pay.example.com/checkout".to_string(),
+            refund_url: "".to_string(),
+            secret: "secret".to_string(),
+            hold: 30 * 60,
+        };
+        match external.create_invoice(&conn, &user(21), invoice(21))? {
+            Reply::Message(m) => {
+                let link = format!("{:?}", m.keyboard);
+                assert!(link.contains("amount=2000") && link.contains("signature="));
+            }
+            _ => panic!("checkout link expected"),
+        }
+        assert!(external.verify_webhook(&body, "ok").is_err());
+        assert!(external
+            .verify_webhook(&body, &sign("other", &body))
+            .is_err());
+        assert_eq!(
+            external
+                .verify_webhook(&body, &sign("secret", &body))?
+                .map(|c| c.charge_id),
+            Some("c1".to_string())
+        );
+        assert!(external
+            .refund(&get_payments(&conn, e.id)?[0], 100)?
+            .is_none());
+
+        Ok(())
+    }
+
+    /// Stand-in for the crypto gateway: answers one request with `response` and returns the request.
+    fn serve_once(response: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        use std::io::{BufRead, Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            request.push_str(&String::from_utf8(body).unwrap());
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .unwrap();
+            request
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_crypto_payments() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_adults: 2,
+            max_adults_per_reservation: 2,
+            adult_ticket_price: 1000,
+            currency: "EUR".to_string(),
+            ..event("paid event", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+        let payload = serde_json::to_string(&Booking {
+            event_id: e.id,
+            adults: 2,
+            children: 0,
+            user_id: 30,
+        })?;
+
+        // seats booked for an invoice the gateway failed to create are released
+        let (url, gateway) = serve_once("{}");
+        let failing = CryptoPayments {
+            api_url: url,
+            api_key: "key".to_string(),
+            secret: "secret".to_string(),
+            hold: 60 * 60,
+        };
+        assert!(failing
+            .create_invoice(
+                &conn,
+                &user(30),
+                Invoice {
+                    title: "Билеты: 2".to_string(),
+                    description: "paid event".to_string(),
+                    payload: payload.to_owned(),
+                    currency: "EUR".to_string(),
+                    amount: 2000,
+                },
+            )
+            .is_err());
+        gateway.join().unwrap();
+        assert_eq!(get_event(&conn, e.id, 30)?.adults.my_reservation, 0);
+
+        let (url, gateway) = serve_once(
+            r#"{"id":"inv1","address":"TXaddr","pay_url":"https://pay.example.com/inv1","amount":"21.5","asset":"USDT"}"#,
+        );
+        let crypto = CryptoPayments {
+            api_url: url,
+            api_key: "key".to_string(),
+            secret: "secret".to_string(),
+            hold: 60 * 60,
+        };
+        match crypto.create_invoice(
+            &conn,
+            &user(30),
+            Invoice {
+                title: "Билеты: 2".to_string(),
+                description: "paid event".to_string(),
+                payload: payload.to_owned(),
+                currency: "EUR".to_string(),
+                amount: 2000,
+            },
+        )? {
+            Reply::Message(m) => {
+                assert!(m.message.contains("<code>TXaddr</code>"));
+                assert!(m.message.contains("21.5 USDT"));
+            }
+            _ => panic!("crypto invoice expected"),
+        }
+        let request = gateway.join().unwrap();
+        assert!(request.starts_with("POST /invoices "));
+        assert!(request.contains("Bearer key"));
+        assert!(request.contains(r#""expires_in":3600"#));
+
+        // the seats stay booked longer than a Telegram payment
+        clear_failed_payments(&conn, now - crypto.hold())?;
+        assert_eq!(get_event(&conn, e.id, 30)?.adults.my_reservation, 2);
+
+        let callback = |status: &str| {
+            serde_json::to_vec(&serde_json::json!({
+                "invoice_id": "inv1",
+                "status": status,
+                "payload": payload,
+                "amount": 2000,
+                "currency": "EUR",
+                "tx_hash": "0xabc",
+            }))
+            .unwrap()
+        };
+        let pending = callback("pending");
+        assert!(crypto.verify_webhook(&pending, "bad").is_err());
+        assert!(crypto
+            .verify_webhook(&pending, &sign("secret", &pending))?
+            .is_none());
+        let paid = callback("paid");
+        let charge = crypto
+            .verify_webhook(&paid, &sign("secret", &paid))?
+            .unwrap();
+        assert_eq!(crypto.complete(&conn, &charge)?.user_id, 30);
+        let payments = get_payments(&conn, e.id)?;
+        assert_eq!(payments.len(), 1);
+        assert_eq!(payments[0].provider_charge_id, "0xabc");
+        assert!(reconcile_payments(&conn, e.id)?
+            .unmatched_payments
+            .is_empty());
+
+        // completed seats are not cleared
+        clear_failed_payments(&conn, get_unix_time() + 1)?;
+        assert_eq!(get_event(&conn, e.id, 30)?.adults.my_reservation, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_languages() -> anyhow::Result<()> {
+        let conn = connection();
+
+        // the Telegram client language is followed until the user picks one
+        assert_eq!(get_language(&conn, 1)?, Lang::Ru);
+        assert_eq!(resolve_language(&conn, 1, Lang::En)?, Lang::En);
+        assert_eq!(resolve_language(&conn, 1, Lang::Ru)?, Lang::Ru);
+        set_language(&conn, 1, Lang::En)?;
+        assert_eq!(resolve_language(&conn, 1, Lang::Ru)?, Lang::En);
+        assert_eq!(resolve_language(&conn, 2, Lang::Ru)?, Lang::Ru);
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_adults: 2,
+            ..event("event", now + 24 * 60 * 60)
+        };
+        e.id = mutate_event(&conn, &e)?;
+        for id in [1, 2] {
+            let user = user(id);
+            sign_up(&conn, e.id, &user, 1, 0, 0, now, 0)?;
+        }
+
+        // queued messages keep the catalog key and are rendered for every recipient
+        let text = text!(
+            "event-message",
+            sender_id = "3",
+            sender = "admin",
+            title = "event",
+            start = "start",
+            text = "hello"
+        );
+        enqueue_message(&conn, e.id, "admin", 0, MessageType::Direct, &text, now)?;
+        let batch = get_pending_messages(&conn, now + 1, 10)?
+            .into_iter()
+            .find(|b| b.message_type == MessageType::Direct)
+            .unwrap();
+        assert_eq!(batch.text, text);
+        assert_eq!(batch.recipients.len(), 2);
+        let rendered = batch
+            .recipients
+            .iter()
+            .map(|id| batch.text.render(get_language(&conn, *id).unwrap()))
+            .collect::<Vec<String>>();
+        assert!(rendered[0].contains("Message about the event event (Start: start)\nhello"));
+        assert!(rendered[1].contains("Сообщение по мероприятию event (Начало: start)\nhello"));
+        assert_eq!(get_group_messages(&conn, e.id, Some(0))?[0].text, text);
+
+        // literal texts are sent as is
+        let literal = Text::Literal("as is".to_string());
+        enqueue_personal_message(&conn, e.id, 1, MessageType::Direct, &literal, now)?;
+        assert!(get_pending_messages(&conn, now + 1, 10)?
+            .iter()
+            .any(|b| b.text == literal && b.recipients == vec![1]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_templates() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut e = Event {
+            max_adults: 2,
+            reminders: vec![Reminder {
+                before: 24 * 60 * 60,
+                template: None,
+            }],
+            venue: "Cafe".to_string(),
+            ..event("event", now + 2 * 24 * 60 * 60)
+        };
+        let user = |id: u64, name: &str| User {
+            user_name1: name.to_string(),
+            ..user(id)
+        };
+        let pending = |message_type: MessageType, ts: u64| -> anyhow::Result<Vec<String>> {
+            let mut res = Vec::new();
+            for b in get_pending_messages(&conn, ts, 10)? {
+                if b.message_type == message_type {
+                    for u in b.recipients {
+                        res.push(
+                            templates::personalize(&conn, &b.text, b.event_id, u).render(Lang::Ru),
+                        );
+                    }
+                }
+            }
+            Ok(res)
+        };
+
+        // without templates the catalog message is sent
+        e.id = mutate_event(&conn, &e)?;
+        sign_up(&conn, e.id, &user(1, "Ann <A>"), 1, 0, 0, now, 0)?;
+        let reminders = pending(MessageType::Reminder, e.ts)?;
+        assert_eq!(reminders.len(), 1);
+        assert!(reminders[0].contains("Не забудьте"));
+
+        // the common template, then the event's one, then the reminder's own one
+        let global = "{name} at {venue}, {seats} seats left, {user}";
+        templates::validate(TemplateKind::Reminder, global)?;
+        set_template(&conn, 0, "reminder", global)?;
+        mutate_event(&conn, &e)?;
+        assert_eq!(
+            pending(MessageType::Reminder, e.ts)?,
+            vec!["event at Cafe, 1 seats left, Ann &lt;A&gt;"]
+        );
+        set_template(&conn, e.id, "reminder", "{name}: {start}")?;
+        mutate_event(&conn, &e)?;
+        assert_eq!(
+            pending(MessageType::Reminder, e.ts)?,
+            vec![format!("event: {}", format::ts(e.ts))]
+        );
+        e.reminders[0].template = Some("Own {user}".to_string());
+        mutate_event(&conn, &e)?;
+        assert_eq!(
+            pending(MessageType::Reminder, e.ts)?,
+            vec!["Own Ann &lt;A&gt;"]
+        );
+        assert_eq!(delete_template(&conn, e.id, "reminder")?, 1);
+        assert_eq!(
+            templates::find(&conn, e.id, TemplateKind::Reminder)?,
+            Some(global.to_string())
+        );
+
+        // the waiting list prompt tells the number of free seats when sent
+        set_template(&conn, e.id, "waiting_list", "{name}: {seats} free, {user}")?;
+        sign_up(&conn, e.id, &user(2, "Bob"), 1, 0, 0, now, 0)?;
+        sign_up(&conn, e.id, &user(3, "Eve"), 1, 0, 1, now, 0)?;
+        cancel(&conn, e.id, 2, 1)?;
+        assert_eq!(
+            pending(MessageType::WaitingListPrompt, now + 11)?,
+            vec!["event: 1 free, Eve"]
+        );
+
+        // previews use the event data
+        assert_eq!(
+            templates::preview(
+                &conn,
+                e.id,
+                TemplateKind::Message,
+                "{sender} {venue}: {text}",
+                "Admin"
+            )?,
+            "Admin Cafe: …"
+        );
+        assert!(
+            templates::preview(&conn, e.id, TemplateKind::WaitingList, "{text}", "Admin").is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_user_bookings() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let event = |name: &str, ts: u64, requires_approval: bool| Event {
+            max_adults: 1,
+            requires_approval,
+            ..event(name, ts)
+        };
+        let upcoming = mutate_event(&conn, &event("upcoming", now + 24 * 60 * 60, false))?;
+        let approval = mutate_event(&conn, &event("approval", now + 48 * 60 * 60, true))?;
+        let past = mutate_event(&conn, &event("past", now + 60, false))?;
+
+        sign_up(&conn, upcoming, &user(1), 1, 0, 0, now, 0)?;
+        add_attachment(&conn, upcoming, 1, "note")?;
+        sign_up(&conn, upcoming, &user(2), 1, 0, 1, now, 0)?;
+        sign_up(&conn, approval, &user(2), 1, 0, 0, now, 0)?;
+        sign_up(&conn, past, &user(1), 1, 0, 0, now, 0)?;
+        sign_up(&conn, past, &user(2), 1, 0, 1, now, 0)?;
+        confirm_presence(&conn, past, 1)?;
+
+        // confirmed seats with the note
+        let later = now + 120;
+        let bookings = get_user_bookings(&conn, 1, later, false)?;
+        assert_eq!(bookings.len(), 1);
+        assert_eq!(bookings[0].event_id, upcoming);
+        assert_eq!((bookings[0].adults, bookings[0].waiting_adults), (1, 0));
+        assert!(bookings[0]
+            .attachment
+            .as_deref()
+            .is_some_and(|a| a.starts_with("note")));
+        assert!(!bookings[0].attended);
+
+        // past events are listed only if attended
+        let bookings = get_user_bookings(&conn, 1, later, true)?;
+        assert_eq!(
+            bookings.iter().map(|b| b.event_id).collect::<Vec<u64>>(),
+            vec![past, upcoming]
+        );
+        assert!(bookings[0].attended);
+
+        // waiting list and applications
+        let bookings = get_user_bookings(&conn, 2, later, true)?;
+        assert_eq!(bookings.len(), 2);
+        assert_eq!((bookings[0].adults, bookings[0].waiting_adults), (0, 1));
+        assert_eq!(
+            (bookings[1].event_id, bookings[1].pending_adults),
+            (approval, 1)
+        );
+        assert_eq!(bookings[1].adults, 0);
+
+        // cancelled bookings disappear
+        wontgo(&conn, upcoming, 2)?;
+        assert_eq!(get_user_bookings(&conn, 2, later, false)?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_calendar() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let event = |name: &str, ts: u64| Event {
+            max_adults: 1,
+            ..event(name, ts)
+        };
+        let first = mutate_event(&conn, &event("first", now + 48 * 60 * 60))?;
+        let second = mutate_event(&conn, &event("second", now + 24 * 60 * 60))?;
+        sign_up(&conn, first, &user(1), 1, 0, 0, now, 0)?;
+        sign_up(&conn, second, &user(1), 1, 0, 0, now, 0)?;
+        sign_up(&conn, first, &user(2), 1, 0, 1, now, 0)?;
+
+        // the token is kept until reset
+        let token = get_calendar_token(&conn, 1, false)?;
+        assert_eq!(get_calendar_token(&conn, 1, false)?, token);
+        assert_eq!(get_calendar_user(&conn, &token)?, Some(1));
+        let reset = get_calendar_token(&conn, 1, true)?;
+        assert_ne!(reset, token);
+        assert_eq!(get_calendar_user(&conn, &token)?, None);
+        assert_eq!(get_calendar_user(&conn, &reset)?, Some(1));
+
+        // confirmed bookings by start time, the waiting list is left out
+        assert_eq!(
+            get_confirmed_events(&conn, 1)?
+                .iter()
+                .map(|e| e.id)
+                .collect::<Vec<u64>>(),
+            vec![second, first]
+        );
+        assert!(get_confirmed_events(&conn, 2)?.is_empty());
+        assert_eq!(
+            find_event(&conn, first)?.map(|e| e.name),
+            Some("first".to_string())
+        );
+        assert!(find_event(&conn, 1000)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_public_feed() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut event = Event {
+            max_adults: 1,
+            organization: "Club".to_string(),
+            tags: vec!["Music".to_string(), "kids".to_string()],
+            ..event("concert", now - 60)
+        };
+        assert_eq!(get_events_modified(&conn, now)?, 0);
+        event.id = mutate_event(&conn, &event)?;
+
+        // organization and tags are stored with the event
+        let stored = find_event(&conn, event.id)?.unwrap();
+        assert_eq!(stored.organization, "Club");
+        assert_eq!(stored.tags, vec!["Music".to_string(), "kids".to_string()]);
+        assert!(stored.has_tag("music") && !stored.has_tag("talks"));
+
+        // saving or the start of an event changes the list
+        assert!(get_events_modified(&conn, now)? >= now);
+        event.ts = now + 24 * 60 * 60;
+        mutate_event(&conn, &event)?;
+        assert_eq!(get_events_modified(&conn, now + 48 * 60 * 60)?, event.ts);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_broadcasts() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let event = Event {
+            max_adults: 2,
+            ..event("concert", now + 60)
+        };
+        let event_id = mutate_event(&conn, &event)?;
+        sign_up(&conn, event_id, &user(1), 1, 0, 0, now, 0)?;
+        sign_up(&conn, event_id, &user(2), 1, 0, 0, now, 0)?;
+        sign_up(&conn, event_id, &user(3), 1, 0, 1, now, 0)?;
+        resolve_language(&conn, 4, Lang::En)?;
+        confirm_presence(&conn, event_id, 1)?;
+
+        // segments
+        assert_eq!(get_audience(&conn, &Audience::All)?, vec![1, 2, 3, 4]);
+        assert_eq!(get_audience(&conn, &Audience::Attended(event_id))?, vec![1]);
+        assert_eq!(get_audience(&conn, &Audience::NoShow(event_id))?, vec![2]);
+
+        // a draft is scheduled once with the recipients at that time
+        let text = text!("broadcast-message", sender = "admin", text = "hi");
+        let message_id =
+            create_broadcast(&conn, "admin", &Audience::NoShow(event_id), &text, None)?;
+        let (audience, _) = get_broadcast(&conn, message_id)?.unwrap();
+        assert_eq!(audience, Audience::NoShow(event_id));
+        assert_eq!(get_pending_messages(&conn, now + 10, 10)?.len(), 0);
+        assert_eq!(schedule_broadcast(&conn, message_id, now)?, 1);
+        assert_eq!(schedule_broadcast(&conn, message_id, now)?, 0);
+        assert_eq!(delete_broadcast(&conn, message_id)?, 0);
+
+        // delivered through the outbox, also after the event is gone
+        delete_event(&conn, event_id, false, false, &HashSet::new())?;
+        let batches = get_pending_messages(&conn, now + 10, 10)?;
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].event_id, 0);
+        assert_eq!(batches[0].recipients, vec![2]);
+        save_receipt(&conn, message_id, 2)?;
+        assert!(get_pending_messages(&conn, now + 10, 10)?[0]
+            .recipients
+            .is_empty());
+        assert!(get_pending_messages(&conn, now + 10, 10)?.is_empty());
+        assert_eq!(schedule_broadcast(&conn, message_id, now)?, 0);
+
+        // drafts can be cancelled
+        let message_id = create_broadcast(&conn, "admin", &Audience::All, &text, None)?;
+        assert_eq!(delete_broadcast(&conn, message_id)?, 1);
+        assert!(get_broadcast(&conn, message_id)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_media_messages() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let event = Event {
+            max_adults: 2,
+            ..event("concert", now + 60)
+        };
+        let user = user(1);
+        let event_id = mutate_event(&conn, &event)?;
+        sign_up(&conn, event_id, &user, 1, 0, 0, now, 0)?;
+
+        // media of a /send message
+        let photo = Media::Photo {
+            file_id: "photo-id".to_string(),
+        };
+        let text = text!("broadcast-message", sender = "admin", text = "hi");
+        enqueue_media_message(&conn, event_id, "admin", 0, &text, Some(&photo), now)?;
+        let batches = get_pending_messages(&conn, now + 1, 10)?;
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].media, Some(photo));
+        assert_eq!(batches[0].recipients, vec![1]);
+        save_receipt(&conn, batches[0].message_id, 1)?;
+        get_pending_messages(&conn, now + 1, 10)?;
+
+        // and of a broadcast
+        let poll = Media::Poll {
+            question: "When?".to_string(),
+            options: vec!["Today".to_string(), "Tomorrow".to_string()],
+            is_anonymous: false,
+            allows_multiple_answers: false,
+            correct_option_id: None,
+        };
+        let message_id = create_broadcast(&conn, "admin", &Audience::All, &text, Some(&poll))?;
+        schedule_broadcast(&conn, message_id, now)?;
+        let batches = get_pending_messages(&conn, now + 1, 10)?;
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].media, Some(poll));
+
+        // plain text stays without media
+        let message_id = create_broadcast(&conn, "admin", &Audience::All, &text, None)?;
+        schedule_broadcast(&conn, message_id, now)?;
+        let batches = get_pending_messages(&conn, now + 1, 10)?;
+        assert_eq!(batches.last().unwrap().media, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_delivery_reports() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let event = Event {
+            max_adults: 3,
+            ..event("concert", now + 60)
+        };
+        let event_id = mutate_event(&conn, &event)?;
+        for id in 1..=3 {
+            sign_up(&conn, event_id, &user(id), 1, 0, 0, now, 0)?;
+        }
+        assert!(get_delivery_report(&conn, 1000)?.is_none());
+
+        // all recipients are pending until the message is sent
+        let text = Text::Literal("hi".to_string());
+        let message_id =
+            enqueue_message(&conn, event_id, "admin", 0, MessageType::Direct, &text, now)?;
+        let report = get_delivery_report(&conn, message_id)?.unwrap();
+        assert_eq!(report.event_id, event_id);
+        assert_eq!((report.targeted, report.pending), (3, 3));
+
+        save_receipt(&conn, message_id, 1)?;
+        save_failure(&conn, message_id, 2, "bot_blocked")?;
+        let report = get_delivery_report(&conn, message_id)?.unwrap();
+        assert_eq!(report.targeted, 3);
+        assert_eq!(report.delivered, 1);
+        assert_eq!(report.failed, 1);
+        assert_eq!(report.pending, 1);
+        assert_eq!(report.failures.get("bot_blocked"), Some(&1));
+
+        // failed recipients are not retried and the records outlive the batch
+        let batches = get_pending_messages(&conn, now + 1, 10)?;
+        assert_eq!(batches[0].recipients, vec![3]);
+        save_receipt(&conn, message_id, 3)?;
+        assert!(get_pending_messages(&conn, now + 1, 10)?[0]
+            .recipients
+            .is_empty());
+        let report = get_delivery_report(&conn, message_id)?.unwrap();
+        assert_eq!((report.targeted, report.delivered), (3, 2));
+        assert_eq!((report.failed, report.pending), (1, 0));
+
+        // broadcasts count the recipients fixed when scheduled
+        let broadcast_id = create_broadcast(&conn, "admin", &Audience::All, &text, None)?;
+        assert_eq!(
+            get_delivery_report(&conn, broadcast_id)?.unwrap().targeted,
+            0
+        );
+        schedule_broadcast(&conn, broadcast_id, now)?;
+        save_failure(&conn, broadcast_id, 3, "user_deactivated")?;
+        let report = get_delivery_report(&conn, broadcast_id)?.unwrap();
+        assert_eq!((report.targeted, report.failed, report.pending), (3, 1, 2));
+
+        // removed with the event, broadcasts stay
+        delete_event(&conn, event_id, false, false, &HashSet::new())?;
+        assert!(get_delivery_report(&conn, message_id)?.is_none());
+        assert_eq!(get_delivery_report(&conn, broadcast_id)?.unwrap().failed, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_feedback() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let mut event = Event {
+            max_adults: 3,
+            ..event("concert", now + 60)
+        };
+        let event_id = mutate_event(&conn, &event)?;
+        for id in 1..=3 {
+            sign_up(&conn, event_id, &user(id), 1, 0, 0, now, 0)?;
+        }
+        confirm_presence(&conn, event_id, 1)?;
+        confirm_presence(&conn, event_id, 2)?;
+        event.link = "https://example.com/2".to_string();
+        let old_event_id = mutate_event(&conn, &event)?;
+        sign_up(&conn, old_event_id, &user(4), 1, 0, 0, now, 0)?;
+        confirm_presence(&conn, old_event_id, 4)?;
+        // over, the other one long before the survey was turned on
+        event.id = event_id;
+        event.ts = now - 3 * 60 * 60;
+        mutate_event(&conn, &event)?;
+        event.id = old_event_id;
+        event.ts = now - 3 * 24 * 60 * 60;
+        mutate_event(&conn, &event)?;
+
+        // participants present are asked once, after the delay
+        assert_eq!(schedule_feedback(&conn, now, 60 * 60)?, 2);
+        assert_eq!(schedule_feedback(&conn, now, 60 * 60)?, 0);
+        assert!(get_pending_messages(&conn, now, 10)?.is_empty());
+        delete_event(&conn, event_id, false, false, &HashSet::new())?;
+        let batches = get_pending_messages(&conn, now + 60 * 60, 10)?;
+        assert_eq!(batches.len(), 1);
+        assert!(batches[0].message_type == MessageType::Feedback);
+        assert_eq!(batches[0].event_id, event_id);
+        assert_eq!(batches[0].recipients, vec![1, 2]);
+
+        // only the surveyed can answer
+        assert!(!save_feedback_rating(&conn, event_id, 3, 4)?);
+        assert!(!save_feedback_rating(&conn, old_event_id, 4, 4)?);
+        assert!(save_feedback_rating(&conn, event_id, 1, 2)?);
+        assert!(save_feedback_rating(&conn, event_id, 1, 5)?);
+        assert!(save_feedback_rating(&conn, event_id, 2, 4)?);
+        set_feedback_session(&conn, 1, event_id)?;
+        assert_eq!(get_feedback_session(&conn, 1)?, Some(event_id));
+        save_feedback_comment(&conn, event_id, 1, "great")?;
+        set_feedback_session(&conn, 1, 0)?;
+        assert_eq!(get_feedback_session(&conn, 1)?, None);
+        // viewing another event ends waiting for the comment
+        set_feedback_session(&conn, 2, event_id)?;
+        get_event(&conn, old_event_id, 2)?;
+        assert_eq!(get_feedback_session(&conn, 2)?, None);
+
+        let summary = get_feedback_summary(&conn, event_id)?.unwrap();
+        assert_eq!(summary.name, "concert");
+        assert_eq!(summary.surveyed, 2);
+        assert_eq!(summary.responses, 2);
+        assert_eq!(summary.ratings, [0, 0, 0, 1, 1]);
+        assert_eq!(summary.comments, 1);
+        assert!((summary.average - 4.5).abs() < f64::EPSILON);
+        assert_eq!(get_feedback_summaries(&conn, 0, 10)?.len(), 1);
+        assert!(get_feedback_summary(&conn, old_event_id)?.is_none());
+
+        let responses = get_feedback_responses(&conn, event_id, 0, 0)?;
+        assert_eq!(responses.len(), 2);
+        assert_eq!(
+            responses.iter().find(|r| r.user_id == 1).unwrap().comment,
+            "great"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscriptions() -> anyhow::Result<()> {
+        let conn = connection();
+
+        let now = get_unix_time();
+        let music = Audience::Tag("Music".to_string());
+        let club = Audience::Organization("club".to_string());
+        assert!(toggle_subscription(&conn, 1, &music)?);
+        assert!(toggle_subscription(&conn, 2, &club)?);
+        assert!(toggle_subscription(&conn, 3, &club)?);
+        assert!(toggle_subscription(&conn, 3, &music)?);
+        assert!(toggle_subscription(
+            &conn,
+            4,
+            &Audience::Tag("art".to_string())
+        )?);
+        assert!(toggle_subscription(&conn, 5, &music)?);
+        assert!(toggle_subscription(&conn, 6, &music)?);
+        assert!(!toggle_subscription(&conn, 6, &music)?);
+        assert_eq!(
+            get_audience(&conn, &Audience::Tag("music".to_string()))?,
+            vec![1, 3, 5]
+        );
+
+        let subscriptions = get_subscriptions(&conn, 3)?;
+        assert_eq!(
+            subscriptions.iter().map(|(_, s)| s).collect::<Vec<_>>(),
+            vec![&club, &Audience::Tag("music".to_string())]
+        );
+        assert_eq!(delete_subscription(&conn, 1, subscriptions[0].0)?, 0);
+
+        // subscribers learn about a new event unless they booked it
+        let mut event = Event {
+            max_adults: 3,
+            organization: "Club".to_string(),
+            tags: vec!["MUSIC".to_string()],
+            ..event("concert", now + 24 * 60 * 60)
+        };
+        let event_id = mutate_event(&conn, &event)?;
+        sign_up(&conn, event_id, &user(5), 1, 0, 0, now, 0)?;
+        let batches = get_pending_messages(&conn, now + 1, 10)?;
+        assert_eq!(batches.len(), 1);
+        assert!(batches[0].message_type == MessageType::NewEvent);
+        assert_eq!(batches[0].recipients, vec![1, 2, 3]);
+        let report = get_delivery_report(&conn, batches[0].message_id)?.unwrap();
+        assert_eq!(report.pending, 3);
+        for u in &batches[0].recipients {
+            save_receipt(&conn, batches[0].message_id, *u)?;
+        }
+        assert!(get_pending_messages(&conn, now + 1, 10)?[0]
+            .recipients
+            .is_empty());
+        assert!(get_pending_messages(&conn, now + 1, 10)?.is_empty());
+
+        // or when its registration opens
+        event.link = "https://example.com/2".to_string();
+        event.registration_opens_at = now + 60 * 60;
+        event.id = mutate_event(&conn, &event)?;
+        assert!(get_pending_messages(&conn, now + 1, 10)?.is_empty());
+        event.registration_opens_at = now + 2 * 60 * 60;
+        mutate_event(&conn, &event)?;
+        assert!(get_pending_messages(&conn, now + 60 * 60 + 1, 10)?.is_empty());
+        let batches = get_pending_messages(&conn, now + 2 * 60 * 60 + 1, 10)?;
+        assert_eq!(batches[0].recipients, vec![1, 2, 3, 5]);
+
+        // not for events without organization or tags
+        event.id = 0;
+        event.link = "https://example.com/3".to_string();
+        event.registration_opens_at = 0;
+        event.organization = "".to_string();
+        event.tags = vec![];
+        mutate_event(&conn, &event)?;
+        assert_eq!(get_pending_messages(&conn, now + 1, 10)?.len(), 0);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,177 @@
+use crate::db;
+use crate::format;
+use crate::message_handler::CallbackQuery;
+use crate::reply::*;
+use crate::types::{Connection, Context, FeedbackSummary, User};
+use teloxide::{types::InlineKeyboardButton, utils::html};
+
+/// Longer comments are refused.
+const MAX_COMMENT_LENGTH: usize = 1000;
+
+/// Rating buttons of the survey sent after the event.
+pub fn controls(event_id: u64) -> anyhow::Result<Vec<Vec<InlineKeyboardButton>>> {
+    let mut row = Vec::new();
+    for rating in 1..=5 {
+        row.push(InlineKeyboardButton::callback(
+            format!("{}⭐", rating),
+            serde_json::to_string(&CallbackQuery::RateEvent { event_id, rating })?,
+        ));
+    }
+    Ok(vec![row])
+}
+
+/// Stores the rating, the next message from the user is taken as a comment.
+pub fn rate(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    rating: u64,
+    _ctx: &Context,
+) -> anyhow::Result<Reply> {
+    if !(1..=5).contains(&rating) || !db::save_feedback_rating(conn, event_id, user.id.0, rating)? {
+        return Err(text!("feedback-unavailable").into());
+    }
+    db::set_feedback_session(conn, user.id.0, event_id)?;
+    Ok(ReplyMessage::new(t!(user.lang, "feedback-thanks"))
+        .keyboard(vec![vec![InlineKeyboardButton::callback(
+            t!(user.lang, "feedback-skip-button"),
+            serde_json::to_string(&CallbackQuery::SkipFeedbackComment { event_id })?,
+        )]])
+        .into())
+}
+
+pub fn skip_comment(
+    conn: &Connection,
+    user: &User,
+    _event_id: u64,
+    _ctx: &Context,
+) -> anyhow::Result<Reply> {
+    db::set_feedback_session(conn, user.id.0, 0)?;
+    Ok(ReplyMessage::new(t!(user.lang, "feedback-done")).into())
+}
+
+/// Message from a user who was asked for a comment on the rated event.
+pub fn handle_text(
+    conn: &Connection,
+    user: &User,
+    data: &str,
+    _ctx: &Context,
+) -> anyhow::Result<Option<Reply>> {
+    let event_id = match db::get_feedback_session(conn, user.id.0)? {
+        Some(event_id) => event_id,
+        None => return Ok(None),
+    };
+    if data.chars().count() > MAX_COMMENT_LENGTH {
+        return Err(text!("feedback-comment-too-long", max = MAX_COMMENT_LENGTH).into());
+    }
+    db::save_feedback_comment(conn, event_id, user.id.0, data.trim())?;
+    db::set_feedback_session(conn, user.id.0, 0)?;
+    Ok(Some(
+        ReplyMessage::new(t!(user.lang, "feedback-done")).into(),
+    ))
+}
+
+/// Surveyed events from the latest one, each opens its responses.
+pub fn show_summaries(
+    conn: &Connection,
+    user: &User,
+    offset: u64,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    let summaries = db::get_feedback_summaries(conn, offset, ctx.config.event_list_page_size)?;
+    let mut text = String::new();
+    let mut keyboard = Vec::new();
+    if summaries.is_empty() {
+        text.push_str(&t!(user.lang, "no-feedback"));
+    }
+    for s in &summaries {
+        text.push_str(&format!("{}\n", summary_line(s, user)));
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            format!("{} {}", format::ts(s.ts), s.name),
+            serde_json::to_string(&CallbackQuery::ShowFeedback {
+                event_id: s.event_id,
+                offset: 0,
+            })?,
+        )]);
+    }
+    Ok(ReplyMessage::new(text)
+        .keyboard(keyboard)
+        .pagination(
+            &CallbackQuery::ShowFeedbackSummaries {
+                offset: offset.saturating_sub(1),
+            },
+            &CallbackQuery::ShowFeedbackSummaries { offset: offset + 1 },
+            summaries.len() as u64,
+            ctx.config.event_list_page_size,
+            offset,
+        )?
+        .into())
+}
+
+/// Ratings of the event with the responses and comments.
+pub fn show_responses(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    offset: u64,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    let s = match db::get_feedback_summary(conn, event_id)? {
+        Some(s) => s,
+        None => return Err(text!("feedback-unavailable").into()),
+    };
+    let mut text = summary_line(&s, user);
+    for (i, count) in s.ratings.iter().enumerate().rev() {
+        text.push_str(&format!(
+            "\n{}",
+            t!(user.lang, "feedback-rating", rating = i + 1, count = *count)
+        ));
+    }
+    text.push('\n');
+    let responses =
+        db::get_feedback_responses(conn, event_id, offset, ctx.config.presence_page_size)?;
+    if responses.is_empty() {
+        text.push_str(&format!("\n{}", t!(user.lang, "feedback-no-responses")));
+    }
+    for r in &responses {
+        text.push_str(&format!(
+            "\n<a href=\"tg://user?id={}\">{}</a> {}⭐",
+            r.user_id, r.user_id, r.rating
+        ));
+        if !r.comment.is_empty() {
+            text.push_str(&format!(": {}", html::escape(&r.comment)));
+        }
+    }
+    Ok(ReplyMessage::new(text)
+        .keyboard(vec![vec![InlineKeyboardButton::callback(
+            t!(user.lang, "back"),
+            serde_json::to_string(&CallbackQuery::ShowFeedbackSummaries { offset: 0 })?,
+        )]])
+        .pagination(
+            &CallbackQuery::ShowFeedback {
+                event_id,
+                offset: offset.saturating_sub(1),
+            },
+            &CallbackQuery::ShowFeedback {
+                event_id,
+                offset: offset + 1,
+            },
+            responses.len() as u64,
+            ctx.config.presence_page_size,
+            offset,
+        )?
+        .into())
+}
+
+fn summary_line(s: &FeedbackSummary, user: &User) -> String {
+    t!(
+        user.lang,
+        "feedback-summary",
+        name = html::escape(&s.name),
+        start = format::ts(s.ts),
+        average = format!("{:.1}", s.average),
+        responses = s.responses,
+        surveyed = s.surveyed,
+        comments = s.comments
+    )
+}
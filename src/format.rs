@@ -1,8 +1,9 @@
-use crate::types::{Connection, Event};
+use crate::types::{Attendance, AttendanceStats, Connection, Event};
 use crate::types::{EventState, Participant};
 use chrono::{DateTime, NaiveDateTime, Utc};
 
 use crate::db;
+use crate::i18n::{Lang, Text};
 use db::EventStats;
 
 pub fn from_timestamp(ts: u64) -> DateTime<Utc> {
@@ -25,37 +26,83 @@ pub fn event_title(event: &Event) -> String {
     }
 }
 
+/// Amount in minor currency units, e.g. cents.
+pub fn price(amount: u64, currency: &str) -> String {
+    format!("{:.2} {}", amount as f64 / 100.0, currency)
+}
+
+pub fn reminder(event: &Event) -> Text {
+    text!(
+        "reminder",
+        link = event.link.as_str(),
+        name = event.name.as_str(),
+        start = ts(event.ts)
+    )
+}
+
 pub fn header(
     s: &EventStats,
     free_adults: i64,
     free_children: i64,
     is_admin: bool,
     no_age_distinction: bool,
+    lang: Lang,
 ) -> String {
     let mut header = format!(
-        "\n \n{}\nНачало: {}.",
+        "\n \n{}\n{}",
         event_title(&s.event),
-        ts(s.event.ts)
+        t!(lang, "event-start", start = ts(s.event.ts))
     );
     if is_admin {
         header.push_str(&format!(
-            " Мероприятие {} / {}({})",
-            s.event.id, s.event.max_adults, s.event.max_children
+            " {}",
+            t!(
+                lang,
+                "event-limits",
+                id = s.event.id,
+                adults = s.event.max_adults,
+                children = s.event.max_children
+            )
         ));
     }
 
-    if s.state == EventState::Open {
-        if no_age_distinction {
+    match s.state {
+        EventState::Open => {
+            if no_age_distinction {
+                header.push_str(&format!(
+                    " {}",
+                    t!(lang, "free-seats", seats = free_adults + free_children)
+                ));
+            } else {
+                header.push_str(&format!(
+                    "\n{}",
+                    t!(lang, "free-adult-seats", seats = free_adults)
+                ));
+                header.push_str(&format!(
+                    "\n{}",
+                    t!(lang, "free-child-seats", seats = free_children)
+                ));
+            }
+            if s.event.registration_closes_at != 0 {
+                header.push_str(&format!(
+                    "\n{}",
+                    t!(
+                        lang,
+                        "registration-closes",
+                        closes = ts(s.event.registration_closes_at)
+                    )
+                ));
+            }
+        }
+        EventState::Upcoming(opens_at) => {
             header.push_str(&format!(
-                " Свободные места: {}",
-                free_adults + free_children,
+                " {}",
+                t!(lang, "registration-opens", opens = ts(opens_at))
             ));
-        } else {
-            header.push_str(&format!("\nВзрослые свободные места: {}", free_adults));
-            header.push_str(&format!("\nДетские свободные места: {}", free_children));
         }
-    } else {
-        header.push_str(" Запись остановлена.");
+        EventState::Closed => {
+            header.push_str(&format!(" {}", t!(lang, "registration-closed")));
+        }
     }
     header
 }
@@ -65,12 +112,18 @@ pub fn participants(
     participants: &Vec<Participant>,
     is_admin: bool,
     no_age_distinction: bool,
+    lang: Lang,
 ) -> String {
     let mut list = "".to_string();
     if participants.len() != 0 {
         list.push_str(&format!(
-            "\n\nЗаписались {}({}):",
-            s.adults.reserved, s.children.reserved
+            "\n\n{}",
+            t!(
+                lang,
+                "participants",
+                adults = s.adults.reserved,
+                children = s.children.reserved
+            )
         ));
     }
 
@@ -115,6 +168,7 @@ pub fn messages(
     s: &EventStats,
     event_id: u64,
     is_admin: bool,
+    lang: Lang,
 ) -> Option<String> {
     let waiting_list = if is_admin {
         None
@@ -135,9 +189,9 @@ pub fn messages(
                             msg.sender,
                             ts(msg.ts),
                             if msg.waiting_list == 0 {
-                                "для забронировавших"
+                                t!(lang, "messages-for-reserved")
                             } else {
-                                "для списка ожидания"
+                                t!(lang, "messages-for-waiting-list")
                             },
                             msg.text
                         )
@@ -145,7 +199,8 @@ pub fn messages(
                 })
                 .collect();
             return Some(format!(
-                "\n\n<b>Cообщения по мероприятию</b>{}",
+                "\n\n<b>{}</b>{}",
+                t!(lang, "event-messages"),
                 formatted_list
             ));
         }
@@ -153,7 +208,80 @@ pub fn messages(
     None
 }
 
+fn attendance_line(title: &str, a: &Attendance, lang: Lang) -> String {
+    format!(
+        "\n{}",
+        t!(
+            lang,
+            "attendance-line",
+            title = title,
+            present = a.present,
+            reserved = a.reserved,
+            no_show = a.no_show
+        )
+    )
+}
+
+pub fn attendance(stats: &AttendanceStats, no_age_distinction: bool, lang: Lang) -> String {
+    let mut text = attendance_line(
+        &t!(lang, "attendance-participants"),
+        &stats.participants,
+        lang,
+    );
+    if no_age_distinction {
+        text.push_str(&attendance_line(
+            &t!(lang, "attendance-seats"),
+            &{
+                let mut seats = stats.adults.clone();
+                seats.reserved += stats.children.reserved;
+                seats.present += stats.children.present;
+                seats.no_show += stats.children.no_show;
+                seats
+            },
+            lang,
+        ));
+    } else {
+        text.push_str(&attendance_line(
+            &t!(lang, "attendance-adults"),
+            &stats.adults,
+            lang,
+        ));
+        text.push_str(&attendance_line(
+            &t!(lang, "attendance-children"),
+            &stats.children,
+            lang,
+        ));
+    }
+    text
+}
+
 #[test]
 fn test_format() {
     assert_eq!(ts(1650445814), "20.04 09:10");
+    let stats = AttendanceStats {
+        event_id: 1,
+        participants: Attendance {
+            reserved: 3,
+            present: 2,
+            no_show: 1,
+        },
+        adults: Attendance {
+            reserved: 4,
+            present: 3,
+            no_show: 1,
+        },
+        children: Attendance {
+            reserved: 1,
+            present: 1,
+            no_show: 0,
+        },
+    };
+    assert_eq!(
+        attendance(&stats, true, Lang::Ru),
+        "\nУчастники: пришли 2 из 3, не пришли 1.\nМеста: пришли 4 из 5, не пришли 1."
+    );
+    assert_eq!(
+        attendance(&stats, false, Lang::En),
+        "\nParticipants: 2 of 3 came, 1 did not.\nAdults: 3 of 4 came, 1 did not.\nChildren: 1 of 1 came, 0 did not."
+    );
 }
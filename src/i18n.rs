@@ -0,0 +1,220 @@
+use crate::db;
+use crate::templates;
+use crate::types::{Connection, User};
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+/// Message catalogs, the first one is the default.
+const CATALOGS: [(Lang, &str, &str); 2] = [
+    (Lang::Ru, "ru", include_str!("../locales/ru/bot.ftl")),
+    (Lang::En, "en", include_str!("../locales/en/bot.ftl")),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Lang {
+    #[default]
+    Ru,
+    En,
+}
+
+impl Lang {
+    /// Language of the Telegram client, Russian if it is unknown.
+    pub fn from_code(code: Option<&str>) -> Lang {
+        match code {
+            None => Lang::Ru,
+            Some(code) => Lang::parse(code).unwrap_or(Lang::En),
+        }
+    }
+
+    pub fn parse(code: &str) -> Option<Lang> {
+        let code = code.trim().to_lowercase();
+        CATALOGS
+            .iter()
+            .find(|(_, prefix, _)| code.starts_with(prefix))
+            .map(|(lang, _, _)| *lang)
+    }
+
+    pub fn code(&self) -> &'static str {
+        CATALOGS
+            .iter()
+            .find(|(lang, _, _)| lang == self)
+            .map(|(_, code, _)| *code)
+            .unwrap_or_default()
+    }
+}
+
+/// Arguments of a catalog message, strings or numbers.
+pub type Args = serde_json::Map<String, serde_json::Value>;
+
+/// Text rendered in the language of the reader: a catalog message, an organizer's template
+/// or a literal text. It is also an error, so that failures are shown to the user in their language.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Text {
+    Literal(String),
+    Message { key: String, args: Args },
+    Template { template: String, args: Args },
+}
+
+impl Text {
+    pub fn message(key: &str) -> Text {
+        Text::Message {
+            key: key.to_string(),
+            args: Args::new(),
+        }
+    }
+
+    pub fn arg<T: Into<serde_json::Value>>(mut self, name: &str, value: T) -> Text {
+        if let Text::Message { args, .. } | Text::Template { args, .. } = &mut self {
+            args.insert(name.to_string(), value.into());
+        }
+        self
+    }
+
+    pub fn render(&self, lang: Lang) -> String {
+        match self {
+            Text::Literal(text) => text.to_string(),
+            Text::Message { key, args } => translate(lang, key, args),
+            Text::Template { template, args } => templates::render(template, args),
+        }
+    }
+}
+
+impl std::fmt::Display for Text {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render(Lang::default()))
+    }
+}
+
+impl std::error::Error for Text {}
+
+/// Catalog message with arguments, e.g. `text!("event-start", start = format::ts(ts))`.
+macro_rules! text {
+    ($key:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::Text::message($key)$(.arg(stringify!($name), $value))*
+    };
+}
+
+/// Catalog message rendered in the language, e.g. `t!(user.lang, "event-start", start = ...)`.
+macro_rules! t {
+    ($lang:expr, $($message:tt)+) => {
+        text!($($message)+).render($lang)
+    };
+}
+
+/// Error message in the language of the user.
+pub fn error(e: &anyhow::Error, lang: Lang) -> String {
+    match e.downcast_ref::<Text>() {
+        Some(text) => text.render(lang),
+        None => e.to_string(),
+    }
+}
+
+/// Sets the language of the user: chosen with /language, or the one of the Telegram client.
+pub fn resolve(conn: &Connection, user: &mut User) {
+    match db::resolve_language(conn, user.id.0, user.lang) {
+        Ok(lang) => user.lang = lang,
+        Err(e) => error!("Failed to resolve language of {}: {}", user.id.0, e),
+    }
+}
+
+fn bundles() -> &'static Vec<(Lang, FluentBundle<FluentResource>)> {
+    static BUNDLES: OnceLock<Vec<(Lang, FluentBundle<FluentResource>)>> = OnceLock::new();
+    BUNDLES.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .map(|(lang, code, source)| {
+                let resource = FluentResource::try_new(source.to_string()).unwrap_or_else(
+                    |(resource, errors)| {
+                        error!("Errors in catalog {}: {:?}", code, errors);
+                        resource
+                    },
+                );
+                let id: LanguageIdentifier = code.parse().expect("Invalid language code");
+                let mut bundle = FluentBundle::new_concurrent(vec![id]);
+                // Telegram shows isolation marks around arguments as is.
+                bundle.set_use_isolating(false);
+                if let Err(errors) = bundle.add_resource(resource) {
+                    error!("Duplicate messages in catalog {}: {:?}", code, errors);
+                }
+                (*lang, bundle)
+            })
+            .collect()
+    })
+}
+
+/// Message of the catalog, the default catalog fills in missing messages.
+fn translate(lang: Lang, key: &str, args: &Args) -> String {
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        let value = match value {
+            serde_json::Value::String(s) => FluentValue::from(s.to_string()),
+            serde_json::Value::Number(n) => match n.as_f64() {
+                Some(n) => FluentValue::from(n),
+                None => FluentValue::from(n.to_string()),
+            },
+            value => FluentValue::from(value.to_string()),
+        };
+        fluent_args.set(name.to_string(), value);
+    }
+    let bundles = bundles();
+    let found = bundles
+        .iter()
+        .filter(|(l, _)| *l == lang)
+        .chain(bundles.iter().take(1))
+        .find_map(|(_, bundle)| bundle.get_message(key).map(|m| (bundle, m)));
+    match found.and_then(|(bundle, message)| message.value().map(|v| (bundle, v))) {
+        Some((bundle, pattern)) => {
+            let mut errors = vec![];
+            let text = bundle.format_pattern(pattern, Some(&fluent_args), &mut errors);
+            if !errors.is_empty() {
+                error!("Failed to format message {}: {:?}", key, errors);
+            }
+            text.to_string()
+        }
+        None => {
+            error!("Message {} not found", key);
+            key.to_string()
+        }
+    }
+}
+
+#[test]
+fn test_catalogs() {
+    use fluent_bundle::FluentResource;
+    use std::collections::BTreeSet;
+    let keys = |source: &str| {
+        FluentResource::try_new(source.to_string())
+            .expect("catalog parses")
+            .entries()
+            .filter_map(|entry| match entry {
+                fluent_syntax::ast::Entry::Message(m) => Some(m.id.name.to_string()),
+                _ => None,
+            })
+            .collect::<BTreeSet<String>>()
+    };
+    let default = keys(CATALOGS[0].2);
+    for (_, code, source) in CATALOGS.iter().skip(1) {
+        assert_eq!(keys(source), default, "messages of catalog {}", code);
+    }
+
+    // Every message used in the code is in the catalogs.
+    let usage = regex::Regex::new(r#"(?:\btext!\(|\bt!\([^,]+,)\s*"([a-z0-9-]+)""#).unwrap();
+    let mut sources = vec![std::path::PathBuf::from("src")];
+    while let Some(path) = sources.pop() {
+        if path.is_dir() {
+            sources.extend(std::fs::read_dir(path).unwrap().map(|e| e.unwrap().path()));
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            let source = std::fs::read_to_string(&path).unwrap();
+            for key in usage.captures_iter(&source) {
+                assert!(default.contains(&key[1]), "{} in {:?}", &key[1], path);
+            }
+        }
+    }
+
+    assert_eq!(Lang::from_code(Some("en-US")), Lang::En);
+    assert_eq!(Lang::from_code(Some("ru")), Lang::Ru);
+    assert_eq!(Lang::from_code(Some("de")), Lang::En);
+    assert_eq!(Lang::from_code(None), Lang::Ru);
+}
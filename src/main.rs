@@ -16,24 +16,40 @@ extern crate r2d2_sqlite;
 extern crate rusqlite;
 
 use teloxide::{
+    net::Download,
     prelude::*,
     types::{
-        InlineKeyboardButton, InlineKeyboardMarkup, LabeledPrice, MessageKind,
-        MessageSuccessfulPayment, ParseMode, PreCheckoutQuery, UserId,
+        InlineKeyboardButton, LabeledPrice, MessageKind, MessageSuccessfulPayment,
+        PreCheckoutQuery, UserId,
     },
     RequestError,
 };
 
+#[macro_use]
+mod i18n;
 mod admin_message_handler;
 mod api;
+mod bookings;
+mod broadcasts;
+mod calendar;
+mod check_in;
 mod configuration;
 mod db;
+mod feedback;
 mod format;
 mod message_handler;
+mod payment_providers;
 mod payments;
+mod promo_codes;
+mod questionnaire;
+mod refunds;
 mod reply;
+mod subscriptions;
+mod templates;
+mod tickets;
 mod types;
 mod util;
+mod waiting_list;
 
 use crate::api::setup_api_server;
 
@@ -58,7 +74,14 @@ async fn main() {
         db::create(&conn).expect("Failed to create db.");
     }
 
-    tokio::spawn(setup_api_server(&config.api_socket_address, &pool));
+    let payment_provider = payment_providers::from_config(&config);
+
+    tokio::spawn(setup_api_server(
+        &config.api_socket_address,
+        &pool,
+        &config,
+        &payment_provider,
+    ));
 
     let bot = Bot::new(&config.telegram_bot_token).auto_send();
 
@@ -75,6 +98,7 @@ async fn main() {
         config,
         pool,
         sign_up_mutex: Arc::new(Mutex::new(0u64)),
+        payment_provider,
     });
 
     tokio::spawn(perform_bulk_tasks(bot.clone(), context.clone()));
@@ -114,13 +138,22 @@ async fn message_handler(
                         return Ok(());
                     }
                     trace!("received {:?}", msg);
-                    let u = crate::types::User::new(&user, &context.config.admins);
+                    let mut u = crate::types::User::new(user, &context.config.admins);
                     if let Ok(conn) = context.pool.get() {
-                        let reply = if u.is_admin {
-                            crate::admin_message_handler::handle_message(&conn, &u, text, &context)
-                        } else {
-                            crate::message_handler::handle_message(&conn, &u, text, &context)
-                        };
+                        i18n::resolve(&conn, &mut u);
+                        drop(conn);
+                        let media = msg.reply_to_message().and_then(broadcasts::media);
+                        let (user, text) = (u.clone(), text.to_string());
+                        let reply = handle_blocking(&context, move |conn, context| {
+                            if user.is_admin {
+                                crate::admin_message_handler::handle_message(
+                                    conn, &user, &text, media, context,
+                                )
+                            } else {
+                                crate::message_handler::handle_message(conn, &user, &text, context)
+                            }
+                        })
+                        .await;
                         match reply {
                             Ok(reply) => match reply {
                                 Reply::Message(r) => {
@@ -158,7 +191,38 @@ async fn message_handler(
                             },
                             Err(e) => {
                                 error!("Error in reply: {}", e);
-                                bot.send_message(msg.chat.id, e.to_string()).await?;
+                                bot.send_message(msg.chat.id, i18n::error(&e, u.lang))
+                                    .await?;
+                            }
+                        }
+                    }
+                }
+            } else if let (Some(photos), Some(user)) = (msg.photo(), msg.from()) {
+                // Photo of a ticket QR code during check-in.
+                let mut u = crate::types::User::new(user, &context.config.admins);
+                let in_session = context
+                    .pool
+                    .get()
+                    .map(|conn| db::get_check_in_event(&conn, u.id.0).unwrap_or(0) != 0)
+                    .unwrap_or(false);
+                if let (true, Some(photo)) = (in_session, photos.iter().max_by_key(|p| p.width)) {
+                    let file = bot.get_file(&photo.file_id).await?;
+                    let mut data = Vec::new();
+                    if let Err(e) = bot.download_file(&file.file_path, &mut data).await {
+                        error!("Failed to download photo: {}", e);
+                        return Ok(());
+                    }
+                    if let Ok(conn) = context.pool.get() {
+                        i18n::resolve(&conn, &mut u);
+                        match crate::check_in::handle_photo(&conn, &u, &data, &context) {
+                            Ok(Some(Reply::Message(r))) => {
+                                r.send(&msg, &bot).await?;
+                            }
+                            Ok(_) => {}
+                            Err(e) => {
+                                error!("Error in reply: {}", e);
+                                bot.send_message(msg.chat.id, i18n::error(&e, u.lang))
+                                    .await?;
                             }
                         }
                     }
@@ -167,11 +231,41 @@ async fn message_handler(
         }
         MessageKind::SuccessfulPayment(MessageSuccessfulPayment { successful_payment }) => {
             trace!("successful_payment {:?}", &successful_payment);
-            if let Ok(conn) = context.pool.get() {
+            if let (Ok(conn), Some(user)) = (context.pool.get(), msg.from()) {
+                let mut u = crate::types::User::new(user, &context.config.admins);
+                i18n::resolve(&conn, &mut u);
                 let res = crate::payments::checkout(&conn, successful_payment, &context);
-                if let Err(e) = res {
-                    error!("Failed to check out: {}", e);
-                    bot.send_message(msg.chat.id, e.to_string()).await?;
+                match res {
+                    Ok(booking) => {
+                        // Ask attendee names and registration questions if the event has any.
+                        let dialog = crate::message_handler::start_dialog(
+                            &conn,
+                            &u,
+                            booking.event_id,
+                            &context,
+                        )
+                        .unwrap_or_else(|e| {
+                            error!("Failed to start dialog: {}", e);
+                            None
+                        });
+                        let has_dialog = dialog.is_some();
+                        let reply = dialog.unwrap_or_else(|| {
+                            ReplyMessage::new(t!(u.lang, "payment-received")).into()
+                        });
+                        // Send QR tickets along with the dialog, or alone if there is none.
+                        match crate::tickets::attach_new(&conn, &u, booking.event_id, reply) {
+                            Ok(Reply::Message(r)) if has_dialog || !r.images.is_empty() => {
+                                r.send(&msg, &bot).await?;
+                            }
+                            Ok(_) => {}
+                            Err(e) => error!("Failed to issue tickets: {}", e),
+                        }
+                    }
+                    Err(e) => {
+                        error!("Failed to check out: {}", e);
+                        bot.send_message(msg.chat.id, i18n::error(&e, u.lang))
+                            .await?;
+                    }
                 }
             }
         }
@@ -182,6 +276,18 @@ async fn message_handler(
     Ok(())
 }
 
+/// Runs the handler on the blocking thread pool with a connection of its own,
+/// handlers query the database and payment services synchronously.
+async fn handle_blocking<F>(context: &Arc<Context>, handler: F) -> anyhow::Result<Reply>
+where
+    F: FnOnce(&types::Connection, &Context) -> anyhow::Result<Reply> + Send + 'static,
+{
+    let context = context.clone();
+    tokio::task::spawn_blocking(move || handler(&context.pool.get()?, &context))
+        .await
+        .unwrap_or_else(|e| Err(e.into()))
+}
+
 async fn callback_handler(
     q: CallbackQuery,
     bot: AutoSend<Bot>,
@@ -190,7 +296,7 @@ async fn callback_handler(
     match (q.message, q.data) {
         (Some(msg), Some(data)) => {
             trace!("received {:?} {:?}", &msg, &data);
-            let u = crate::types::User::new(&q.from, &context.config.admins);
+            let mut u = crate::types::User::new(&q.from, &context.config.admins);
             let mut lock;
             if data.starts_with("sign_up ") {
                 lock = context.sign_up_mutex.lock().await;
@@ -198,11 +304,17 @@ async fn callback_handler(
                 // todo: use event based locking
             }
             if let Ok(conn) = context.pool.get() {
-                let reply = if u.is_admin {
-                    crate::admin_message_handler::handle_callback(&conn, &u, &data, &context)
-                } else {
-                    crate::message_handler::handle_callback(&conn, &u, &data, &context)
-                };
+                i18n::resolve(&conn, &mut u);
+                drop(conn);
+                let user = u.clone();
+                let reply = handle_blocking(&context, move |conn, context| {
+                    if user.is_admin {
+                        crate::admin_message_handler::handle_callback(conn, &user, &data, context)
+                    } else {
+                        crate::message_handler::handle_callback(conn, &user, &data, context)
+                    }
+                })
+                .await;
                 match reply {
                     Ok(reply) => match reply {
                         Reply::Message(r) => {
@@ -241,7 +353,8 @@ async fn callback_handler(
                     },
                     Err(e) => {
                         error!("Error in reply: {}", e);
-                        bot.send_message(msg.chat.id, e.to_string()).await?;
+                        bot.send_message(msg.chat.id, i18n::error(&e, u.lang))
+                            .await?;
                     }
                 }
             }
@@ -291,8 +404,9 @@ async fn pre_checkout_handler(
     context: Arc<Context>,
 ) -> Result<(), RequestError> {
     trace!("pre_checkout_handler::received {:?}", pre_checkout);
-    let u = crate::types::User::new(&pre_checkout.from, &context.config.admins);
+    let mut u = crate::types::User::new(&pre_checkout.from, &context.config.admins);
     if let Ok(conn) = context.pool.get() {
+        i18n::resolve(&conn, &mut u);
         let mut lock = context.sign_up_mutex.lock().await;
         *lock = *lock + 1;
 
@@ -304,7 +418,7 @@ async fn pre_checkout_handler(
             Err(e) => {
                 bot.answer_pre_checkout_query(pre_checkout.id, false)
                     .await?;
-                bot.send_message(u.id, e.to_string()).await?;
+                bot.send_message(u.id, i18n::error(&e, u.lang)).await?;
             }
         }
     }
@@ -319,6 +433,7 @@ async fn perform_bulk_tasks(bot: AutoSend<Bot>, ctx: Arc<Context>) -> Result<boo
 
         let mut notifications = 0;
         let mut batch_contains_waiting_list_prompt = false;
+        let mut interrupted = false;
         let ts = get_unix_time();
         let num_seconds_from_midnight = ts % 86400;
 
@@ -338,40 +453,95 @@ async fn perform_bulk_tasks(bot: AutoSend<Bot>, ctx: Arc<Context>) -> Result<boo
                 Vec::new()
             };
 
-            for m in messages {
+            'sending: for m in messages {
                 notifications += m.recipients.len();
-                let keyboard: Vec<Vec<InlineKeyboardButton>> =
-                    vec![vec![InlineKeyboardButton::callback(
-                        "К мероприятию",
-                        if m.is_paid {
-                            serde_json::to_string(&message_handler::CallbackQuery::PaidEvent {
-                                event_id: m.event_id,
-                                adults: 0,
-                                children: 0,
-                                offset: 0,
-                            })
-                        } else {
-                            serde_json::to_string(&message_handler::CallbackQuery::Event {
-                                event_id: m.event_id,
-                                offset: 0,
-                            })
-                        }
-                        .unwrap(),
-                    )]];
-                let keyboard = InlineKeyboardMarkup::new(keyboard);
+                let event_query = if m.is_paid {
+                    serde_json::to_string(&message_handler::CallbackQuery::PaidEvent {
+                        event_id: m.event_id,
+                        adults: 0,
+                        children: 0,
+                        offset: 0,
+                    })
+                } else {
+                    serde_json::to_string(&message_handler::CallbackQuery::Event {
+                        event_id: m.event_id,
+                        offset: 0,
+                    })
+                }
+                .unwrap();
                 for u in m.recipients {
-                    debug!("Sending notification {} to {} {}", m.message_id, u, &m.text);
-                    bot.send_message(UserId(u), &m.text)
-                        .parse_mode(ParseMode::Html)
-                        .disable_web_page_preview(true)
-                        .reply_markup(keyboard.clone())
-                        .await?;
+                    // Rendered in the language and with the name of the recipient.
+                    let (lang, text) = match ctx.pool.get() {
+                        Ok(conn) => (
+                            db::get_language(&conn, u).unwrap_or_default(),
+                            templates::personalize(&conn, &m.text, m.event_id, u),
+                        ),
+                        Err(_) => (i18n::Lang::default(), m.text.clone()),
+                    };
+                    let text = text.render(lang);
+                    // Broadcasts are not about an event, surveys are answered with the rating.
+                    let keyboard: Vec<Vec<InlineKeyboardButton>> =
+                        if m.message_type == MessageType::Feedback {
+                            feedback::controls(m.event_id).unwrap_or_default()
+                        } else if m.event_id != 0 {
+                            vec![vec![InlineKeyboardButton::callback(
+                                t!(lang, "to-event"),
+                                event_query.to_owned(),
+                            )]]
+                        } else {
+                            Vec::new()
+                        };
+                    debug!("Sending notification {} to {} {}", m.message_id, u, &text);
+                    let failure = match broadcasts::deliver(
+                        &bot,
+                        UserId(u),
+                        text,
+                        m.media.as_ref(),
+                        keyboard,
+                    )
+                    .await
+                    {
+                        Ok(()) => None,
+                        Err(e) => match broadcasts::failure_reason(&e) {
+                            Some(reason) => {
+                                warn!("Failed to send message {} to {}: {}", m.message_id, u, e);
+                                Some(reason)
+                            }
+                            None => {
+                                // Left pending, sending resumes with the next round.
+                                error!("Failed to send message {} to {}: {}", m.message_id, u, e);
+                                interrupted = true;
+                                break 'sending;
+                            }
+                        },
+                    };
 
                     if let Ok(conn) = ctx.pool.get() {
-                        if let Err(e) = db::save_receipt(&conn, m.message_id, u) {
+                        let res = match &failure {
+                            Some(reason) => db::save_failure(&conn, m.message_id, u, reason),
+                            None => db::save_receipt(&conn, m.message_id, u),
+                        };
+                        if let Err(e) = res {
                             error!("Failed to save receipt: {}", e);
                         }
                     }
+                    if failure.is_none() && m.message_type == MessageType::Tickets {
+                        let images = ctx
+                            .pool
+                            .get()
+                            .map_err(anyhow::Error::from)
+                            .and_then(|conn| tickets::issue(&conn, u, m.event_id, lang));
+                        match images {
+                            Ok(images) => {
+                                if let Err(e) =
+                                    reply::send_images(images, ChatId(u as i64), &bot).await
+                                {
+                                    error!("Failed to send tickets to {}: {}", u, e);
+                                }
+                            }
+                            Err(e) => error!("Failed to issue tickets for {}: {}", u, e),
+                        }
+                    }
                     if m.message_type == MessageType::WaitingListPrompt {
                         batch_contains_waiting_list_prompt = true;
                     }
@@ -379,6 +549,15 @@ async fn perform_bulk_tasks(bot: AutoSend<Bot>, ctx: Arc<Context>) -> Result<boo
             }
         }
 
+        // Survey participants before the events are cleaned up.
+        if let Some(delay) = ctx.config.feedback_after {
+            if let Ok(conn) = ctx.pool.get() {
+                if let Err(e) = db::schedule_feedback(&conn, ts, delay) {
+                    error!("Failed to schedule feedback surveys at {}: {}", ts, e);
+                }
+            }
+        }
+
         if ctx.config.cleanup_old_events {
             if let Ok(conn) = ctx.pool.get() {
                 // Clean up.
@@ -410,14 +589,18 @@ async fn perform_bulk_tasks(bot: AutoSend<Bot>, ctx: Arc<Context>) -> Result<boo
 
         // Clear failed payments.
         if let Ok(conn) = ctx.pool.get() {
-            if db::clear_failed_payments(&conn, ts - 5 * 60).is_ok() == false {
+            if db::clear_failed_payments(&conn, ts - ctx.payment_provider.hold()).is_err() {
                 error!("Failed to clear failed payments at {}", ts);
             }
+            // Pass seats held too long to the next in the waiting list.
+            if let Err(e) = db::expire_offers(&conn, ts) {
+                error!("Failed to expire waiting list offers at {}: {}", ts, e);
+            }
         }
 
         next_break = tokio::time::Instant::now()
             + Duration::from_millis(
-                if notifications > 0 && batch_contains_waiting_list_prompt == false {
+                if notifications > 0 && !batch_contains_waiting_list_prompt && !interrupted {
                     1000
                 } else {
                     30000
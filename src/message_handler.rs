@@ -1,4 +1,5 @@
 use crate::get_unix_time;
+use crate::i18n::Lang;
 use crate::payments::{donate, prepare_invoice, show_paid_event};
 use crate::reply::*;
 use crate::types::{Connection, Context, EventState, EventType, ReservationState, User};
@@ -6,8 +7,18 @@ use anyhow::anyhow;
 use teloxide::{types::InlineKeyboardButton, utils::html};
 use url::Url;
 
+use crate::bookings;
+use crate::calendar;
+use crate::check_in;
 use crate::db;
+use crate::feedback;
 use crate::format;
+use crate::promo_codes;
+use crate::questionnaire;
+use crate::refunds;
+use crate::subscriptions;
+use crate::tickets;
+use crate::waiting_list;
 use db::EventStats;
 
 use serde_compact::compact;
@@ -30,33 +41,73 @@ pub fn handle_message(
                 // Direct link
                 if pars[1].starts_with("donate_") {
                     if let Ok(amount) = pars[1][7..].parse::<u64>() {
-                        return donate(user, amount, ctx);
+                        return donate(conn, user, amount, ctx);
                     }
+                } else if let Some(code) = pars[1].strip_prefix('t') {
+                    // Scanned ticket
+                    return check_in::handle_link(conn, user, code, ctx);
                 } else {
                     if let Ok(event_id) = pars[1].parse::<u64>() {
                         return show_event(conn, user, event_id, ctx, None, 0);
                     }
                 }
             } else {
-                return show_event_list(conn, user.id.0, ctx, 0);
+                return show_event_list(conn, user, ctx, 0);
             }
         }
         "/donate" => {
-            return donate(user, 500, ctx);
+            return donate(conn, user, 500, ctx);
+        }
+        "/my" => {
+            // /my [past]
+            return bookings::show_my_bookings(conn, user, pars.get(1) == Some(&"past"), ctx);
+        }
+        "/calendar" => {
+            // /calendar [reset]
+            return calendar::show_subscription(conn, user, pars.get(1) == Some(&"reset"), ctx);
+        }
+        "/subscriptions" => {
+            return subscriptions::show(conn, user, ctx);
+        }
+        "/my_tickets" => {
+            return tickets::show_my_tickets(conn, user, None, ctx);
+        }
+        "/language" => {
+            // /language [ru|en]
+            return match pars.get(1).and_then(|code| Lang::parse(code)) {
+                Some(lang) => match db::set_language(conn, user.id.0, lang) {
+                    Ok(_) => Ok(ReplyMessage::new(t!(lang, "language-set")).into()),
+                    Err(e) => Err(anyhow!("Failed to set language: {}.", e)),
+                },
+                None => Ok(ReplyMessage::new(t!(user.lang, "language-usage")).into()),
+            };
         }
         "/help" => {
-            return Ok(ReplyMessage::new(format!(
-                "Здесь вы можете бронировать места на мероприятия.\n \
-                            \n /start - показать список мероприятий \
-                            \n /help - эта подсказка \
-                            \n <a href=\"{}\">Подробная инструкция</a> \
-                            \n /donate - поддержать канал.",
-                ctx.config.help
-            ))
-            .into());
+            return Ok(
+                ReplyMessage::new(t!(user.lang, "help", help = ctx.config.help.as_str())).into(),
+            );
         }
         _ => {
-            // Message from user - try to add as attachment to the last reservation.
+            // Message from user - try to check in a ticket, name attendees and answer the questionnaire first.
+            if let Some(reply) = check_in::handle_text(conn, user, data, ctx)? {
+                return Ok(reply);
+            }
+            if let Some(reply) = handle_rejection_reason(conn, user, data, ctx)? {
+                return Ok(reply);
+            }
+            if let Some(reply) = promo_codes::handle_text(conn, user, data, ctx)? {
+                return Ok(reply);
+            }
+            if let Some(reply) = feedback::handle_text(conn, user, data, ctx)? {
+                return Ok(reply);
+            }
+            if let Some(reply) = tickets::handle_text(conn, user, data, ctx)? {
+                return Ok(reply);
+            }
+            if let Some(reply) = questionnaire::handle_text_answer(conn, user, data, ctx)? {
+                return Ok(reply);
+            }
+            // Otherwise add as attachment to the last reservation.
             return add_attachment(conn, &user, data, ctx);
         }
     }
@@ -98,6 +149,34 @@ pub enum CallbackQuery {
         user_id: u64,
         offset: u64,
     },
+    ShowPresentList {
+        event_id: u64,
+        offset: u64,
+    },
+    UnconfirmPresence {
+        event_id: u64,
+        user_id: u64,
+        offset: u64,
+    },
+    ShowApprovalQueue {
+        event_id: u64,
+        offset: u64,
+    },
+    Approve {
+        event_id: u64,
+        user_id: u64,
+        offset: u64,
+    },
+    Reject {
+        event_id: u64,
+        user_id: u64,
+        offset: u64,
+    },
+    RejectWithoutReason {
+        event_id: u64,
+        user_id: u64,
+        offset: u64,
+    },
     PaidEvent {
         event_id: u64,
         adults: u64,
@@ -109,6 +188,110 @@ pub enum CallbackQuery {
         adults: u64,
         children: u64,
     },
+    RequestApproval {
+        event_id: u64,
+        adults: u64,
+        children: u64,
+    },
+    Questionnaire {
+        event_id: u64,
+    },
+    AnswerQuestion {
+        event_id: u64,
+        question: u64,
+        option: u64,
+    },
+    ConfirmAnswer {
+        event_id: u64,
+        question: u64,
+    },
+    ShowAnswers {
+        event_id: u64,
+        offset: u64,
+    },
+    Tickets {
+        event_id: u64,
+    },
+    ClearTickets {
+        event_id: u64,
+    },
+    MyTickets {
+        event_id: u64,
+    },
+    MyBookings {
+        past: bool,
+    },
+    Calendar {
+        event_id: u64,
+    },
+    SendBroadcast {
+        message_id: u64,
+        send_at: u64,
+    },
+    CancelBroadcast {
+        message_id: u64,
+    },
+    DeliveryReport {
+        message_id: u64,
+    },
+    RateEvent {
+        event_id: u64,
+        rating: u64,
+    },
+    EventSubscriptions {
+        event_id: u64,
+    },
+    ToggleSubscription {
+        event_id: u64,
+        index: u64,
+    },
+    Unsubscribe {
+        subscription_id: u64,
+    },
+    SkipFeedbackComment {
+        event_id: u64,
+    },
+    ShowFeedbackSummaries {
+        offset: u64,
+    },
+    ShowFeedback {
+        event_id: u64,
+        offset: u64,
+    },
+    CheckIn {
+        event_id: u64,
+    },
+    StopCheckIn {
+        event_id: u64,
+    },
+    AcceptOffer {
+        event_id: u64,
+    },
+    LeaveWaitingList {
+        event_id: u64,
+    },
+    RequestRefund {
+        event_id: u64,
+    },
+    EnterPromoCode {
+        event_id: u64,
+        adults: u64,
+        children: u64,
+    },
+    ShowRefunds {
+        event_id: u64,
+        offset: u64,
+    },
+    ApproveRefund {
+        event_id: u64,
+        user_id: u64,
+        offset: u64,
+    },
+    RejectRefund {
+        event_id: u64,
+        user_id: u64,
+        offset: u64,
+    },
 
     // admin callbacks
     ChangeEventState {
@@ -136,7 +319,7 @@ pub fn handle_callback(
     if let Ok(q) = serde_json::from_str::<CallbackQuery>(&data) {
         use CallbackQuery::*;
         match q {
-            EventList { offset } => show_event_list(conn, user.id.0, ctx, offset),
+            EventList { offset } => show_event_list(conn, user, ctx, offset),
             Event { event_id, offset } => show_event(conn, user, event_id, ctx, None, offset),
             SignUp {
                 event_id,
@@ -153,20 +336,43 @@ pub fn handle_callback(
                     get_unix_time(),
                     0,
                 ) {
+                    Ok((_, false))
+                        if wait
+                            && db::get_event(conn, event_id, user.id.0)?.event.get_type()
+                                == EventType::Paid =>
+                    {
+                        // Paid waiting list, the seat is paid once offered.
+                        show_paid_event(event_id, 0, 0, 0, conn, user, ctx)
+                    }
+                    Ok((_, false)) => {
+                        let reply = match start_dialog(conn, user, event_id, ctx)? {
+                            Some(reply) => reply,
+                            None => show_event(conn, user, event_id, ctx, None, 0)?,
+                        };
+                        tickets::attach_new(conn, user, event_id, reply)
+                    }
                     Ok((_, black_listed)) => show_event(
                         conn,
                         user,
                         event_id,
                         ctx,
                         if black_listed {
-                            Some(format!("\n\nИзвините, но бронирование невозможно, поскольку ранее Вы не использовали и не отменили бронь. \
-                                    Если это ошибка, пожалуйста, свяжитесь с <a href=\"tg://user?id={}\">поддержкой</a> и сообщите код {}. <a href=\"{}\">Инструкция</a>.", ctx.config.support, user.id, ctx.config.help))
+                            Some(format!(
+                                "\n\n{}",
+                                t!(
+                                    user.lang,
+                                    "booking-blocked",
+                                    support = ctx.config.support.as_str(),
+                                    code = user.id.to_string(),
+                                    help = ctx.config.help.as_str(),
+                                )
+                            ))
                         } else {
                             None
                         },
                         0,
                     ),
-                    Err(e) => Err(anyhow!("{}", e)),
+                    Err(e) => Err(e),
                 }
             }
             Cancel { event_id, is_adult } => {
@@ -201,7 +407,10 @@ pub fn handle_callback(
                                             user.id
                                         ));
                                     }
-                                    ps = Some(format!("\n\nВНИМАНИЕ!\nК сожалению, вы отказались от билетов слишком поздно и не сможете больше бронировать бесплатные билеты."));
+                                    ps = Some(format!(
+                                        "\n\n{}",
+                                        t!(user.lang, "late-cancel-banned")
+                                    ));
                                 }
                             }
                         }
@@ -210,18 +419,16 @@ pub fn handle_callback(
                     Err(e) => Err(anyhow!("Failed to cancel reservation: {}.", e)),
                 }
             }
-            WontGo { event_id } => {
-                match db::wontgo(conn, event_id, user.id.0) {
-                    Ok(_) => {
-                        if is_too_late_to_cancel(conn, event_id, user, ctx) {
-                            Ok(ReplyMessage::new("К сожалению, вы отказываетесь от билетов слишком поздно и не сможете больше бронировать бесплатные билеты.".to_string()).into())
-                        } else {
-                            Ok(ReplyMessage::new("Мы сожалеем, что вы не сможете пойти. Увидимся в другой раз. Спасибо!".to_string()).into())
-                        }
+            WontGo { event_id } => match db::wontgo(conn, event_id, user.id.0) {
+                Ok(_) => {
+                    if is_too_late_to_cancel(conn, event_id, user, ctx) {
+                        Ok(ReplyMessage::new(t!(user.lang, "late-wont-go")).into())
+                    } else {
+                        Ok(ReplyMessage::new(t!(user.lang, "wont-go")).into())
                     }
-                    Err(e) => Err(anyhow!("Failed to add event: {}.", e)),
                 }
-            }
+                Err(e) => Err(anyhow!("Failed to add event: {}.", e)),
+            },
             ShowWaitingList { event_id, offset } => {
                 if ctx.config.public_lists || user.is_admin != false {
                     show_waiting_list(conn, user, event_id, ctx, offset)
@@ -237,15 +444,7 @@ pub fn handle_callback(
                 user_id,
                 offset,
             } => {
-                let user_has_permissions = if user.is_admin {
-                    true
-                } else {
-                    match db::is_group_leader(conn, event_id, user.id.0) {
-                        Ok(res) => res,
-                        Err(_) => false,
-                    }
-                };
-                if user_has_permissions {
+                if is_manager(conn, event_id, user) {
                     match db::confirm_presence(conn, event_id, user_id) {
                         Ok(_) => show_presence_list(conn, event_id, user, ctx, offset),
                         Err(e) => Err(anyhow!("Failed to confirm presence: {}.", e)),
@@ -254,6 +453,27 @@ pub fn handle_callback(
                     Err(anyhow!("not allowed"))
                 }
             }
+            ShowPresentList { event_id, offset } => {
+                if is_manager(conn, event_id, user) {
+                    show_present_list(conn, event_id, user, ctx, offset)
+                } else {
+                    Err(anyhow!("not allowed"))
+                }
+            }
+            UnconfirmPresence {
+                event_id,
+                user_id,
+                offset,
+            } => {
+                if is_manager(conn, event_id, user) {
+                    match db::unconfirm_presence(conn, event_id, user_id) {
+                        Ok(_) => show_present_list(conn, event_id, user, ctx, offset),
+                        Err(e) => Err(anyhow!("Failed to unconfirm presence: {}.", e)),
+                    }
+                } else {
+                    Err(anyhow!("not allowed"))
+                }
+            }
             PaidEvent {
                 event_id,
                 adults,
@@ -265,6 +485,133 @@ pub fn handle_callback(
                 adults,
                 children,
             } => prepare_invoice(event_id, adults, children, conn, user, ctx),
+            RequestApproval {
+                event_id,
+                adults,
+                children,
+            } => match db::sign_up(
+                conn,
+                event_id,
+                user,
+                adults,
+                children,
+                0,
+                get_unix_time(),
+                0,
+            ) {
+                Ok(_) => match start_dialog(conn, user, event_id, ctx)? {
+                    Some(reply) => Ok(reply),
+                    None => show_paid_event(event_id, 0, 0, 0, conn, user, ctx),
+                },
+                Err(e) => Err(e),
+            },
+            Questionnaire { event_id } => {
+                questionnaire::show_questionnaire(conn, user, event_id, ctx)
+            }
+            AnswerQuestion {
+                event_id,
+                question,
+                option,
+            } => questionnaire::answer_choice(conn, user, event_id, question, option, ctx),
+            ConfirmAnswer { event_id, question } => {
+                questionnaire::confirm_answer(conn, user, event_id, question, ctx)
+            }
+            Tickets { event_id } => tickets::show_tickets(conn, user, event_id, ctx),
+            MyTickets { event_id } => tickets::show_my_tickets(conn, user, Some(event_id), ctx),
+            MyBookings { past } => bookings::show_my_bookings(conn, user, past, ctx),
+            Calendar { event_id } => calendar::send_event(conn, user, event_id, ctx),
+            RateEvent { event_id, rating } => feedback::rate(conn, user, event_id, rating, ctx),
+            EventSubscriptions { event_id } => {
+                subscriptions::show_event_subscriptions(conn, user, event_id, ctx)
+            }
+            ToggleSubscription { event_id, index } => {
+                subscriptions::toggle(conn, user, event_id, index, ctx)
+            }
+            Unsubscribe { subscription_id } => {
+                subscriptions::unsubscribe(conn, user, subscription_id, ctx)
+            }
+            SkipFeedbackComment { event_id } => feedback::skip_comment(conn, user, event_id, ctx),
+            CheckIn { event_id } => check_in::start(conn, user, event_id, ctx),
+            StopCheckIn { event_id } => check_in::stop(conn, user, event_id, ctx),
+            AcceptOffer { event_id } => waiting_list::accept(conn, user, event_id, ctx),
+            LeaveWaitingList { event_id } => waiting_list::leave(conn, user, event_id, ctx),
+            RequestRefund { event_id } => refunds::request(conn, user, event_id, ctx),
+            EnterPromoCode {
+                event_id,
+                adults,
+                children,
+            } => promo_codes::enter(conn, user, event_id, adults, children, ctx),
+            ShowRefunds { event_id, offset } => {
+                refunds::show_requests(conn, user, event_id, offset, ctx)
+            }
+            ApproveRefund {
+                event_id,
+                user_id,
+                offset,
+            } => refunds::approve(conn, user, event_id, user_id, offset, ctx),
+            RejectRefund {
+                event_id,
+                user_id,
+                offset,
+            } => refunds::reject(conn, user, event_id, user_id, offset, ctx),
+            ClearTickets { event_id } => match db::clear_tickets(conn, event_id, user.id.0) {
+                Ok(_) => match tickets::start(conn, user, event_id, ctx)? {
+                    Some(reply) => Ok(reply),
+                    None => tickets::show_tickets(conn, user, event_id, ctx),
+                },
+                Err(e) => Err(anyhow!("Failed to clear names: {}", e)),
+            },
+            ShowAnswers { event_id, offset } => {
+                if is_manager(conn, event_id, user) {
+                    questionnaire::show_answers(conn, event_id, user, ctx, offset)
+                } else {
+                    Err(anyhow!("not allowed"))
+                }
+            }
+            ShowApprovalQueue { event_id, offset } => {
+                if is_manager(conn, event_id, user) {
+                    db::set_rejection_session(conn, user.id.0, 0, 0, 0)?;
+                    show_approval_queue(conn, event_id, user, ctx, offset)
+                } else {
+                    Err(anyhow!("not allowed"))
+                }
+            }
+            Approve {
+                event_id,
+                user_id,
+                offset,
+            } => {
+                if is_manager(conn, event_id, user) {
+                    db::approve_reservation(conn, event_id, user_id)?;
+                    show_approval_queue(conn, event_id, user, ctx, offset)
+                } else {
+                    Err(anyhow!("not allowed"))
+                }
+            }
+            Reject {
+                event_id,
+                user_id,
+                offset,
+            } => {
+                if is_manager(conn, event_id, user) {
+                    ask_rejection_reason(conn, event_id, user_id, user, offset)
+                } else {
+                    Err(anyhow!("not allowed"))
+                }
+            }
+            RejectWithoutReason {
+                event_id,
+                user_id,
+                offset,
+            } => {
+                if is_manager(conn, event_id, user) {
+                    db::set_rejection_session(conn, user.id.0, 0, 0, 0)?;
+                    db::reject_reservation(conn, event_id, user_id, None)?;
+                    show_approval_queue(conn, event_id, user, ctx, offset)
+                } else {
+                    Err(anyhow!("not allowed"))
+                }
+            }
             _ => Err(anyhow!("Not allowed.")),
         }
     } else {
@@ -289,7 +636,7 @@ pub fn add_attachment(
                         event_id,
                         ctx,
                         if data.chars().any(char::is_numeric) {
-                            Some("\n\nВНИМАНИЕ!\nВаше примечание содержит цифры. Они никак не влияют на количество забронированных мест. Количество мест можно менять только кнопками \"Записать/Отписать\".".to_string())
+                            Some(format!("\n\n{}", t!(user.lang, "attachment-has-digits")))
                         } else {
                             None
                         },
@@ -305,91 +652,107 @@ pub fn add_attachment(
 
 pub fn show_event_list(
     conn: &Connection,
-    user_id: u64,
+    user: &User,
     ctx: &Context,
     offset: u64,
 ) -> anyhow::Result<Reply> {
-    match db::get_events(conn, user_id, offset, ctx.config.event_list_page_size) {
+    match db::get_events(conn, user.id.0, offset, ctx.config.event_list_page_size) {
         Ok(events) => {
             Ok(
                 // header
-                ReplyMessage::new(
-                    if offset != 0 || events.len() != 0 {
-                        format!("Программа\nвремя / взросл.(детск.) места  / мероприятие\n<a href=\"{}\">инструкция</a> /donate", ctx.config.help)
-                    } else {
-                        "Нет мероприятий.".to_string()
-                    }
-                )
+                ReplyMessage::new(if offset != 0 || !events.is_empty() {
+                    t!(user.lang, "event-list", help = ctx.config.help.as_str())
+                } else {
+                    t!(user.lang, "no-events")
+                })
                 .keyboard(
                     events
-                    .iter()
-                    .map(|s| {
-                        let event_type = s.event.get_type();
-                        if event_type == EventType::Announcement {
-                            if let Ok(url) = Url::parse(&s.event.link) {
-                                vec![InlineKeyboardButton::url(format!("ℹ️ {} {}", format::ts(s.event.ts), s.event.name), url)]
+                        .iter()
+                        .map(|s| {
+                            let event_type = s.event.get_type();
+                            if event_type == EventType::Announcement {
+                                if let Ok(url) = Url::parse(&s.event.link) {
+                                    vec![InlineKeyboardButton::url(
+                                        format!("ℹ️ {} {}", format::ts(s.event.ts), s.event.name),
+                                        url,
+                                    )]
+                                } else {
+                                    vec![]
+                                }
                             } else {
-                                vec![]
-                            }
-                        } else {
-                            vec![InlineKeyboardButton::callback(
-                                format!(
-                                    "{} {} / {} / {}",
-                                    if s.adults.my_reservation != 0 || s.children.my_reservation != 0 {
-                                        "✅"
-                                    } else if s.adults.my_waiting != 0 || s.children.my_waiting != 0 {
-                                        "⏳"
-                                    } else if event_type != EventType::Paid {
-                                        "✨" // todo: find a better emoji for free events
-                                    } else {
-                                        ""
-                                    },
-                                    format::ts(s.event.ts),
-                                    if s.state == EventState::Open {
-                                        if s.event.max_adults == 0 || s.event.max_children == 0 {
-                                            (s.event.max_adults - s.adults.reserved + s.event.max_children
-                                                - s.children.reserved)
-                                                .to_string()
+                                vec![InlineKeyboardButton::callback(
+                                    format!(
+                                        "{} {} / {} / {}",
+                                        if s.adults.my_reservation != 0
+                                            || s.children.my_reservation != 0
+                                        {
+                                            "✅"
+                                        } else if s.adults.my_waiting != 0
+                                            || s.children.my_waiting != 0
+                                        {
+                                            "⏳"
+                                        } else if s.adults.my_pending != 0
+                                            || s.children.my_pending != 0
+                                        {
+                                            "📝"
+                                        } else if event_type != EventType::Paid {
+                                            "✨" // todo: find a better emoji for free events
                                         } else {
-                                            format!(
-                                                "{}({})",
-                                                s.event.max_adults - s.adults.reserved,
-                                                s.event.max_children - s.children.reserved
-                                            )
-                                        }
+                                            ""
+                                        },
+                                        format::ts(s.event.ts),
+                                        match s.state {
+                                            EventState::Open => {
+                                                if s.event.max_adults == 0
+                                                    || s.event.max_children == 0
+                                                {
+                                                    (s.event.max_adults - s.adults.reserved
+                                                        + s.event.max_children
+                                                        - s.children.reserved)
+                                                        .to_string()
+                                                } else {
+                                                    format!(
+                                                        "{}({})",
+                                                        s.event.max_adults - s.adults.reserved,
+                                                        s.event.max_children - s.children.reserved
+                                                    )
+                                                }
+                                            }
+                                            EventState::Upcoming(_) => "🔜".to_string(),
+                                            EventState::Closed => "-".to_string(),
+                                        },
+                                        s.event.name
+                                    ),
+                                    if event_type == EventType::Paid {
+                                        serde_json::to_string(&CallbackQuery::PaidEvent {
+                                            event_id: s.event.id,
+                                            adults: 0,
+                                            children: 0,
+                                            offset: 0,
+                                        })
                                     } else {
-                                        "-".to_string()
-                                    },
-                                    s.event.name
-                                ),
-                                if event_type == EventType::Paid {
-                                    serde_json::to_string(&CallbackQuery::PaidEvent {
-                                        event_id: s.event.id,
-                                        adults: 0,
-                                        children: 0,
-                                        offset: 0,
-                                    })
-                                } else {
-                                    serde_json::to_string(&CallbackQuery::Event {
-                                        event_id: s.event.id,
-                                        offset: 0,
-                                    })
-                                }
-                                .unwrap(),
-                            )]
-                        }
-                    })
-                    .collect()
+                                        serde_json::to_string(&CallbackQuery::Event {
+                                            event_id: s.event.id,
+                                            offset: 0,
+                                        })
+                                    }
+                                    .unwrap(),
+                                )]
+                            }
+                        })
+                        .collect(),
                 )
                 // pagination
                 .pagination(
-                    &CallbackQuery::EventList {offset: offset.saturating_sub(1)},
-                    &CallbackQuery::EventList {offset: offset + 1},
+                    &CallbackQuery::EventList {
+                        offset: offset.saturating_sub(1),
+                    },
+                    &CallbackQuery::EventList { offset: offset + 1 },
                     events.len() as u64,
                     ctx.config.event_list_page_size,
                     offset,
                 )?
-                .into()
+                .into(),
             )
         }
         Err(e) => Err(anyhow!("Failed to query events: {}", e)),
@@ -427,15 +790,14 @@ pub fn show_event(
 
             Ok(
                 // header
-                ReplyMessage::new(
-                    format::header(
-                        &s,
-                        free_adults,
-                        free_children,
-                        is_admin,
-                        no_age_distinction,
-                    )
-                )
+                ReplyMessage::new(format::header(
+                    &s,
+                    free_adults,
+                    free_children,
+                    is_admin,
+                    no_age_distinction,
+                    user.lang,
+                ))
                 // participants
                 .text(participants.and_then(|participants| {
                     Some(format::participants(
@@ -443,42 +805,66 @@ pub fn show_event(
                         &participants,
                         is_admin,
                         no_age_distinction,
+                        user.lang,
                     ))
                 }))
                 // messages
-                .text(format::messages(conn, &s, event_id, is_admin))
+                .text(format::messages(conn, &s, event_id, is_admin, user.lang))
                 // attachment
                 .text({
                     if is_admin
                         || s.adults.my_reservation > 0
                         || s.adults.my_waiting > 0
+                        || s.adults.my_pending > 0
                         || s.children.my_reservation > 0
                         || s.children.my_waiting > 0
+                        || s.children.my_pending > 0
                     {
                         let mut text = "".to_string();
                         if ctx.config.public_lists == false {
                             match db::get_attachment(conn, event_id, user.id.0) {
                                 Ok(v) => {
                                     if let Some(attachment) = v {
-                                        text.push_str(&format!("\nПримечание: {}.", attachment));
+                                        text.push_str(&format!(
+                                            "\n{}",
+                                            t!(user.lang, "attachment", attachment = attachment)
+                                        ));
                                     }
                                 }
                                 Err(e) => error!("Failed to get attachment: {}", e),
                             }
                         }
                         if is_admin == false {
-                            text.push_str("\nКоличество мест можно менять кнопками \"Записаться/Отписаться\". Примечание к брони можно добавить, послав сообщение боту.\n");
+                            text.push_str(&format!("\n{}\n", t!(user.lang, "seats-hint")));
                         }
                         if s.adults.my_reservation + s.children.my_reservation > 0 {
                             text.push_str(&format!(
-                                "\n<b>У вас забронировано: {}</b>",
-                                s.adults.my_reservation + s.children.my_reservation
+                                "\n{}",
+                                t!(
+                                    user.lang,
+                                    "my-reserved",
+                                    count = s.adults.my_reservation + s.children.my_reservation
+                                )
                             ));
                         }
                         if s.adults.my_waiting + s.children.my_waiting > 0 {
                             text.push_str(&format!(
-                                "\n<b>У вас в списке ожидания: {}</b>",
-                                s.adults.my_waiting + s.children.my_waiting
+                                "\n{}",
+                                t!(
+                                    user.lang,
+                                    "my-waiting",
+                                    count = s.adults.my_waiting + s.children.my_waiting
+                                )
+                            ));
+                        }
+                        if s.adults.my_pending + s.children.my_pending > 0 {
+                            text.push_str(&format!(
+                                "\n{}",
+                                t!(
+                                    user.lang,
+                                    "my-pending",
+                                    count = s.adults.my_pending + s.children.my_pending
+                                )
                             ));
                         }
                         Some(text)
@@ -486,6 +872,10 @@ pub fn show_event(
                         None
                     }
                 })
+                // held seat, attendees and questionnaire
+                .text(waiting_list::prompt(conn, &s, user.id.0, user.lang))
+                .text(tickets::prompt(conn, &s, user.id.0, user.lang))
+                .text(questionnaire::prompt(conn, &s, user.id.0, user.lang))
                 // controls
                 .keyboard(get_signup_controls(
                     &s,
@@ -493,7 +883,7 @@ pub fn show_event(
                     free_children,
                     no_age_distinction,
                     is_admin,
-                    user.id.0,
+                    user,
                     conn,
                 )?)
                 // pagination
@@ -511,7 +901,7 @@ pub fn show_event(
                     offset,
                 )?
                 .text(ps)
-                .into()
+                .into(),
             )
         }
         Err(e) => Err(anyhow!("Failed to fetch event: {}", e)),
@@ -524,18 +914,20 @@ fn get_signup_controls(
     free_children: i64,
     no_age_distinction: bool,
     is_admin: bool,
-    user_id: u64,
+    user: &User,
     conn: &Connection,
 ) -> anyhow::Result<Vec<Vec<InlineKeyboardButton>>> {
+    let user_id = user.id.0;
+    let lang = user.lang;
     let mut keyboard: Vec<Vec<InlineKeyboardButton>> = Vec::new();
     let mut row: Vec<InlineKeyboardButton> = Vec::new();
     if s.state == EventState::Open && s.adults.my_reservation < s.event.max_adults_per_reservation {
         if free_adults > 0 {
             row.push(InlineKeyboardButton::callback(
                 if no_age_distinction {
-                    "Записаться +1"
+                    t!(lang, "sign-up")
                 } else {
-                    "Записать взрослого +1"
+                    t!(lang, "sign-up-adult")
                 },
                 &serde_json::to_string(&CallbackQuery::SignUp {
                     event_id: s.event.id,
@@ -547,9 +939,9 @@ fn get_signup_controls(
         {
             row.push(InlineKeyboardButton::callback(
                 if no_age_distinction {
-                    "В лист ожидания +1"
+                    t!(lang, "wait")
                 } else {
-                    "В лист ожидания взрослого +1"
+                    t!(lang, "wait-adult")
                 },
                 &serde_json::to_string(&CallbackQuery::SignUp {
                     event_id: s.event.id,
@@ -559,12 +951,12 @@ fn get_signup_controls(
             ));
         }
     }
-    if s.adults.my_reservation > 0 || s.adults.my_waiting > 0 {
+    if s.adults.my_reservation > 0 || s.adults.my_waiting > 0 || s.adults.my_pending > 0 {
         row.push(InlineKeyboardButton::callback(
             if no_age_distinction {
-                "Отписаться -1"
+                t!(lang, "cancel-seat")
             } else {
-                "Отписать взрослого -1"
+                t!(lang, "cancel-adult")
             },
             &serde_json::to_string(&CallbackQuery::Cancel {
                 event_id: s.event.id,
@@ -580,9 +972,9 @@ fn get_signup_controls(
         if free_children > 0 {
             row.push(InlineKeyboardButton::callback(
                 if no_age_distinction {
-                    "Записаться +1"
+                    t!(lang, "sign-up")
                 } else {
-                    "Записать ребёнка +1"
+                    t!(lang, "sign-up-child")
                 },
                 &serde_json::to_string(&CallbackQuery::SignUp {
                     event_id: s.event.id,
@@ -595,9 +987,9 @@ fn get_signup_controls(
         {
             row.push(InlineKeyboardButton::callback(
                 if no_age_distinction {
-                    "В лист ожидания +1"
+                    t!(lang, "wait")
                 } else {
-                    "В лист ожидания ребёнка +1"
+                    t!(lang, "wait-child")
                 },
                 &serde_json::to_string(&CallbackQuery::SignUp {
                     event_id: s.event.id,
@@ -607,12 +999,12 @@ fn get_signup_controls(
             ));
         }
     }
-    if s.children.my_reservation > 0 || s.children.my_waiting > 0 {
+    if s.children.my_reservation > 0 || s.children.my_waiting > 0 || s.children.my_pending > 0 {
         row.push(InlineKeyboardButton::callback(
             if no_age_distinction {
-                "Отписаться -1"
+                t!(lang, "cancel-seat")
             } else {
-                "Отписать ребёнка -1"
+                t!(lang, "cancel-child")
             },
             &serde_json::to_string(&CallbackQuery::Cancel {
                 event_id: s.event.id,
@@ -624,14 +1016,14 @@ fn get_signup_controls(
 
     row = Vec::new();
     row.push(InlineKeyboardButton::callback(
-        "Список мероприятий",
+        t!(lang, "event-list-button"),
         serde_json::to_string(&CallbackQuery::EventList { offset: 0 })?,
     ));
 
     let event_id = s.event.id;
     if s.adults.reserved > 0 || s.children.reserved > 0 {
         row.push(InlineKeyboardButton::callback(
-            "Список ожидания",
+            t!(lang, "waiting-list-button"),
             &serde_json::to_string(&CallbackQuery::ShowWaitingList {
                 event_id,
                 offset: 0,
@@ -639,25 +1031,43 @@ fn get_signup_controls(
         ));
     }
 
+    if s.event.requires_approval
+        && (is_admin || db::is_group_leader(conn, event_id, user_id).unwrap_or(false))
+    {
+        row.push(InlineKeyboardButton::callback(
+            t!(lang, "applications-button"),
+            &serde_json::to_string(&CallbackQuery::ShowApprovalQueue {
+                event_id,
+                offset: 0,
+            })?,
+        ));
+    }
+
     if is_admin {
         if s.adults.reserved > 0 || s.children.reserved > 0 {
             row.push(InlineKeyboardButton::callback(
-                "Присутствие",
+                t!(lang, "presence-button"),
                 &serde_json::to_string(&CallbackQuery::ShowPresenceList {
                     event_id,
                     offset: 0,
                 })?,
             ));
         }
-        if s.state == EventState::Open {
+        if s.state != EventState::Closed {
             row.push(InlineKeyboardButton::callback(
-                "Остановить запись",
-                serde_json::to_string(&CallbackQuery::ChangeEventState { event_id, state: 1 })?,
+                t!(lang, "close-registration"),
+                serde_json::to_string(&CallbackQuery::ChangeEventState {
+                    event_id,
+                    state: db::REGISTRATION_CLOSED,
+                })?,
             ));
         } else {
             row.push(InlineKeyboardButton::callback(
-                "Разрешить запись",
-                serde_json::to_string(&CallbackQuery::ChangeEventState { event_id, state: 0 })?,
+                t!(lang, "open-registration"),
+                serde_json::to_string(&CallbackQuery::ChangeEventState {
+                    event_id,
+                    state: db::REGISTRATION_OPENED,
+                })?,
             ));
         }
     } else {
@@ -665,7 +1075,7 @@ fn get_signup_controls(
             if let Ok(check) = db::is_group_leader(conn, event_id, user_id) {
                 if check {
                     row.push(InlineKeyboardButton::callback(
-                        "Присутствие",
+                        t!(lang, "presence-button"),
                         &serde_json::to_string(&CallbackQuery::ShowPresenceList {
                             event_id,
                             offset: 0,
@@ -676,6 +1086,22 @@ fn get_signup_controls(
         }
     }
     keyboard.push(row);
+    keyboard.append(&mut waiting_list::controls(conn, s, user_id, lang)?);
+    keyboard.append(&mut check_in::controls(
+        s,
+        is_admin || db::is_group_leader(conn, event_id, user_id).unwrap_or(false),
+        lang,
+    )?);
+    keyboard.append(&mut tickets::controls(conn, s, user_id, lang)?);
+    keyboard.append(&mut calendar::controls(s, lang)?);
+    keyboard.append(&mut subscriptions::controls(s, lang)?);
+    keyboard.append(&mut questionnaire::controls(
+        conn,
+        s,
+        user_id,
+        is_admin || db::is_group_leader(conn, event_id, user_id).unwrap_or(false),
+        lang,
+    )?);
     Ok(keyboard)
 }
 
@@ -693,9 +1119,9 @@ fn show_waiting_list(
         Ok(s) => {
             no_age_distinction = s.event.max_adults == 0 || s.event.max_children == 0;
             list.push_str(&format!(
-                "\n \n{}\nНачало: {}\n",
+                "\n \n{}\n{}\n",
                 format::event_title(&s.event),
-                format::ts(s.event.ts)
+                t!(user.lang, "starts-at", start = format::ts(s.event.ts))
             ));
         }
         Err(e) => {
@@ -712,9 +1138,10 @@ fn show_waiting_list(
     ) {
         Ok(participants) => {
             Ok(ReplyMessage::new(if participants.len() == 0 {
-                "Пустой список ожидания.".to_string()
+                t!(user.lang, "waiting-list-empty")
             } else {
-                "Список ожидания:\n".to_string()
+                t!(user.lang, "waiting-list")
+                    + "\n"
                     + &participants
                         .iter()
                         .map(|p| {
@@ -749,7 +1176,7 @@ fn show_waiting_list(
             })
             // controls
             .keyboard(vec![vec![InlineKeyboardButton::callback(
-                "Назад",
+                t!(user.lang, "back"),
                 &serde_json::to_string(&CallbackQuery::Event {
                     event_id,
                     offset: 0,
@@ -775,6 +1202,188 @@ fn show_waiting_list(
     }
 }
 
+/// "Back" button leading to the free or paid event screen.
+pub fn back_to_event(s: &EventStats, lang: Lang) -> anyhow::Result<InlineKeyboardButton> {
+    let event_id = s.event.id;
+    Ok(InlineKeyboardButton::callback(
+        t!(lang, "back"),
+        serde_json::to_string(&if s.event.get_type() == EventType::Paid {
+            CallbackQuery::PaidEvent {
+                event_id,
+                adults: 0,
+                children: 0,
+                offset: 0,
+            }
+        } else {
+            CallbackQuery::Event {
+                event_id,
+                offset: 0,
+            }
+        })?,
+    ))
+}
+
+/// Follow-up dialog after sign-up or payment: attendee names, then the questionnaire.
+pub fn start_dialog(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    ctx: &Context,
+) -> anyhow::Result<Option<Reply>> {
+    match tickets::start(conn, user, event_id, ctx)? {
+        Some(reply) => Ok(Some(reply)),
+        None => questionnaire::start(conn, user, event_id, ctx),
+    }
+}
+
+/// Admins and group leaders manage event participants.
+pub fn is_manager(conn: &Connection, event_id: u64, user: &User) -> bool {
+    user.is_admin || db::is_group_leader(conn, event_id, user.id.0).unwrap_or(false)
+}
+
+/// Asks the manager for a rejection reason, the next message is sent to the applicant.
+fn ask_rejection_reason(
+    conn: &Connection,
+    event_id: u64,
+    applicant: u64,
+    user: &User,
+    offset: u64,
+) -> anyhow::Result<Reply> {
+    db::set_rejection_session(conn, user.id.0, event_id, applicant, offset)?;
+    Ok(ReplyMessage::new(t!(user.lang, "send-rejection-reason"))
+        .keyboard(vec![
+            vec![InlineKeyboardButton::callback(
+                t!(user.lang, "reject-without-reason"),
+                serde_json::to_string(&CallbackQuery::RejectWithoutReason {
+                    event_id,
+                    user_id: applicant,
+                    offset,
+                })?,
+            )],
+            vec![InlineKeyboardButton::callback(
+                t!(user.lang, "back"),
+                serde_json::to_string(&CallbackQuery::ShowApprovalQueue { event_id, offset })?,
+            )],
+        ])
+        .into())
+}
+
+/// Message from a manager who was asked for a rejection reason.
+fn handle_rejection_reason(
+    conn: &Connection,
+    user: &User,
+    data: &str,
+    ctx: &Context,
+) -> anyhow::Result<Option<Reply>> {
+    let (event_id, applicant, offset) = match db::get_rejection_session(conn, user.id.0)? {
+        Some(session) => session,
+        None => return Ok(None),
+    };
+    db::set_rejection_session(conn, user.id.0, 0, 0, 0)?;
+    if !is_manager(conn, event_id, user) {
+        return Ok(None);
+    }
+    let reason = html::escape(data.trim());
+    db::reject_reservation(
+        conn,
+        event_id,
+        applicant,
+        Some(reason.as_str()).filter(|r| !r.is_empty()),
+    )?;
+    show_approval_queue(conn, event_id, user, ctx, offset).map(Some)
+}
+
+fn show_approval_queue(
+    conn: &Connection,
+    event_id: u64,
+    user: &User,
+    ctx: &Context,
+    offset: u64,
+) -> anyhow::Result<Reply> {
+    let s = match db::get_event(conn, event_id, user.id.0) {
+        Ok(s) => s,
+        Err(e) => {
+            return Err(anyhow!("Failed to find event: {}", e));
+        }
+    };
+    let no_age_distinction = s.event.max_adults == 0 || s.event.max_children == 0;
+    match db::get_approval_queue(conn, event_id, offset, ctx.config.presence_page_size) {
+        Ok(participants) => {
+            Ok(
+                // header
+                ReplyMessage::new(format!(
+                    "\n \n{}\n{}\n{}",
+                    format::event_title(&s.event),
+                    t!(user.lang, "starts-at", start = format::ts(s.event.ts)),
+                    if participants.is_empty() {
+                        t!(user.lang, "no-applications")
+                    } else {
+                        t!(user.lang, "applications")
+                    }
+                ))
+                .keyboard(
+                    participants
+                        .iter()
+                        .map(|p| {
+                            let mut text = if p.user_name2.is_empty() {
+                                p.user_name1.to_string()
+                            } else {
+                                format!("{} ({})", p.user_name1, p.user_name2)
+                            };
+                            if no_age_distinction {
+                                text.push_str(&format!(" {}", p.adults + p.children));
+                            } else {
+                                text.push_str(&format!(" {}({})", p.adults, p.children));
+                            }
+                            if let Some(a) = &p.attachment {
+                                text.push_str(&format!(" - {}", a));
+                            }
+                            vec![
+                                InlineKeyboardButton::callback(
+                                    format!("✅ {}", text),
+                                    serde_json::to_string(&CallbackQuery::Approve {
+                                        event_id,
+                                        user_id: p.user_id,
+                                        offset,
+                                    })
+                                    .unwrap(),
+                                ),
+                                InlineKeyboardButton::callback(
+                                    "❌",
+                                    serde_json::to_string(&CallbackQuery::Reject {
+                                        event_id,
+                                        user_id: p.user_id,
+                                        offset,
+                                    })
+                                    .unwrap(),
+                                ),
+                            ]
+                        })
+                        .collect(),
+                )
+                // controls
+                .keyboard(vec![vec![back_to_event(&s, user.lang)?]])
+                // pagination
+                .pagination(
+                    &CallbackQuery::ShowApprovalQueue {
+                        event_id,
+                        offset: offset.saturating_sub(1),
+                    },
+                    &CallbackQuery::ShowApprovalQueue {
+                        event_id,
+                        offset: offset + 1,
+                    },
+                    participants.len() as u64,
+                    ctx.config.presence_page_size,
+                    offset,
+                )?
+                .into(),
+            )
+        }
+        Err(e) => Err(anyhow!("Failed to get approval queue: {}", e)),
+    }
+}
+
 fn is_too_late_to_cancel(conn: &Connection, event_id: u64, user: &User, ctx: &Context) -> bool {
     if let Ok(s) = db::get_event(conn, event_id, user.id.0) {
         if s.event.ts - get_unix_time() < ctx.config.too_late_to_cancel_hours * 60 * 60 {
@@ -791,28 +1400,20 @@ fn show_presence_list(
     ctx: &Context,
     offset: u64,
 ) -> anyhow::Result<Reply> {
-    let mut header = "".to_string();
-    match db::get_event(conn, event_id, user.id.0) {
-        Ok(s) => {
-            header.push_str(&format!(
-                "\n \n{}\nНачало: {}\n",
-                format::event_title(&s.event),
-                format::ts(s.event.ts)
-            ));
-        }
-        Err(e) => {
-            return Err(anyhow!("Failed to find event: {}", e));
-        }
-    }
+    let header = presence_header(conn, event_id, user)?;
     match db::get_presence_list(conn, event_id, offset, ctx.config.presence_page_size) {
         Ok(participants) => {
             Ok(
                 // header
-                ReplyMessage::new(if participants.len() == 0 {
-                    "Пустой список ожидания."
-                } else {
-                    "Пожалуйста, выберите присутствующих:\n"
-                })
+                ReplyMessage::new(format!(
+                    "{}\n\n{}",
+                    header,
+                    if participants.is_empty() {
+                        t!(user.lang, "all-present")
+                    } else {
+                        t!(user.lang, "select-present") + "\n"
+                    }
+                ))
                 .keyboard(
                     participants
                         .iter()
@@ -827,6 +1428,9 @@ fn show_presence_list(
                                 } else {
                                     text = format!("{} {}", p.user_name1, p.reserved);
                                 }
+                                if !p.attendees.is_empty() {
+                                    text.push_str(&format!(" ({})", p.attendees.join(", ")));
+                                }
                                 if let Some(a) = &p.attachment {
                                     text.push_str(&format!(" - {}", a));
                                 }
@@ -845,13 +1449,22 @@ fn show_presence_list(
                         .collect(),
                 )
                 // controls
-                .keyboard(vec![vec![InlineKeyboardButton::callback(
-                    "Назад",
-                    &serde_json::to_string(&CallbackQuery::Event {
-                        event_id,
-                        offset: 0,
-                    })?,
-                )]])
+                .keyboard(vec![vec![
+                    InlineKeyboardButton::callback(
+                        t!(user.lang, "back"),
+                        &serde_json::to_string(&CallbackQuery::Event {
+                            event_id,
+                            offset: 0,
+                        })?,
+                    ),
+                    InlineKeyboardButton::callback(
+                        t!(user.lang, "present-button"),
+                        &serde_json::to_string(&CallbackQuery::ShowPresentList {
+                            event_id,
+                            offset: 0,
+                        })?,
+                    ),
+                ]])
                 // pagination
                 .pagination(
                     &CallbackQuery::ShowPresenceList {
@@ -872,3 +1485,91 @@ fn show_presence_list(
         Err(e) => Err(anyhow!("Failed to get precense list: {}", e)),
     }
 }
+
+/// Event title with the attendance summary.
+fn presence_header(conn: &Connection, event_id: u64, user: &User) -> anyhow::Result<String> {
+    let s = match db::get_event(conn, event_id, user.id.0) {
+        Ok(s) => s,
+        Err(e) => {
+            return Err(anyhow!("Failed to find event: {}", e));
+        }
+    };
+    let stats = db::get_attendance_stats(conn, event_id)?;
+    Ok(format!(
+        "\n \n{}\n{}\n{}",
+        format::event_title(&s.event),
+        t!(user.lang, "starts-at", start = format::ts(s.event.ts)),
+        format::attendance(
+            &stats,
+            s.event.max_adults == 0 || s.event.max_children == 0,
+            user.lang,
+        )
+    ))
+}
+
+/// Participants marked present, tapping one reverts the confirmation.
+fn show_present_list(
+    conn: &Connection,
+    event_id: u64,
+    user: &User,
+    ctx: &Context,
+    offset: u64,
+) -> anyhow::Result<Reply> {
+    let header = presence_header(conn, event_id, user)?;
+    match db::get_present_list(conn, event_id, offset, ctx.config.presence_page_size) {
+        Ok(participants) => Ok(ReplyMessage::new(format!(
+            "{}\n\n{}",
+            header,
+            if participants.is_empty() {
+                t!(user.lang, "nobody-present")
+            } else {
+                t!(user.lang, "present-list") + "\n"
+            }
+        ))
+        .keyboard(
+            participants
+                .iter()
+                .map(|p| {
+                    let mut text = if p.user_name2.is_empty() {
+                        format!("✅ {} {}", p.user_name1, p.reserved)
+                    } else {
+                        format!("✅ {} ({}) {}", p.user_name1, p.user_name2, p.reserved)
+                    };
+                    if !p.attendees.is_empty() {
+                        text.push_str(&format!(" ({})", p.attendees.join(", ")));
+                    }
+                    Ok(vec![InlineKeyboardButton::callback(
+                        text,
+                        serde_json::to_string(&CallbackQuery::UnconfirmPresence {
+                            event_id,
+                            user_id: p.user_id,
+                            offset,
+                        })?,
+                    )])
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        )
+        .keyboard(vec![vec![InlineKeyboardButton::callback(
+            t!(user.lang, "back"),
+            serde_json::to_string(&CallbackQuery::ShowPresenceList {
+                event_id,
+                offset: 0,
+            })?,
+        )]])
+        .pagination(
+            &CallbackQuery::ShowPresentList {
+                event_id,
+                offset: offset.saturating_sub(1),
+            },
+            &CallbackQuery::ShowPresentList {
+                event_id,
+                offset: offset + 1,
+            },
+            participants.len() as u64,
+            ctx.config.presence_page_size,
+            offset,
+        )?
+        .into()),
+        Err(e) => Err(anyhow!("Failed to get present list: {}", e)),
+    }
+}
@@ -0,0 +1,372 @@
+use crate::configuration::config::Config;
+use crate::db;
+use crate::format;
+use crate::payments;
+use crate::reply::*;
+use crate::types::{Booking, Charge, Connection, Payment, PaymentKind, User};
+use crate::util::get_unix_time;
+use anyhow::anyhow;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use teloxide::types::InlineKeyboardButton;
+use url::Url;
+
+/// Invoice for a booking or a donation, `payload` is the serialized `Booking`.
+pub struct Invoice {
+    pub title: String,
+    pub description: String,
+    pub payload: String,
+    pub currency: String,
+    pub amount: u64,
+}
+
+/// Payment service taking the money for invoices.
+pub trait PaymentProvider: Send + Sync {
+    /// Reply asking the user to pay the invoice.
+    fn create_invoice(
+        &self,
+        conn: &Connection,
+        user: &User,
+        invoice: Invoice,
+    ) -> anyhow::Result<Reply>;
+
+    /// Validates the booking right before the charge and holds the seats.
+    fn pre_checkout(
+        &self,
+        conn: &Connection,
+        user: &User,
+        booking: &Booking,
+        amount: u64,
+    ) -> anyhow::Result<()> {
+        payments::book(conn, user, booking, amount, self.hold())
+    }
+
+    /// Seconds the seats stay booked while the payment is pending.
+    fn hold(&self) -> u64 {
+        5 * 60
+    }
+
+    /// Checks the signature of a webhook notification and returns the charge it confirms, if any.
+    fn verify_webhook(&self, _body: &[u8], _signature: &str) -> anyhow::Result<Option<Charge>> {
+        Err(anyhow!("Webhooks are not supported"))
+    }
+
+    /// Records the charge and completes the booking.
+    fn complete(&self, conn: &Connection, charge: &Charge) -> anyhow::Result<Booking> {
+        payments::complete(conn, charge)
+    }
+
+    /// Link returning `amount` of the payment, None if it is returned in the payment system.
+    fn refund(&self, payment: &Payment, amount: u64) -> anyhow::Result<Option<String>>;
+}
+
+/// Links returning the refund of the user, newest payments are returned first.
+/// The approved refund is the latest in the ledger, payments before an earlier refund were returned by it.
+pub fn refund_links(
+    provider: &dyn PaymentProvider,
+    conn: &Connection,
+    event_id: u64,
+    user_id: u64,
+    amount: u64,
+) -> anyhow::Result<Vec<String>> {
+    let payments: Vec<Payment> = db::get_payments(conn, event_id)?
+        .into_iter()
+        .filter(|p| p.user_id == user_id)
+        .collect();
+    let refunded = payments
+        .iter()
+        .rev()
+        .filter(|p| p.kind == PaymentKind::Refund)
+        .nth(1)
+        .map_or(0, |p| p.id);
+    let mut left = amount;
+    let mut links = Vec::new();
+    for p in payments
+        .iter()
+        .rev()
+        .filter(|p| p.kind == PaymentKind::Ticket && p.id > refunded)
+    {
+        if left == 0 {
+            break;
+        }
+        let part = left.min(p.amount);
+        if let Some(link) = provider.refund(p, part)? {
+            links.push(link);
+        }
+        left -= part;
+    }
+    Ok(links)
+}
+
+pub fn from_config(config: &Config) -> Arc<dyn PaymentProvider> {
+    let hold = |default_minutes: u64| {
+        if config.payment_hold_minutes != 0 {
+            config.payment_hold_minutes * 60
+        } else {
+            default_minutes * 60
+        }
+    };
+    match config.payment_provider.as_str() {
+        "external" => Arc::new(ExternalCheckout {
+            checkout_url: config.checkout_url.to_owned(),
+            refund_url: config.refund_url.to_owned(),
+            secret: config.payment_webhook_secret.to_owned(),
+            hold: hold(30),
+        }),
+        "crypto" => Arc::new(CryptoPayments {
+            api_url: config.crypto_api_url.to_owned(),
+            api_key: config.crypto_api_key.to_owned(),
+            secret: config.payment_webhook_secret.to_owned(),
+            hold: hold(60),
+        }),
+        _ => Arc::new(TelegramPayments),
+    }
+}
+
+/// Telegram Payments, the bot sends the invoice and gets pre-checkout and payment updates.
+pub struct TelegramPayments;
+
+impl PaymentProvider for TelegramPayments {
+    fn create_invoice(
+        &self,
+        _conn: &Connection,
+        _user: &User,
+        invoice: Invoice,
+    ) -> anyhow::Result<Reply> {
+        Ok(Reply::Invoice {
+            title: invoice.title,
+            description: invoice.description,
+            payload: invoice.payload,
+            currency: invoice.currency,
+            amount: invoice.amount,
+        })
+    }
+
+    fn refund(&self, _payment: &Payment, _amount: u64) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// Checkout page of a payment service, which confirms charges with a signed webhook.
+/// There is no pre-checkout step, so the seats are held when the link is given out.
+pub struct ExternalCheckout {
+    pub checkout_url: String,
+    pub refund_url: String,
+    pub secret: String,
+    pub hold: u64,
+}
+
+impl ExternalCheckout {
+    /// Adds the signature of the query, so that the payment service can trust the amount.
+    fn signed_link(&self, base: &str, params: &[(&str, &str)]) -> anyhow::Result<Url> {
+        let mut url = Url::parse_with_params(base, params)?;
+        let signature = sign(&self.secret, url.query().unwrap_or_default().as_bytes());
+        url.query_pairs_mut().append_pair("signature", &signature);
+        Ok(url)
+    }
+}
+
+impl PaymentProvider for ExternalCheckout {
+    fn create_invoice(
+        &self,
+        conn: &Connection,
+        user: &User,
+        invoice: Invoice,
+    ) -> anyhow::Result<Reply> {
+        let booking: Booking = serde_json::from_str(&invoice.payload)?;
+        self.pre_checkout(conn, user, &booking, invoice.amount)?;
+        let url = self.signed_link(
+            &self.checkout_url,
+            &[
+                ("payload", &invoice.payload),
+                ("amount", &invoice.amount.to_string()),
+                ("currency", &invoice.currency),
+                ("description", &invoice.description),
+            ],
+        )?;
+        Ok(ReplyMessage::new(format!(
+            "<b>{}</b>\n{}\n{}",
+            invoice.title,
+            invoice.description,
+            t!(
+                user.lang,
+                "amount-due",
+                amount = format::price(invoice.amount, &invoice.currency)
+            )
+        ))
+        .keyboard(vec![vec![InlineKeyboardButton::url(
+            t!(user.lang, "pay-link"),
+            url,
+        )]])
+        .into())
+    }
+
+    fn hold(&self) -> u64 {
+        self.hold
+    }
+
+    fn verify_webhook(&self, body: &[u8], signature: &str) -> anyhow::Result<Option<Charge>> {
+        if !verify(&self.secret, body, signature) {
+            return Err(anyhow!("Invalid signature"));
+        }
+        Ok(Some(serde_json::from_slice(body)?))
+    }
+
+    fn refund(&self, payment: &Payment, amount: u64) -> anyhow::Result<Option<String>> {
+        if self.refund_url.is_empty() {
+            return Ok(None);
+        }
+        let url = self.signed_link(
+            &self.refund_url,
+            &[
+                ("charge_id", &payment.charge_id),
+                ("amount", &amount.to_string()),
+                ("currency", &payment.currency),
+            ],
+        )?;
+        Ok(Some(url.to_string()))
+    }
+}
+
+/// Crypto payment gateway, the invoice has an address to transfer to
+/// and the gateway confirms the transfer with a signed callback.
+pub struct CryptoPayments {
+    pub api_url: String,
+    pub api_key: String,
+    pub secret: String,
+    pub hold: u64,
+}
+
+/// Invoice created by the crypto gateway, `amount` is in `asset`.
+#[derive(Deserialize)]
+struct CryptoInvoice {
+    id: String,
+    address: String,
+    #[serde(default)]
+    pay_url: String,
+    amount: String,
+    asset: String,
+}
+
+/// Callback of the crypto gateway, `amount` and `currency` are the ones of the invoice request.
+#[derive(Deserialize)]
+struct CryptoCallback {
+    invoice_id: String,
+    status: String,
+    payload: String,
+    amount: u64,
+    currency: String,
+    #[serde(default)]
+    tx_hash: String,
+}
+
+impl CryptoPayments {
+    /// Blocks on the gateway, bot handlers run on the blocking thread pool.
+    fn request_invoice(&self, invoice: &Invoice) -> anyhow::Result<CryptoInvoice> {
+        Ok(
+            ureq::post(&format!("{}/invoices", self.api_url.trim_end_matches('/')))
+                .set("Authorization", &format!("Bearer {}", self.api_key))
+                .timeout(Duration::from_secs(10))
+                .send_json(serde_json::json!({
+                    "amount": invoice.amount,
+                    "currency": invoice.currency,
+                    "description": invoice.description,
+                    "payload": invoice.payload,
+                    "expires_in": self.hold,
+                }))?
+                .into_json()?,
+        )
+    }
+}
+
+impl PaymentProvider for CryptoPayments {
+    fn create_invoice(
+        &self,
+        conn: &Connection,
+        user: &User,
+        invoice: Invoice,
+    ) -> anyhow::Result<Reply> {
+        let booking: Booking = serde_json::from_str(&invoice.payload)?;
+        // The seats are booked first so that no gateway invoice is created for seats which can not be booked.
+        self.pre_checkout(conn, user, &booking, invoice.amount)?;
+        let crypto = match self.request_invoice(&invoice) {
+            Ok(crypto) => crypto,
+            Err(e) => {
+                db::cancel_pending_payment(conn, &booking)?;
+                return Err(e);
+            }
+        };
+        debug!("crypto invoice {} for {}", crypto.id, invoice.payload);
+        let mut message = ReplyMessage::new(format!(
+            "<b>{}</b>\n{}\n{}",
+            invoice.title,
+            invoice.description,
+            t!(
+                user.lang,
+                "crypto-invoice",
+                amount = format::price(invoice.amount, &invoice.currency),
+                crypto_amount = format!("{} {}", crypto.amount, crypto.asset),
+                address = crypto.address.as_str(),
+                valid_until = format::ts(get_unix_time() + self.hold)
+            )
+        ));
+        if !crypto.pay_url.is_empty() {
+            message = message.keyboard(vec![vec![InlineKeyboardButton::url(
+                t!(user.lang, "pay-link"),
+                Url::parse(&crypto.pay_url)?,
+            )]]);
+        }
+        Ok(message.into())
+    }
+
+    fn hold(&self) -> u64 {
+        self.hold
+    }
+
+    fn verify_webhook(&self, body: &[u8], signature: &str) -> anyhow::Result<Option<Charge>> {
+        if !verify(&self.secret, body, signature) {
+            return Err(anyhow!("Invalid signature"));
+        }
+        let callback: CryptoCallback = serde_json::from_slice(body)?;
+        if callback.status != "paid" {
+            debug!(
+                "crypto invoice {} is {}",
+                callback.invoice_id, callback.status
+            );
+            return Ok(None);
+        }
+        Ok(Some(Charge {
+            payload: callback.payload,
+            name: "".to_string(),
+            amount: callback.amount,
+            currency: callback.currency,
+            charge_id: callback.invoice_id,
+            provider_charge_id: callback.tx_hash,
+        }))
+    }
+
+    fn refund(&self, _payment: &Payment, _amount: u64) -> anyhow::Result<Option<String>> {
+        // Transfers are returned from the wallet.
+        Ok(None)
+    }
+}
+
+/// Hex encoded HMAC-SHA256 of the data.
+pub fn sign(secret: &str, data: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes any key size");
+    mac.update(data);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub fn verify(secret: &str, data: &[u8], signature: &str) -> bool {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes any key size");
+    mac.update(data);
+    match hex::decode(signature.trim()) {
+        Ok(signature) => !secret.is_empty() && mac.verify_slice(&signature).is_ok(),
+        Err(_) => false,
+    }
+}
@@ -1,80 +1,161 @@
 use crate::message_handler::CallbackQuery;
 use crate::reply::*;
-use crate::types::{Booking, Connection, Context, EventState, OrderInfo, ReservationState, User};
+use crate::types::{
+    Booking, Charge, Connection, Context, EventState, OrderInfo, Payment, PaymentKind,
+    PaymentTotals, Reconciliation, ReservationState, User,
+};
 use crate::util::get_unix_time;
 use anyhow::anyhow;
 use teloxide::types::{InlineKeyboardButton, PreCheckoutQuery, SuccessfulPayment};
 
+use crate::calendar;
 use crate::db;
 use crate::format;
+use crate::i18n::Lang;
+use crate::payment_providers::Invoice;
+use crate::promo_codes;
+use crate::questionnaire;
+use crate::refunds;
+use crate::subscriptions;
+use crate::tickets;
+use crate::waiting_list;
 use db::EventStats;
 
-/// Book tickets and wait for payment checkout.
+/// Telegram asks to confirm the order right before the charge.
 pub fn pre_checkout(
     conn: &Connection,
     user: &User,
     pre_checkout: &PreCheckoutQuery,
-    _ctx: &Context,
+    ctx: &Context,
 ) -> anyhow::Result<()> {
     // if pre_checkout.currency != Currency::EUR {
     //     return Err(anyhow!("Only EUR is currently accepted"));
     // }
     if let Some(_) = &pre_checkout.order_info.name {
         let booking: Booking = serde_json::from_str(&pre_checkout.invoice_payload)?;
-        if booking.event_id == 0 {
-            // Donation
-            Ok(())
-        } else {
-            match db::sign_up(
-                conn,
-                booking.event_id,
-                user,
-                booking.adults,
-                booking.children,
-                0,
-                get_unix_time(),
-                pre_checkout.total_amount as u64,
-            ) {
-                Ok(_) => Ok(()),
-                Err(e) => Err(anyhow!("{}", e)),
-            }
-        }
+        ctx.payment_provider
+            .pre_checkout(conn, user, &booking, pre_checkout.total_amount as u64)
     } else {
         Err(anyhow!("Name not found"))
     }
 }
 
-/// Payment successful.
+/// Book tickets and wait for payment checkout for `hold` seconds.
+pub fn book(
+    conn: &Connection,
+    user: &User,
+    booking: &Booking,
+    amount: u64,
+    hold: u64,
+) -> anyhow::Result<()> {
+    if booking.event_id == 0 {
+        // Donation
+        Ok(())
+    } else if is_offered(conn, booking)? {
+        // Seats were held from the waiting list.
+        db::check_offered_booking(conn, booking, amount, hold)
+    } else if db::get_event(conn, booking.event_id, user.id.0)?
+        .event
+        .requires_approval
+    {
+        // Seats were booked when the application was approved.
+        db::check_approved_booking(conn, booking, amount, hold)
+    } else {
+        match db::sign_up(
+            conn,
+            booking.event_id,
+            user,
+            booking.adults,
+            booking.children,
+            0,
+            get_unix_time(),
+            amount,
+        ) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Whether the booking pays for seats held from the waiting list.
+fn is_offered(conn: &Connection, booking: &Booking) -> anyhow::Result<bool> {
+    let (adults, children, _) = db::get_offer(conn, booking.event_id, booking.user_id)?;
+    Ok(adults + children > 0 && (adults, children) == (booking.adults, booking.children))
+}
+
+/// Telegram payment successful.
 pub fn checkout(
     conn: &Connection,
     payment: &SuccessfulPayment,
-    _ctx: &Context,
-) -> anyhow::Result<()> {
+    ctx: &Context,
+) -> anyhow::Result<Booking> {
     if let Some(name) = &payment.order_info.name {
-        let booking: Booking = serde_json::from_str(&payment.invoice_payload)?;
-        if booking.event_id == 0 {
-            // Donation
-            // todo: save to donations table
-            Ok(())
-        } else {
-            match db::checkout(
-                conn,
-                &booking,
-                OrderInfo {
-                    id: payment.telegram_payment_charge_id.to_owned(),
-                    name: name.to_owned(),
-                    amount: payment.total_amount as u64,
-                },
-            ) {
-                Ok(_) => Ok(()),
-                Err(e) => Err(anyhow!("{}", e)),
-            }
-        }
+        ctx.payment_provider.complete(
+            conn,
+            &Charge {
+                payload: payment.invoice_payload.to_owned(),
+                name: name.to_owned(),
+                amount: payment.total_amount as u64,
+                currency: format!("{:?}", payment.currency),
+                charge_id: payment.telegram_payment_charge_id.to_owned(),
+                provider_charge_id: payment.provider_payment_charge_id.to_owned(),
+            },
+        )
     } else {
         Err(anyhow!("Name not found"))
     }
 }
 
+/// Records the charge in the ledger and completes the booking.
+pub fn complete(conn: &Connection, charge: &Charge) -> anyhow::Result<Booking> {
+    let booking: Booking = serde_json::from_str(&charge.payload)?;
+    if charge.charge_id.is_empty() {
+        return Err(anyhow!("Charge id not found"));
+    }
+    if db::is_payment_recorded(conn, &charge.charge_id)? {
+        // Repeated notification.
+        return Ok(booking);
+    }
+    // Every charge goes to the ledger, so that a failed booking can be reconciled.
+    db::record_payment(
+        conn,
+        &Payment {
+            id: 0,
+            ts: get_unix_time(),
+            kind: if booking.event_id == 0 {
+                PaymentKind::Donation
+            } else {
+                PaymentKind::Ticket
+            },
+            event_id: booking.event_id,
+            user_id: booking.user_id,
+            amount: charge.amount,
+            currency: charge.currency.to_owned(),
+            charge_id: charge.charge_id.to_owned(),
+            provider_charge_id: charge.provider_charge_id.to_owned(),
+            payload: charge.payload.to_owned(),
+            promo_code: db::get_order_promo_code(conn, &booking)?,
+        },
+    )?;
+    if booking.event_id == 0 {
+        // Donation
+        Ok(booking)
+    } else {
+        match db::checkout(
+            conn,
+            &booking,
+            OrderInfo {
+                id: charge.charge_id.to_owned(),
+                name: charge.name.to_owned(),
+                amount: charge.amount,
+            },
+        ) {
+            Ok(_) => Ok(booking),
+            Err(e) => Err(e),
+        }
+    }
+}
+
 pub fn show_paid_event(
     event_id: u64,
     adults: u64,
@@ -91,6 +172,11 @@ pub fn show_paid_event(
                 s.event.max_children as i64 - s.children.reserved as i64 - children as i64;
             let no_age_distinction = s.event.max_adults == 0 || s.event.max_children == 0;
             let is_admin = ctx.config.admins.contains(&user.id.0);
+            let (approved_adults, approved_children) = if s.event.requires_approval {
+                db::get_approved_booking(conn, event_id, user.id.0)?
+            } else {
+                (0, 0)
+            };
 
             let (participants, participants_len) = if is_admin {
                 let participants = db::get_participants(
@@ -115,6 +201,7 @@ pub fn show_paid_event(
                     free_children,
                     is_admin,
                     no_age_distinction,
+                    user.lang,
                 ))
                 // participants
                 .text(participants.and_then(|participants| {
@@ -123,42 +210,76 @@ pub fn show_paid_event(
                         &participants,
                         is_admin,
                         no_age_distinction,
+                        user.lang,
                     ))
                 }))
                 // messages
-                .text(format::messages(conn, &s, event_id, is_admin))
+                .text(format::messages(conn, &s, event_id, is_admin, user.lang))
                 // attachment
                 .text({
                     if is_admin
                         || s.adults.my_reservation > 0
                         || s.adults.my_waiting > 0
+                        || s.adults.my_pending > 0
                         || s.children.my_reservation > 0
                         || s.children.my_waiting > 0
+                        || s.children.my_pending > 0
                     {
                         let mut text = "".to_string();
                         if ctx.config.public_lists == false {
                             match db::get_attachment(conn, event_id, user.id.0) {
                                 Ok(v) => {
                                     if let Some(attachment) = v {
-                                        text.push_str(&format!("\nПримечание: {}.", attachment));
+                                        text.push_str(&format!(
+                                            "\n{}",
+                                            t!(user.lang, "attachment", attachment = attachment)
+                                        ));
                                     }
                                 }
                                 Err(e) => error!("Failed to get attachment: {}", e),
                             }
                         }
                         if is_admin == false {
-                            text.push_str("\nКоличество мест можно менять кнопками \"Записаться/Отписаться\". Примечание к брони можно добавить, послав сообщение боту.\n");
+                            text.push_str(&format!("\n{}\n", t!(user.lang, "seats-hint")));
                         }
                         if s.adults.my_reservation + s.children.my_reservation > 0 {
                             text.push_str(&format!(
-                                "\n<b>У вас забронировано: {}</b>",
-                                s.adults.my_reservation + s.children.my_reservation
+                                "\n{}",
+                                t!(
+                                    user.lang,
+                                    "my-reserved",
+                                    count = s.adults.my_reservation + s.children.my_reservation
+                                )
                             ));
                         }
                         if s.adults.my_waiting + s.children.my_waiting > 0 {
                             text.push_str(&format!(
-                                "\n<b>У вас в списке ожидания: {}</b>",
-                                s.adults.my_waiting + s.children.my_waiting
+                                "\n{}",
+                                t!(
+                                    user.lang,
+                                    "my-waiting",
+                                    count = s.adults.my_waiting + s.children.my_waiting
+                                )
+                            ));
+                        }
+                        if s.adults.my_pending + s.children.my_pending > 0 {
+                            text.push_str(&format!(
+                                "\n{}",
+                                t!(
+                                    user.lang,
+                                    "my-pending",
+                                    count = s.adults.my_pending + s.children.my_pending
+                                )
+                            ));
+                        }
+                        if approved_adults + approved_children > 0 {
+                            text.push_str(&format!(
+                                "\n{}",
+                                t!(
+                                    user.lang,
+                                    "my-approved",
+                                    count = approved_adults + approved_children
+                                )
                             ));
                         }
                         Some(text)
@@ -166,41 +287,50 @@ pub fn show_paid_event(
                         None
                     }
                 })
+                // held seat, promo code, refund, attendees and questionnaire
+                .text(waiting_list::prompt(conn, &s, user.id.0, user.lang))
+                .text(promo_codes::prompt(conn, &s, user.id.0, user.lang))
+                .text(refunds::prompt(conn, &s, user.id.0, user.lang))
+                .text(tickets::prompt(conn, &s, user.id.0, user.lang))
+                .text(questionnaire::prompt(conn, &s, user.id.0, user.lang))
                 // footer
-                .text(
-                    if s.adults.my_reservation + s.children.my_reservation > 0 {
-                        Some(format!("\n<b>Вы ранее купили: {}</b>", s.adults.my_reservation + s.children.my_reservation))
-                    } else {
-                        None
-                    }
-                )
+                .text(if s.adults.my_reservation + s.children.my_reservation > 0 {
+                    Some(format!(
+                        "\n{}",
+                        t!(
+                            user.lang,
+                            "bought-before",
+                            count = s.adults.my_reservation + s.children.my_reservation
+                        )
+                    ))
+                } else {
+                    None
+                })
                 // order
-                .text(
-                    if adults + children > 0 {
-                        let mut order = "Сбор за бронирование: ".to_string();
-                        if no_age_distinction {
-                            order.push_str(&format!("{}", adults));
-                        } else {
-                            if adults > 0 {
-                                order.push_str(&format!("{} взросл.", adults));
-                            }
-                            if children > 0 {
-                                order.push_str(&format!(", {} детск.", children));
-                            }
-                        }
-
-                        let total_amount = (adults * s.event.adult_ticket_price
-                            + children * s.event.child_ticket_price)
-                            as f32
-                            / 100f32;
-                        Some(format!(
-                            "\n<b>{}, всего {} {}</b>",
-                            order, total_amount, s.event.currency
-                        ))
+                .text(if adults + children > 0 {
+                    let total_amount =
+                        db::get_booking_amount(conn, &s.event, user.id.0, adults, children)
+                            .unwrap_or_default();
+                    Some(format!(
+                        "\n{}",
+                        t!(
+                            user.lang,
+                            "booking-fee",
+                            order = describe_order(adults, children, no_age_distinction, user.lang),
+                            total = format::price(total_amount, &s.event.currency)
+                        )
+                    ))
+                } else if s.event.requires_approval {
+                    if approved_adults + approved_children > 0 {
+                        Some(format!("\n{}", t!(user.lang, "pay-approved-hint")))
+                    } else if s.adults.my_pending + s.children.my_pending > 0 {
+                        None
                     } else {
-                        Some("\nВыберите необходимое количество билетов и нажмите \"К оплате\". Введённое имя будет на билете.".to_string())
+                        Some(format!("\n{}", t!(user.lang, "apply-hint")))
                     }
-                )
+                } else {
+                    Some(format!("\n{}", t!(user.lang, "pay-hint")))
+                })
                 // controls
                 .keyboard(get_controls(
                     &s,
@@ -211,7 +341,8 @@ pub fn show_paid_event(
                     free_children,
                     no_age_distinction,
                     is_admin,
-                    user.id.0,
+                    (approved_adults, approved_children),
+                    user,
                     conn,
                 )?)
                 // pagination
@@ -232,7 +363,7 @@ pub fn show_paid_event(
                     ctx.config.event_page_size,
                     offset,
                 )?
-                .into()
+                .into(),
             )
         }
         Err(e) => Err(anyhow!("Failed to fetch event: {}", e)),
@@ -248,21 +379,25 @@ fn get_controls(
     free_children: i64,
     no_age_distinction: bool,
     is_admin: bool,
-    _user_id: u64,
-    _conn: &Connection,
+    approved: (u64, u64),
+    user: &User,
+    conn: &Connection,
 ) -> anyhow::Result<Vec<Vec<InlineKeyboardButton>>> {
+    let user_id = user.id.0;
+    let lang = user.lang;
     let mut keyboard: Vec<Vec<InlineKeyboardButton>> = Vec::new();
     let mut row: Vec<InlineKeyboardButton> = Vec::new();
     let event_id = s.event.id;
 
-    if s.state == EventState::Open {
+    let has_application = s.adults.my_pending + s.children.my_pending + approved.0 + approved.1 > 0;
+    if s.state == EventState::Open && !(s.event.requires_approval && has_application) {
         if s.adults.my_reservation + adults < s.event.max_adults_per_reservation {
             if free_adults > 0 {
                 row.push(InlineKeyboardButton::callback(
                     if no_age_distinction {
-                        "Забронировать +1"
+                        t!(lang, "book")
                     } else {
-                        "Забронировать взрослый +1"
+                        t!(lang, "book-adult")
                     },
                     &serde_json::to_string(&CallbackQuery::PaidEvent {
                         event_id,
@@ -271,14 +406,31 @@ fn get_controls(
                         offset,
                     })?,
                 ));
+            } else if adults == 0
+                && !s.event.requires_approval
+                && s.adults.my_reservation + s.adults.my_waiting
+                    < s.event.max_adults_per_reservation
+            {
+                row.push(InlineKeyboardButton::callback(
+                    if no_age_distinction {
+                        t!(lang, "wait")
+                    } else {
+                        t!(lang, "wait-adult")
+                    },
+                    &serde_json::to_string(&CallbackQuery::SignUp {
+                        event_id,
+                        is_adult: true,
+                        wait: true,
+                    })?,
+                ));
             }
         }
         if adults > 0 {
             row.push(InlineKeyboardButton::callback(
                 if no_age_distinction {
-                    "Отменить -1"
+                    t!(lang, "unbook")
                 } else {
-                    "Отменить взрослый -1"
+                    t!(lang, "unbook-adult")
                 },
                 &serde_json::to_string(&CallbackQuery::PaidEvent {
                     event_id,
@@ -294,9 +446,9 @@ fn get_controls(
             if free_children > 0 {
                 row.push(InlineKeyboardButton::callback(
                     if no_age_distinction {
-                        "Забронировать +1"
+                        t!(lang, "book")
                     } else {
-                        "Забронировать детский +1"
+                        t!(lang, "book-child")
                     },
                     &serde_json::to_string(&CallbackQuery::PaidEvent {
                         event_id,
@@ -305,14 +457,28 @@ fn get_controls(
                         offset,
                     })?,
                 ));
+            } else if children == 0
+                && !no_age_distinction
+                && !s.event.requires_approval
+                && s.children.my_reservation + s.children.my_waiting
+                    < s.event.max_children_per_reservation
+            {
+                row.push(InlineKeyboardButton::callback(
+                    t!(lang, "wait-child"),
+                    &serde_json::to_string(&CallbackQuery::SignUp {
+                        event_id,
+                        is_adult: false,
+                        wait: true,
+                    })?,
+                ));
             }
         }
         if children > 0 {
             row.push(InlineKeyboardButton::callback(
                 if no_age_distinction {
-                    "Отменить -1"
+                    t!(lang, "unbook")
                 } else {
-                    "Отменить детский -1"
+                    t!(lang, "unbook-child")
                 },
                 &serde_json::to_string(&CallbackQuery::PaidEvent {
                     event_id,
@@ -326,27 +492,53 @@ fn get_controls(
     }
     row = Vec::new();
     row.push(InlineKeyboardButton::callback(
-        "Список мероприятий",
+        t!(lang, "event-list-button"),
         serde_json::to_string(&CallbackQuery::EventList { offset: 0 })?,
     ));
 
     if is_admin {
-        if s.state == EventState::Open {
+        if s.state != EventState::Closed {
             row.push(InlineKeyboardButton::callback(
-                "Остановить запись",
-                serde_json::to_string(&CallbackQuery::ChangeEventState { event_id, state: 1 })?,
+                t!(lang, "close-registration"),
+                serde_json::to_string(&CallbackQuery::ChangeEventState {
+                    event_id,
+                    state: db::REGISTRATION_CLOSED,
+                })?,
             ));
         } else {
             row.push(InlineKeyboardButton::callback(
-                "Разрешить запись",
-                serde_json::to_string(&CallbackQuery::ChangeEventState { event_id, state: 0 })?,
+                t!(lang, "open-registration"),
+                serde_json::to_string(&CallbackQuery::ChangeEventState {
+                    event_id,
+                    state: db::REGISTRATION_OPENED,
+                })?,
             ));
         }
     }
 
-    if adults + children > 0 {
+    if s.event.requires_approval {
+        if approved.0 + approved.1 > 0 {
+            row.push(InlineKeyboardButton::callback(
+                t!(lang, "pay-button"),
+                serde_json::to_string(&CallbackQuery::SendInvoice {
+                    event_id,
+                    adults: approved.0,
+                    children: approved.1,
+                })?,
+            ));
+        } else if adults + children > 0 {
+            row.push(InlineKeyboardButton::callback(
+                t!(lang, "apply-button"),
+                serde_json::to_string(&CallbackQuery::RequestApproval {
+                    event_id,
+                    adults,
+                    children,
+                })?,
+            ));
+        }
+    } else if adults + children > 0 {
         row.push(InlineKeyboardButton::callback(
-            "К оплате",
+            t!(lang, "pay-button"),
             serde_json::to_string(&CallbackQuery::SendInvoice {
                 event_id,
                 adults,
@@ -356,6 +548,34 @@ fn get_controls(
     }
     keyboard.push(row);
 
+    let is_manager = is_admin || db::is_group_leader(conn, event_id, user_id).unwrap_or(false);
+    if s.event.requires_approval && is_manager {
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            t!(lang, "applications-button"),
+            serde_json::to_string(&CallbackQuery::ShowApprovalQueue {
+                event_id,
+                offset: 0,
+            })?,
+        )]);
+    }
+    let (offered_adults, offered_children, _) = db::get_offer(conn, event_id, user_id)?;
+    let payable = if s.event.requires_approval {
+        approved.0 + approved.1 > 0
+    } else {
+        adults + children > 0
+    } || offered_adults + offered_children > 0;
+    keyboard.append(&mut waiting_list::controls(conn, s, user_id, lang)?);
+    keyboard.append(&mut promo_codes::controls(
+        s, adults, children, payable, lang,
+    )?);
+    keyboard.append(&mut refunds::controls(conn, s, user)?);
+    keyboard.append(&mut tickets::controls(conn, s, user_id, lang)?);
+    keyboard.append(&mut calendar::controls(s, lang)?);
+    keyboard.append(&mut subscriptions::controls(s, lang)?);
+    keyboard.append(&mut questionnaire::controls(
+        conn, s, user_id, is_manager, lang,
+    )?);
+
     Ok(keyboard)
 }
 
@@ -365,60 +585,170 @@ pub fn prepare_invoice(
     children: u64,
     conn: &Connection,
     user: &User,
-    _ctx: &Context,
+    ctx: &Context,
 ) -> anyhow::Result<Reply> {
     match db::get_event(conn, event_id, user.id.0) {
         Ok(s) => {
             let no_age_distinction = s.event.max_adults == 0 || s.event.max_children == 0;
-            if s.state != EventState::Open {
-                Err(anyhow!("Event has been closed"))
-            } else if s.adults.my_reservation + adults > s.event.max_adults_per_reservation
-                || s.children.my_reservation + children > s.event.max_children_per_reservation
-            {
-                Err(anyhow!("Limits error"))
-            } else {
-                let mut title = "Билеты: ".to_string();
-                if no_age_distinction {
-                    title.push_str(&format!("{}", adults + children));
-                } else {
-                    if adults > 0 {
-                        title.push_str(&format!("{} взросл. ", adults));
-                    }
-                    if children > 0 {
-                        title.push_str(&format!(", {} детск.", children));
-                    }
+            if s.event.requires_approval {
+                let (approved_adults, approved_children) =
+                    db::get_approved_booking(conn, event_id, user.id.0)?;
+                if approved_adults != adults || approved_children != children {
+                    return Err(text!("application-not-approved").into());
                 }
-
-                Ok(Reply::Invoice {
-                    title,
-                    description: format!("{} - {}", s.event.name, format::ts(s.event.ts)),
-                    currency: s.event.currency,
-                    amount: adults * s.event.adult_ticket_price
-                        + children * s.event.child_ticket_price,
-                    payload: serde_json::to_string(&Booking {
+            }
+            if let EventState::Upcoming(opens_at) = s.state {
+                Err(text!("registration-opens", opens = format::ts(opens_at)).into())
+            } else if s.state != EventState::Open {
+                Err(anyhow!("Event has been closed"))
+            } else if !s.event.requires_approval
+                && !is_offered(
+                    conn,
+                    &Booking {
                         event_id,
                         adults,
                         children,
                         user_id: user.id.0,
-                    })?,
-                })
+                    },
+                )?
+                && (s.adults.my_reservation + adults > s.event.max_adults_per_reservation
+                    || s.children.my_reservation + children > s.event.max_children_per_reservation)
+            {
+                Err(anyhow!("Limits error"))
+            } else {
+                let title = t!(
+                    user.lang,
+                    "invoice-title",
+                    order = describe_order(adults, children, no_age_distinction, user.lang)
+                );
+
+                ctx.payment_provider.create_invoice(
+                    conn,
+                    user,
+                    Invoice {
+                        title,
+                        description: format!("{} - {}", s.event.name, format::ts(s.event.ts)),
+                        amount: db::get_booking_amount(
+                            conn, &s.event, user.id.0, adults, children,
+                        )?,
+                        currency: s.event.currency,
+                        payload: serde_json::to_string(&Booking {
+                            event_id,
+                            adults,
+                            children,
+                            user_id: user.id.0,
+                        })?,
+                    },
+                )
             }
         }
         Err(e) => Err(anyhow!("Failed to fetch event: {}", e)),
     }
 }
 
-pub fn donate(user: &User, amount: u64, _ctx: &Context) -> anyhow::Result<Reply> {
-    Ok(Reply::Invoice {
-        title: "Донат".to_string(),
-        description: "Поддержать работу канала \"Венские Истории\"".to_string(),
-        currency: "EUR".to_string(),
-        amount,
-        payload: serde_json::to_string(&Booking {
-            event_id: 0,
-            adults: 0,
-            children: 0,
-            user_id: user.id.0,
-        })?,
-    })
+pub fn donate(conn: &Connection, user: &User, amount: u64, ctx: &Context) -> anyhow::Result<Reply> {
+    ctx.payment_provider.create_invoice(
+        conn,
+        user,
+        Invoice {
+            title: t!(user.lang, "donation-title"),
+            description: t!(user.lang, "donation-description"),
+            currency: "EUR".to_string(),
+            amount,
+            payload: serde_json::to_string(&Booking {
+                event_id: 0,
+                adults: 0,
+                children: 0,
+                user_id: user.id.0,
+            })?,
+        },
+    )
+}
+
+/// Ledger entries of the event as CSV.
+pub fn export_payments(payments: &[Payment]) -> String {
+    let escape = |v: &str| format!("\"{}\"", v.replace('"', "\"\""));
+    let mut csv =
+        "id,ts,kind,event_id,user_id,amount,currency,charge_id,provider_charge_id,payload"
+            .to_string();
+    for p in payments {
+        csv.push_str(&format!(
+            "\n{},{},{},{},{},{},{},{},{},{}",
+            p.id,
+            format::from_timestamp(p.ts).to_rfc3339(),
+            serde_json::to_value(p.kind)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default(),
+            p.event_id,
+            p.user_id,
+            p.amount,
+            escape(&p.currency),
+            escape(&p.charge_id),
+            escape(&p.provider_charge_id),
+            escape(&p.payload)
+        ));
+    }
+    csv
+}
+
+/// Seats of an order, e.g. "2 adults, 1 child".
+pub fn describe_order(adults: u64, children: u64, no_age_distinction: bool, lang: Lang) -> String {
+    if no_age_distinction {
+        return (adults + children).to_string();
+    }
+    let mut order = Vec::new();
+    if adults > 0 {
+        order.push(t!(lang, "order-adults", count = adults));
+    }
+    if children > 0 {
+        order.push(t!(lang, "order-children", count = children));
+    }
+    order.join(", ")
+}
+
+/// Ledger totals with discrepancies against paid reservations.
+pub fn describe_totals(
+    totals: &PaymentTotals,
+    reconciliation: &Reconciliation,
+    lang: Lang,
+) -> String {
+    let price = |amount| format::price(amount, &totals.currency);
+    let mut text = if totals.event_id == 0 {
+        t!(
+            lang,
+            "donation-totals",
+            count = totals.donations,
+            amount = price(totals.donation_amount)
+        )
+    } else {
+        t!(
+            lang,
+            "payment-totals",
+            tickets = totals.tickets,
+            ticket_amount = price(totals.ticket_amount),
+            refunds = totals.refunds,
+            refund_amount = price(totals.refund_amount),
+            total = price(totals.ticket_amount.saturating_sub(totals.refund_amount))
+        )
+    };
+    for p in &reconciliation.unmatched_payments {
+        text.push_str(&format!(
+            "\n{}",
+            t!(
+                lang,
+                "unmatched-payment",
+                user_id = p.user_id.to_string(),
+                amount = price(p.amount),
+                charge_id = p.charge_id.as_str()
+            )
+        ));
+    }
+    for id in &reconciliation.unrecorded_charges {
+        text.push_str(&format!(
+            "\n{}",
+            t!(lang, "unrecorded-charge", charge_id = id.as_str())
+        ));
+    }
+    text
 }
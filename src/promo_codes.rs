@@ -0,0 +1,145 @@
+use crate::db::{self, EventStats};
+use crate::format;
+use crate::i18n::Lang;
+use crate::message_handler::CallbackQuery;
+use crate::payments::show_paid_event;
+use crate::reply::*;
+use crate::types::{Connection, Context, PromoCode, User};
+use teloxide::types::InlineKeyboardButton;
+
+const MAX_CODE_LENGTH: usize = 32;
+
+/// "10%" or "5.50" in the event currency.
+pub fn describe(p: &PromoCode, currency: &str) -> String {
+    if p.percent > 0 {
+        format!("{}%", p.percent)
+    } else {
+        format::price(p.amount, currency)
+    }
+}
+
+/// Parses "10%" into a percentage or "5.50" into a fixed amount in minor units.
+pub fn parse_discount(value: &str) -> Option<(u64, u64)> {
+    match value.strip_suffix('%') {
+        Some(percent) => match percent.parse::<u64>() {
+            Ok(percent) if percent > 0 && percent <= 100 => Some((percent, 0)),
+            _ => None,
+        },
+        None => match value.parse::<f64>() {
+            Ok(amount) if amount > 0.0 => Some((0, (amount * 100.0).round() as u64)),
+            _ => None,
+        },
+    }
+}
+
+/// Prompt shown on the paid event screen while a promo code is applied.
+pub fn prompt(conn: &Connection, s: &EventStats, user_id: u64, lang: Lang) -> Option<String> {
+    match db::get_applied_promo_code(conn, s.event.id, user_id) {
+        Ok(Some(p)) => Some(format!(
+            "\n{}",
+            t!(
+                lang,
+                "promo-code-applied",
+                code = p.code.as_str(),
+                discount = describe(&p, &s.event.currency)
+            )
+        )),
+        Ok(None) => None,
+        Err(e) => {
+            error!("Failed to get promo code: {}", e);
+            None
+        }
+    }
+}
+
+pub fn controls(
+    s: &EventStats,
+    adults: u64,
+    children: u64,
+    payable: bool,
+    lang: Lang,
+) -> anyhow::Result<Vec<Vec<InlineKeyboardButton>>> {
+    if payable {
+        Ok(vec![vec![InlineKeyboardButton::callback(
+            t!(lang, "promo-code-button"),
+            serde_json::to_string(&CallbackQuery::EnterPromoCode {
+                event_id: s.event.id,
+                adults,
+                children,
+            })?,
+        )]])
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Asks for a promo code, the next message from the user is taken as one.
+pub fn enter(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    adults: u64,
+    children: u64,
+    _ctx: &Context,
+) -> anyhow::Result<Reply> {
+    db::set_promo_code_session(conn, user.id.0, event_id, adults, children)?;
+    Ok(ReplyMessage::new(t!(user.lang, "send-promo-code"))
+        .keyboard(vec![vec![InlineKeyboardButton::callback(
+            t!(user.lang, "back"),
+            serde_json::to_string(&CallbackQuery::PaidEvent {
+                event_id,
+                adults,
+                children,
+                offset: 0,
+            })?,
+        )]])
+        .into())
+}
+
+/// Message from a user who was asked for a promo code.
+pub fn handle_text(
+    conn: &Connection,
+    user: &User,
+    data: &str,
+    ctx: &Context,
+) -> anyhow::Result<Option<Reply>> {
+    let (event_id, adults, children) = match db::get_promo_code_session(conn, user.id.0)? {
+        Some(session) => session,
+        None => return Ok(None),
+    };
+    db::set_promo_code_session(conn, user.id.0, 0, 0, 0)?;
+    let code = data.trim();
+    if code.is_empty() || code.len() > MAX_CODE_LENGTH {
+        return Err(text!("promo-code-invalid").into());
+    }
+    db::apply_promo_code(conn, event_id, user.id.0, code)?;
+    show_paid_event(event_id, adults, children, 0, conn, user, ctx).map(Some)
+}
+
+#[test]
+fn test_discount() {
+    assert_eq!(parse_discount("10%"), Some((10, 0)));
+    assert_eq!(parse_discount("5.50"), Some((0, 550)));
+    assert_eq!(parse_discount("0%"), None);
+    assert_eq!(parse_discount("150%"), None);
+    assert_eq!(parse_discount("abc"), None);
+    let p = PromoCode {
+        code: "SUMMER".to_string(),
+        event_id: 0,
+        percent: 10,
+        amount: 0,
+        max_uses: 0,
+        uses: 0,
+        expires_at: 0,
+    };
+    assert_eq!(p.apply(2000), 1800);
+    assert_eq!(
+        PromoCode {
+            percent: 0,
+            amount: 3000,
+            ..p
+        }
+        .apply(2000),
+        2000
+    );
+}
@@ -0,0 +1,427 @@
+use crate::db::{self, EventStats};
+use crate::format;
+use crate::i18n::Lang;
+use crate::message_handler::{back_to_event, CallbackQuery};
+use crate::reply::*;
+use crate::types::{Answer, Connection, Context, QuestionKind, User};
+use crate::util::{is_valid_email, is_valid_phone};
+use teloxide::types::InlineKeyboardButton;
+use teloxide::utils::html;
+
+const CHOICE_SEPARATOR: &str = "; ";
+
+/// Position of the first question without a complete answer.
+fn next_question(s: &EventStats, answers: &[Answer]) -> Option<usize> {
+    s.event
+        .questions
+        .iter()
+        .position(|q| !answers.iter().any(|a| a.question == q.id && a.complete))
+}
+
+/// Position of the question with the given id.
+fn find_question(s: &EventStats, question: u64) -> anyhow::Result<usize> {
+    match s.event.questions.iter().position(|q| q.id == question) {
+        Some(index) => Ok(index),
+        None => Err(text!("question-not-found").into()),
+    }
+}
+
+fn has_booking(s: &EventStats) -> bool {
+    s.adults.my_reservation
+        + s.adults.my_waiting
+        + s.adults.my_pending
+        + s.children.my_reservation
+        + s.children.my_waiting
+        + s.children.my_pending
+        > 0
+}
+
+/// Whether the user still has to fill in the questionnaire.
+pub fn is_incomplete(conn: &Connection, s: &EventStats, user_id: u64) -> bool {
+    if s.event.questions.is_empty() || !has_booking(s) {
+        return false;
+    }
+    match db::get_answers(conn, s.event.id, user_id) {
+        Ok(answers) => next_question(s, &answers).is_some(),
+        Err(e) => {
+            error!("Failed to get answers: {}", e);
+            false
+        }
+    }
+}
+
+/// Prompt shown on the event screen while the questionnaire is incomplete.
+pub fn prompt(conn: &Connection, s: &EventStats, user_id: u64, lang: Lang) -> Option<String> {
+    if is_incomplete(conn, s, user_id) {
+        Some(format!("\n{}", t!(lang, "fill-questionnaire")))
+    } else {
+        None
+    }
+}
+
+/// Questionnaire button for participants who have not answered yet, answers for managers.
+pub fn controls(
+    conn: &Connection,
+    s: &EventStats,
+    user_id: u64,
+    is_manager: bool,
+    lang: Lang,
+) -> anyhow::Result<Vec<Vec<InlineKeyboardButton>>> {
+    let event_id = s.event.id;
+    let mut row = Vec::new();
+    if is_incomplete(conn, s, user_id) {
+        row.push(InlineKeyboardButton::callback(
+            t!(lang, "questionnaire-button"),
+            serde_json::to_string(&CallbackQuery::Questionnaire { event_id })?,
+        ));
+    }
+    if is_manager && !s.event.questions.is_empty() {
+        row.push(InlineKeyboardButton::callback(
+            t!(lang, "answers-button"),
+            serde_json::to_string(&CallbackQuery::ShowAnswers {
+                event_id,
+                offset: 0,
+            })?,
+        ));
+    }
+    Ok(if row.is_empty() { vec![] } else { vec![row] })
+}
+
+/// Starts the questionnaire right after sign-up, returns None if there is nothing to ask.
+pub fn start(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    _ctx: &Context,
+) -> anyhow::Result<Option<Reply>> {
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    if !is_incomplete(conn, &s, user.id.0) {
+        return Ok(None);
+    }
+    let answers = db::get_answers(conn, event_id, user.id.0)?;
+    Ok(next_question(&s, &answers).map(|i| ask(&s, i, &answers, None, user.lang)))
+}
+
+/// Shows the next unanswered question or a confirmation once all are answered.
+pub fn show_questionnaire(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    _ctx: &Context,
+) -> anyhow::Result<Reply> {
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    if !has_booking(&s) {
+        return Err(text!("sign-up-first").into());
+    }
+    let answers = db::get_answers(conn, event_id, user.id.0)?;
+    match next_question(&s, &answers) {
+        Some(i) => Ok(ask(&s, i, &answers, None, user.lang)),
+        None => Ok(ReplyMessage::new(format!(
+            "{}\n\n{}",
+            format::event_title(&s.event),
+            t!(user.lang, "questionnaire-complete")
+        ))
+        .keyboard(vec![vec![back_to_event(&s, user.lang)?]])
+        .into()),
+    }
+}
+
+fn selected_options(answer: Option<&Answer>) -> Vec<&str> {
+    match answer {
+        Some(a) if !a.answer.is_empty() => a.answer.split(CHOICE_SEPARATOR).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn ask(
+    s: &EventStats,
+    index: usize,
+    answers: &[Answer],
+    warning: Option<&str>,
+    lang: Lang,
+) -> Reply {
+    let event_id = s.event.id;
+    let question = &s.event.questions[index];
+    let current = answers.iter().find(|a| a.question == question.id);
+
+    let mut text = format!(
+        "{}\n\n{}\n<b>{}</b>{}\n\n",
+        format::event_title(&s.event),
+        t!(
+            lang,
+            "question",
+            number = index + 1,
+            count = s.event.questions.len()
+        ),
+        html::escape(&question.text),
+        if question.required { " *" } else { "" }
+    );
+    text.push_str(&match question.kind {
+        QuestionKind::Text => t!(lang, "send-answer"),
+        QuestionKind::Phone => t!(lang, "send-phone"),
+        QuestionKind::Email => t!(lang, "send-email"),
+        QuestionKind::SingleChoice => t!(lang, "choose-option"),
+        QuestionKind::MultipleChoice => t!(lang, "choose-options"),
+    });
+    if let Some(warning) = warning {
+        text.push_str(&format!("\n\n<b>{}</b>", warning));
+    }
+
+    let selected = selected_options(current);
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = question
+        .options
+        .iter()
+        .enumerate()
+        .map(|(i, option)| {
+            vec![InlineKeyboardButton::callback(
+                if selected.contains(&option.as_str()) {
+                    format!("✅ {}", option)
+                } else {
+                    option.to_string()
+                },
+                serde_json::to_string(&CallbackQuery::AnswerQuestion {
+                    event_id,
+                    question: question.id,
+                    option: i as u64,
+                })
+                .unwrap(),
+            )]
+        })
+        .collect();
+
+    let mut row = Vec::new();
+    if let Ok(back) = back_to_event(s, lang) {
+        row.push(back);
+    }
+    if question.kind == QuestionKind::MultipleChoice || !question.required {
+        row.push(InlineKeyboardButton::callback(
+            if question.kind == QuestionKind::MultipleChoice {
+                t!(lang, "done-button")
+            } else {
+                t!(lang, "skip-button")
+            },
+            serde_json::to_string(&CallbackQuery::ConfirmAnswer {
+                event_id,
+                question: question.id,
+            })
+            .unwrap(),
+        ));
+    }
+    keyboard.push(row);
+
+    ReplyMessage::new(text).keyboard(keyboard).into()
+}
+
+/// Choice button pressed.
+pub fn answer_choice(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    question: u64,
+    option: u64,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    if !has_booking(&s) {
+        return Err(text!("sign-up-first").into());
+    }
+    let index = find_question(&s, question)?;
+    let q = &s.event.questions[index];
+    let option = match q.options.get(option as usize) {
+        Some(option) => option,
+        None => return Err(text!("option-not-found").into()),
+    };
+    if q.kind == QuestionKind::MultipleChoice {
+        let answers = db::get_answers(conn, event_id, user.id.0)?;
+        let mut selected = selected_options(answers.iter().find(|a| a.question == question));
+        if let Some(pos) = selected.iter().position(|v| *v == option) {
+            selected.remove(pos);
+        } else {
+            selected.push(option);
+        }
+        // Keep the order of options.
+        let selection = q
+            .options
+            .iter()
+            .filter(|o| selected.contains(&o.as_str()))
+            .map(|o| o.as_str())
+            .collect::<Vec<&str>>()
+            .join(CHOICE_SEPARATOR);
+        db::save_answer(conn, event_id, user.id.0, question, &selection, false)?;
+        let answers = db::get_answers(conn, event_id, user.id.0)?;
+        Ok(ask(&s, index, &answers, None, user.lang))
+    } else {
+        db::save_answer(conn, event_id, user.id.0, question, option, true)?;
+        show_questionnaire(conn, user, event_id, ctx)
+    }
+}
+
+/// "Done" for multiple choice questions, "Skip" for optional ones.
+pub fn confirm_answer(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    question: u64,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    if !has_booking(&s) {
+        return Err(text!("sign-up-first").into());
+    }
+    let index = find_question(&s, question)?;
+    let q = &s.event.questions[index];
+    let answers = db::get_answers(conn, event_id, user.id.0)?;
+    let answer = match answers.iter().find(|a| a.question == question) {
+        Some(a) if q.kind == QuestionKind::MultipleChoice => a.answer.to_string(),
+        _ => "".to_string(),
+    };
+    if answer.is_empty() && q.required {
+        return Ok(ask(
+            &s,
+            index,
+            &answers,
+            Some(&t!(user.lang, "question-required")),
+            user.lang,
+        ));
+    }
+    db::save_answer(conn, event_id, user.id.0, question, &answer, true)?;
+    show_questionnaire(conn, user, event_id, ctx)
+}
+
+/// Text message from user - try to use it as an answer to the current question.
+pub fn handle_text_answer(
+    conn: &Connection,
+    user: &User,
+    data: &str,
+    ctx: &Context,
+) -> anyhow::Result<Option<Reply>> {
+    let event_id = db::get_current_event(conn, user.id.0)?;
+    if event_id == 0 {
+        return Ok(None);
+    }
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    if !is_incomplete(conn, &s, user.id.0) {
+        return Ok(None);
+    }
+    let answers = db::get_answers(conn, event_id, user.id.0)?;
+    let index = match next_question(&s, &answers) {
+        Some(index) => index,
+        None => return Ok(None),
+    };
+    let question = &s.event.questions[index];
+    let value = data.trim();
+    let warning = match question.kind {
+        QuestionKind::SingleChoice | QuestionKind::MultipleChoice => {
+            Some(t!(user.lang, "use-option-buttons"))
+        }
+        QuestionKind::Phone if !is_valid_phone(value) => Some(t!(user.lang, "invalid-phone")),
+        QuestionKind::Email if !is_valid_email(value) => Some(t!(user.lang, "invalid-email")),
+        _ if value.is_empty() => Some(t!(user.lang, "empty-answer")),
+        _ => None,
+    };
+    if warning.is_some() {
+        return Ok(Some(ask(
+            &s,
+            index,
+            &answers,
+            warning.as_deref(),
+            user.lang,
+        )));
+    }
+    let value: String = value.chars().take(1024).collect();
+    db::save_answer(conn, event_id, user.id.0, question.id, &value, true)?;
+    show_questionnaire(conn, user, event_id, ctx).map(Some)
+}
+
+/// Questionnaires of event participants.
+pub fn show_answers(
+    conn: &Connection,
+    event_id: u64,
+    user: &User,
+    ctx: &Context,
+    offset: u64,
+) -> anyhow::Result<Reply> {
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    let participants =
+        db::get_event_answers(conn, event_id, offset, ctx.config.presence_page_size)?;
+    let mut text = format!(
+        "{}\n{}\n",
+        format::event_title(&s.event),
+        t!(user.lang, "starts-at", start = format::ts(s.event.ts))
+    );
+    if participants.is_empty() {
+        text.push_str(&format!("\n{}", t!(user.lang, "no-answers")));
+    }
+    for p in &participants {
+        text.push_str(&format!(
+            "\n<a href=\"tg://user?id={}\">{}</a>",
+            p.user_id,
+            html::escape(&p.user_name1)
+        ));
+        for a in &p.answers {
+            if let Some(q) = s.event.questions.iter().find(|q| q.id == a.question) {
+                text.push_str(&format!(
+                    "\n  {}: {}",
+                    html::escape(&q.text),
+                    if a.answer.is_empty() {
+                        "-".to_string()
+                    } else {
+                        html::escape(&a.answer)
+                    }
+                ));
+            }
+        }
+    }
+    Ok(ReplyMessage::new(text)
+        .keyboard(vec![vec![back_to_event(&s, user.lang)?]])
+        .pagination(
+            &CallbackQuery::ShowAnswers {
+                event_id,
+                offset: offset.saturating_sub(1),
+            },
+            &CallbackQuery::ShowAnswers {
+                event_id,
+                offset: offset + 1,
+            },
+            participants.len() as u64,
+            ctx.config.presence_page_size,
+            offset,
+        )?
+        .into())
+}
+
+/// All answers as CSV, one line per participant.
+pub fn export_answers(conn: &Connection, event_id: u64) -> anyhow::Result<String> {
+    let s = db::get_event(conn, event_id, 0)?;
+    let participants = db::get_event_answers(conn, event_id, 0, 0)?;
+    let escape = |v: &str| format!("\"{}\"", v.replace('"', "\"\""));
+    let mut csv = ["user_id", "name", "username"]
+        .iter()
+        .map(|v| v.to_string())
+        .chain(s.event.questions.iter().map(|q| escape(&q.text)))
+        .collect::<Vec<String>>()
+        .join(",");
+    for p in &participants {
+        csv.push('\n');
+        csv.push_str(
+            &[
+                p.user_id.to_string(),
+                escape(&p.user_name1),
+                escape(&p.user_name2),
+            ]
+            .into_iter()
+            .chain(s.event.questions.iter().map(|q| {
+                escape(
+                    p.answers
+                        .iter()
+                        .find(|a| a.question == q.id)
+                        .map(|a| a.answer.as_str())
+                        .unwrap_or(""),
+                )
+            }))
+            .collect::<Vec<String>>()
+            .join(","),
+        );
+    }
+    Ok(csv)
+}
@@ -0,0 +1,217 @@
+use crate::db::{self, EventStats};
+use crate::format;
+use crate::i18n::Lang;
+use crate::message_handler::{back_to_event, CallbackQuery};
+use crate::payment_providers;
+use crate::payments::show_paid_event;
+use crate::reply::*;
+use crate::types::{Connection, Context, EventType, User};
+use crate::util::get_unix_time;
+use anyhow::anyhow;
+use teloxide::types::InlineKeyboardButton;
+
+/// Prompt shown on the event screen while the user's refund request is pending.
+pub fn prompt(conn: &Connection, s: &EventStats, user_id: u64, lang: Lang) -> Option<String> {
+    match db::get_refund_request(conn, s.event.id, user_id) {
+        Ok(Some(amount)) => Some(format!(
+            "\n{}",
+            t!(
+                lang,
+                "refund-pending",
+                amount = format::price(amount, &s.event.currency)
+            )
+        )),
+        Ok(None) => None,
+        Err(e) => {
+            error!("Failed to get refund request: {}", e);
+            None
+        }
+    }
+}
+
+pub fn controls(
+    conn: &Connection,
+    s: &EventStats,
+    user: &User,
+) -> anyhow::Result<Vec<Vec<InlineKeyboardButton>>> {
+    if s.event.get_type() != EventType::Paid {
+        return Ok(vec![]);
+    }
+    let event_id = s.event.id;
+    let mut row = Vec::new();
+    let percent = s.event.refund_percent(get_unix_time());
+    if percent > 0
+        && s.adults.my_reservation + s.children.my_reservation > 0
+        && db::get_refund_request(conn, event_id, user.id.0)?.is_none()
+    {
+        row.push(InlineKeyboardButton::callback(
+            t!(user.lang, "refund-button", percent = percent),
+            serde_json::to_string(&CallbackQuery::RequestRefund { event_id })?,
+        ));
+    }
+    if user.is_admin {
+        row.push(InlineKeyboardButton::callback(
+            t!(user.lang, "refunds-button"),
+            serde_json::to_string(&CallbackQuery::ShowRefunds {
+                event_id,
+                offset: 0,
+            })?,
+        ));
+    }
+    if row.is_empty() {
+        Ok(vec![])
+    } else {
+        Ok(vec![row])
+    }
+}
+
+/// Refund request from a participant, admins decide on it in the refund queue.
+pub fn request(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    db::request_refund(conn, event_id, user.id.0, get_unix_time())?;
+    show_paid_event(event_id, 0, 0, 0, conn, user, ctx)
+}
+
+pub fn show_requests(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    offset: u64,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    Ok(requests_message(conn, user, event_id, offset, ctx)?.into())
+}
+
+fn requests_message(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    offset: u64,
+    ctx: &Context,
+) -> anyhow::Result<ReplyMessage> {
+    if !user.is_admin {
+        return Err(anyhow!("not allowed"));
+    }
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    let no_age_distinction = s.event.max_adults == 0 || s.event.max_children == 0;
+    let requests = db::get_refund_requests(conn, event_id, offset, ctx.config.presence_page_size)?;
+    ReplyMessage::new(format!(
+        "\n \n{}\n{}\n{}",
+        format::event_title(&s.event),
+        t!(user.lang, "starts-at", start = format::ts(s.event.ts)),
+        if requests.is_empty() {
+            t!(user.lang, "no-refund-requests")
+        } else {
+            t!(user.lang, "refund-requests")
+        }
+    ))
+    .keyboard(
+        requests
+            .iter()
+            .map(|r| {
+                let mut text = if r.user_name2.is_empty() {
+                    r.user_name1.to_string()
+                } else {
+                    format!("{} ({})", r.user_name1, r.user_name2)
+                };
+                if no_age_distinction {
+                    text.push_str(&format!(" {}", r.adults + r.children));
+                } else {
+                    text.push_str(&format!(" {}({})", r.adults, r.children));
+                }
+                text.push_str(&format!(
+                    " - {}",
+                    format::price(r.amount, &s.event.currency)
+                ));
+                Ok(vec![
+                    InlineKeyboardButton::callback(
+                        format!("✅ {}", text),
+                        serde_json::to_string(&CallbackQuery::ApproveRefund {
+                            event_id,
+                            user_id: r.user_id,
+                            offset,
+                        })?,
+                    ),
+                    InlineKeyboardButton::callback(
+                        "❌",
+                        serde_json::to_string(&CallbackQuery::RejectRefund {
+                            event_id,
+                            user_id: r.user_id,
+                            offset,
+                        })?,
+                    ),
+                ])
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?,
+    )
+    .keyboard(vec![vec![back_to_event(&s, user.lang)?]])
+    .pagination(
+        &CallbackQuery::ShowRefunds {
+            event_id,
+            offset: offset.saturating_sub(1),
+        },
+        &CallbackQuery::ShowRefunds {
+            event_id,
+            offset: offset + 1,
+        },
+        requests.len() as u64,
+        ctx.config.presence_page_size,
+        offset,
+    )
+}
+
+pub fn approve(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    user_id: u64,
+    offset: u64,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    if !user.is_admin {
+        return Err(anyhow!("not allowed"));
+    }
+    let amount = db::approve_refund(conn, event_id, user_id)?;
+    let links = payment_providers::refund_links(
+        ctx.payment_provider.as_ref(),
+        conn,
+        event_id,
+        user_id,
+        amount,
+    )?;
+    Ok(requests_message(conn, user, event_id, offset, ctx)?
+        .text((!links.is_empty()).then(|| {
+            format!(
+                "\n\n{}",
+                t!(
+                    user.lang,
+                    "refund-links",
+                    links = links
+                        .iter()
+                        .map(|link| t!(user.lang, "refund-link", url = link.replace('&', "&amp;")))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            )
+        }))
+        .into())
+}
+
+pub fn reject(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    user_id: u64,
+    offset: u64,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    if !user.is_admin {
+        return Err(anyhow!("not allowed"));
+    }
+    db::reject_refund(conn, event_id, user_id)?;
+    show_requests(conn, user, event_id, offset, ctx)
+}
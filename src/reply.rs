@@ -1,6 +1,6 @@
 use teloxide::{
     prelude::*,
-    types::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode},
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, InputFile, ParseMode},
     RequestError,
 };
 
@@ -21,7 +21,39 @@ pub struct ReplyMessage {
     pub parse_mode: ParseMode,
     pub disable_preview: bool,
     pub keyboard: Option<Vec<Vec<InlineKeyboardButton>>>,
+    pub images: Vec<ReplyImage>,
+    pub documents: Vec<ReplyDocument>,
 }
+
+/// PNG image sent after the message text.
+pub struct ReplyImage {
+    pub caption: String,
+    pub png: Vec<u8>,
+}
+
+/// File sent after the message text and images.
+pub struct ReplyDocument {
+    pub file_name: String,
+    pub content: Vec<u8>,
+}
+
+impl std::fmt::Debug for ReplyDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ReplyDocument({}, {} bytes)",
+            self.file_name,
+            self.content.len()
+        )
+    }
+}
+
+impl std::fmt::Debug for ReplyImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ReplyImage({}, {} bytes)", self.caption, self.png.len())
+    }
+}
+
 impl ReplyMessage {
     pub fn new<T>(message: T) -> Self
     where
@@ -32,9 +64,21 @@ impl ReplyMessage {
             parse_mode: ParseMode::Html,
             disable_preview: true,
             keyboard: None,
+            images: Vec::new(),
+            documents: Vec::new(),
         }
     }
 
+    pub fn images(mut self, mut images: Vec<ReplyImage>) -> Self {
+        self.images.append(&mut images);
+        self
+    }
+
+    pub fn documents(mut self, mut documents: Vec<ReplyDocument>) -> Self {
+        self.documents.append(&mut documents);
+        self
+    }
+
     pub fn text<T>(mut self, text: Option<T>) -> Self
     where
         T: AsRef<str>,
@@ -112,7 +156,8 @@ impl ReplyMessage {
             error!("Failed to send message to Telegram: {}", e);
             Err(e)
         })?;
-        Ok(())
+        send_images(self.images, msg.chat.id, bot).await?;
+        send_documents(self.documents, msg, bot).await
     }
 
     pub async fn edit(self, msg: &Message, bot: &AutoSend<Bot>) -> Result<(), RequestError> {
@@ -130,8 +175,48 @@ impl ReplyMessage {
             error!("Failed to send message to Telegram: {}", e);
             Err(e)
         })?;
-        Ok(())
+        send_images(self.images, msg.chat.id, bot).await?;
+        send_documents(self.documents, msg, bot).await
+    }
+}
+
+pub async fn send_images(
+    images: Vec<ReplyImage>,
+    chat_id: ChatId,
+    bot: &AutoSend<Bot>,
+) -> Result<(), RequestError> {
+    for image in images {
+        if let Err(e) = bot
+            .send_photo(chat_id, InputFile::memory(image.png).file_name("image.png"))
+            .caption(image.caption)
+            .parse_mode(ParseMode::Html)
+            .await
+        {
+            error!("Failed to send image to Telegram: {}", e);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+async fn send_documents(
+    documents: Vec<ReplyDocument>,
+    msg: &Message,
+    bot: &AutoSend<Bot>,
+) -> Result<(), RequestError> {
+    for document in documents {
+        if let Err(e) = bot
+            .send_document(
+                msg.chat.id,
+                InputFile::memory(document.content).file_name(document.file_name),
+            )
+            .await
+        {
+            error!("Failed to send document to Telegram: {}", e);
+            return Err(e);
+        }
     }
+    Ok(())
 }
 
 impl Into<Reply> for ReplyMessage {
@@ -0,0 +1,122 @@
+use crate::db::{self, EventStats};
+use crate::i18n::Lang;
+use crate::message_handler::{back_to_event, CallbackQuery};
+use crate::reply::*;
+use crate::types::{Audience, Connection, Context, Event, User};
+use teloxide::{types::InlineKeyboardButton, utils::html};
+
+pub fn controls(s: &EventStats, lang: Lang) -> anyhow::Result<Vec<Vec<InlineKeyboardButton>>> {
+    if topics(&s.event).is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(vec![vec![InlineKeyboardButton::callback(
+        t!(lang, "subscription-button"),
+        serde_json::to_string(&CallbackQuery::EventSubscriptions {
+            event_id: s.event.id,
+        })?,
+    )]])
+}
+
+/// Organization and tags of the event users can subscribe to.
+fn topics(e: &Event) -> Vec<Audience> {
+    let mut topics = Vec::new();
+    if !e.organization.is_empty() {
+        topics.push(Audience::Organization(e.organization.to_lowercase()));
+    }
+    for tag in &e.tags {
+        topics.push(Audience::Tag(tag.to_lowercase()));
+    }
+    topics
+}
+
+fn label(topic: &Audience, lang: Lang) -> String {
+    match topic {
+        Audience::Organization(organization) => {
+            t!(
+                lang,
+                "subscription-organization",
+                name = organization.as_str()
+            )
+        }
+        Audience::Tag(tag) => t!(lang, "subscription-tag", tag = tag.as_str()),
+        _ => topic.to_string(),
+    }
+}
+
+/// Organization and tags of the event, each button subscribes or unsubscribes.
+pub fn show_event_subscriptions(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    _ctx: &Context,
+) -> anyhow::Result<Reply> {
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    let subscribed: Vec<Audience> = db::get_subscriptions(conn, user.id.0)?
+        .into_iter()
+        .map(|(_, topic)| topic)
+        .collect();
+    let mut keyboard = Vec::new();
+    for (index, topic) in topics(&s.event).iter().enumerate() {
+        let key = if subscribed.contains(topic) {
+            "subscription-on"
+        } else {
+            "subscription-off"
+        };
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            t!(user.lang, key, topic = label(topic, user.lang)),
+            serde_json::to_string(&CallbackQuery::ToggleSubscription {
+                event_id,
+                index: index as u64,
+            })?,
+        )]);
+    }
+    keyboard.push(vec![back_to_event(&s, user.lang)?]);
+    Ok(ReplyMessage::new(t!(user.lang, "subscription-choose"))
+        .keyboard(keyboard)
+        .into())
+}
+
+pub fn toggle(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    index: u64,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    if let Some(topic) = topics(&s.event).get(index as usize) {
+        db::toggle_subscription(conn, user.id.0, topic)?;
+    }
+    show_event_subscriptions(conn, user, event_id, ctx)
+}
+
+/// Subscriptions of the user, each button unsubscribes.
+pub fn show(conn: &Connection, user: &User, _ctx: &Context) -> anyhow::Result<Reply> {
+    let subscriptions = db::get_subscriptions(conn, user.id.0)?;
+    if subscriptions.is_empty() {
+        return Ok(ReplyMessage::new(t!(user.lang, "no-subscriptions")).into());
+    }
+    let mut text = t!(user.lang, "subscriptions");
+    let mut keyboard = Vec::new();
+    for (subscription_id, topic) in &subscriptions {
+        let label = label(topic, user.lang);
+        text.push_str(&format!("\n{}", html::escape(&label)));
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            t!(user.lang, "subscription-remove", topic = label),
+            serde_json::to_string(&CallbackQuery::Unsubscribe {
+                subscription_id: *subscription_id,
+            })?,
+        )]);
+    }
+    Ok(ReplyMessage::new(text).keyboard(keyboard).into())
+}
+
+pub fn unsubscribe(
+    conn: &Connection,
+    user: &User,
+    subscription_id: u64,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    db::delete_subscription(conn, user.id.0, subscription_id)?;
+    show(conn, user, ctx)
+}
@@ -0,0 +1,239 @@
+use crate::db;
+use crate::format;
+use crate::i18n::{Args, Text};
+use crate::types::{Connection, Event};
+use teloxide::utils::html;
+
+/// Maximum length of a Telegram message.
+const MAX_LENGTH: usize = 4096;
+
+/// Placeholders filled in with the data of the event and the recipient.
+const EVENT_PLACEHOLDERS: [&str; 6] = ["name", "start", "link", "venue", "seats", "user"];
+
+/// Placeholders of messages sent with /send in addition to the event ones.
+const MESSAGE_PLACEHOLDERS: [&str; 2] = ["sender", "text"];
+
+/// Texts organizers can override for all events or for a single one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TemplateKind {
+    Reminder,
+    WaitingList,
+    Message,
+}
+
+impl TemplateKind {
+    pub fn parse(name: &str) -> Option<TemplateKind> {
+        match name {
+            "reminder" => Some(TemplateKind::Reminder),
+            "waiting_list" => Some(TemplateKind::WaitingList),
+            "send" => Some(TemplateKind::Message),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TemplateKind::Reminder => "reminder",
+            TemplateKind::WaitingList => "waiting_list",
+            TemplateKind::Message => "send",
+        }
+    }
+
+    pub fn placeholders(&self) -> Vec<&'static str> {
+        let mut placeholders = EVENT_PLACEHOLDERS.to_vec();
+        if *self == TemplateKind::Message {
+            placeholders.extend(MESSAGE_PLACEHOLDERS);
+        }
+        placeholders
+    }
+
+    /// "{name}, {start}, ..." for help texts.
+    pub fn describe_placeholders(&self) -> String {
+        self.placeholders()
+            .iter()
+            .map(|p| format!("{{{}}}", p))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// Splits the template into literal parts and placeholder names.
+fn parse(template: &str) -> Result<Vec<(bool, &str)>, Text> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find(['{', '}']) {
+        if rest[open..].starts_with('}') {
+            return Err(text!("template-unbalanced"));
+        }
+        let close = match rest[open + 1..].find(['{', '}']) {
+            Some(close) if rest[open + 1 + close..].starts_with('}') => open + 1 + close,
+            _ => return Err(text!("template-unbalanced")),
+        };
+        parts.push((false, &rest[..open]));
+        parts.push((true, &rest[open + 1..close]));
+        rest = &rest[close + 1..];
+    }
+    parts.push((false, rest));
+    Ok(parts)
+}
+
+/// Checks the template before saving it.
+pub fn validate(kind: TemplateKind, template: &str) -> Result<(), Text> {
+    if template.trim().is_empty() {
+        return Err(text!("template-empty"));
+    }
+    if template.chars().count() > MAX_LENGTH {
+        return Err(text!("template-too-long", max = MAX_LENGTH));
+    }
+    let placeholders = kind.placeholders();
+    for (is_placeholder, name) in parse(template)? {
+        if is_placeholder && !placeholders.contains(&name) {
+            return Err(text!(
+                "template-unknown-placeholder",
+                name = name,
+                placeholders = kind.describe_placeholders()
+            ));
+        }
+    }
+    if kind == TemplateKind::Message && !template.contains("{text}") {
+        return Err(text!("template-no-text"));
+    }
+    Ok(())
+}
+
+/// Fills in the placeholders, the ones without a value are kept to be filled in later.
+pub fn render(template: &str, args: &Args) -> String {
+    let parts = match parse(template) {
+        Ok(parts) => parts,
+        Err(_) => return template.to_string(),
+    };
+    let mut res = String::with_capacity(template.len());
+    for (is_placeholder, part) in parts {
+        match args.get(part) {
+            Some(serde_json::Value::String(v)) if is_placeholder => res.push_str(v),
+            Some(v) if is_placeholder => res.push_str(&v.to_string()),
+            _ if is_placeholder => {
+                res.push('{');
+                res.push_str(part);
+                res.push('}');
+            }
+            _ => res.push_str(part),
+        }
+    }
+    res
+}
+
+/// Template filled in with the event data, free seats and the user name are known when sent.
+pub fn template(template: &str, event: &Event) -> Text {
+    Text::Template {
+        template: template.to_string(),
+        args: Args::new(),
+    }
+    .arg("name", event.name.as_str())
+    .arg("start", format::ts(event.ts))
+    .arg("link", event.link.as_str())
+    .arg("venue", event.venue.as_str())
+}
+
+/// Template of the event, the one for all events or none.
+pub fn find(
+    conn: &Connection,
+    event_id: u64,
+    kind: TemplateKind,
+) -> Result<Option<String>, rusqlite::Error> {
+    match db::get_template(conn, event_id, kind.name())? {
+        Some(template) => Ok(Some(template)),
+        None if event_id != 0 => db::get_template(conn, 0, kind.name()),
+        None => Ok(None),
+    }
+}
+
+/// Text for the event from its template or the catalog message if there is none.
+pub fn text(
+    conn: &Connection,
+    kind: TemplateKind,
+    event_id: u64,
+    event: &Event,
+    default: Text,
+) -> Result<Text, rusqlite::Error> {
+    Ok(match find(conn, event_id, kind)? {
+        Some(t) => template(&t, event),
+        None => default,
+    })
+}
+
+/// Fills in the name of the recipient of a templated message.
+pub fn personalize(conn: &Connection, text: &Text, event_id: u64, user_id: u64) -> Text {
+    match text {
+        Text::Template { .. } => {
+            let name = db::get_participant_name(conn, event_id, user_id)
+                .ok()
+                .flatten()
+                .unwrap_or_default();
+            text.clone().arg("user", html::escape(&name))
+        }
+        _ => text.clone(),
+    }
+}
+
+/// Template rendered with the event data as the organizer would receive it.
+pub fn preview(
+    conn: &Connection,
+    event_id: u64,
+    kind: TemplateKind,
+    t: &str,
+    user_name: &str,
+) -> anyhow::Result<String> {
+    validate(kind, t)?;
+    let mut text = match event_id {
+        0 => Text::Template {
+            template: t.to_string(),
+            args: Args::new(),
+        },
+        _ => {
+            let s = db::get_event(conn, event_id, 0)?;
+            let (adults, children) = db::get_vacancies(conn, event_id)?;
+            template(t, &s.event).arg("seats", adults + children)
+        }
+    };
+    text = text.arg("user", html::escape(user_name));
+    if kind == TemplateKind::Message {
+        text = text.arg("sender", user_name).arg("text", "…");
+    }
+    Ok(text.render(Default::default()))
+}
+
+#[test]
+fn test_validate() {
+    assert!(validate(TemplateKind::Reminder, "{name} at {venue}, {seats} seats").is_ok());
+    assert_eq!(
+        validate(TemplateKind::Reminder, " "),
+        Err(text!("template-empty"))
+    );
+    assert_eq!(
+        validate(TemplateKind::Reminder, "{name"),
+        Err(text!("template-unbalanced"))
+    );
+    assert_eq!(
+        validate(TemplateKind::Reminder, "name}"),
+        Err(text!("template-unbalanced"))
+    );
+    assert!(validate(TemplateKind::Reminder, "{text}").is_err());
+    assert!(validate(TemplateKind::Message, "{sender}: {text}").is_ok());
+    assert_eq!(
+        validate(TemplateKind::Message, "{sender}"),
+        Err(text!("template-no-text"))
+    );
+    assert!(validate(TemplateKind::WaitingList, &"a".repeat(MAX_LENGTH + 1)).is_err());
+}
+
+#[test]
+fn test_render() {
+    let mut args = Args::new();
+    args.insert("name".to_string(), "Concert".into());
+    args.insert("seats".to_string(), 3.into());
+    assert_eq!(
+        render("{name}: {seats} seats, {user}", &args),
+        "Concert: 3 seats, {user}"
+    );
+}
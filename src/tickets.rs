@@ -0,0 +1,308 @@
+use crate::db::{self, EventStats};
+use crate::format;
+use crate::i18n::Lang;
+use crate::message_handler::{back_to_event, CallbackQuery};
+use crate::questionnaire;
+use crate::reply::*;
+use crate::types::{Connection, Context, Ticket, User};
+use crate::util::{is_valid_email, qr_png};
+use std::env;
+use teloxide::types::InlineKeyboardButton;
+use teloxide::utils::html;
+
+const MAX_NAME_LENGTH: usize = 128;
+
+/// First booked seat without an attendee name.
+fn next_seat(seats: u64, tickets: &[Ticket]) -> Option<u64> {
+    (0..seats).find(|seat| {
+        !tickets
+            .iter()
+            .any(|t| t.seat == *seat && !t.name.is_empty())
+    })
+}
+
+/// Splits "Иван Петров, ivan@example.com" into name and optional email.
+fn parse_attendee(value: &str) -> (String, Option<String>) {
+    if let Some((name, email)) = value.rsplit_once(',') {
+        if is_valid_email(email) {
+            return (name.trim().to_string(), Some(email.trim().to_string()));
+        }
+    }
+    (value.trim().to_string(), None)
+}
+
+/// Whether the event collects attendee names and some booked seats are still unnamed.
+pub fn is_incomplete(conn: &Connection, s: &EventStats, user_id: u64) -> bool {
+    if !s.event.named_tickets {
+        return false;
+    }
+    match (
+        db::get_seat_count(conn, s.event.id, user_id),
+        db::get_tickets(conn, s.event.id, user_id),
+    ) {
+        (Ok(seats), Ok(tickets)) => next_seat(seats, &tickets).is_some(),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to get tickets: {}", e);
+            false
+        }
+    }
+}
+
+/// Prompt shown on the event screen while some seats are unnamed.
+pub fn prompt(conn: &Connection, s: &EventStats, user_id: u64, lang: Lang) -> Option<String> {
+    if is_incomplete(conn, s, user_id) {
+        Some(format!("\n{}", t!(lang, "name-attendees")))
+    } else {
+        None
+    }
+}
+
+pub fn controls(
+    conn: &Connection,
+    s: &EventStats,
+    user_id: u64,
+    lang: Lang,
+) -> anyhow::Result<Vec<Vec<InlineKeyboardButton>>> {
+    let mut row = Vec::new();
+    if s.event.named_tickets && db::get_seat_count(conn, s.event.id, user_id)? > 0 {
+        row.push(InlineKeyboardButton::callback(
+            t!(lang, "attendees-button"),
+            serde_json::to_string(&CallbackQuery::Tickets {
+                event_id: s.event.id,
+            })?,
+        ));
+    }
+    if !db::get_valid_tickets(conn, user_id, Some(s.event.id))?.is_empty() {
+        row.push(InlineKeyboardButton::callback(
+            t!(lang, "tickets-button"),
+            serde_json::to_string(&CallbackQuery::MyTickets {
+                event_id: s.event.id,
+            })?,
+        ));
+    }
+    if row.is_empty() {
+        Ok(vec![])
+    } else {
+        Ok(vec![row])
+    }
+}
+
+/// QR code images of tickets, the code is a deep link for check-in.
+fn ticket_images(
+    conn: &Connection,
+    tickets: &[Ticket],
+    lang: Lang,
+) -> anyhow::Result<Vec<ReplyImage>> {
+    let bot_name = env::var("BOT_NAME").unwrap_or_default();
+    let mut images = Vec::new();
+    for t in tickets {
+        let code = match &t.code {
+            Some(code) => code,
+            None => continue,
+        };
+        let s = db::get_event(conn, t.event_id, t.user_id)?;
+        let mut caption = format!(
+            "{}\n{}\n{}",
+            format::event_title(&s.event),
+            t!(lang, "starts-at", start = format::ts(s.event.ts)),
+            t!(lang, "seat", seat = t.seat + 1)
+        );
+        if !t.name.is_empty() {
+            caption.push_str(&format!(", {}", html::escape(&t.name)));
+        }
+        images.push(ReplyImage {
+            caption,
+            png: qr_png(&format!("https://t.me/{}?start=t{}", bot_name, code))?,
+        });
+    }
+    Ok(images)
+}
+
+/// Issues tickets for newly confirmed seats and attaches them to the reply.
+pub fn attach_new(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    reply: Reply,
+) -> anyhow::Result<Reply> {
+    match reply {
+        Reply::Message(m) => Ok(m
+            .images(issue(conn, user.id.0, event_id, user.lang)?)
+            .into()),
+        reply => Ok(reply),
+    }
+}
+
+/// Issues tickets for newly confirmed seats, for messages sent outside of a dialog.
+pub fn issue(
+    conn: &Connection,
+    user_id: u64,
+    event_id: u64,
+    lang: Lang,
+) -> anyhow::Result<Vec<ReplyImage>> {
+    let issued = db::issue_tickets(conn, event_id, user_id)?;
+    ticket_images(conn, &issued, lang)
+}
+
+/// Valid tickets of the user, for one event or all upcoming events.
+pub fn show_my_tickets(
+    conn: &Connection,
+    user: &User,
+    event_id: Option<u64>,
+    _ctx: &Context,
+) -> anyhow::Result<Reply> {
+    match event_id {
+        Some(event_id) => db::issue_tickets(conn, event_id, user.id.0).map(|_| ())?,
+        None => db::issue_user_tickets(conn, user.id.0)?,
+    }
+    let tickets = db::get_valid_tickets(conn, user.id.0, event_id)?;
+    if tickets.is_empty() {
+        return Ok(ReplyMessage::new(t!(user.lang, "no-tickets")).into());
+    }
+    let mut message = ReplyMessage::new(t!(user.lang, "my-tickets", count = tickets.len()));
+    if let Some(event_id) = event_id {
+        let s = db::get_event(conn, event_id, user.id.0)?;
+        message = message.keyboard(vec![vec![back_to_event(&s, user.lang)?]]);
+    }
+    Ok(message
+        .images(ticket_images(conn, &tickets, user.lang)?)
+        .into())
+}
+
+/// Asks for the next attendee name after sign-up, returns None if all seats are named.
+pub fn start(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    _ctx: &Context,
+) -> anyhow::Result<Option<Reply>> {
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    if !s.event.named_tickets {
+        return Ok(None);
+    }
+    let seats = db::get_seat_count(conn, event_id, user.id.0)?;
+    let tickets = db::get_tickets(conn, event_id, user.id.0)?;
+    match next_seat(seats, &tickets) {
+        Some(seat) => Ok(Some(ask(&s, seat, seats, None, user.lang)?)),
+        None => Ok(None),
+    }
+}
+
+fn ask(
+    s: &EventStats,
+    seat: u64,
+    seats: u64,
+    warning: Option<&str>,
+    lang: Lang,
+) -> anyhow::Result<Reply> {
+    let mut text = format!(
+        "{}\n\n{}",
+        format::event_title(&s.event),
+        t!(lang, "ask-attendee", seat = seat + 1, seats = seats)
+    );
+    if let Some(warning) = warning {
+        text.push_str(&format!("\n\n<b>{}</b>", warning));
+    }
+    Ok(ReplyMessage::new(text)
+        .keyboard(vec![vec![back_to_event(s, lang)?]])
+        .into())
+}
+
+/// Text message from user - try to use it as the name of the next unnamed seat.
+pub fn handle_text(
+    conn: &Connection,
+    user: &User,
+    data: &str,
+    ctx: &Context,
+) -> anyhow::Result<Option<Reply>> {
+    let event_id = db::get_current_event(conn, user.id.0)?;
+    if event_id == 0 {
+        return Ok(None);
+    }
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    if !s.event.named_tickets {
+        return Ok(None);
+    }
+    let seats = db::get_seat_count(conn, event_id, user.id.0)?;
+    let tickets = db::get_tickets(conn, event_id, user.id.0)?;
+    let seat = match next_seat(seats, &tickets) {
+        Some(seat) => seat,
+        None => return Ok(None),
+    };
+    let (name, email) = parse_attendee(data);
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Ok(Some(ask(
+            &s,
+            seat,
+            seats,
+            Some(&t!(user.lang, "invalid-name")),
+            user.lang,
+        )?));
+    }
+    db::set_ticket_name(conn, event_id, user.id.0, seat, &name, email.as_deref())?;
+    if let Some(seat) = next_seat(seats, &db::get_tickets(conn, event_id, user.id.0)?) {
+        return Ok(Some(ask(&s, seat, seats, None, user.lang)?));
+    }
+    match questionnaire::start(conn, user, event_id, ctx)? {
+        Some(reply) => Ok(Some(reply)),
+        None => show_tickets(conn, user, event_id, ctx).map(Some),
+    }
+}
+
+/// Attendee names of the user's seats.
+pub fn show_tickets(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    _ctx: &Context,
+) -> anyhow::Result<Reply> {
+    let s = db::get_event(conn, event_id, user.id.0)?;
+    let seats = db::get_seat_count(conn, event_id, user.id.0)?;
+    if seats == 0 {
+        return Err(text!("sign-up-first").into());
+    }
+    let tickets = db::get_tickets(conn, event_id, user.id.0)?;
+    let mut text = format!(
+        "{}\n\n{}",
+        format::event_title(&s.event),
+        t!(user.lang, "attendees")
+    );
+    for seat in 0..seats {
+        text.push_str(&format!("\n{}. ", seat + 1));
+        match tickets.iter().find(|t| t.seat == seat) {
+            Some(t) => {
+                text.push_str(&html::escape(&t.name));
+                if let Some(email) = &t.email {
+                    text.push_str(&format!(" ({})", html::escape(email)));
+                }
+            }
+            None => text.push('-'),
+        }
+    }
+    let mut row = vec![back_to_event(&s, user.lang)?];
+    if next_seat(seats, &tickets).is_some() {
+        text.push_str(&format!("\n\n{}", t!(user.lang, "next-attendee")));
+    } else {
+        row.push(InlineKeyboardButton::callback(
+            t!(user.lang, "edit-button"),
+            serde_json::to_string(&CallbackQuery::ClearTickets { event_id })?,
+        ));
+    }
+    Ok(ReplyMessage::new(text).keyboard(vec![row]).into())
+}
+
+/// All named seats as CSV.
+pub fn export_tickets(conn: &Connection, event_id: u64) -> anyhow::Result<String> {
+    let escape = |v: &str| format!("\"{}\"", v.replace('"', "\"\""));
+    let mut csv = "user_id,seat,name,email".to_string();
+    for t in db::get_event_tickets(conn, event_id)? {
+        csv.push_str(&format!(
+            "\n{},{},{},{}",
+            t.user_id,
+            t.seat + 1,
+            escape(&t.name),
+            escape(t.email.as_deref().unwrap_or(""))
+        ));
+    }
+    Ok(csv)
+}
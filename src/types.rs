@@ -1,12 +1,14 @@
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
 use serde_compact::compact;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 
 use tokio::sync::Mutex;
 
 use crate::configuration::config::Config;
+use crate::i18n::{Lang, Text};
+use crate::payment_providers::PaymentProvider;
 use teloxide::types::UserId;
 
 pub type DbPool = r2d2::Pool<SqliteConnectionManager>;
@@ -30,10 +32,109 @@ pub struct Event {
     pub max_adults_per_reservation: u64,
     pub max_children_per_reservation: u64,
     pub ts: u64,
-    pub remind: u64,
+    pub reminders: Vec<Reminder>,
     pub adult_ticket_price: u64,
     pub child_ticket_price: u64,
     pub currency: String,
+    pub registration_opens_at: u64,
+    pub registration_closes_at: u64,
+    pub requires_approval: bool,
+    pub questions: Vec<Question>,
+    pub named_tickets: bool,
+    /// Seconds a freed seat is held for the next waiting reservation, 0 announces it to the whole waiting list.
+    pub waiting_list_hold: u64,
+    /// Seconds before the start until which paid tickets are refunded in full, 0 disables full refunds.
+    pub refund_deadline: u64,
+    /// Percentage refunded after `refund_deadline` and until the start, 0 means no refund.
+    pub late_refund_percent: u64,
+    /// Where the event takes place, shown in message templates.
+    pub venue: String,
+    /// Who runs the event, the public feed can be filtered by it.
+    pub organization: String,
+    pub tags: Vec<String>,
+}
+
+/// Reminder sent to participants `before` seconds prior to the event start.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Reminder {
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub before: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+}
+
+/// Accepts seconds or a duration like "1d", as in the bot and the config.
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Duration {
+        Seconds(u64),
+        Text(String),
+    }
+    match <Duration as serde::Deserialize>::deserialize(deserializer)? {
+        Duration::Seconds(v) => Ok(v),
+        Duration::Text(v) => crate::util::parse_duration(&v)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid duration: {}", v))),
+    }
+}
+
+/// Registration question asked by the bot after sign-up.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Question {
+    /// Stable id which answers refer to, assigned when the event is saved.
+    #[serde(default)]
+    pub id: u64,
+    pub text: String,
+    #[serde(default)]
+    pub kind: QuestionKind,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionKind {
+    #[default]
+    Text,
+    SingleChoice,
+    MultipleChoice,
+    Phone,
+    Email,
+}
+
+impl Question {
+    /// Choice questions need options, other kinds must not have any.
+    pub fn is_valid(&self) -> bool {
+        !self.text.trim().is_empty() && self.kind.is_choice() != self.options.is_empty()
+    }
+}
+
+impl QuestionKind {
+    pub fn is_choice(&self) -> bool {
+        *self == QuestionKind::SingleChoice || *self == QuestionKind::MultipleChoice
+    }
+}
+
+/// Answer to the question with id `question`. Multiple choice answers stay incomplete until confirmed.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Answer {
+    pub question: u64,
+    pub answer: String,
+    #[serde(skip)]
+    pub complete: bool,
+}
+
+#[derive(Serialize)]
+pub struct ParticipantAnswers {
+    pub user_id: u64,
+    pub user_name1: String,
+    pub user_name2: String,
+    pub answers: Vec<Answer>,
 }
 
 impl Event {
@@ -47,12 +148,39 @@ impl Event {
             EventType::Announcement
         }
     }
+
+    /// Share of the ticket price refunded when requested at `ts`.
+    pub fn refund_percent(&self, ts: u64) -> u64 {
+        if ts >= self.ts {
+            0
+        } else if self.refund_deadline != 0 && ts + self.refund_deadline <= self.ts {
+            100
+        } else {
+            self.late_refund_percent.min(100)
+        }
+    }
+
+    /// Tags are matched case-insensitively.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.to_lowercase();
+        self.tags.iter().any(|t| t.to_lowercase() == tag)
+    }
+}
+
+/// Tags are short non-empty words, a few per event.
+pub fn is_valid_tags(tags: &[String]) -> bool {
+    tags.len() <= 20
+        && tags
+            .iter()
+            .all(|t| !t.trim().is_empty() && t.chars().count() <= 64)
 }
 
 #[derive(PartialEq)]
 pub enum EventState {
     Open,
     Closed,
+    /// Registration opens at the given time.
+    Upcoming(u64),
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -61,6 +189,7 @@ pub struct User {
     pub user_name1: String,
     pub user_name2: String,
     pub is_admin: bool,
+    pub lang: Lang,
 }
 
 impl User {
@@ -80,6 +209,7 @@ impl User {
             user_name1,
             user_name2: user_name2.clone(),
             is_admin: admins.contains(&u.id.0),
+            lang: Lang::from_code(u.language_code.as_deref()),
         }
     }
 }
@@ -99,6 +229,77 @@ pub struct Presence {
     pub user_name2: String,
     pub reserved: u64,
     pub attachment: Option<String>,
+    pub attendees: Vec<String>,
+}
+
+/// Seats of confirmed reservations split by presence.
+#[derive(Clone, Serialize, Debug, Default, PartialEq)]
+pub struct Attendance {
+    pub reserved: u64,
+    pub present: u64,
+    pub no_show: u64,
+}
+
+/// Attendance summary of an event, participants are counted by user, adults and children by seat.
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct AttendanceStats {
+    pub event_id: u64,
+    pub participants: Attendance,
+    pub adults: Attendance,
+    pub children: Attendance,
+}
+
+/// Seats the user holds for an event, listed by /my.
+#[derive(Clone, Serialize, Debug, Default, PartialEq)]
+pub struct UserBooking {
+    pub event_id: u64,
+    pub name: String,
+    pub link: String,
+    pub ts: u64,
+    pub venue: String,
+    pub is_paid: bool,
+    /// Seats are counted without splitting them into adults and children.
+    pub no_age_distinction: bool,
+    /// Confirmed seats, paid ones on paid events.
+    pub adults: u64,
+    pub children: u64,
+    pub waiting_adults: u64,
+    pub waiting_children: u64,
+    /// Applications waiting for approval.
+    pub pending_adults: u64,
+    pub pending_children: u64,
+    /// Seats approved or held from the waiting list which are not paid yet.
+    pub unpaid_adults: u64,
+    pub unpaid_children: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<String>,
+    /// Checked in at the event.
+    pub attended: bool,
+}
+
+/// Named seat of a reservation, seats are numbered per user starting from 0.
+#[derive(Clone, Serialize, Debug)]
+pub struct Ticket {
+    pub event_id: u64,
+    pub user_id: u64,
+    pub seat: u64,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Entrance code, only valid while the reservation it was issued for stays confirmed.
+    #[serde(skip)]
+    pub code: Option<String>,
+}
+
+/// Result of checking a ticket code at the entrance.
+#[derive(Clone, Serialize, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CheckIn {
+    CheckedIn { ticket: Ticket },
+    AlreadyCheckedIn { ticket: Ticket, ts: u64 },
+    Cancelled { ticket: Ticket },
+    WrongEvent { ticket: Ticket },
+    Unknown,
 }
 
 pub struct MessageBatch {
@@ -107,9 +308,108 @@ pub struct MessageBatch {
     pub sender: String,
     pub message_type: MessageType,
     pub waiting_list: u64,
-    pub text: String,
+    pub text: Text,
     pub is_paid: bool,
     pub recipients: Vec<u64>,
+    pub media: Option<Media>,
+}
+
+/// Media sent with a message, the text goes into the caption where Telegram allows one.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Media {
+    Photo {
+        file_id: String,
+    },
+    Document {
+        file_id: String,
+    },
+    Video {
+        file_id: String,
+    },
+    Animation {
+        file_id: String,
+    },
+    Audio {
+        file_id: String,
+    },
+    Poll {
+        question: String,
+        options: Vec<String>,
+        is_anonymous: bool,
+        allows_multiple_answers: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        correct_option_id: Option<u8>,
+    },
+    /// Any other message, copied from the chat it was sent to.
+    Copy {
+        chat_id: i64,
+        message_id: i32,
+    },
+}
+
+impl Media {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Media::Photo { .. } => "photo",
+            Media::Document { .. } => "document",
+            Media::Video { .. } => "video",
+            Media::Animation { .. } => "animation",
+            Media::Audio { .. } => "audio",
+            Media::Poll { .. } => "poll",
+            Media::Copy { .. } => "copy",
+        }
+    }
+
+    pub fn has_caption(&self) -> bool {
+        !matches!(self, Media::Poll { .. } | Media::Copy { .. })
+    }
+}
+
+/// Recipients of a broadcast, stored as "all", "attended 12", "tag music", ...
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Audience {
+    /// Everyone who has used the bot.
+    All,
+    /// Participants whose presence was confirmed at the event.
+    Attended(u64),
+    /// Confirmed participants who did not come to the event.
+    NoShow(u64),
+    /// Subscribers to the tag.
+    Tag(String),
+    /// Subscribers to the organization.
+    Organization(String),
+}
+
+impl std::fmt::Display for Audience {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Audience::All => write!(f, "all"),
+            Audience::Attended(event_id) => write!(f, "attended {}", event_id),
+            Audience::NoShow(event_id) => write!(f, "no_show {}", event_id),
+            Audience::Tag(tag) => write!(f, "tag {}", tag),
+            Audience::Organization(organization) => write!(f, "organization {}", organization),
+        }
+    }
+}
+
+impl std::str::FromStr for Audience {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = match s.split_once(' ') {
+            Some((kind, value)) => (kind, value.trim()),
+            None => (s, ""),
+        };
+        match (kind, value) {
+            ("all", "") => Ok(Audience::All),
+            ("attended", v) => v.parse().map(Audience::Attended).map_err(|_| ()),
+            ("no_show", v) => v.parse().map(Audience::NoShow).map_err(|_| ()),
+            ("tag", v) if !v.is_empty() => Ok(Audience::Tag(v.to_string())),
+            ("organization", v) if !v.is_empty() => Ok(Audience::Organization(v.to_string())),
+            _ => Err(()),
+        }
+    }
 }
 
 #[derive(FromPrimitive, ToPrimitive, PartialEq)]
@@ -117,12 +417,18 @@ pub enum MessageType {
     Direct = 0,
     Reminder = 1,
     WaitingListPrompt = 2,
+    Notification = 3,
+    Feedback = 4,
+    NewEvent = 5,
+    /// Personal message followed by the QR codes of newly issued tickets.
+    Tickets = 6,
 }
 
 pub struct Context {
     pub config: Config,
     pub pool: DbPool,
     pub sign_up_mutex: Arc<Mutex<u64>>,
+    pub payment_provider: Arc<dyn PaymentProvider>,
 }
 
 #[compact]
@@ -141,8 +447,169 @@ pub struct OrderInfo {
     pub amount: u64,
 }
 
+/// Charge confirmed by a payment provider, `payload` is the serialized `Booking`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Charge {
+    pub payload: String,
+    #[serde(default)]
+    pub name: String,
+    pub amount: u64,
+    pub currency: String,
+    pub charge_id: String,
+    #[serde(default)]
+    pub provider_charge_id: String,
+}
+
 pub enum ReservationState {
     Free = 0,
     PaymentPending = 1,
     PaymentCompleted = 2,
+    PendingApproval = 3,
+    /// Approved application waiting for payment.
+    Approved = 4,
+    /// Seat from the waiting list held for the user until `hold_until`.
+    Offered = 5,
+    /// Paid reservation cancelled with a refund, the seat is released.
+    Refunded = 6,
+}
+
+/// Kind of a payments ledger entry.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentKind {
+    Ticket = 0,
+    Refund = 1,
+    Donation = 2,
+}
+
+impl PaymentKind {
+    pub fn from_u64(kind: u64) -> Option<PaymentKind> {
+        match kind {
+            0 => Some(PaymentKind::Ticket),
+            1 => Some(PaymentKind::Refund),
+            2 => Some(PaymentKind::Donation),
+            _ => None,
+        }
+    }
+}
+
+/// Payments ledger entry, amounts are in minor currency units.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Payment {
+    pub id: u64,
+    pub ts: u64,
+    pub kind: PaymentKind,
+    pub event_id: u64,
+    pub user_id: u64,
+    pub amount: u64,
+    pub currency: String,
+    pub charge_id: String,
+    pub provider_charge_id: String,
+    pub payload: String,
+    /// Promo code applied to the order, empty without a discount.
+    pub promo_code: String,
+}
+
+/// Discount on paid tickets, either a percentage or a fixed amount in minor currency units.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PromoCode {
+    pub code: String,
+    /// Event the code is valid for, 0 for all events.
+    pub event_id: u64,
+    pub percent: u64,
+    pub amount: u64,
+    /// Number of orders the code can be used for, 0 for unlimited.
+    pub max_uses: u64,
+    pub uses: u64,
+    /// 0 for no expiry.
+    pub expires_at: u64,
+}
+
+impl PromoCode {
+    pub fn is_valid(&self, event_id: u64, ts: u64) -> bool {
+        (self.event_id == 0 || self.event_id == event_id)
+            && (self.max_uses == 0 || self.uses < self.max_uses)
+            && (self.expires_at == 0 || ts < self.expires_at)
+    }
+
+    /// Discounted total, codes which would make the order free are not applied
+    /// since an invoice can't be issued for nothing.
+    pub fn apply(&self, total: u64) -> u64 {
+        let discounted = total
+            .saturating_sub(total * self.percent.min(100) / 100)
+            .saturating_sub(self.amount);
+        if discounted == 0 {
+            total
+        } else {
+            discounted
+        }
+    }
+}
+
+/// Ledger totals of an event, event 0 holds donations.
+#[derive(Serialize, Default, Debug, PartialEq)]
+pub struct PaymentTotals {
+    pub event_id: u64,
+    pub currency: String,
+    pub tickets: u64,
+    pub ticket_amount: u64,
+    pub refunds: u64,
+    pub refund_amount: u64,
+    pub donations: u64,
+    pub donation_amount: u64,
+}
+
+/// Discrepancies between the payments ledger and paid reservations.
+#[derive(Serialize, Default, Debug)]
+pub struct Reconciliation {
+    /// Ticket payments without a paid reservation, e.g. when booking failed after the charge.
+    pub unmatched_payments: Vec<Payment>,
+    /// Telegram charge ids of paid reservations missing from the ledger.
+    pub unrecorded_charges: Vec<String>,
+}
+
+/// Delivery of a message to its recipients so far.
+#[derive(Serialize, Default, Debug, PartialEq)]
+pub struct DeliveryReport {
+    pub message_id: u64,
+    pub event_id: u64,
+    pub targeted: u64,
+    pub delivered: u64,
+    pub failed: u64,
+    pub pending: u64,
+    /// Failed deliveries by reason, e.g. "bot_blocked".
+    pub failures: BTreeMap<String, u64>,
+}
+
+/// Ratings of an event collected by the survey after it.
+#[derive(Serialize, Default, Debug, PartialEq)]
+pub struct FeedbackSummary {
+    pub event_id: u64,
+    pub name: String,
+    pub ts: u64,
+    /// Participants the survey was sent to.
+    pub surveyed: u64,
+    pub responses: u64,
+    pub average: f64,
+    /// Number of responses with each rating from 1 to 5.
+    pub ratings: [u64; 5],
+    pub comments: u64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FeedbackResponse {
+    pub user_id: u64,
+    pub rating: u64,
+    pub comment: String,
+    pub ts: u64,
+}
+
+/// Refund requested by a participant and waiting for an admin.
+pub struct RefundRequest {
+    pub user_id: u64,
+    pub user_name1: String,
+    pub user_name2: String,
+    pub adults: u64,
+    pub children: u64,
+    pub amount: u64,
 }
@@ -1,3 +1,5 @@
+use qrcode::QrCode;
+use std::io::Cursor;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn get_unix_time() -> u64 {
@@ -16,7 +18,100 @@ pub fn max_or_value<Value: Ord>(value: Value, max: Value) -> Value {
     value
 }
 
+/// Parses durations like "7d", "2h" or "30m" into seconds.
+pub fn parse_duration(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let unit = match value.chars().last()? {
+        'd' => 24 * 60 * 60,
+        'h' => 60 * 60,
+        'm' => 60,
+        _ => return None,
+    };
+    value[..value.len() - 1]
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|v| v.checked_mul(unit))
+}
+
+/// Accepts phone numbers like "+43 660 123-45-67" or "(01) 234 5678".
+pub fn is_valid_phone(value: &str) -> bool {
+    let digits = value.chars().filter(char::is_ascii_digit).count();
+    (7..=15).contains(&digits)
+        && value
+            .trim()
+            .trim_start_matches('+')
+            .chars()
+            .all(|c| c.is_ascii_digit() || " -()".contains(c))
+}
+
+pub fn is_valid_email(value: &str) -> bool {
+    match value.trim().split_once('@') {
+        Some((user, domain)) => {
+            !user.is_empty()
+                && !domain.contains('@')
+                && !domain.contains(char::is_whitespace)
+                && !user.contains(char::is_whitespace)
+                && domain.split('.').count() > 1
+                && domain.split('.').all(|p| !p.is_empty())
+        }
+        None => false,
+    }
+}
+
+/// Unguessable code: 128 random bits as hex.
+pub fn generate_code() -> anyhow::Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Renders data as a QR code PNG.
+pub fn qr_png(data: &str) -> anyhow::Result<Vec<u8>> {
+    let image = QrCode::new(data.as_bytes())?
+        .render::<image::Luma<u8>>()
+        .min_dimensions(300, 300)
+        .build();
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)?;
+    Ok(png)
+}
+
+/// Finds a QR code on a photo and returns its content.
+pub fn read_qr(photo: &[u8]) -> anyhow::Result<Option<String>> {
+    let image = image::load_from_memory(photo)?.to_luma8();
+    let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+        image.width() as usize,
+        image.height() as usize,
+        |x, y| image.get_pixel(x as u32, y as u32).0[0],
+    );
+    Ok(prepared
+        .detect_grids()
+        .into_iter()
+        .find_map(|grid| grid.decode().ok())
+        .map(|(_, content)| content))
+}
+
 #[test]
 fn test_util() {
     assert_eq!(get_seconds_before_midnight(1651503600), 9 * 60 * 60);
+    assert_eq!(parse_duration("7d"), Some(7 * 24 * 60 * 60));
+    assert_eq!(parse_duration(" 2h"), Some(2 * 60 * 60));
+    assert_eq!(parse_duration("30m"), Some(30 * 60));
+    assert_eq!(parse_duration("30"), None);
+    assert_eq!(parse_duration("18446744073709551615d"), None);
+    assert_eq!(parse_duration(""), None);
+    assert!(is_valid_phone("+43 660 123-45-67"));
+    assert!(is_valid_phone("(01) 234 5678"));
+    assert!(!is_valid_phone("12345"));
+    assert!(!is_valid_phone("+43 abc 1234567"));
+    assert!(is_valid_email("user@example.com"));
+    assert!(!is_valid_email("user@example"));
+    assert!(!is_valid_email("user example.com"));
+    assert!(!is_valid_email("@example.com"));
+    let code = generate_code().unwrap();
+    assert_eq!(code.len(), 32);
+    assert_ne!(code, generate_code().unwrap());
+    assert!(qr_png(&code).unwrap().starts_with(b"\x89PNG"));
+    assert_eq!(read_qr(&qr_png(&code).unwrap()).unwrap(), Some(code));
 }
@@ -0,0 +1,103 @@
+use crate::db::{self, EventStats};
+use crate::format;
+use crate::i18n::Lang;
+use crate::message_handler::{show_event, start_dialog, CallbackQuery};
+use crate::payments::show_paid_event;
+use crate::reply::*;
+use crate::tickets;
+use crate::types::{Connection, Context, EventType, User};
+use teloxide::types::InlineKeyboardButton;
+
+/// Prompt shown on the event screen while a seat from the waiting list is held for the user.
+pub fn prompt(conn: &Connection, s: &EventStats, user_id: u64, lang: Lang) -> Option<String> {
+    match db::get_offer(conn, s.event.id, user_id) {
+        Ok((adults, children, hold_until)) if adults + children > 0 => Some(format!(
+            "\n{}",
+            t!(
+                lang,
+                "seats-held",
+                seats = adults + children,
+                paid = if s.event.get_type() == EventType::Paid {
+                    "yes"
+                } else {
+                    "no"
+                },
+                hold_until = format::ts(hold_until),
+            )
+        )),
+        Ok(_) => None,
+        Err(e) => {
+            error!("Failed to get offer: {}", e);
+            None
+        }
+    }
+}
+
+pub fn controls(
+    conn: &Connection,
+    s: &EventStats,
+    user_id: u64,
+    lang: Lang,
+) -> anyhow::Result<Vec<Vec<InlineKeyboardButton>>> {
+    let event_id = s.event.id;
+    let (adults, children, _) = db::get_offer(conn, event_id, user_id)?;
+    let mut row = Vec::new();
+    if adults + children > 0 {
+        row.push(if s.event.get_type() == EventType::Paid {
+            InlineKeyboardButton::callback(
+                t!(lang, "pay-seat"),
+                serde_json::to_string(&CallbackQuery::SendInvoice {
+                    event_id,
+                    adults,
+                    children,
+                })?,
+            )
+        } else {
+            InlineKeyboardButton::callback(
+                t!(lang, "accept-seat"),
+                serde_json::to_string(&CallbackQuery::AcceptOffer { event_id })?,
+            )
+        });
+    }
+    if adults + children + s.adults.my_waiting + s.children.my_waiting > 0 {
+        row.push(InlineKeyboardButton::callback(
+            t!(lang, "leave-waiting-list"),
+            serde_json::to_string(&CallbackQuery::LeaveWaitingList { event_id })?,
+        ));
+    }
+    if row.is_empty() {
+        Ok(vec![])
+    } else {
+        Ok(vec![row])
+    }
+}
+
+/// Leaves the waiting list, held seats pass to the next in line.
+pub fn leave(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    db::leave_waiting_list(conn, event_id, user.id.0)?;
+    if db::get_event(conn, event_id, user.id.0)?.event.get_type() == EventType::Paid {
+        show_paid_event(event_id, 0, 0, 0, conn, user, ctx)
+    } else {
+        show_event(conn, user, event_id, ctx, None, 0)
+    }
+}
+
+/// Confirms held seats and continues with the sign-up dialog.
+pub fn accept(
+    conn: &Connection,
+    user: &User,
+    event_id: u64,
+    ctx: &Context,
+) -> anyhow::Result<Reply> {
+    db::accept_offer(conn, event_id, user.id.0)?;
+    let reply = match start_dialog(conn, user, event_id, ctx)? {
+        Some(reply) => reply,
+        None => show_event(conn, user, event_id, ctx, None, 0)?,
+    };
+    tickets::attach_new(conn, user, event_id, reply)
+}